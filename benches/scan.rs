@@ -0,0 +1,89 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Throughput of the scanner against a naive implementation that reparses
+//! every candidate position from a string.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use heidi::any::AnyNumber;
+use heidi::scan::Scanner;
+use std::hint::black_box;
+use std::str::FromStr;
+
+/// Builds a log-like corpus of roughly `size` bytes with an identifier every
+/// few lines.
+fn corpus(size: usize) -> String {
+    let lines = [
+        "2020-04-01T10:00:00Z INFO request id=8f2c latency=12ms status=200\n",
+        "2020-04-01T10:00:01Z WARN patient 654 100 3238 missing address\n",
+        "2020-04-01T10:00:02Z INFO callback tel +44 0161 496 0000 queued\n",
+        "2020-04-01T10:00:03Z DEBUG payload bytes=1048576 checksum=98127341234\n",
+        "2020-04-01T10:00:04Z INFO NHS No 6541003238 matched\n",
+    ];
+    let mut text = String::with_capacity(size + 128);
+
+    while text.len() < size {
+        for line in &lines {
+            text.push_str(line);
+        }
+    }
+
+    text
+}
+
+/// Tries every position where a digit run starts and reparses the following
+/// bytes as each supported style.
+fn naive(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut found = 0;
+
+    for idx in 0..bytes.len() {
+        if !bytes[idx].is_ascii_digit() || (idx > 0 && bytes[idx - 1].is_ascii_digit()) {
+            continue;
+        }
+
+        for len in &[10, 12] {
+            if let Some(slice) = text.get(idx..idx + len) {
+                let after = bytes.get(idx + len);
+                let compact: String = slice.chars().filter(|c| c.is_ascii_digit()).collect();
+                let shaped = match len {
+                    10 => compact.len() == 10,
+                    _ => {
+                        compact.len() == 10
+                            && (slice.as_bytes()[3] == b' ' || slice.as_bytes()[3] == b'-')
+                            && slice.as_bytes()[7] == slice.as_bytes()[3]
+                    }
+                };
+
+                if shaped
+                    && !after.is_some_and(u8::is_ascii_digit)
+                    && AnyNumber::from_str(&compact).is_ok()
+                {
+                    found += 1;
+                }
+            }
+        }
+    }
+
+    found
+}
+
+fn throughput(c: &mut Criterion) {
+    let text = corpus(4 * 1024 * 1024);
+    let scanner = Scanner::default();
+    let mut group = c.benchmark_group("scan");
+
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    group.sample_size(10);
+    group.bench_function("scanner", |b| {
+        b.iter(|| scanner.find_iter(black_box(text.as_bytes())).count())
+    });
+    group.bench_function("naive", |b| b.iter(|| naive(black_box(&text))));
+    group.finish();
+}
+
+criterion_group!(benches, throughput);
+criterion_main!(benches);
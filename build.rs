@@ -0,0 +1,38 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Generates the C header for the `ffi` module when the `ffi` feature is
+//! enabled. The header is written to `OUT_DIR`; a test checks that the
+//! tracked `include/heidi.h` matches it.
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    #[cfg(feature = "ffi")]
+    header();
+}
+
+#[cfg(feature = "ffi")]
+fn header() {
+    use std::path::Path;
+
+    let dir = std::env::var("CARGO_MANIFEST_DIR").expect("manifest directory");
+    let out = std::env::var("OUT_DIR").expect("output directory");
+    let config = cbindgen::Config::from_file(Path::new(&dir).join("cbindgen.toml"))
+        .expect("Unable to read cbindgen.toml");
+
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    // Only the ffi module is parsed so public items elsewhere in the crate,
+    // such as `number::MAX`, stay out of the header.
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(Path::new(&dir).join("src/ffi.rs"))
+        .generate()
+        .expect("Unable to generate the C header")
+        .write_to_file(Path::new(&out).join("heidi.h"));
+}
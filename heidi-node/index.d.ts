@@ -0,0 +1,43 @@
+/* Type definitions for the heidi Node.js bindings. */
+
+export type Scheme = 'nhs' | 'chi'
+
+export type Style = 'official' | 'compact' | 'hyphen'
+
+/** The outcome of validating a number. */
+export interface Validation {
+  valid: boolean
+  /** The number as 10 digits with no separators when valid. */
+  number?: string
+  /** The category of the failure, e.g. `checkdigit`, when invalid. */
+  kind?: 'length' | 'digit' | 'checkdigit' | 'unusable' | 'date' | 'other'
+  /** The stable code of the failure, e.g. `HEIDI_E002`, when invalid. */
+  code?: string
+  /** Why the number is invalid. */
+  message?: string
+}
+
+/** How to generate numbers. */
+export interface GenerateOptions {
+  /** The amount of numbers. Defaults to 1. */
+  count?: number
+  /** Generates numbers never issued to patients. */
+  test?: boolean
+  /** The same seed always gives the same numbers. */
+  seed?: number
+}
+
+/** Validates a number of 10 digits, ignoring whitespace. */
+export function validate(input: string, scheme: Scheme): Validation
+
+/** Whether a number of 10 digits is valid. */
+export function isValid(input: string, scheme: Scheme): boolean
+
+/**
+ * Formats a valid number, `official` by default. Throws when the number is
+ * not valid.
+ */
+export function format(input: string, scheme: Scheme, style?: Style): string
+
+/** Generates random valid numbers. */
+export function generate(scheme: Scheme, options?: GenerateOptions): Array<string>
@@ -0,0 +1,184 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Node.js bindings for heidi so services in Node validate numbers the same
+//! way as the Rust services.
+//!
+//! ```js
+//! const heidi = require('heidi');
+//!
+//! heidi.isValid('654 100 3238', 'nhs');
+//! heidi.format('6541003238', 'nhs', 'hyphen');
+//! heidi.generate('chi', { count: 10, test: true, seed: 42 });
+//! ```
+//!
+//! Build with `napi build --release` from this directory. The
+//! type definitions are in `index.d.ts`.
+
+use heidi::error::ValidationError;
+use heidi::number::Digit;
+use heidi::{chi, nhs};
+use napi::{Error, Result};
+use napi_derive::napi;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::str::FromStr;
+
+/// The outcome of validating a number.
+#[napi(object)]
+pub struct Validation {
+    pub valid: bool,
+    /// The number as 10 digits with no separators when valid.
+    pub number: Option<String>,
+    /// The category of the failure, e.g. `checkdigit`, when invalid.
+    pub kind: Option<String>,
+    /// The stable code of the failure, e.g. `HEIDI_E002`, when invalid.
+    pub code: Option<String>,
+    /// Why the number is invalid.
+    pub message: Option<String>,
+}
+
+/// How to generate numbers.
+#[napi(object)]
+pub struct GenerateOptions {
+    /// The amount of numbers. Defaults to 1.
+    pub count: Option<u32>,
+    /// Generates numbers never issued to patients.
+    pub test: Option<bool>,
+    /// The same seed always gives the same numbers.
+    pub seed: Option<i64>,
+}
+
+/// The identifier schemes.
+#[derive(PartialEq, Clone, Copy)]
+enum Scheme {
+    Nhs,
+    Chi,
+}
+
+impl FromStr for Scheme {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "nhs" => Ok(Scheme::Nhs),
+            "chi" => Ok(Scheme::Chi),
+            _ => Err(Error::from_reason(format!(
+                "Unknown scheme '{}', expected 'nhs' or 'chi'",
+                name
+            ))),
+        }
+    }
+}
+
+/// The main digits and check digit of a valid number.
+struct Number([Digit; 9], Digit);
+
+/// Validates a number of the given scheme.
+fn parse(input: &str, scheme: Scheme) -> std::result::Result<Number, ValidationError> {
+    match scheme {
+        Scheme::Nhs => nhs::Number::from_str(input).map(|n| Number(*n.digits(), *n.checkdigit())),
+        Scheme::Chi => chi::Number::from_str(input).map(|n| Number(*n.digits(), *n.checkdigit())),
+    }
+}
+
+/// Validates a number of 10 digits, ignoring whitespace.
+#[napi]
+pub fn validate(input: String, scheme: String) -> Result<Validation> {
+    let scheme = scheme.parse()?;
+    let validation = match parse(&input, scheme) {
+        Ok(number) => Validation {
+            valid: true,
+            number: Some(render(&number, scheme, "compact")?),
+            kind: None,
+            code: None,
+            message: None,
+        },
+        Err(err) => Validation {
+            valid: false,
+            number: None,
+            kind: Some(err.kind().to_string()),
+            code: Some(err.code().to_string()),
+            message: Some(err.to_string()),
+        },
+    };
+
+    Ok(validation)
+}
+
+/// Whether a number of 10 digits is valid.
+#[napi]
+pub fn is_valid(input: String, scheme: String) -> Result<bool> {
+    Ok(parse(&input, scheme.parse()?).is_ok())
+}
+
+/// Formats a valid number as `official`, `compact` or `hyphen`. Throws when
+/// the number is not valid.
+#[napi]
+pub fn format(input: String, scheme: String, style: Option<String>) -> Result<String> {
+    let scheme = scheme.parse()?;
+    let number = parse(&input, scheme).map_err(|err| Error::from_reason(err.to_string()))?;
+
+    render(&number, scheme, style.as_deref().unwrap_or("official"))
+}
+
+/// Generates random valid numbers.
+#[napi]
+pub fn generate(scheme: String, options: Option<GenerateOptions>) -> Result<Vec<String>> {
+    let scheme = scheme.parse()?;
+    let (count, test, seed) = match options {
+        Some(options) => (
+            options.count.unwrap_or(1),
+            options.test.unwrap_or(false),
+            options.seed,
+        ),
+        None => (1, false, None),
+    };
+    let mut rng: Box<dyn RngCore> = match seed {
+        Some(seed) => Box::new(ChaCha8Rng::seed_from_u64(seed as u64)),
+        None => Box::new(rand::thread_rng()),
+    };
+
+    (0..count)
+        .map(|_| {
+            let number = match (scheme, test) {
+                (Scheme::Nhs, false) => nhs::lottery_with(&mut rng).map(|n| n.to_string()),
+                (Scheme::Nhs, true) => nhs::test_lottery_with(&mut rng).map(|n| n.to_string()),
+                (Scheme::Chi, false) => chi::lottery_with(&mut rng).map(|n| n.to_string()),
+                (Scheme::Chi, true) => chi::test_lottery_with(&mut rng).map(|n| n.to_string()),
+            };
+
+            number.map_err(|err| Error::from_reason(err.to_string()))
+        })
+        .collect()
+}
+
+/// Renders a number in the given style. CHI numbers have no separators in
+/// their official format.
+fn render(number: &Number, scheme: Scheme, style: &str) -> Result<String> {
+    let separator = match (style, scheme) {
+        ("compact", _) | ("official", Scheme::Chi) => None,
+        ("official", _) => Some(' '),
+        ("hyphen", _) => Some('-'),
+        _ => {
+            return Err(Error::from_reason(format!(
+                "Unknown style '{}', expected 'official', 'compact' or 'hyphen'",
+                style
+            )))
+        }
+    };
+    let Number(digits, checkdigit) = number;
+    let mut rendered = String::with_capacity(12);
+
+    for (idx, digit) in digits.iter().chain(Some(checkdigit)).enumerate() {
+        if let (3 | 6, Some(separator)) = (idx, separator) {
+            rendered.push(separator);
+        }
+        rendered.push_str(&digit.to_string());
+    }
+
+    Ok(rendered)
+}
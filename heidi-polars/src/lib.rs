@@ -0,0 +1,174 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Polars expressions for heidi so lazy frames can validate columns of
+//! numbers without calling back into Python for every row.
+//!
+//! Every expression takes a string column and keeps nulls as nulls.
+//!
+//! ```
+//! use heidi_polars::{chi_dob, nhs_valid};
+//! use polars::prelude::*;
+//!
+//! let frame = df!("nhs" => ["654 100 3238", "6541003239"], "chi" => ["0101990014", "3201990014"])
+//!     .unwrap()
+//!     .lazy()
+//!     .select([nhs_valid(col("nhs")), chi_dob(col("chi"))])
+//!     .collect()
+//!     .unwrap();
+//!
+//! assert_eq!(frame.column("nhs").unwrap().bool().unwrap().get(1), Some(false));
+//! assert_eq!(frame.column("chi").unwrap().null_count(), 1);
+//! ```
+
+use heidi::error::{ErrorKind, ValidationError};
+use heidi::number::Digit;
+use heidi::privacy::{self, Date};
+use heidi::{chi, nhs};
+use polars::prelude::*;
+use std::iter::FromIterator;
+use std::str::FromStr;
+
+/// Maps every string of a column to a value of another type.
+fn map_str<T, F>(expr: Expr, dtype: DataType, f: F) -> Expr
+where
+    T: PolarsDataType,
+    ChunkedArray<T>: IntoColumn + FromIterator<Option<T::Physical<'static>>>,
+    F: Fn(&str) -> Option<T::Physical<'static>> + Send + Sync + 'static,
+{
+    expr.map(
+        move |column| {
+            let mapped: ChunkedArray<T> =
+                column.str()?.into_iter().map(|s| s.and_then(&f)).collect();
+
+            Ok(mapped.with_name(column.name().clone()).into_column())
+        },
+        move |_, field| Ok(Field::new(field.name().clone(), dtype.clone())),
+    )
+}
+
+/// Whether each NHS Number is valid.
+pub fn nhs_valid(expr: Expr) -> Expr {
+    map_str::<BooleanType, _>(expr, DataType::Boolean, |s| {
+        Some(nhs::Number::from_str(s).is_ok())
+    })
+}
+
+/// Whether each CHI Number is valid.
+pub fn chi_valid(expr: Expr) -> Expr {
+    map_str::<BooleanType, _>(expr, DataType::Boolean, |s| {
+        Some(chi::Number::from_str(s).is_ok())
+    })
+}
+
+/// The NHS check digit of each 9-digit stem, or null when no valid number
+/// can be made from it.
+pub fn nhs_checkdigit(expr: Expr) -> Expr {
+    map_str::<UInt8Type, _>(expr, DataType::UInt8, |s| {
+        let number = nhs::Number::new(stem(s).ok()?).ok()?;
+
+        Some(*number.checkdigit())
+    })
+}
+
+/// The CHI check digit of each 9-digit stem, or null when no valid number
+/// can be made from it.
+pub fn chi_checkdigit(expr: Expr) -> Expr {
+    map_str::<UInt8Type, _>(expr, DataType::UInt8, |s| {
+        let number = chi::Number::new(stem(s).ok()?).ok()?;
+
+        Some(*number.checkdigit())
+    })
+}
+
+/// The date of birth of each CHI Number, or null when the number is not
+/// valid. The century is resolved so the date is not after today.
+pub fn chi_dob(expr: Expr) -> Expr {
+    let today = Date::today();
+
+    map_str::<Int32Type, _>(expr, DataType::Int32, move |s| {
+        let number = chi::Number::from_str(s).ok()?;
+
+        Some(privacy::date_of_birth(&number, today).days() as i32)
+    })
+    .cast(DataType::Date)
+}
+
+/// Reads the 9 main digits of a number, ignoring whitespace.
+fn stem(input: &str) -> Result<[Digit; 9], ValidationError> {
+    let digits: Vec<Digit> = input
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| {
+            c.to_digit(10).map(|d| d as Digit).ok_or_else(|| {
+                ValidationError::with_kind(
+                    ErrorKind::Digit,
+                    &format!("The given character '{}' is not a digit", c),
+                )
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    let mut stem = [0; 9];
+
+    if digits.len() != stem.len() {
+        return Err(ValidationError::with_kind(
+            ErrorKind::Length,
+            "The main digits must be nine-digit long",
+        ));
+    }
+
+    stem.copy_from_slice(&digits);
+
+    Ok(stem)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checkdigits() {
+        let frame = df!("stem" => [Some("654100323"), Some("65410"), None])
+            .unwrap()
+            .lazy()
+            .select([
+                nhs_checkdigit(col("stem")).alias("nhs"),
+                chi_checkdigit(col("stem")).alias("chi"),
+            ])
+            .collect()
+            .unwrap();
+
+        assert_eq!(
+            frame.column("nhs").unwrap().u8().unwrap().to_vec(),
+            vec![Some(8), None, None]
+        );
+        assert_eq!(
+            frame.column("chi").unwrap().u8().unwrap().to_vec(),
+            vec![None, None, None]
+        );
+    }
+
+    #[test]
+    fn validity() {
+        let frame = df!("chi" => [Some("0101990014"), Some("6541003238"), None])
+            .unwrap()
+            .lazy()
+            .select([chi_valid(col("chi"))])
+            .collect()
+            .unwrap();
+
+        assert_eq!(
+            frame
+                .column("chi")
+                .unwrap()
+                .bool()
+                .unwrap()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![Some(true), Some(false), None]
+        );
+    }
+}
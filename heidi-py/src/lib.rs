@@ -0,0 +1,247 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Python bindings for heidi so the same validation can be used in Python
+//! pipelines as in Rust services.
+//!
+//! ```python
+//! import heidi
+//!
+//! heidi.NhsNumber.is_valid("654 100 3238")
+//! heidi.ChiNumber.parse("0101990014").dob()
+//! heidi.NhsNumber.generate(10, test=True, seed=42)
+//! ```
+//!
+//! Build with `maturin build --release` from this directory.
+
+use heidi::error::ValidationError;
+use heidi::privacy::{self, Date};
+use heidi::{chi, nhs};
+use pyo3::create_exception;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDate, PyDateAccess};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::str::FromStr;
+
+create_exception!(
+    heidi,
+    InvalidNumberError,
+    PyValueError,
+    "The number is not valid."
+);
+
+/// The exception for an invalid number, with the stable code of the error
+/// as its `code` attribute.
+fn invalid(err: ValidationError) -> PyErr {
+    let exception = InvalidNumberError::new_err(err.to_string());
+
+    Python::with_gil(|py| {
+        exception
+            .value(py)
+            .setattr("code", err.code())
+            .expect("Exceptions take new attributes");
+    });
+
+    exception
+}
+
+/// The source of randomness for generators, seeded when given a seed.
+fn rng(seed: Option<u64>) -> Box<dyn RngCore> {
+    match seed {
+        Some(seed) => Box::new(ChaCha8Rng::seed_from_u64(seed)),
+        None => Box::new(rand::thread_rng()),
+    }
+}
+
+/// An NHS Number.
+#[pyclass(module = "heidi", frozen, eq)]
+#[derive(PartialEq, Clone)]
+struct NhsNumber(nhs::Number);
+
+#[pymethods]
+impl NhsNumber {
+    /// Parses a number of 10 digits, ignoring whitespace.
+    ///
+    /// Raises `InvalidNumberError` when the number is not valid.
+    #[staticmethod]
+    fn parse(input: &str) -> PyResult<Self> {
+        nhs::Number::from_str(input).map(NhsNumber).map_err(invalid)
+    }
+
+    /// Whether the number of 10 digits is valid.
+    #[staticmethod]
+    fn is_valid(input: &str) -> bool {
+        nhs::Number::from_str(input).is_ok()
+    }
+
+    /// Random valid numbers. With `test`, from the range reserved for
+    /// testing which is never issued to patients. The same `seed` always
+    /// gives the same numbers.
+    #[staticmethod]
+    #[pyo3(signature = (count = 1, test = false, seed = None))]
+    fn generate(count: usize, test: bool, seed: Option<u64>) -> PyResult<Vec<Self>> {
+        let mut rng = rng(seed);
+
+        (0..count)
+            .map(|_| {
+                let number = if test {
+                    nhs::test_lottery_with(&mut rng)
+                } else {
+                    nhs::lottery_with(&mut rng)
+                };
+
+                number.map(NhsNumber).map_err(invalid)
+            })
+            .collect()
+    }
+
+    /// The number in the 3-3-4 format, e.g. `654 100 3238`.
+    #[getter]
+    fn official(&self) -> String {
+        let number = self.__str__();
+
+        format!("{} {} {}", &number[..3], &number[3..6], &number[6..])
+    }
+
+    #[getter]
+    fn digits(&self) -> Vec<u16> {
+        self.0
+            .digits()
+            .iter()
+            .map(|&digit| u16::from(digit))
+            .collect()
+    }
+
+    #[getter]
+    fn checkdigit(&self) -> u16 {
+        u16::from(*self.0.checkdigit())
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("NhsNumber('{}')", self.__str__())
+    }
+
+    fn __hash__(&self) -> u64 {
+        self.__str__().parse().unwrap_or_default()
+    }
+}
+
+/// A CHI Number.
+#[pyclass(module = "heidi", frozen, eq)]
+#[derive(PartialEq, Clone)]
+struct ChiNumber(chi::Number);
+
+#[pymethods]
+impl ChiNumber {
+    /// Parses a number of 10 digits, ignoring whitespace.
+    ///
+    /// Raises `InvalidNumberError` when the number is not valid.
+    #[staticmethod]
+    fn parse(input: &str) -> PyResult<Self> {
+        chi::Number::from_str(input).map(ChiNumber).map_err(invalid)
+    }
+
+    /// Whether the number of 10 digits is valid.
+    #[staticmethod]
+    fn is_valid(input: &str) -> bool {
+        chi::Number::from_str(input).is_ok()
+    }
+
+    /// Random valid numbers. With `test`, born on the 30th or 31st of
+    /// February, a date no patient has. The same `seed` always gives the
+    /// same numbers.
+    #[staticmethod]
+    #[pyo3(signature = (count = 1, test = false, seed = None))]
+    fn generate(count: usize, test: bool, seed: Option<u64>) -> PyResult<Vec<Self>> {
+        let mut rng = rng(seed);
+
+        (0..count)
+            .map(|_| {
+                let number = if test {
+                    chi::test_lottery_with(&mut rng)
+                } else {
+                    chi::lottery_with(&mut rng)
+                };
+
+                number.map(ChiNumber).map_err(invalid)
+            })
+            .collect()
+    }
+
+    /// The number as 10 digits with no separators, e.g. `0101990014`.
+    #[getter]
+    fn official(&self) -> String {
+        self.__str__()
+    }
+
+    #[getter]
+    fn digits(&self) -> Vec<u16> {
+        self.0
+            .digits()
+            .iter()
+            .map(|&digit| u16::from(digit))
+            .collect()
+    }
+
+    #[getter]
+    fn checkdigit(&self) -> u16 {
+        u16::from(*self.0.checkdigit())
+    }
+
+    /// The date of birth as a `datetime.date`. The century is resolved so
+    /// the date is not after `reference`, today by default.
+    #[pyo3(signature = (reference = None))]
+    fn dob<'py>(
+        &self,
+        py: Python<'py>,
+        reference: Option<&Bound<'py, PyDate>>,
+    ) -> PyResult<Bound<'py, PyDate>> {
+        let reference = match reference {
+            Some(date) => Date::new(date.get_year() as u16, date.get_month(), date.get_day()),
+            None => Date::today(),
+        };
+        let born = privacy::date_of_birth(&self.0, reference);
+
+        PyDate::new(py, i32::from(born.year), born.month, born.day)
+    }
+
+    /// The sex given by the 9th digit, `F` or `M`.
+    #[getter]
+    fn sex(&self) -> &'static str {
+        privacy::sex(&self.0).as_str()
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("ChiNumber('{}')", self.__str__())
+    }
+
+    fn __hash__(&self) -> u64 {
+        self.__str__().parse().unwrap_or_default()
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "heidi")]
+fn bindings(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add(
+        "InvalidNumberError",
+        m.py().get_type::<InvalidNumberError>(),
+    )?;
+    m.add_class::<NhsNumber>()?;
+    m.add_class::<ChiNumber>()?;
+
+    Ok(())
+}
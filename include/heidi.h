@@ -0,0 +1,79 @@
+/* Copyright 2020 Arnau Siches. Licensed under the MIT license. */
+
+#ifndef HEIDI_H
+#define HEIDI_H
+
+/* Generated by cbindgen from src/ffi.rs. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The outcome of a call.
+typedef enum HeidiStatus {
+  // The number is valid, or the check digit was computed.
+  HEIDI_STATUS_VALID = 0,
+  // The input does not have the expected amount of digits.
+  HEIDI_STATUS_LENGTH = 1,
+  // The input has characters other than digits and whitespace.
+  HEIDI_STATUS_DIGIT = 2,
+  // The given check digit does not match the computed one.
+  HEIDI_STATUS_CHECK_DIGIT = 3,
+  // The main digits compute a check digit of 10 which is never issued.
+  HEIDI_STATUS_UNUSABLE = 4,
+  // The embedded date of birth is not a valid date.
+  HEIDI_STATUS_DATE = 5,
+  // Any other failure.
+  HEIDI_STATUS_OTHER = 6,
+  // A pointer is null or the input is not UTF-8.
+  HEIDI_STATUS_ARGUMENT = 7,
+} HeidiStatus;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Validates a NUL-terminated NHS Number, e.g. `"654 100 3238"`.
+//
+// # Safety
+//
+// `input` must be null or point to a NUL-terminated string.
+enum HeidiStatus heidi_nhs_validate(const char *input);
+
+// Validates a NUL-terminated CHI Number, e.g. `"010199 0014"`.
+//
+// # Safety
+//
+// `input` must be null or point to a NUL-terminated string.
+enum HeidiStatus heidi_chi_validate(const char *input);
+
+// Computes the NHS check digit for the NUL-terminated 9 main digits and
+// writes it to `checkdigit` when the status is valid.
+//
+// # Safety
+//
+// `stem` must be null or point to a NUL-terminated string, and `checkdigit`
+// must be null or point to writable memory.
+enum HeidiStatus heidi_nhs_checkdigit(const char *stem, uint8_t *checkdigit);
+
+// Computes the CHI check digit for the NUL-terminated 9 main digits and
+// writes it to `checkdigit` when the status is valid.
+//
+// # Safety
+//
+// `stem` must be null or point to a NUL-terminated string, and `checkdigit`
+// must be null or point to writable memory.
+enum HeidiStatus heidi_chi_checkdigit(const char *stem, uint8_t *checkdigit);
+
+// A static NUL-terminated lowercase name for the status, e.g. `"checkdigit"`.
+const char *heidi_status_str(enum HeidiStatus status);
+
+// A static NUL-terminated version of the library, e.g. `"0.2.0"`.
+const char *heidi_version(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* HEIDI_H */
@@ -0,0 +1,112 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! A number parsed without knowing its scheme in advance.
+
+use crate::error::ValidationError;
+use crate::scheme::Scheme;
+use crate::{chi, nhs, number};
+use std::fmt;
+use std::str::FromStr;
+
+/// Represents a number with a valid check digit together with the schemes
+/// it validates under.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::any::AnyNumber;
+/// use heidi::number::Format;
+/// use heidi::scheme::Scheme;
+/// use std::str::FromStr;
+///
+/// let number = AnyNumber::from_str("0101990014").unwrap();
+///
+/// assert_eq!(number.schemes(), &[Scheme::Chi]);
+/// assert!(number.to_chi().is_some());
+/// assert!(number.to_nhs().is_none());
+/// assert_eq!(number.to_formatted(Format::Official), "0101990014");
+/// ```
+#[derive(PartialEq, Clone, Debug)]
+pub struct AnyNumber {
+    number: number::Number,
+    schemes: Vec<Scheme>,
+}
+
+impl AnyNumber {
+    /// The schemes the number validates under, see [`Scheme::detect`]. Empty
+    /// when the number is outside the ranges of every scheme.
+    pub fn schemes(&self) -> &[Scheme] {
+        &self.schemes
+    }
+
+    /// Whether the number validates under the given scheme.
+    pub fn is(&self, scheme: Scheme) -> bool {
+        self.schemes.contains(&scheme)
+    }
+
+    /// The number as an NHS Number, if it validates under that scheme.
+    pub fn to_nhs(&self) -> Option<nhs::Number> {
+        if !self.is(Scheme::Nhs) {
+            return None;
+        }
+
+        nhs::Number::new(*self.number.digits()).ok()
+    }
+
+    /// The number as a CHI Number, if it validates under that scheme.
+    pub fn to_chi(&self) -> Option<chi::Number> {
+        if !self.is(Scheme::Chi) {
+            return None;
+        }
+
+        chi::Number::new(*self.number.digits()).ok()
+    }
+
+    /// Writes the number in the given format as its first scheme does, e.g.
+    /// CHI Numbers are officially written compact. See
+    /// [`number::Number::to_formatted`].
+    pub fn to_formatted(&self, format: number::Format) -> String {
+        match (self.schemes.first(), self.to_chi()) {
+            (Some(Scheme::Chi), Some(chi)) => chi.to_formatted(format),
+            _ => self.number.to_formatted(format),
+        }
+    }
+}
+
+impl From<number::Number> for AnyNumber {
+    fn from(number: number::Number) -> Self {
+        AnyNumber {
+            schemes: Scheme::detect(&number),
+            number,
+        }
+    }
+}
+
+impl AsRef<number::Number> for AnyNumber {
+    fn as_ref(&self) -> &number::Number {
+        &self.number
+    }
+}
+
+impl fmt::Display for AnyNumber {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.number.fmt(formatter)
+    }
+}
+
+impl FromStr for AnyNumber {
+    type Err = ValidationError;
+
+    /// Converts a string slice of 10 digits into an [`AnyNumber`].
+    ///
+    /// # Errors
+    ///
+    /// Fails with [ValidationError] when the check digit cannot be verified.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(AnyNumber::from(number::Number::from_str(s)?))
+    }
+}
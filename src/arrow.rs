@@ -0,0 +1,125 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Kernels to validate Arrow columns of numbers in one call.
+//!
+//! Nulls in the input stay null in the output.
+//!
+//! Requires the `arrow` feature.
+
+use crate::error::{ErrorKind, ValidationError};
+use crate::scheme::Scheme;
+use crate::{chi, nhs};
+use arrow_array::types::Int8Type;
+use arrow_array::{
+    BooleanArray, DictionaryArray, GenericStringArray, Int8Array, OffsetSizeTrait, StringArray,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The failure categories in the order they take in the dictionary of
+/// [`categorise`].
+const KINDS: [ErrorKind; 6] = [
+    ErrorKind::Length,
+    ErrorKind::Digit,
+    ErrorKind::CheckDigit,
+    ErrorKind::Unusable,
+    ErrorKind::Date,
+    ErrorKind::Other,
+];
+
+fn check(scheme: Scheme, input: &str) -> Result<(), ValidationError> {
+    match scheme {
+        Scheme::Nhs => nhs::Number::from_str(input).map(|_| ()),
+        Scheme::Chi => chi::Number::from_str(input).map(|_| ()),
+        Scheme::Hcn => Scheme::Hcn.validate(input).map(|_| ()),
+    }
+}
+
+/// Tells whether each number in the column is valid under the scheme.
+///
+/// # Examples
+///
+/// ```
+/// use arrow_array::{BooleanArray, StringArray};
+/// use heidi::scheme::Scheme;
+///
+/// let column = StringArray::from(vec![Some("654 100 3238"), Some("6541003239"), None]);
+///
+/// assert_eq!(
+///     heidi::arrow::validate(&column, Scheme::Nhs),
+///     BooleanArray::from(vec![Some(true), Some(false), None])
+/// );
+/// ```
+pub fn validate<O: OffsetSizeTrait>(array: &GenericStringArray<O>, scheme: Scheme) -> BooleanArray {
+    array
+        .iter()
+        .map(|input| input.map(|input| check(scheme, input).is_ok()))
+        .collect()
+}
+
+/// Tells why each number in the column is invalid under the scheme, as a
+/// dictionary of [`ErrorKind`] names such as `checkdigit`. Valid numbers are
+/// null.
+///
+/// # Examples
+///
+/// ```
+/// use arrow_array::cast::AsArray;
+/// use arrow_array::types::Int8Type;
+/// use arrow_array::StringArray;
+/// use heidi::scheme::Scheme;
+///
+/// let column = StringArray::from(vec!["654 100 3238", "6541003239", "65410"]);
+/// let categories = heidi::arrow::categorise(&column, Scheme::Nhs);
+/// let names = categories.downcast_dict::<StringArray>().unwrap();
+///
+/// assert_eq!(
+///     names.into_iter().collect::<Vec<_>>(),
+///     vec![None, Some("checkdigit"), Some("length")]
+/// );
+/// ```
+pub fn categorise<O: OffsetSizeTrait>(
+    array: &GenericStringArray<O>,
+    scheme: Scheme,
+) -> DictionaryArray<Int8Type> {
+    let keys: Int8Array = array
+        .iter()
+        .map(|input| {
+            let kind = check(scheme, input?).err()?.kind();
+
+            KINDS
+                .iter()
+                .position(|&known| known == kind)
+                .map(|idx| idx as i8)
+        })
+        .collect();
+    let values = StringArray::from_iter_values(KINDS.iter().map(ErrorKind::as_str));
+
+    DictionaryArray::try_new(keys, Arc::new(values)).expect("keys within the dictionary")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::{Array, LargeStringArray};
+
+    #[test]
+    fn validates_columns() {
+        let column = LargeStringArray::from(vec![Some("0101990014"), Some("6541003238"), None]);
+        let valid = validate(&column, Scheme::Chi);
+        let categories = categorise(&column, Scheme::Chi);
+
+        assert_eq!(
+            valid,
+            BooleanArray::from(vec![Some(true), Some(false), None])
+        );
+        assert_eq!(categories.len(), 3);
+        assert!(categories.is_null(0));
+        assert!(categories.is_null(2));
+        assert_eq!(categories.keys().value(1), 4);
+    }
+}
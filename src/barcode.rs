@@ -0,0 +1,356 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Code 128 barcodes of NHS Numbers for patient wristbands.
+//!
+//! The NHS wristband standard asks for the NHS Number as a linear barcode
+//! in Code 128. [`code128`] encodes the 10 digits alone, which is what most
+//! wristband printers and scanners expect. [`gs1`] encodes them as a Global
+//! Service Relation Number under application identifier (8018) for
+//! organisations that identify patients with GS1 keys.
+//!
+//! Every digit pair takes a single symbol in Code 128 subset C so the
+//! barcodes stay short enough for neonatal wristbands.
+//!
+//! [`Barcode::to_svg`] and [`Barcode::write_png`] render labels ready to
+//! print and require the `barcode-image` feature.
+
+use crate::error::{ErrorKind, ValidationError};
+use crate::nhs;
+#[cfg(feature = "barcode-image")]
+use std::io::{self, Write};
+
+/// The GS1 application identifier of a Global Service Relation Number for a
+/// recipient of services, e.g. a patient.
+pub const GSRN_AI: &str = "8018";
+
+const START_C: u8 = 105;
+const FNC1: u8 = 102;
+const STOP: u8 = 106;
+
+/// The modules of blank space rendered on either side of the bars.
+#[cfg(feature = "barcode-image")]
+const QUIET_ZONE: u32 = 10;
+
+/// Bar and space widths of every Code 128 symbol value, starting with a bar.
+const PATTERNS: [&[u8]; 107] = [
+    b"212222", b"222122", b"222221", b"121223", b"121322", b"131222", b"122213", b"122312",
+    b"132212", b"221213", b"221312", b"231212", b"112232", b"122132", b"122231", b"113222",
+    b"123122", b"123221", b"223211", b"221132", b"221231", b"213212", b"223112", b"312131",
+    b"311222", b"321122", b"321221", b"312212", b"322112", b"322211", b"212123", b"212321",
+    b"232121", b"111323", b"131123", b"131321", b"112313", b"132113", b"132311", b"211313",
+    b"231113", b"231311", b"112133", b"112331", b"132131", b"113123", b"113321", b"133121",
+    b"313121", b"211331", b"231131", b"213113", b"213311", b"213131", b"311123", b"311321",
+    b"331121", b"312113", b"312311", b"332111", b"314111", b"221411", b"431111", b"111224",
+    b"111422", b"121124", b"121421", b"141122", b"141221", b"112214", b"112412", b"122114",
+    b"122411", b"142112", b"142211", b"241211", b"221114", b"413111", b"241112", b"134111",
+    b"111242", b"121142", b"121241", b"114212", b"124112", b"124211", b"411212", b"421112",
+    b"421211", b"212141", b"214121", b"412121", b"111143", b"111341", b"131141", b"114113",
+    b"114311", b"411113", b"411311", b"113141", b"114131", b"311141", b"411131", b"211412",
+    b"211214", b"211232", b"2331112",
+];
+
+/// The content of a barcode and the Code 128 symbols encoding it.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Barcode {
+    content: String,
+    text: String,
+    symbols: Vec<u8>,
+}
+
+impl Barcode {
+    /// Encodes an even amount of digits in subset C.
+    fn new(content: String, text: String, gs1: bool) -> Self {
+        let mut symbols = vec![START_C];
+
+        if gs1 {
+            symbols.push(FNC1);
+        }
+
+        for pair in content.as_bytes().chunks(2) {
+            symbols.push((pair[0] - b'0') * 10 + (pair[1] - b'0'));
+        }
+
+        let checksum = symbols
+            .iter()
+            .enumerate()
+            .fold(u32::from(START_C), |sum, (idx, &value)| {
+                sum + idx as u32 * u32::from(value)
+            })
+            % 103;
+
+        symbols.push(checksum as u8);
+        symbols.push(STOP);
+
+        Barcode {
+            content,
+            text,
+            symbols,
+        }
+    }
+
+    /// The data a scanner reads, without the symbology identifier.
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// The text to print under the bars.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The symbol values from the start symbol to the stop symbol, both
+    /// included.
+    pub fn symbols(&self) -> &[u8] {
+        &self.symbols
+    }
+
+    /// The width of every bar and space in modules, starting with a bar.
+    /// Renderers need to add a quiet zone of at least 10 modules on either
+    /// side.
+    pub fn modules(&self) -> Vec<u8> {
+        self.symbols
+            .iter()
+            .flat_map(|&value| PATTERNS[usize::from(value)].iter().map(|w| w - b'0'))
+            .collect()
+    }
+
+    /// Renders the barcode as an SVG document with the text under the bars,
+    /// taking `module` pixels for the narrowest bar and `height` pixels for
+    /// the bars.
+    ///
+    /// Requires the `barcode-image` feature.
+    #[cfg(feature = "barcode-image")]
+    pub fn to_svg(&self, module: u32, height: u32) -> String {
+        let font_size = module * 8;
+        let width = self.width(module);
+        let total_height = height + module * 2 + font_size;
+        let mut path = String::new();
+        let mut x = QUIET_ZONE * module;
+
+        for (idx, modules) in self.modules().into_iter().enumerate() {
+            let bar = u32::from(modules) * module;
+
+            if idx % 2 == 0 {
+                path.push_str(&format!("M{},0h{}v{}h-{}z", x, bar, height, bar));
+            }
+
+            x += bar;
+        }
+
+        format!(
+            concat!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n",
+                "<rect width=\"100%\" height=\"100%\" fill=\"#fff\"/>\n",
+                "<path d=\"{path}\" fill=\"#000\"/>\n",
+                "<text x=\"{middle}\" y=\"{baseline}\" text-anchor=\"middle\" font-family=\"monospace\" font-size=\"{font_size}\">{text}</text>\n",
+                "</svg>\n"
+            ),
+            width = width,
+            height = total_height,
+            path = path,
+            middle = width / 2,
+            baseline = height + module + font_size,
+            font_size = font_size,
+            text = self.text,
+        )
+    }
+
+    /// Writes the bars as a greyscale PNG image, taking `module` pixels for
+    /// the narrowest bar and `height` pixels for the bars. The text is left
+    /// out for the label template to print.
+    ///
+    /// Requires the `barcode-image` feature.
+    ///
+    /// # Errors
+    ///
+    /// Fails when `module` or `height` are 0 or the image cannot be written.
+    #[cfg(feature = "barcode-image")]
+    pub fn write_png<W: Write>(&self, writer: W, module: u32, height: u32) -> io::Result<()> {
+        if module == 0 || height == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The module and height of a barcode image must be at least 1 pixel",
+            ));
+        }
+
+        let mut row = vec![0xff; (QUIET_ZONE * module) as usize];
+
+        for (idx, modules) in self.modules().into_iter().enumerate() {
+            let shade = if idx % 2 == 0 { 0x00 } else { 0xff };
+
+            row.extend(std::iter::repeat_n(
+                shade,
+                (u32::from(modules) * module) as usize,
+            ));
+        }
+
+        row.extend(std::iter::repeat_n(0xff, (QUIET_ZONE * module) as usize));
+
+        let mut encoder = png::Encoder::new(writer, self.width(module), height);
+
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+
+        writer.write_image_data(&row.repeat(height as usize))?;
+        writer.finish()?;
+
+        Ok(())
+    }
+
+    /// The width in pixels of the bars and both quiet zones.
+    #[cfg(feature = "barcode-image")]
+    fn width(&self, module: u32) -> u32 {
+        let modules: u32 = self.modules().iter().map(|&w| u32::from(w)).sum();
+
+        (modules + QUIET_ZONE * 2) * module
+    }
+}
+
+/// Encodes the 10 digits of an NHS Number.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::nhs::Number;
+/// use std::str::FromStr;
+///
+/// let number = Number::from_str("6541003238").unwrap();
+/// let barcode = heidi::barcode::code128(&number);
+///
+/// assert_eq!(barcode.content(), "6541003238");
+/// assert_eq!(barcode.text(), "654 100 3238");
+/// assert_eq!(barcode.symbols(), &[105, 65, 41, 0, 32, 38, 55, 106]);
+/// ```
+pub fn code128(number: &nhs::Number) -> Barcode {
+    let digits = number.to_string();
+    let text = format!("{} {} {}", &digits[..3], &digits[3..6], &digits[6..]);
+
+    Barcode::new(digits, text, false)
+}
+
+/// Encodes an NHS Number as the service reference of a Global Service
+/// Relation Number in GS1-128, under the given GS1 company prefix.
+///
+/// The NHS Number is padded with zeroes to fill the 17 digits before the
+/// check digit, so the prefix can be at most 7 digits long.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::nhs::Number;
+/// use std::str::FromStr;
+///
+/// let number = Number::from_str("6541003238").unwrap();
+/// let barcode = heidi::barcode::gs1(&number, "5012345").unwrap();
+///
+/// assert_eq!(barcode.content(), "8018501234565410032388");
+/// assert_eq!(barcode.text(), "(8018) 501234565410032388");
+/// ```
+///
+/// # Errors
+///
+/// Fails with [ValidationError] when the prefix is not 6 or 7 digits long.
+pub fn gs1(number: &nhs::Number, company_prefix: &str) -> Result<Barcode, ValidationError> {
+    if !company_prefix.chars().all(|c| c.is_ascii_digit()) {
+        return Err(ValidationError::with_kind(
+            ErrorKind::Digit,
+            "GS1 company prefixes must only have digits",
+        ));
+    }
+
+    if !(6..=7).contains(&company_prefix.len()) {
+        return Err(ValidationError::with_kind(
+            ErrorKind::Length,
+            "GS1 company prefixes must be 6 or 7 digits long to fit an NHS Number",
+        ));
+    }
+
+    let gsrn = format!(
+        "{}{:0>width$}",
+        company_prefix,
+        number.to_string(),
+        width = 17 - company_prefix.len()
+    );
+    let gsrn = format!("{}{}", gsrn, gs1_checkdigit(&gsrn));
+    let text = format!("({}) {}", GSRN_AI, gsrn);
+
+    Ok(Barcode::new(format!("{}{}", GSRN_AI, gsrn), text, true))
+}
+
+/// The GS1 modulo 10 check digit of the given digits.
+fn gs1_checkdigit(digits: &str) -> u32 {
+    let sum: u32 = digits
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(idx, byte)| u32::from(byte - b'0') * if idx % 2 == 0 { 3 } else { 1 })
+        .sum();
+
+    (10 - sum % 10) % 10
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn modules() {
+        let number = nhs::Number::from_str("6541003238").unwrap();
+        let plain = code128(&number);
+        let gs1 = gs1(&number, "5012345").unwrap();
+
+        // 11 modules per symbol and 13 for the stop symbol.
+        assert_eq!(
+            plain
+                .modules()
+                .iter()
+                .map(|&w| usize::from(w))
+                .sum::<usize>(),
+            7 * 11 + 13
+        );
+        assert_eq!(&plain.modules()[..6], &[2, 1, 1, 2, 3, 2]);
+        assert_eq!(gs1.symbols()[..2], [START_C, FNC1]);
+        assert_eq!(gs1.symbols().len(), 15);
+        assert_eq!(
+            super::gs1(&number, "501234").unwrap().content(),
+            "8018501234065410032383"
+        );
+    }
+
+    #[test]
+    fn checkdigits() {
+        assert_eq!(gs1_checkdigit("629104150021"), 3);
+        assert_eq!(
+            super::gs1(&nhs::Number::from_str("6541003238").unwrap(), "50123456")
+                .unwrap_err()
+                .kind(),
+            ErrorKind::Length
+        );
+    }
+
+    #[cfg(feature = "barcode-image")]
+    #[test]
+    fn images() {
+        let barcode = code128(&nhs::Number::from_str("6541003238").unwrap());
+        let svg = barcode.to_svg(2, 60);
+        let mut png = Vec::new();
+
+        barcode.write_png(&mut png, 2, 60).unwrap();
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"220\""));
+        // The start symbol in subset C.
+        assert!(svg.contains("M20,0h4v60h-4zM26,0h2v60h-2zM32,0h6v60h-6z"));
+        assert!(svg.contains(">654 100 3238</text>"));
+        assert_eq!(&png[1..4], b"PNG");
+        // The width in the IHDR chunk.
+        assert_eq!(&png[16..20], &220u32.to_be_bytes());
+        assert!(barcode.write_png(Vec::new(), 0, 60).is_err());
+    }
+}
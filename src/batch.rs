@@ -0,0 +1,146 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Tools to validate numbers in bulk.
+
+use crate::error::{ErrorKind, ValidationError};
+use crate::number;
+use crate::region::Region;
+use crate::scheme::Scheme;
+use crate::set::NumberSet;
+use std::collections::BTreeMap;
+
+/// Aggregates validation outcomes incrementally.
+///
+/// Only counters are kept so any amount of records can be fed. Duplicates are
+/// tracked with a [`NumberSet`].
+///
+/// # Examples
+///
+/// ```
+/// use heidi::batch::Stats;
+/// use heidi::error::ErrorKind;
+/// use heidi::nhs::Number;
+/// use std::str::FromStr;
+///
+/// let mut stats = Stats::new();
+///
+/// for input in &["6541003238", "654 100 3238", "6541003239"] {
+///     stats.record(&Number::from_str(input));
+/// }
+///
+/// assert_eq!(stats.total(), 3);
+/// assert_eq!(stats.valid(), 2);
+/// assert_eq!(stats.duplicates(), 1);
+/// assert_eq!(stats.errors()[&ErrorKind::CheckDigit], 1);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    total: usize,
+    valid: usize,
+    duplicates: usize,
+    errors: BTreeMap<ErrorKind, usize>,
+    schemes: BTreeMap<Scheme, usize>,
+    regions: BTreeMap<Region, usize>,
+    seen: NumberSet,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the outcome of validating a single record.
+    pub fn record<N: AsRef<number::Number>>(&mut self, outcome: &Result<N, ValidationError>) {
+        self.total += 1;
+
+        match outcome {
+            Ok(number) => {
+                let number = number.as_ref();
+
+                self.valid += 1;
+
+                for scheme in Scheme::detect(number) {
+                    *self.schemes.entry(scheme).or_insert(0) += 1;
+                }
+
+                *self.regions.entry(Region::of(number.digits())).or_insert(0) += 1;
+
+                if !self.seen.insert(number.digits()) {
+                    self.duplicates += 1;
+                }
+            }
+            Err(error) => {
+                *self.errors.entry(error.kind()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    pub fn valid(&self) -> usize {
+        self.valid
+    }
+
+    pub fn invalid(&self) -> usize {
+        self.total - self.valid
+    }
+
+    /// Number of valid records already seen before.
+    pub fn duplicates(&self) -> usize {
+        self.duplicates
+    }
+
+    /// Invalid records per error category.
+    pub fn errors(&self) -> &BTreeMap<ErrorKind, usize> {
+        &self.errors
+    }
+
+    /// Valid records per scheme they validate under. A record can count
+    /// towards more than one scheme.
+    pub fn schemes(&self) -> &BTreeMap<Scheme, usize> {
+        &self.schemes
+    }
+
+    /// Valid records per allocation region.
+    pub fn regions(&self) -> &BTreeMap<Region, usize> {
+        &self.regions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn counts_per_category() {
+        let mut stats = Stats::new();
+        let inputs = [
+            "0101990014",
+            "9990001235",
+            "123",
+            "0101990014",
+            "12345678X0",
+        ];
+
+        for input in &inputs {
+            stats.record(&number::Number::from_str(input));
+        }
+
+        assert_eq!(stats.total(), 5);
+        assert_eq!(stats.invalid(), 2);
+        assert_eq!(stats.duplicates(), 1);
+        assert_eq!(stats.errors()[&ErrorKind::Length], 1);
+        assert_eq!(stats.errors()[&ErrorKind::Digit], 1);
+        assert_eq!(stats.schemes()[&Scheme::Nhs], 1);
+        assert_eq!(stats.schemes()[&Scheme::Chi], 2);
+        assert_eq!(stats.regions()[&Region::Scotland], 2);
+        assert_eq!(stats.regions()[&Region::Test], 1);
+    }
+}
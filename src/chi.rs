@@ -15,17 +15,42 @@
 //! digit is random as well but it is always even for females and odd for males.
 //!
 //! The last digit of the number is the “check digit” to aid in integrity checks.
+//!
+//! There is no range of CHI numbers reserved for testing. Numbers born on the
+//! 30th or 31st of February are used instead as they cannot belong to anyone.
 
-use crate::error::ValidationError;
+use crate::error::{ErrorKind, ValidationError};
 use crate::number;
+use crate::privacy::{Date, Sex};
+use crate::region;
+use crate::validator;
 use std::convert::TryFrom;
 use std::fmt;
+use std::ops::{Index, RangeInclusive};
 use std::str::FromStr;
 
 /// A digit can be from 0 to 9.
-pub type Digit = u16;
+pub type Digit = u8;
+
+/// The amount of digits of a CHI Number, check digit included.
+pub const LENGTH: usize = number::LENGTH;
+
+/// The span of numbers issued in Scotland, from the 1st of January to the
+/// 31st of December of any year.
+pub const VALID_RANGE: RangeInclusive<u64> = region::SCOTLAND;
+
+/// Validates CHI Numbers with additional local rules.
+pub type Validator = validator::Validator<Number>;
 
 #[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(
+    feature = "diesel",
+    derive(diesel::expression::AsExpression, diesel::deserialize::FromSqlRow)
+)]
+#[cfg_attr(
+    feature = "diesel",
+    diesel(sql_type = diesel::sql_types::Text, sql_type = diesel::sql_types::BigInt)
+)]
 pub struct Number(number::Number);
 
 impl Number {
@@ -38,23 +63,103 @@ impl Number {
     /// ```
     /// use heidi::chi::Number;
     ///
-    /// let n: [u16; 9] = [0, 1, 0, 1, 9, 9, 0, 0, 1];
+    /// let n: [u8; 9] = [0, 1, 0, 1, 9, 9, 0, 0, 1];
     /// let number = Number::new(n);
     ///
     /// assert_eq!(*number.unwrap().checkdigit(), 4);
     /// ```
     pub fn new(digits: [Digit; 9]) -> Result<Self, ValidationError> {
-        validate(&digits)?;
+        // The digits are checked before the date of birth is read from them.
+        let number = number::Number::new(digits)?;
+
+        validate(number.digits())?;
+
+        Ok(Number(number))
+    }
+
+    /// See [`number::Number::from_digits`].
+    pub fn from_digits<I: IntoIterator<Item = Digit>>(digits: I) -> Result<Self, ValidationError> {
+        let number = number::Number::from_digits(digits)?;
+
+        validate(number.digits())?;
+
+        Ok(Number(number))
+    }
 
-        Ok(Number(number::Number::new(digits)?))
+    /// See [`number::Number::from_u16_digits`]. The date of birth is
+    /// validated as well.
+    pub fn from_u16_digits(value: &[u16; 10]) -> Result<Self, ValidationError> {
+        let number = number::Number::from_u16_digits(value)?;
+
+        validate(number.digits())?;
+
+        Ok(Number(number))
+    }
+
+    /// See [`number::Number::get`].
+    pub fn get(&self, index: usize) -> Option<Digit> {
+        self.0.get(index)
     }
 
     pub fn checkdigit(&self) -> &Digit {
-        &self.0.checkdigit()
+        self.0.checkdigit()
+    }
+
+    /// See [`number::Number::explain_checkdigit`].
+    pub fn explain_checkdigit(&self) -> number::Explanation {
+        self.0.explain_checkdigit()
     }
 
     pub fn digits(&self) -> &[Digit; 9] {
-        &self.0.digits()
+        self.0.digits()
+    }
+
+    /// See [`number::Number::to_uuid`].
+    #[cfg(feature = "uuid")]
+    pub fn to_uuid(&self, namespace: uuid::Uuid) -> uuid::Uuid {
+        self.0.to_uuid(namespace)
+    }
+
+    /// See [`number::Number::to_be_bytes`].
+    pub fn to_be_bytes(&self) -> [u8; 5] {
+        self.0.to_be_bytes()
+    }
+
+    /// See [`number::Number::to_formatted`]. CHI Numbers are officially
+    /// written compact.
+    pub fn to_formatted(&self, format: number::Format) -> String {
+        match format {
+            number::Format::Official => self.0.to_formatted(number::Format::Compact),
+            format => self.0.to_formatted(format),
+        }
+    }
+
+    /// See [`number::Number::format_with`].
+    pub fn format_with(&self, options: &number::FormatOptions) -> String {
+        self.0.format_with(options)
+    }
+
+    /// See [`number::Number::from_be_bytes`].
+    pub fn from_be_bytes(bytes: [u8; 5]) -> Result<Self, ValidationError> {
+        let number = number::Number::from_be_bytes(bytes)?;
+
+        validate(number.digits())?;
+
+        Ok(Number(number))
+    }
+}
+
+impl AsRef<number::Number> for Number {
+    fn as_ref(&self) -> &number::Number {
+        &self.0
+    }
+}
+
+impl Index<usize> for Number {
+    type Output = Digit;
+
+    fn index(&self, index: usize) -> &Digit {
+        &self.0[index]
     }
 }
 
@@ -72,6 +177,20 @@ impl TryFrom<&[Digit; 10]> for Number {
     }
 }
 
+impl TryFrom<&[Digit]> for Number {
+    type Error = ValidationError;
+
+    /// See [`number::Number`]'s `TryFrom<&[Digit]>`. The date of birth is
+    /// validated as well.
+    fn try_from(value: &[Digit]) -> Result<Self, Self::Error> {
+        let number = number::Number::try_from(value)?;
+
+        validate(number.digits())?;
+
+        Ok(Number(number))
+    }
+}
+
 impl TryFrom<String> for Number {
     type Error = ValidationError;
 
@@ -126,17 +245,39 @@ impl FromStr for Number {
 /// Checks the date boundaries.
 ///
 /// TODO: Validaton is naive. Does not check for real month limits nor leap years.
-fn validate(digits: &[u16; 9]) -> Result<(), ValidationError> {
+fn validate(digits: &[Digit; 9]) -> Result<(), ValidationError> {
     let day = digits[0] * 10 + digits[1];
     let month = digits[2] * 10 + digits[3];
 
     if day == 0 || day > 31 || month == 0 || month > 12 {
-        return Err(ValidationError::new("Invalid CHI number"));
+        return Err(ValidationError::with_kind(
+            ErrorKind::Date,
+            "Invalid CHI number",
+        ));
     }
 
     Ok(())
 }
 
+/// Parses a CHI Number leniently, ignoring whitespace and hyphens, and
+/// returns its compact form.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::error::ErrorKind;
+///
+/// assert_eq!(heidi::chi::normalize("010199-0014").unwrap(), "0101990014");
+/// assert_eq!(heidi::chi::normalize("6541003238").unwrap_err().kind(), ErrorKind::Date);
+/// ```
+///
+/// # Errors
+///
+/// Fails with [ValidationError] when the number is not valid.
+pub fn normalize(input: &str) -> Result<String, ValidationError> {
+    number::normalize_as::<Number>(input)
+}
+
 /// Returns a random Chi Number.
 ///
 /// If the result is not valid (e.g. the modulus 11 is 10) it will generate a new one.
@@ -150,18 +291,130 @@ fn validate(digits: &[u16; 9]) -> Result<(), ValidationError> {
 /// assert!(number.is_ok());
 /// ```
 pub fn lottery() -> Result<Number, ValidationError> {
-    use rand::prelude::*;
+    lottery_with(&mut rand::thread_rng())
+}
 
-    let mut rng = rand::thread_rng();
-    let mut digits = [0u16; 9];
+/// Returns a random Chi Number drawn from the given source of randomness.
+///
+/// A seeded source gives the same numbers every time, e.g. for test fixtures.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::chi::lottery_with;
+/// use rand::SeedableRng;
+/// use rand_chacha::ChaCha8Rng;
+///
+/// let a = lottery_with(&mut ChaCha8Rng::seed_from_u64(42));
+/// let b = lottery_with(&mut ChaCha8Rng::seed_from_u64(42));
+/// assert_eq!(a, b);
+/// ```
+pub fn lottery_with<R: rand::Rng + ?Sized>(rng: &mut R) -> Result<Number, ValidationError> {
     let distr = rand::distributions::Uniform::new_inclusive(0, 9);
 
-    for x in &mut digits {
-        *x = rng.sample(distr);
+    loop {
+        let mut digits: [Digit; 9] = [0; 9];
+
+        for x in &mut digits {
+            *x = rng.sample(distr);
+        }
+
+        if let Ok(number) = Number::new(digits) {
+            return Ok(number);
+        }
     }
+}
+
+/// Returns a random Chi Number born between `from` and `to`, both included,
+/// and of the given sex if any.
+///
+/// # Errors
+///
+/// Fails with [`ErrorKind::Date`] when `from` is after `to` or either is not
+/// a calendar date.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::chi::demographic_lottery_with;
+/// use heidi::privacy::{date_of_birth, sex, Date, Sex};
+///
+/// let born = Date::new(1985, 3, 12);
+/// let number =
+///     demographic_lottery_with(&mut rand::thread_rng(), born, born, Some(Sex::Female)).unwrap();
+///
+/// assert_eq!(date_of_birth(&number, Date::new(2020, 1, 1)), born);
+/// assert_eq!(sex(&number), Sex::Female);
+/// ```
+pub fn demographic_lottery_with<R: rand::Rng + ?Sized>(
+    rng: &mut R,
+    from: Date,
+    to: Date,
+    sex: Option<Sex>,
+) -> Result<Number, ValidationError> {
+    if !from.is_valid() || !to.is_valid() || from > to {
+        return Err(ValidationError::with_kind(
+            ErrorKind::Date,
+            &format!("There are no dates from {} to {}", from, to),
+        ));
+    }
+
+    let distr = rand::distributions::Uniform::new_inclusive(0, 9);
+
+    loop {
+        let born = Date::from_days(rng.gen_range(from.days(), to.days() + 1));
+        let day = born.day;
+        let month = born.month;
+        let year = (born.year % 100) as Digit;
+        let parity = match sex {
+            Some(Sex::Female) => 0,
+            Some(Sex::Male) => 1,
+            None => rng.gen_range(0, 2),
+        };
+        let digits = [
+            day / 10,
+            day % 10,
+            month / 10,
+            month % 10,
+            year / 10,
+            year % 10,
+            rng.sample(distr),
+            rng.sample(distr),
+            rng.gen_range(0, 5) * 2 + parity,
+        ];
+
+        if let Ok(number) = Number::new(digits) {
+            return Ok(number);
+        }
+    }
+}
+
+/// Returns a random Chi Number born on the 30th or 31st of February, a date
+/// no patient has, for use in tests and demos.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::chi::test_lottery_with;
+///
+/// let number = test_lottery_with(&mut rand::thread_rng()).unwrap();
+/// let digits = number.digits();
+///
+/// assert_eq!(digits[0], 3);
+/// assert_eq!(&digits[2..4], &[0, 2]);
+/// ```
+pub fn test_lottery_with<R: rand::Rng + ?Sized>(rng: &mut R) -> Result<Number, ValidationError> {
+    let distr = rand::distributions::Uniform::new_inclusive(0, 9);
+
+    loop {
+        let mut digits = [3, rng.gen_range(0, 2), 0, 2, 0, 0, 0, 0, 0];
+
+        for x in &mut digits[4..] {
+            *x = rng.sample(distr);
+        }
 
-    match Number::new(digits) {
-        Err(_) => lottery(),
-        number => number,
+        if let Ok(number) = Number::new(digits) {
+            return Ok(number);
+        }
     }
 }
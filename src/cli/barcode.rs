@@ -0,0 +1,88 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Barcode labels of NHS Numbers.
+
+use crate::output::Reporter;
+use heidi::barcode;
+use heidi::nhs::Number;
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// The image formats labels are rendered in.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Image {
+    Svg,
+    Png,
+}
+
+impl FromStr for Image {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "svg" => Ok(Image::Svg),
+            "png" => Ok(Image::Png),
+            _ => Err(format!("Unknown image format '{}'", s)),
+        }
+    }
+}
+
+impl Image {
+    /// The format given, or the one the extension of the destination tells,
+    /// defaulting to SVG.
+    pub fn pick(image: Option<Image>, destination: Option<&Path>) -> Image {
+        image
+            .or_else(|| {
+                destination
+                    .and_then(Path::extension)
+                    .and_then(|extension| extension.to_str())
+                    .and_then(|extension| Image::from_str(extension).ok())
+            })
+            .unwrap_or(Image::Svg)
+    }
+}
+
+/// Writes the barcode of an NHS Number, as GS1-128 when a company prefix is
+/// given.
+pub fn label(
+    reporter: &mut Reporter,
+    number: &str,
+    gs1_prefix: Option<&str>,
+    image: Image,
+    module: u32,
+    height: u32,
+) -> io::Result<()> {
+    let invalid = |e| io::Error::new(io::ErrorKind::InvalidInput, e);
+    let number = Number::from_str(number).map_err(invalid)?;
+    let barcode = match gs1_prefix {
+        Some(prefix) => barcode::gs1(&number, prefix).map_err(invalid)?,
+        None => barcode::code128(&number),
+    };
+
+    match image {
+        Image::Svg => reporter
+            .writer()
+            .write_all(barcode.to_svg(module, height).as_bytes()),
+        Image::Png => barcode.write_png(reporter.writer(), module, height),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_formats() {
+        assert_eq!(Image::pick(None, Some(Path::new("label.PNG"))), Image::Png);
+        assert_eq!(Image::pick(None, Some(Path::new("label"))), Image::Svg);
+        assert_eq!(
+            Image::pick(Some(Image::Svg), Some(Path::new("label.png"))),
+            Image::Svg
+        );
+    }
+}
@@ -0,0 +1,636 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Validation of numbers given as arguments, on stdin or in files.
+
+use crate::convert::{convert, Source};
+use crate::fix::{candidates, Candidate};
+use crate::output::{Outcome, Reporter};
+use crate::watch::{self, Change, Position, Watcher};
+use crate::Typeid;
+use crate::{input, jobs};
+use clap::arg_enum;
+use heidi::any::AnyNumber;
+use heidi::batch::Stats;
+use heidi::error::{ErrorKind, ValidationError};
+use heidi::i18n::{self, Language, Text};
+use heidi::number::{self, Digit, Format};
+use heidi::scheme::Scheme;
+use heidi::{chi, nhs};
+use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt::Write;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+arg_enum! {
+    /// How to treat blank lines in a file.
+    #[derive(PartialEq, Debug)]
+    pub enum Blank {
+        Skip,
+        Invalid,
+    }
+}
+
+arg_enum! {
+    /// The type to validate numbers as, or `auto` to try every type.
+    #[derive(PartialEq, Clone, Debug)]
+    pub enum Selector {
+        Nhs,
+        Chi,
+        Hcn,
+        Auto,
+    }
+}
+
+impl Selector {
+    /// The type selected, if any.
+    pub fn typeid(&self) -> Option<Typeid> {
+        match self {
+            Selector::Nhs => Some(Typeid::Nhs),
+            Selector::Chi => Some(Typeid::Chi),
+            Selector::Hcn => Some(Typeid::Hcn),
+            Selector::Auto => None,
+        }
+    }
+}
+
+/// Splits the positional arguments of `check` into the type and the number,
+/// if any.
+///
+/// A type given before the number takes precedence over `declared`, e.g.
+/// from `--type` or the environment. A single argument is taken as the type
+/// when it names one and as the number otherwise.
+pub fn arguments(
+    args: &[String],
+    declared: Option<Selector>,
+) -> io::Result<(Selector, Option<String>)> {
+    let positional = args.first().and_then(|arg| Selector::from_str(arg).ok());
+    let missing = || {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "The type is missing: give nhs, chi, hcn or auto before the number, in --type, in \
+             HEIDI_TYPE or in the configuration file",
+        )
+    };
+
+    match (args, positional, declared) {
+        ([_, number], Some(_type), _) => Ok((_type, Some(number.clone()))),
+        ([arg, _], None, _) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("'{}' is not a type: expected nhs, chi, hcn or auto", arg),
+        )),
+        ([_], Some(_type), _) => Ok((_type, None)),
+        ([number], None, Some(_type)) => Ok((_type, Some(number.clone()))),
+        ([], _, Some(_type)) => Ok((_type, None)),
+        _ => Err(missing()),
+    }
+}
+
+/// How to read lines from a file and report them.
+#[derive(Debug)]
+pub struct LineOptions {
+    pub blank: Blank,
+    /// Lines starting with this prefix are skipped. Empty to disable.
+    pub comment: String,
+    /// The byte ending each line, usually `\n`.
+    pub separator: u8,
+    /// Whether to explain why numbers are invalid.
+    pub explain: bool,
+}
+
+/// Parses a number of the given type and returns it together with its
+/// official format.
+pub fn validate(_type: &Typeid, number: &str) -> Result<(number::Number, String), ValidationError> {
+    match _type {
+        Typeid::Nhs => {
+            nhs::Number::from_str(number).map(|n| (n.as_ref().clone(), format!("{:#}", n)))
+        }
+        Typeid::Chi => {
+            chi::Number::from_str(number).map(|n| (n.as_ref().clone(), format!("{:#}", n)))
+        }
+        Typeid::Hcn => Scheme::Hcn
+            .validate(number)
+            .map(|n| (n.clone(), n.to_formatted(Format::Official))),
+    }
+}
+
+/// Parses a number of the given type, or of any type when missing, and
+/// returns it together with its official format and the types it validates
+/// as, e.g. `nhs,chi`.
+pub fn validate_as(
+    _type: Option<&Typeid>,
+    number: &str,
+) -> Result<(number::Number, String, Cow<'static, str>), ValidationError> {
+    match _type {
+        Some(_type) => {
+            validate(_type, number).map(|(n, official)| (n, official, kind(_type).into()))
+        }
+        None => AnyNumber::from_str(number).and_then(|any| {
+            if any.schemes().is_empty() {
+                return Err(ValidationError::with_kind(
+                    ErrorKind::Other,
+                    &format!("{} is not in the range of any scheme", any),
+                ));
+            }
+
+            let official = any.to_formatted(Format::Official);
+            let schemes: Vec<&str> = any.schemes().iter().map(Scheme::as_str).collect();
+
+            Ok((any.as_ref().clone(), official, schemes.join(",").into()))
+        }),
+    }
+}
+
+/// A short lowercase name for the type.
+pub fn kind(_type: &Typeid) -> &'static str {
+    match _type {
+        Typeid::Nhs => "nhs",
+        Typeid::Chi => "chi",
+        Typeid::Hcn => "hcn",
+    }
+}
+
+/// The name of the given type, or `any` without one.
+fn any(_type: Option<&Typeid>) -> Cow<'static, str> {
+    _type.map_or("any", kind).into()
+}
+
+/// Validates a number and reports the result. Without a type, the number is
+/// tried under every type and reported with the ones it validates as.
+/// Returns whether it is valid.
+///
+/// When printing, a valid number is written alone in the given format and an
+/// invalid one is told on stderr, so the output can be piped. When
+/// explaining, an invalid number is reported with its explanation.
+pub fn one(
+    reporter: &mut Reporter,
+    _type: Option<&Typeid>,
+    number: &str,
+    print: Option<Format>,
+    explain: bool,
+) -> io::Result<bool> {
+    let outcome = tell(reporter, _type, number, print, explain)?;
+
+    Ok(outcome.is_ok())
+}
+
+/// Validates numbers separated by `separator`, reporting each. Returns the
+/// summary.
+pub fn each<R: BufRead>(
+    reporter: &mut Reporter,
+    _type: Option<&Typeid>,
+    reader: R,
+    separator: u8,
+    print: Option<Format>,
+    explain: bool,
+) -> io::Result<Stats> {
+    let mut stats = Stats::new();
+
+    for line in input::records(reader, separator) {
+        let line = line?;
+        let number = line.trim();
+
+        if number.is_empty() {
+            continue;
+        }
+
+        stats.record(&tell(reporter, _type, number, print, explain)?);
+
+        if reporter.gate().stops(&stats) {
+            break;
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Validates and reports a single number. Returns the outcome.
+fn tell(
+    reporter: &mut Reporter,
+    _type: Option<&Typeid>,
+    number: &str,
+    print: Option<Format>,
+    explain: bool,
+) -> io::Result<Result<number::Number, ValidationError>> {
+    let validated = validate_as(_type, number);
+
+    if let Some(target) = print {
+        let source = match _type {
+            None => Source::Any,
+            Some(Typeid::Nhs) => Source::Nhs,
+            Some(Typeid::Chi) => Source::Chi,
+            Some(Typeid::Hcn) => Source::Any,
+        };
+        let converted = match &validated {
+            Ok((n, _, _)) => convert(&source, &n.to_string(), target),
+            Err(e) => Err(e.clone()),
+        };
+
+        match converted {
+            Ok(converted) => reporter.print(&converted)?,
+            Err(e) => reporter.warn(&format!("'{}' is invalid: {}.", number, e)),
+        }
+
+        return Ok(validated.map(|(n, _, _)| n));
+    }
+
+    let (kind, parsed, result) = match validated {
+        Ok((n, formatted, kind)) => (kind, Ok(n), Ok(formatted)),
+        Err(e) => (any(_type), Err(e.clone()), Err(e)),
+    };
+
+    let outcome = Outcome {
+        kind: &kind,
+        source: None,
+        location: None,
+        column: None,
+        input: number,
+        result,
+    };
+
+    report(reporter, _type, &outcome, explain)?;
+
+    Ok(parsed)
+}
+
+/// Reports an outcome, explaining why it is invalid when asked to.
+fn report(
+    reporter: &mut Reporter,
+    _type: Option<&Typeid>,
+    outcome: &Outcome,
+    explain: bool,
+) -> io::Result<()> {
+    if !explain {
+        return reporter.outcome(outcome);
+    }
+
+    let (text, fields) = explanation(
+        _type,
+        outcome.input,
+        outcome.result.as_ref().err(),
+        reporter.lang(),
+    );
+
+    reporter.explained(outcome, &text, fields)
+}
+
+/// Explains why a number is invalid: the category of the error, the check
+/// digit given against the one its main digits compute, and the valid numbers
+/// one typing mistake away. Returns the explanation in plain text, in the
+/// given language, and as fields, which are empty for valid numbers.
+fn explanation(
+    _type: Option<&Typeid>,
+    input: &str,
+    error: Option<&ValidationError>,
+    language: Language,
+) -> (String, Vec<(&'static str, Value)>) {
+    let error = match error {
+        Some(error) => error,
+        None => {
+            return (
+                String::new(),
+                vec![
+                    ("given_checkdigit", Value::Null),
+                    ("expected_checkdigit", Value::Null),
+                    ("candidates", json!([])),
+                ],
+            )
+        }
+    };
+    let mut text = String::new();
+    let digits: Option<Vec<Digit>> = input
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_digit(10).map(|d| d as Digit))
+        .collect();
+    let checkdigits = digits.filter(|digits| digits.len() == 10).map(|digits| {
+        let main: [Digit; 9] = digits[..9].try_into().expect("nine digits");
+        let expected = number::Number::new(main).map(|n| *n.checkdigit()).ok();
+
+        (digits[9], expected)
+    });
+    let types: &[Typeid] = match _type {
+        Some(Typeid::Nhs) => &[Typeid::Nhs],
+        Some(Typeid::Chi) => &[Typeid::Chi],
+        Some(Typeid::Hcn) => &[Typeid::Hcn],
+        None => &[Typeid::Nhs, Typeid::Chi, Typeid::Hcn],
+    };
+    let mut found: Vec<Candidate> = Vec::new();
+
+    for _type in types {
+        for candidate in candidates(_type, input) {
+            if !found.iter().any(|c| c.number == candidate.number) {
+                found.push(candidate);
+            }
+        }
+    }
+
+    writeln!(
+        text,
+        "{}",
+        i18n::render(
+            Text::Category,
+            language,
+            &[("kind", &i18n::kind(error.kind(), language))]
+        )
+    )
+    .expect("write to string");
+
+    match checkdigits {
+        Some((given, Some(expected))) => writeln!(
+            text,
+            "{}",
+            i18n::render(
+                Text::CheckDigitGiven,
+                language,
+                &[("given", &given), ("expected", &expected)]
+            )
+        ),
+        Some((given, None)) => writeln!(
+            text,
+            "{}",
+            i18n::render(Text::CheckDigitUnusable, language, &[("given", &given)])
+        ),
+        None => Ok(()),
+    }
+    .expect("write to string");
+
+    if found.is_empty() {
+        writeln!(text, "{}", i18n::text(Text::NoCandidates, language))
+    } else {
+        writeln!(text, "{}", i18n::text(Text::Candidates, language)).and_then(|_| {
+            found
+                .iter()
+                .try_for_each(|c| writeln!(text, "  {} ({})", c.number, c.edit.describe(language)))
+        })
+    }
+    .expect("write to string");
+
+    let candidates: Vec<_> = found
+        .iter()
+        .map(|candidate| {
+            json!({
+                "number": candidate.number,
+                "edit": candidate.edit.to_string(),
+            })
+        })
+        .collect();
+
+    (
+        text,
+        vec![
+            (
+                "given_checkdigit",
+                json!(checkdigits.map(|(given, _)| given)),
+            ),
+            (
+                "expected_checkdigit",
+                json!(checkdigits.and_then(|(_, expected)| expected)),
+            ),
+            ("candidates", json!(candidates)),
+        ],
+    )
+}
+
+/// Validates files with a number per line, reporting the invalid ones with
+/// their line number and a summary of all of them. Returns the summary.
+///
+/// Lines are validated in batches using up to `jobs` threads.
+pub fn file(
+    reporter: &mut Reporter,
+    _type: Option<&Typeid>,
+    paths: &[PathBuf],
+    options: &LineOptions,
+    jobs: usize,
+) -> io::Result<Stats> {
+    let mut stats = Stats::new();
+    let mut skipped = 0;
+
+    if paths.len() > 1 {
+        reporter.progress().start(None);
+    }
+
+    for path in paths {
+        if reporter.gate().stops(&stats) {
+            break;
+        }
+
+        let reader = input::open(path, reporter.progress())?;
+        let records = input::records(reader, options.separator).enumerate();
+
+        skipped += lines(reporter, _type, path, records, options, jobs, &mut stats)?;
+    }
+
+    reporter.summary(&stats, skipped)?;
+
+    Ok(stats)
+}
+
+/// Watches files with a number per line, validating them as they change
+/// until the gate stops the run. Returns the summary.
+///
+/// New files are validated once they stop changing, as they may be
+/// compressed, while lines appended to plain files are validated as soon as
+/// they are complete. Files truncated or rewritten are validated again.
+pub fn watch(
+    reporter: &mut Reporter,
+    _type: Option<&Typeid>,
+    patterns: &[String],
+    options: &LineOptions,
+    jobs: usize,
+) -> io::Result<Stats> {
+    let mut watcher = Watcher::new(patterns);
+    let mut positions: HashMap<PathBuf, Position> = HashMap::new();
+    let mut stats = Stats::new();
+
+    while !reporter.gate().stops(&stats) {
+        for (path, change) in watcher.poll()? {
+            if reporter.gate().stops(&stats) {
+                break;
+            }
+
+            if change == Change::Created {
+                positions.insert(path.clone(), Position::default());
+            }
+
+            if input::is_compressed(&path)? {
+                if change == Change::Settled {
+                    let reader = input::open(&path, reporter.progress())?;
+                    let records = input::records(reader, options.separator).enumerate();
+
+                    lines(reporter, _type, &path, records, options, jobs, &mut stats)?;
+                }
+                continue;
+            }
+
+            let position = positions.entry(path.clone()).or_default();
+            let first = position.records;
+            let records = watch::tail(
+                &path,
+                position,
+                options.separator,
+                change == Change::Settled,
+            )?
+            .enumerate()
+            .map(|(idx, record)| (first + idx, record));
+
+            lines(reporter, _type, &path, records, options, jobs, &mut stats)?;
+        }
+
+        watch::idle();
+    }
+
+    Ok(stats)
+}
+
+/// Validates the lines of a single file, given with their zero-based index,
+/// recording them in `stats`. Returns the amount of lines skipped.
+fn lines<I: Iterator<Item = (usize, io::Result<String>)>>(
+    reporter: &mut Reporter,
+    _type: Option<&Typeid>,
+    path: &Path,
+    mut lines: I,
+    options: &LineOptions,
+    jobs: usize,
+    stats: &mut Stats,
+) -> io::Result<usize> {
+    let source = path.display().to_string();
+    let mut skipped = 0;
+    let mut batch: Vec<(usize, String)> = Vec::with_capacity(jobs::BATCH);
+
+    while !reporter.gate().stops(stats) {
+        batch.clear();
+
+        for (idx, line) in lines.by_ref() {
+            let line = line?;
+            let number = line.trim();
+
+            if number.is_empty() && options.blank == Blank::Skip
+                || !options.comment.is_empty() && number.starts_with(&options.comment)
+            {
+                skipped += 1;
+                continue;
+            }
+
+            batch.push((idx + 1, number.to_string()));
+            if batch.len() == jobs::BATCH {
+                break;
+            }
+        }
+
+        if batch.is_empty() {
+            break;
+        }
+
+        let results = jobs::map(jobs, &batch, |(_, number)| validate_as(_type, number));
+
+        for ((line, number), result) in batch.iter().zip(results) {
+            let (kind, parsed, result) = match result {
+                Ok((n, formatted, kind)) => (kind, Ok(n), Ok(formatted)),
+                Err(e) => (any(_type), Err(e.clone()), Err(e)),
+            };
+
+            let outcome = Outcome {
+                kind: &kind,
+                source: Some(&source),
+                location: Some(line.to_string()),
+                column: None,
+                input: number,
+                result,
+            };
+
+            report(reporter, _type, &outcome, options.explain)?;
+            stats.record(&parsed);
+
+            if reporter.gate().stops(stats) {
+                break;
+            }
+        }
+    }
+
+    Ok(skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_types() {
+        let kind =
+            |_type: Option<&Typeid>, number| validate_as(_type, number).map(|(_, _, kind)| kind);
+
+        assert_eq!(kind(None, "6541003238").unwrap(), "nhs");
+        assert_eq!(kind(None, "0101990014").unwrap(), "chi");
+        assert_eq!(kind(None, "3200000015").unwrap(), "hcn");
+        assert!(kind(None, "8931774583").is_err());
+        assert_eq!(kind(Some(&Typeid::Chi), "0101990014").unwrap(), "chi");
+        assert_eq!(validate_as(None, "0101990014").unwrap().1, "0101990014");
+        assert_eq!(validate_as(None, "6541003238").unwrap().1, "654 100 3238");
+        assert!(kind(None, "6541003237").is_err());
+        assert_eq!(any(None), "any");
+    }
+
+    #[test]
+    fn explanations() {
+        let error = validate(&Typeid::Nhs, "6541003237").unwrap_err();
+        let (text, fields) = explanation(
+            Some(&Typeid::Nhs),
+            "6541003237",
+            Some(&error),
+            Language::English,
+        );
+
+        assert!(text.contains("Check digit: 7 given, 8 expected"));
+        assert!(text.contains("654 100 3238 (position 10: 8 instead of 7)"));
+        assert_eq!(fields[0], ("given_checkdigit", json!(7)));
+        assert_eq!(fields[1], ("expected_checkdigit", json!(8)));
+
+        let (text, _) = explanation(None, "654100323", Some(&error), Language::English);
+
+        assert!(!text.contains("Check digit"));
+        assert!(explanation(None, "6541003238", None, Language::English)
+            .0
+            .is_empty());
+
+        let (text, _) = explanation(
+            Some(&Typeid::Nhs),
+            "6541003237",
+            Some(&error),
+            Language::Welsh,
+        );
+
+        assert!(text.contains("Digid gwirio: rhoddwyd 7, disgwylir 8"));
+        assert!(text.contains("654 100 3238 (safle 10: 8 yn lle 7)"));
+    }
+
+    #[test]
+    fn positional_arguments() {
+        let args = |args: &[&str], declared| {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+
+            arguments(&args, declared).ok()
+        };
+        let number = Some("0101990014".to_string());
+
+        assert_eq!(
+            args(&["nhs", "0101990014"], Some(Selector::Chi)),
+            Some((Selector::Nhs, number.clone()))
+        );
+        assert_eq!(
+            args(&["0101990014"], Some(Selector::Chi)),
+            Some((Selector::Chi, number))
+        );
+        assert_eq!(args(&["auto"], None), Some((Selector::Auto, None)));
+        assert_eq!(args(&[], Some(Selector::Chi)), Some((Selector::Chi, None)));
+        assert_eq!(args(&["0101990014"], None), None);
+        assert_eq!(args(&["x", "0101990014"], None), None);
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Detection of the schemes and region of numbers.
+
+use crate::input;
+use crate::output::{Outcome, Reporter};
+use heidi::number;
+use heidi::region::Region;
+use heidi::scheme::Scheme;
+use serde_json::json;
+use std::io::{self, BufRead};
+use std::str::FromStr;
+
+/// Reports the schemes a number validates under and the region it is
+/// allocated to. Returns whether it is valid under any scheme.
+pub fn one(reporter: &mut Reporter, input: &str) -> io::Result<bool> {
+    match number::Number::from_str(input) {
+        Ok(number) => {
+            let schemes: Vec<&str> = Scheme::detect(&number).iter().map(Scheme::as_str).collect();
+            let region = Region::of(number.digits());
+
+            reporter.report(
+                &format!("{}: {} ({})\n", input, schemes.join(", "), region),
+                vec![
+                    ("input", json!(input)),
+                    ("valid", json!(true)),
+                    ("schemes", json!(schemes)),
+                    ("region", json!(region.as_str())),
+                ],
+            )?;
+
+            Ok(true)
+        }
+        Err(e) => {
+            reporter.outcome(&Outcome {
+                kind: "any",
+                source: None,
+                location: None,
+                column: None,
+                input,
+                result: Err(e),
+            })?;
+
+            Ok(false)
+        }
+    }
+}
+
+/// Classifies numbers separated by `separator`. Returns whether all of them
+/// are valid.
+pub fn each<R: BufRead>(reporter: &mut Reporter, reader: R, separator: u8) -> io::Result<bool> {
+    let mut valid = true;
+
+    for line in input::records(reader, separator) {
+        let line = line?;
+        let number = line.trim();
+
+        if !number.is_empty() && !one(reporter, number)? {
+            valid = false;
+        }
+    }
+
+    Ok(valid)
+}
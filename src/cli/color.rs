@@ -0,0 +1,88 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Colours for plain text output on terminals.
+//!
+//! Colours are only used when the stream written to is a terminal, and never
+//! with `--no-color` or a non-empty `NO_COLOR` environment variable. See
+//! <https://no-color.org>.
+
+use heidi::error::{ErrorKind, ValidationError};
+use std::env;
+
+/// An ANSI style.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Style(&'static str);
+
+pub const PASS: Style = Style("1;32");
+pub const FAIL: Style = Style("1;31");
+pub const OFFENDING: Style = Style("4;31");
+
+/// Tells whether colours are allowed at all, regardless of the stream.
+pub fn allowed(no_color: bool) -> bool {
+    !no_color && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+/// Wraps the text in the given style when `on`.
+pub fn paint(text: &str, style: Style, on: bool) -> String {
+    if on {
+        format!("\x1b[{}m{}\x1b[0m", style.0, text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Returns the painted text followed by a space when `on`, to mark the
+/// start of a line, and nothing otherwise.
+pub fn marker(text: &str, style: Style, on: bool) -> String {
+    if on {
+        format!("{} ", paint(text, style, true))
+    } else {
+        String::new()
+    }
+}
+
+/// Returns the input with the characters that make it invalid painted when
+/// `on`: the check digit when it does not match, or the characters that are
+/// not digits.
+pub fn offending(input: &str, error: &ValidationError, on: bool) -> String {
+    if !on {
+        return input.to_string();
+    }
+
+    let last = input.rfind(|c: char| c.is_ascii_digit());
+
+    input
+        .char_indices()
+        .map(|(idx, c)| {
+            let offending = match error.kind() {
+                ErrorKind::CheckDigit => Some(idx) == last,
+                ErrorKind::Digit => !c.is_ascii_digit() && !c.is_whitespace(),
+                _ => false,
+            };
+
+            paint(&c.to_string(), OFFENDING, offending)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use heidi::nhs::Number;
+    use std::str::FromStr;
+
+    #[test]
+    fn offending_characters() {
+        let error = Number::from_str("654 100 3237").unwrap_err();
+
+        assert_eq!(
+            offending("654 100 3237", &error, true),
+            "654 100 323\x1b[4;31m7\x1b[0m"
+        );
+        assert_eq!(offending("654 100 3237", &error, false), "654 100 3237");
+    }
+}
@@ -0,0 +1,94 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Validation of columns in Parquet files.
+
+use crate::columns::Checker;
+use crate::output::Reporter;
+use crate::Typeid;
+use heidi::batch::Stats;
+use parquet::errors::ParquetError;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::record::Field;
+use parquet::schema::types::Type;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Validates the given columns of a Parquet file, reading only those
+/// columns one row group at a time.
+///
+/// Values are reported by row number, starting at 1. Null values are not
+/// validated. Integer values are padded with leading zeros to 10 digits as
+/// numbers starting with 0 lose them when stored as integers.
+pub fn check(
+    reporter: &mut Reporter,
+    _type: &Typeid,
+    input: &Path,
+    columns: &[String],
+    jobs: usize,
+) -> io::Result<Stats> {
+    let reader = SerializedFileReader::new(File::open(input)?).map_err(error)?;
+    let schema = reader.metadata().file_metadata().schema();
+    let mut fields = Vec::new();
+
+    for column in columns {
+        let field = schema
+            .get_fields()
+            .iter()
+            .find(|field| field.name() == column)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Column '{}' not found", column),
+                )
+            })?;
+
+        fields.push(Arc::clone(field));
+    }
+
+    let projection = Type::group_type_builder(schema.name())
+        .with_fields(fields)
+        .build()
+        .map_err(error)?;
+    let mut checker = Checker::new(reporter, _type, &input.display().to_string(), jobs);
+
+    for (idx, row) in reader
+        .get_row_iter(Some(projection))
+        .map_err(error)?
+        .enumerate()
+    {
+        let row = row.map_err(error)?;
+        let values: Vec<(String, String)> = row
+            .get_column_iter()
+            .filter_map(|(name, field)| {
+                let value = match field {
+                    Field::Null => return None,
+                    Field::Str(value) => value.clone(),
+                    Field::Bytes(value) => String::from_utf8_lossy(value.data()).into_owned(),
+                    Field::Int(value) => format!("{:010}", value),
+                    Field::Long(value) => format!("{:010}", value),
+                    Field::UInt(value) => format!("{:010}", value),
+                    Field::ULong(value) => format!("{:010}", value),
+                    other => other.to_string(),
+                };
+
+                Some((name.clone(), value))
+            })
+            .collect();
+        checker.push(((idx + 1).to_string(), values))?;
+        if checker.stopped() {
+            break;
+        }
+    }
+
+    checker.finish()
+}
+
+fn error(err: ParquetError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
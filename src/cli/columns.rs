@@ -0,0 +1,149 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Validation of named columns in tabular inputs.
+
+use crate::check::{kind, validate};
+use crate::jobs;
+use crate::output::{Outcome, Reporter};
+use crate::Typeid;
+use heidi::batch::Stats;
+use heidi::error::ValidationError;
+use std::io;
+
+/// Validates the values of some columns row by row, reporting the invalid
+/// ones.
+#[derive(Debug)]
+pub struct Checker<'a> {
+    reporter: &'a mut Reporter,
+    _type: &'a Typeid,
+    source: String,
+    stats: Stats,
+    jobs: usize,
+    pending: Vec<Row>,
+}
+
+/// A location, e.g. a line number, and the `(column, value)` pairs to
+/// validate there.
+pub type Row = (String, Vec<(String, String)>);
+
+impl<'a> Checker<'a> {
+    /// Creates a checker validating rows in batches using up to `jobs`
+    /// threads.
+    pub fn new(reporter: &'a mut Reporter, _type: &'a Typeid, source: &str, jobs: usize) -> Self {
+        reporter.progress().start(None);
+
+        Checker {
+            reporter,
+            _type,
+            source: source.to_string(),
+            stats: Stats::new(),
+            jobs,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Queues a row for validation. Rows are validated and reported in
+    /// order once enough of them are queued.
+    #[cfg_attr(
+        not(any(feature = "parquet", feature = "sqlite", feature = "xlsx")),
+        allow(dead_code)
+    )]
+    pub fn push(&mut self, row: Row) -> io::Result<()> {
+        if self.stopped() {
+            return Ok(());
+        }
+
+        self.pending.push(row);
+
+        if self.pending.len() >= jobs::BATCH {
+            let pending = std::mem::take(&mut self.pending);
+            self.batch(&pending)?;
+        }
+
+        Ok(())
+    }
+
+    /// Validates several rows in parallel and reports them in order.
+    ///
+    /// Returns the columns with an invalid value and their error for each
+    /// row reported, which are fewer than the given rows if the run stopped.
+    pub fn batch<'r>(
+        &mut self,
+        rows: &'r [Row],
+    ) -> io::Result<Vec<Vec<(&'r str, ValidationError)>>> {
+        let _type = self._type;
+        let results = jobs::map(self.jobs, rows, |(_, values)| {
+            values
+                .iter()
+                .map(|(_, value)| validate(_type, value.trim()))
+                .collect::<Vec<_>>()
+        });
+        let mut errors = Vec::with_capacity(rows.len());
+
+        for ((location, values), results) in rows.iter().zip(results) {
+            if self.stopped() {
+                break;
+            }
+
+            let mut row = Vec::new();
+
+            for ((column, value), result) in values.iter().zip(results) {
+                let (outcome, result) = match result {
+                    Ok((n, formatted)) => (Ok(n), Ok(formatted)),
+                    Err(e) => {
+                        row.push((column.as_str(), e.clone()));
+                        (Err(e.clone()), Err(e))
+                    }
+                };
+
+                self.reporter.outcome(&Outcome {
+                    kind: kind(self._type),
+                    source: Some(&self.source),
+                    location: Some(location.clone()),
+                    column: Some(column),
+                    input: value.trim(),
+                    result,
+                })?;
+                self.stats.record(&outcome);
+            }
+
+            errors.push(row);
+        }
+
+        Ok(errors)
+    }
+
+    /// Whether the run met the conditions to stop early. Rows pushed
+    /// afterwards are ignored.
+    pub fn stopped(&self) -> bool {
+        self.reporter.gate().stops(&self.stats)
+    }
+
+    /// Validates the queued rows, reports the summary and returns the
+    /// statistics.
+    pub fn finish(mut self) -> io::Result<Stats> {
+        let pending = std::mem::take(&mut self.pending);
+
+        self.batch(&pending)?;
+        self.reporter.summary(&self.stats, 0)?;
+
+        Ok(self.stats)
+    }
+}
+
+/// Returns the position of each of the given columns in the header.
+pub fn positions(header: &[&str], columns: &[String]) -> Result<Vec<usize>, String> {
+    columns
+        .iter()
+        .map(|column| {
+            header
+                .iter()
+                .position(|name| name == column)
+                .ok_or_else(|| format!("Column '{}' not found", column))
+        })
+        .collect()
+}
@@ -0,0 +1,126 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Completion of 9-digit stems with their check digit.
+
+use crate::check::kind;
+use crate::output::Reporter;
+use crate::{Format, Typeid};
+use heidi::error::ValidationError;
+use heidi::i18n::{self, Language, Text};
+use heidi::number::{self, Digit};
+use heidi::scheme::Scheme;
+use heidi::{chi, nhs};
+use serde_json::json;
+use std::convert::TryInto;
+use std::io;
+
+/// Reports the number made of the given 9 main digits and their check
+/// digit. Returns whether the stem is usable, i.e. its check digit is not 10
+/// and, for CHI numbers, it starts with a valid date of birth.
+pub fn stem(
+    reporter: &mut Reporter,
+    _type: &Typeid,
+    input: &str,
+    format: Format,
+) -> io::Result<bool> {
+    let result = complete(_type, &digits(input)?, format).map(|(_, formatted)| formatted);
+    let text = match &result {
+        Ok(number) => format!("{}\n", number),
+        Err(e) => format!("{}\n", unusable(input, e, reporter.lang())),
+    };
+    let (error, code, message) = match &result {
+        Ok(_) => (json!(null), json!(null), json!(null)),
+        Err(e) => (
+            json!(e.kind().as_str()),
+            json!(e.code()),
+            json!(e.to_string()),
+        ),
+    };
+
+    reporter.report(
+        &text,
+        vec![
+            ("type", json!(kind(_type))),
+            ("input", json!(input)),
+            ("valid", json!(result.is_ok())),
+            ("number", json!(result.as_ref().ok())),
+            ("error", error),
+            ("code", code),
+            ("message", message),
+        ],
+    )?;
+
+    Ok(result.is_ok())
+}
+
+/// Reports only the check digit of the given 9 main digits, or why there is
+/// none on stderr. Returns whether there is one.
+pub fn checkdigit(reporter: &mut Reporter, _type: &Typeid, input: &str) -> io::Result<bool> {
+    let result = complete(_type, &digits(input)?, Format::Compact).map(|(n, _)| *n.checkdigit());
+
+    match &result {
+        Ok(digit) => reporter.report(
+            &format!("{}\n", digit),
+            vec![
+                ("type", json!(kind(_type))),
+                ("input", json!(input)),
+                ("checkdigit", json!(digit)),
+            ],
+        )?,
+        Err(e) => reporter.warn(&unusable(input, e, reporter.lang())),
+    }
+
+    Ok(result.is_ok())
+}
+
+/// Tells why the stem has no number in the given language.
+fn unusable(input: &str, error: &ValidationError, language: Language) -> String {
+    i18n::render(
+        Text::UnusableStem,
+        language,
+        &[("stem", &input), ("error", &error.message_in(language))],
+    )
+}
+
+/// Returns the number made of the main digits and its given format.
+fn complete(
+    _type: &Typeid,
+    digits: &[Digit; 9],
+    format: Format,
+) -> Result<(number::Number, String), ValidationError> {
+    match _type {
+        Typeid::Nhs => {
+            nhs::Number::new(*digits).map(|n| (n.as_ref().clone(), n.to_formatted(format)))
+        }
+        Typeid::Chi => {
+            chi::Number::new(*digits).map(|n| (n.as_ref().clone(), n.to_formatted(format)))
+        }
+        Typeid::Hcn => number::Number::new(*digits).and_then(|n| {
+            Scheme::Hcn.check(&n)?;
+
+            let formatted = n.to_formatted(format);
+
+            Ok((n, formatted))
+        }),
+    }
+}
+
+/// Parses exactly 9 digits, ignoring whitespace.
+fn digits(input: &str) -> io::Result<[Digit; 9]> {
+    input
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_digit(10).map(|d| d as Digit))
+        .collect::<Option<Vec<Digit>>>()
+        .and_then(|digits| digits.try_into().ok())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "A stem must be exactly 9 digits",
+            )
+        })
+}
@@ -0,0 +1,144 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Defaults read from a configuration file.
+//!
+//! The file is `heidi/config.toml` in `$XDG_CONFIG_HOME`, or in
+//! `~/.config` when unset, and can be moved with `HEIDI_CONFIG`. Flags and
+//! environment variables take precedence over it. For example:
+//!
+//! ```toml
+//! type = "chi"
+//! output = "json"
+//! century_pivot = 30
+//! color = false
+//! jobs = 4
+//! lang = "cy"
+//! ```
+
+use crate::check::Selector;
+use crate::output::Output;
+use heidi::i18n::Language;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+use toml::{Table, Value};
+
+/// Defaults for flags left out.
+#[derive(PartialEq, Debug, Default)]
+pub struct Config {
+    /// The type `check` validates numbers as.
+    pub _type: Option<Selector>,
+    /// The format to report results in.
+    pub output: Option<Output>,
+    /// The two-digit year from which CHI years of birth are in the 1900s.
+    pub century_pivot: Option<u16>,
+    /// Whether to colour plain text on terminals.
+    pub color: Option<bool>,
+    /// The amount of threads batch subcommands use.
+    pub jobs: Option<usize>,
+    /// The language of messages.
+    pub lang: Option<Language>,
+}
+
+/// Returns the path of the configuration file, if a home can be found.
+pub fn path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("HEIDI_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("heidi").join("config.toml"))
+}
+
+/// Reads the configuration file. A missing file gives no defaults.
+pub fn load() -> io::Result<Config> {
+    let path = match path() {
+        Some(path) => path,
+        None => return Ok(Config::default()),
+    };
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(e) => return Err(e),
+    };
+
+    parse(&content).map_err(|msg| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), msg),
+        )
+    })
+}
+
+fn parse(content: &str) -> Result<Config, String> {
+    let table = Table::from_str(content).map_err(|e| e.message().to_string())?;
+    let mut config = Config::default();
+
+    for (key, value) in &table {
+        match (key.as_str(), value) {
+            ("type", Value::String(name)) => {
+                config._type = Some(Selector::from_str(name).map_err(|_| invalid(key))?);
+            }
+            ("output", Value::String(name)) => {
+                config.output = Some(Output::from_str(name).map_err(|_| invalid(key))?);
+            }
+            ("century_pivot", Value::Integer(pivot)) if (0..100).contains(pivot) => {
+                config.century_pivot = Some(*pivot as u16);
+            }
+            ("color", Value::Boolean(color)) => config.color = Some(*color),
+            ("jobs", Value::Integer(jobs)) if *jobs > 0 => config.jobs = Some(*jobs as usize),
+            ("lang", Value::String(name)) => {
+                config.lang = Some(Language::from_str(name).map_err(|_| invalid(key))?);
+            }
+            ("type", _)
+            | ("output", _)
+            | ("century_pivot", _)
+            | ("color", _)
+            | ("jobs", _)
+            | ("lang", _) => return Err(invalid(key)),
+            _ => return Err(format!("unknown setting '{}'", key)),
+        }
+    }
+
+    Ok(config)
+}
+
+fn invalid(key: &str) -> String {
+    format!("invalid value for '{}'", key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings() {
+        let config = parse(
+            "type = \"chi\"\noutput = \"ndjson\"\ncentury_pivot = 30\ncolor = false\njobs = 4\nlang = \"cy\"\n",
+        );
+
+        assert_eq!(
+            config,
+            Ok(Config {
+                _type: Some(Selector::Chi),
+                output: Some(Output::Ndjson),
+                century_pivot: Some(30),
+                color: Some(false),
+                jobs: Some(4),
+                lang: Some(Language::Welsh),
+            })
+        );
+        assert_eq!(parse(""), Ok(Config::default()));
+        assert!(parse("century_pivot = 100").is_err());
+        assert!(parse("type = \"nhs\"\ntpye = \"chi\"").is_err());
+    }
+}
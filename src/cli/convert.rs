@@ -0,0 +1,131 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Reformatting of numbers between the ways systems expect them.
+
+use crate::input;
+use crate::output::{Outcome, Reporter};
+use clap::arg_enum;
+use heidi::any::AnyNumber;
+use heidi::error::ValidationError;
+use heidi::number::Format;
+use heidi::{chi, nhs};
+use serde_json::json;
+use std::io::{self, BufRead};
+use std::str::FromStr;
+
+arg_enum! {
+    /// The type numbers to convert are expected to be.
+    #[derive(PartialEq, Debug)]
+    pub enum Source {
+        Any,
+        Nhs,
+        Chi,
+    }
+}
+
+/// Converts a single number. Returns whether it is valid.
+///
+/// Hyphens in the input are ignored as well as whitespace.
+pub fn one(reporter: &mut Reporter, from: &Source, to: Format, input: &str) -> io::Result<bool> {
+    let cleaned: String = input.chars().filter(|&c| c != '-').collect();
+    let kind = match from {
+        Source::Any => "any",
+        Source::Nhs => "nhs",
+        Source::Chi => "chi",
+    };
+
+    match convert(from, &cleaned, to) {
+        Ok(converted) => {
+            reporter.report(
+                &format!("{}\n", converted),
+                vec![
+                    ("type", json!(kind)),
+                    ("input", json!(input)),
+                    ("number", json!(converted)),
+                ],
+            )?;
+
+            Ok(true)
+        }
+        Err(e) => {
+            reporter.outcome(&Outcome {
+                kind,
+                source: None,
+                location: None,
+                column: None,
+                input,
+                result: Err(e),
+            })?;
+
+            Ok(false)
+        }
+    }
+}
+
+/// Converts numbers separated by `separator`. Returns whether all of them
+/// are valid.
+pub fn each<R: BufRead>(
+    reporter: &mut Reporter,
+    from: &Source,
+    to: Format,
+    reader: R,
+    separator: u8,
+) -> io::Result<bool> {
+    let mut valid = true;
+
+    for line in input::records(reader, separator) {
+        let line = line?;
+        let number = line.trim();
+
+        if !number.is_empty() && !one(reporter, from, to, number)? {
+            valid = false;
+        }
+    }
+
+    Ok(valid)
+}
+
+/// Parses a number of the given type and writes it in the given format as
+/// its scheme does, e.g. CHI numbers are officially written compact.
+pub fn convert(from: &Source, input: &str, to: Format) -> Result<String, ValidationError> {
+    match from {
+        Source::Any => AnyNumber::from_str(input).map(|n| n.to_formatted(to)),
+        Source::Nhs => nhs::Number::from_str(input).map(|n| n.to_formatted(to)),
+        Source::Chi => chi::Number::from_str(input).map(|n| n.to_formatted(to)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats() {
+        let convert = |from, input, to| convert(&from, input, to).unwrap();
+
+        assert_eq!(
+            convert(Source::Any, "6541003238", Format::Official),
+            "654 100 3238"
+        );
+        assert_eq!(
+            convert(Source::Nhs, "6541003238", Format::Hyphenated),
+            "654-100-3238"
+        );
+        assert_eq!(
+            convert(Source::Any, "6541003238", Format::Compact),
+            "6541003238"
+        );
+        assert_eq!(
+            convert(Source::Chi, "0101990014", Format::Official),
+            "0101990014"
+        );
+        assert_eq!(
+            convert(Source::Any, "0101990014", Format::Official),
+            "0101990014"
+        );
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Validation of columns in CSV and other delimited files.
+
+use crate::columns::{positions, Checker, Row};
+use crate::output::Reporter;
+use crate::Typeid;
+use crate::{input, jobs};
+use heidi::batch::Stats;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// Validates the given columns of a delimited file with a header row.
+///
+/// When `annotate` is given, the input is copied there with two extra
+/// columns: `valid` and `error`. A run stopped early only annotates the
+/// records checked.
+///
+/// Records are validated in batches using up to `jobs` threads.
+pub fn check(
+    reporter: &mut Reporter,
+    _type: &Typeid,
+    input: &Path,
+    columns: &[String],
+    delimiter: u8,
+    annotate: Option<&Path>,
+    jobs: usize,
+) -> io::Result<Stats> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(input::open(input, reporter.progress())?);
+    let header = reader.headers()?.clone();
+    let names: Vec<&str> = header.iter().collect();
+    let idxs = positions(&names, columns)
+        .map_err(|msg| io::Error::new(io::ErrorKind::InvalidInput, msg))?;
+    let mut writer = match annotate {
+        Some(path) => {
+            let mut writer = csv::WriterBuilder::new()
+                .delimiter(delimiter)
+                .flexible(true)
+                .from_writer(File::create(path)?);
+            let mut record = header.clone();
+
+            record.push_field("valid");
+            record.push_field("error");
+            writer.write_record(&record)?;
+
+            Some(writer)
+        }
+        None => None,
+    };
+    let mut checker = Checker::new(reporter, _type, &input.display().to_string(), jobs);
+    let mut records = reader.records();
+
+    while !checker.stopped() {
+        let mut batch = Vec::with_capacity(jobs::BATCH);
+
+        for record in records.by_ref() {
+            batch.push(record?);
+            if batch.len() == jobs::BATCH {
+                break;
+            }
+        }
+
+        if batch.is_empty() {
+            break;
+        }
+
+        let rows: Vec<Row> = batch
+            .iter()
+            .map(|record| {
+                let line = record.position().map_or(0, |pos| pos.line());
+                let values = columns
+                    .iter()
+                    .zip(&idxs)
+                    .map(|(column, &idx)| {
+                        (column.clone(), record.get(idx).unwrap_or("").to_string())
+                    })
+                    .collect();
+
+                (line.to_string(), values)
+            })
+            .collect();
+        let errors = checker.batch(&rows)?;
+
+        if let Some(writer) = writer.as_mut() {
+            for (record, errors) in batch.iter().zip(errors) {
+                let mut annotated = record.clone();
+                let error: Vec<String> = errors
+                    .iter()
+                    .map(|(column, e)| format!("{}: {}", column, e.kind()))
+                    .collect();
+
+                annotated.push_field(if errors.is_empty() { "true" } else { "false" });
+                annotated.push_field(&error.join("; "));
+                writer.write_record(&annotated)?;
+            }
+        }
+    }
+
+    if let Some(mut writer) = writer {
+        writer.flush()?;
+    }
+
+    checker.finish()
+}
@@ -0,0 +1,60 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Exit codes shared by every subcommand so scripts can tell failures apart.
+
+use std::io;
+
+/// The outcome of a run as told to the shell.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Code {
+    /// Every number is valid, or the run completed.
+    Success = 0,
+    /// Some number is invalid, or `scan` found an identifier.
+    Invalid = 1,
+    /// The arguments, options or input given cannot be used.
+    Usage = 2,
+    /// A file could not be read or written.
+    Io = 3,
+    /// A batch run has more invalid numbers than `--max-invalid` or
+    /// `--max-invalid-pct` tolerate.
+    Threshold = 4,
+}
+
+impl Code {
+    /// The code for a failed run.
+    pub fn error(error: &io::Error) -> Code {
+        match error.kind() {
+            io::ErrorKind::InvalidInput => Code::Usage,
+            _ => Code::Io,
+        }
+    }
+}
+
+impl From<bool> for Code {
+    fn from(valid: bool) -> Code {
+        if valid {
+            Code::Success
+        } else {
+            Code::Invalid
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors() {
+        let usage = io::Error::new(io::ErrorKind::InvalidInput, "A stem must be 9 digits");
+        let missing = io::Error::new(io::ErrorKind::NotFound, "No such file");
+
+        assert_eq!(Code::error(&usage), Code::Usage);
+        assert_eq!(Code::error(&missing), Code::Io);
+        assert_eq!(Code::from(false) as i32, 1);
+    }
+}
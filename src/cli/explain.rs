@@ -0,0 +1,192 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Step by step working of the Modulus 11 check digit.
+
+use crate::check::{kind, validate};
+use crate::output::{self, Reporter};
+use crate::Typeid;
+use heidi::i18n::{self, Text};
+use heidi::number::{self, Digit};
+use serde_json::json;
+use std::fmt::Write;
+use std::io;
+
+/// Reports how the check digit of a number is derived from its 9 main
+/// digits, as worked out by [`number::explain`].
+///
+/// Takes either the 9 main digits, completed with the derived check digit,
+/// or the full 10 digits. Returns whether the number is valid for the given
+/// type.
+pub fn number(reporter: &mut Reporter, _type: &Typeid, input: &str) -> io::Result<bool> {
+    let digits = main_digits(input)?;
+    let mut stem = [0; 9];
+
+    stem.copy_from_slice(&digits[..9]);
+
+    let number::Explanation {
+        terms,
+        sum,
+        remainder,
+        checkdigit: expected,
+    } = number::explain(&stem);
+    let supplied = digits.get(9).copied();
+    // Without a check digit the number is completed with the derived one.
+    let full: Option<String> = match (supplied, expected) {
+        (Some(_), _) => Some(input.to_string()),
+        (None, Some(digit)) => Some(format!("{}{}", input.trim(), digit)),
+        (None, None) => None,
+    };
+    let result = full.as_deref().map(|full| validate(_type, full));
+    let language = reporter.lang();
+    let label = i18n::text(output::label(kind(_type)), language);
+    let mut text = String::new();
+    // Columns are as wide as their heading in the language of the report.
+    let columns = [Text::Position, Text::Digit, Text::Weight, Text::Product]
+        .map(|column| i18n::text(column, language));
+    let widths = columns.map(|column| column.chars().count());
+
+    writeln!(text, "{}", columns.join("  ")).expect("write to string");
+    for (idx, term) in terms.iter().enumerate() {
+        writeln!(
+            text,
+            "{:>w0$}  {:>w1$}  {:>w2$}  {:>w3$}",
+            idx + 1,
+            term.digit,
+            term.weight,
+            term.product,
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3]
+        )
+        .expect("write to string");
+    }
+    writeln!(text).expect("write to string");
+    writeln!(
+        text,
+        "{}",
+        i18n::render(Text::Sum, language, &[("sum", &sum)])
+    )
+    .expect("write to string");
+    writeln!(
+        text,
+        "{}",
+        i18n::render(
+            Text::Remainder,
+            language,
+            &[("sum", &sum), ("remainder", &remainder)]
+        )
+    )
+    .expect("write to string");
+
+    let derivation = match expected {
+        Some(0) => i18n::text(Text::CheckDigitZero, language).to_string(),
+        Some(digit) => i18n::render(
+            Text::CheckDigit,
+            language,
+            &[("remainder", &remainder), ("checkdigit", &digit)],
+        ),
+        None => i18n::text(Text::CheckDigitTen, language).to_string(),
+    };
+    writeln!(text, "{}", derivation).expect("write to string");
+
+    if let Some(digit) = supplied {
+        writeln!(
+            text,
+            "{}",
+            i18n::render(Text::Supplied, language, &[("checkdigit", &digit)])
+        )
+        .expect("write to string");
+    }
+
+    match &result {
+        Some(Ok((_, official))) => writeln!(
+            text,
+            "{}",
+            i18n::render(
+                Text::NumberValid,
+                language,
+                &[("label", &label), ("number", official)]
+            )
+        ),
+        Some(Err(e)) => writeln!(
+            text,
+            "{}",
+            i18n::render(
+                Text::NumberInvalid,
+                language,
+                &[("label", &label), ("error", &e.message_in(language))]
+            )
+        ),
+        None => Ok(()),
+    }
+    .expect("write to string");
+
+    let (error, code, message) = match &result {
+        Some(Err(e)) => (
+            json!(e.kind().as_str()),
+            json!(e.code()),
+            json!(e.message_in(language)),
+        ),
+        _ => (json!(null), json!(null), json!(null)),
+    };
+    let valid = matches!(result, Some(Ok(_)));
+
+    reporter.report(
+        &text,
+        vec![
+            ("type", json!(kind(_type))),
+            ("input", json!(input)),
+            (
+                "terms",
+                json!(terms
+                    .iter()
+                    .map(|term| json!({
+                        "digit": term.digit,
+                        "weight": term.weight,
+                        "product": term.product,
+                    }))
+                    .collect::<Vec<_>>()),
+            ),
+            ("sum", json!(sum)),
+            ("remainder", json!(remainder)),
+            ("expected", json!(expected)),
+            ("supplied", json!(supplied)),
+            ("valid", json!(valid)),
+            ("error", error),
+            ("code", code),
+            ("message", message),
+        ],
+    )?;
+
+    Ok(valid)
+}
+
+/// Returns the 9 or 10 digits of the input, ignoring whitespace.
+fn main_digits(input: &str) -> io::Result<Vec<Digit>> {
+    let digits = input
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| {
+            c.to_digit(10)
+                .map(|d| d as Digit)
+                .ok_or_else(|| invalid(format!("The given character '{}' is not a digit", c)))
+        })
+        .collect::<io::Result<Vec<Digit>>>()?;
+
+    if digits.len() != 9 && digits.len() != 10 {
+        return Err(invalid(
+            "Expected the 9 main digits or the full 10 digits".to_string(),
+        ));
+    }
+
+    Ok(digits)
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
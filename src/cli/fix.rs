@@ -0,0 +1,222 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Suggestions for mistyped numbers.
+
+use crate::check::{kind, validate};
+use crate::output::{self, Reporter};
+use crate::Typeid;
+use heidi::i18n::{self, Language, Text};
+use serde_json::json;
+use std::fmt::{self, Write};
+use std::io;
+
+/// A single typing mistake.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Edit {
+    /// The digit at the 1-based position was mistyped.
+    Substitution {
+        position: usize,
+        from: char,
+        to: char,
+    },
+    /// The digits at the 1-based position and the next one were swapped.
+    Transposition { position: usize },
+}
+
+impl Edit {
+    /// Describes the edit in the given language.
+    pub fn describe(&self, language: Language) -> String {
+        match self {
+            Edit::Substitution { position, from, to } => i18n::render(
+                Text::Substitution,
+                language,
+                &[("position", position), ("to", to), ("from", from)],
+            ),
+            Edit::Transposition { position } => i18n::render(
+                Text::Transposition,
+                language,
+                &[("position", position), ("next", &(position + 1))],
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Edit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.describe(Language::English))
+    }
+}
+
+/// A valid number close to the input.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Candidate {
+    /// The number in official format.
+    pub number: String,
+    pub edit: Edit,
+}
+
+/// Returns the valid numbers of the given type within one substitution or
+/// adjacent transposition of the input, in position order.
+///
+/// Whitespace in the input is ignored.
+pub fn candidates(_type: &Typeid, input: &str) -> Vec<Candidate> {
+    let digits: Vec<char> = input.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut found: Vec<Candidate> = Vec::new();
+    let mut push = |candidate: Vec<char>, edit: Edit| {
+        let candidate: String = candidate.into_iter().collect();
+
+        if let Ok((_, number)) = validate(_type, &candidate) {
+            if !found.iter().any(|c| c.number == number) {
+                found.push(Candidate { number, edit });
+            }
+        }
+    };
+
+    if digits.len() != 10 {
+        return Vec::new();
+    }
+
+    for idx in 0..digits.len() {
+        if idx + 1 < digits.len() && digits[idx] != digits[idx + 1] {
+            let mut swapped = digits.clone();
+
+            swapped.swap(idx, idx + 1);
+            push(swapped, Edit::Transposition { position: idx + 1 });
+        }
+
+        for to in '0'..='9' {
+            if to != digits[idx] {
+                let mut substituted = digits.clone();
+
+                substituted[idx] = to;
+                push(
+                    substituted,
+                    Edit::Substitution {
+                        position: idx + 1,
+                        from: digits[idx],
+                        to,
+                    },
+                );
+            }
+        }
+    }
+
+    found
+}
+
+/// Reports the valid numbers close to the input. Returns whether there is a
+/// single one, or the input is valid already.
+pub fn number(reporter: &mut Reporter, _type: &Typeid, input: &str) -> io::Result<bool> {
+    let language = reporter.lang();
+    let label = i18n::text(output::label(kind(_type)), language);
+    let mut text = String::new();
+
+    if let Ok((_, official)) = validate(_type, input) {
+        writeln!(
+            text,
+            "{}",
+            i18n::render(
+                Text::NumberValid,
+                language,
+                &[("label", &label), ("number", &official)]
+            )
+        )
+        .expect("write to string");
+        reporter.report(
+            &text,
+            vec![
+                ("type", json!(kind(_type))),
+                ("input", json!(input)),
+                ("valid", json!(true)),
+                ("candidates", json!([])),
+                ("ambiguous", json!(false)),
+            ],
+        )?;
+
+        return Ok(true);
+    }
+
+    let found = candidates(_type, input);
+
+    for candidate in &found {
+        writeln!(
+            text,
+            "{} ({})",
+            candidate.number,
+            candidate.edit.describe(language)
+        )
+        .expect("write to string");
+    }
+
+    match found.len() {
+        0 => writeln!(
+            text,
+            "{}",
+            i18n::render(Text::NoCandidatesOf, language, &[("label", &label)])
+        ),
+        1 => Ok(()),
+        len => writeln!(
+            text,
+            "{}",
+            i18n::render(Text::Ambiguous, language, &[("count", &len)])
+        ),
+    }
+    .expect("write to string");
+
+    let candidates: Vec<_> = found
+        .iter()
+        .map(|candidate| {
+            json!({
+                "number": candidate.number,
+                "edit": candidate.edit.to_string(),
+            })
+        })
+        .collect();
+
+    reporter.report(
+        &text,
+        vec![
+            ("type", json!(kind(_type))),
+            ("input", json!(input)),
+            ("valid", json!(false)),
+            ("candidates", json!(candidates)),
+            ("ambiguous", json!(found.len() > 1)),
+        ],
+    )?;
+
+    Ok(found.len() == 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitutions_and_transpositions() {
+        let found = candidates(&Typeid::Nhs, "6541003228");
+
+        assert!(found.contains(&Candidate {
+            number: "654 100 3238".to_string(),
+            edit: Edit::Substitution {
+                position: 9,
+                from: '2',
+                to: '3'
+            },
+        }));
+        assert!(found
+            .iter()
+            .all(|c| validate(&Typeid::Nhs, &c.number).is_ok()));
+
+        let swapped = candidates(&Typeid::Nhs, "6541002338");
+
+        assert!(swapped.contains(&Candidate {
+            number: "654 100 3238".to_string(),
+            edit: Edit::Transposition { position: 7 },
+        }));
+        assert!(candidates(&Typeid::Nhs, "654100").is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Thresholds deciding whether a batch run passes.
+
+use crate::exit::Code;
+use heidi::batch::Stats;
+
+/// The amount of invalid values a batch run tolerates.
+///
+/// Without thresholds a single invalid value fails the run.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Gate {
+    /// Stops at the first invalid value past `max_invalid`.
+    pub fail_fast: bool,
+    pub max_invalid: Option<usize>,
+    /// The maximum percentage of invalid values, from 0 to 100.
+    pub max_invalid_pct: Option<f64>,
+}
+
+impl Gate {
+    /// Whether the run passes with the given statistics.
+    pub fn passes(&self, stats: &Stats) -> bool {
+        let count = match (self.max_invalid, self.max_invalid_pct) {
+            (Some(max), _) => stats.invalid() <= max,
+            (None, Some(_)) => true,
+            (None, None) => stats.invalid() == 0,
+        };
+        let pct = match self.max_invalid_pct {
+            Some(max) if stats.total() > 0 => {
+                stats.invalid() as f64 * 100.0 / stats.total() as f64 <= max
+            }
+            _ => true,
+        };
+
+        count && pct
+    }
+
+    /// The exit code of a run with the given statistics. A failing run
+    /// exceeds the thresholds when any is set.
+    pub fn code(&self, stats: &Stats) -> Code {
+        if self.passes(stats) {
+            Code::Success
+        } else if self.max_invalid.is_some() || self.max_invalid_pct.is_some() {
+            Code::Threshold
+        } else {
+            Code::Invalid
+        }
+    }
+
+    /// Whether the run should stop early.
+    ///
+    /// Percentages are only known at the end so they never stop a run.
+    pub fn stops(&self, stats: &Stats) -> bool {
+        self.fail_fast && stats.invalid() > self.max_invalid.unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use heidi::nhs::Number;
+    use std::str::FromStr;
+
+    fn stats(valid: usize, invalid: usize) -> Stats {
+        let mut stats = Stats::new();
+
+        for _ in 0..valid {
+            stats.record(&Number::from_str("6541003238"));
+        }
+        for _ in 0..invalid {
+            stats.record(&Number::from_str("6541003239"));
+        }
+
+        stats
+    }
+
+    #[test]
+    fn thresholds() {
+        let strict = Gate::default();
+        let count = Gate {
+            max_invalid: Some(2),
+            ..Gate::default()
+        };
+        let pct = Gate {
+            max_invalid_pct: Some(10.0),
+            ..Gate::default()
+        };
+        let both = Gate {
+            max_invalid: Some(1),
+            max_invalid_pct: Some(10.0),
+            ..Gate::default()
+        };
+
+        assert!(strict.passes(&stats(5, 0)));
+        assert!(!strict.passes(&stats(5, 1)));
+        assert!(count.passes(&stats(1, 2)));
+        assert!(!count.passes(&stats(100, 3)));
+        assert!(pct.passes(&stats(90, 10)));
+        assert!(!pct.passes(&stats(89, 11)));
+        assert!(pct.passes(&stats(0, 0)));
+        assert!(!both.passes(&stats(98, 2)));
+        assert!(!both.passes(&stats(1, 1)));
+        assert!(both.passes(&stats(99, 1)));
+        assert_eq!(strict.code(&stats(5, 1)), Code::Invalid);
+        assert_eq!(count.code(&stats(100, 3)), Code::Threshold);
+        assert_eq!(pct.code(&stats(90, 10)), Code::Success);
+    }
+
+    #[test]
+    fn stopping() {
+        let fail_fast = Gate {
+            fail_fast: true,
+            ..Gate::default()
+        };
+        let tolerant = Gate {
+            max_invalid: Some(1),
+            ..fail_fast
+        };
+
+        assert!(!Gate::default().stops(&stats(0, 5)));
+        assert!(fail_fast.stops(&stats(0, 1)));
+        assert!(!tolerant.stops(&stats(0, 1)));
+        assert!(tolerant.stops(&stats(0, 2)));
+    }
+}
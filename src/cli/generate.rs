@@ -0,0 +1,254 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Generation of random numbers, e.g. to seed test databases.
+
+use crate::check::kind;
+use crate::output::{columns, Reporter};
+use crate::{Format, Typeid};
+use heidi::error::ValidationError;
+use heidi::privacy::{Date, Sex};
+use heidi::region::Region;
+use heidi::scheme::Scheme;
+use heidi::{chi, nhs, number};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+#[cfg(feature = "serve")]
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+/// Consecutive repeated draws after which the numbers left are considered
+/// exhausted.
+const ATTEMPTS: usize = 100_000;
+
+/// What to generate.
+#[derive(Debug)]
+#[cfg_attr(feature = "serve", derive(JsonSchema), schemars(default))]
+pub struct Options {
+    /// The amount of numbers.
+    #[cfg_attr(
+        feature = "serve",
+        schemars(range(min = 1, max = crate::serve::COUNT_LIMIT))
+    )]
+    pub count: usize,
+    /// The seed of the generator, if any.
+    pub seed: Option<u64>,
+    /// Whether to never repeat a number.
+    pub unique: bool,
+    /// Whether to draw numbers from the range reserved for testing only.
+    pub test_range: bool,
+    /// The earliest and latest dates of birth of CHI numbers.
+    #[cfg_attr(feature = "serve", schemars(skip))]
+    pub born: Option<(Date, Date)>,
+    /// The sex of CHI numbers.
+    #[cfg_attr(feature = "serve", schemars(skip))]
+    pub sex: Option<Sex>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            count: 1,
+            seed: None,
+            unique: false,
+            test_range: false,
+            born: None,
+            sex: None,
+        }
+    }
+}
+
+/// A generated number as written to structured reports.
+#[derive(Serialize, Debug)]
+#[cfg_attr(feature = "serve", derive(JsonSchema))]
+pub struct Generated<'a> {
+    /// The type of identifier, e.g. `nhs`.
+    #[serde(rename = "type")]
+    kind: &'a str,
+    /// The number in the requested format.
+    number: &'a str,
+    compact: &'a str,
+    official: &'a str,
+    hyphenated: &'a str,
+    /// The schemes the number validates as.
+    schemes: Vec<&'a str>,
+    /// The region of the number, e.g. `england-wales`.
+    region: &'a str,
+}
+
+/// Parses a sex given as `female`, `male`, `f` or `m`.
+pub fn sex(value: &str) -> Result<Sex, String> {
+    match value.to_lowercase().as_str() {
+        "female" | "f" => Ok(Sex::Female),
+        "male" | "m" => Ok(Sex::Male),
+        _ => Err("expected female or male".to_string()),
+    }
+}
+
+/// Parses a date of birth given as `YYYY-MM-DD`.
+pub fn dob(value: &str) -> Result<(Date, Date), String> {
+    let date = Date::from_str(value).map_err(|e| e.to_string())?;
+
+    Ok((date, date))
+}
+
+/// Parses an inclusive range of years of birth given as `FROM:TO`.
+pub fn years(value: &str) -> Result<(Date, Date), String> {
+    let mut parts = value.splitn(2, ':').map(|part| part.trim().parse::<u16>());
+
+    match (parts.next(), parts.next()) {
+        (Some(Ok(from)), Some(Ok(to))) if from <= to => {
+            Ok((Date::new(from, 1, 1), Date::new(to, 12, 31)))
+        }
+        _ => Err("expected a range of years like 1940:1990".to_string()),
+    }
+}
+
+/// Reports random numbers of the given type, with their other formats,
+/// schemes and region in structured output. Returns whether all of them
+/// could be generated.
+///
+/// Unique numbers are tracked by their main digits, 4 bytes each, and
+/// generation fails once new ones are no longer found, e.g. when asking for
+/// more numbers than a date of birth allows.
+///
+/// Without a seed numbers are drawn from the thread generator. With one they
+/// are drawn from ChaCha8, whose output is the same on every platform, so
+/// the same seed always gives the same numbers.
+pub fn numbers(
+    reporter: &mut Reporter,
+    _type: &Typeid,
+    format: Format,
+    options: &Options,
+) -> io::Result<bool> {
+    let demographic = options.born.is_some() || options.sex.is_some();
+
+    if demographic && matches!(_type, Typeid::Nhs) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Dates of birth and sex only apply to CHI numbers",
+        ));
+    }
+
+    match options.seed {
+        Some(seed) => draw(
+            reporter,
+            _type,
+            format,
+            options,
+            &mut ChaCha8Rng::seed_from_u64(seed),
+        ),
+        None => draw(reporter, _type, format, options, &mut rand::thread_rng()),
+    }
+}
+
+fn draw<R: Rng>(
+    reporter: &mut Reporter,
+    _type: &Typeid,
+    format: Format,
+    options: &Options,
+    rng: &mut R,
+) -> io::Result<bool> {
+    let demographic = options.born.is_some() || options.sex.is_some();
+    let mut seen = HashSet::new();
+    let mut generated = 0;
+    let mut repeated = 0;
+    // Without dates, demographics default to anyone alive under 100.
+    let (from, to) = options.born.unwrap_or_else(|| {
+        let today = Date::today();
+
+        (Date::new(today.year - 99, 1, 1), today)
+    });
+
+    while generated < options.count {
+        let result = match (_type, options.test_range) {
+            (Typeid::Nhs, false) => nhs::lottery_with(rng).map(forms),
+            (Typeid::Nhs, true) => nhs::test_lottery_with(rng).map(forms),
+            (Typeid::Chi, true) => chi::test_lottery_with(rng).map(forms),
+            (Typeid::Chi, false) if demographic => {
+                chi::demographic_lottery_with(rng, from, to, options.sex).map(forms)
+            }
+            (Typeid::Chi, false) => chi::lottery_with(rng).map(forms),
+            (Typeid::Hcn, _) => Err(ValidationError::new("H&C numbers cannot be generated yet")),
+        };
+        let (number, official) = match result {
+            Ok(forms) => forms,
+            Err(e) => {
+                reporter.warn(&e.to_string());
+                return Ok(false);
+            }
+        };
+
+        // The main digits are kept as an integer so large batches fit in
+        // memory.
+        let stem = number
+            .digits()
+            .iter()
+            .fold(0u32, |acc, &digit| acc * 10 + u32::from(digit));
+
+        if options.unique && !seen.insert(stem) {
+            repeated += 1;
+            if repeated == ATTEMPTS {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Only {} distinct numbers could be generated", seen.len()),
+                ));
+            }
+            continue;
+        }
+
+        repeated = 0;
+        generated += 1;
+
+        let compact = number.to_string();
+        let hyphenated = number.to_formatted(Format::Hyphenated);
+        let text = match format {
+            Format::Official => official.clone(),
+            format => number.to_formatted(format),
+        };
+
+        let generated = Generated {
+            kind: kind(_type),
+            number: &text,
+            compact: &compact,
+            official: &official,
+            hyphenated: &hyphenated,
+            schemes: Scheme::detect(&number).iter().map(Scheme::as_str).collect(),
+            region: Region::of(number.digits()).as_str(),
+        };
+
+        reporter.report(&format!("{}\n", text), columns(&generated)?)?;
+    }
+
+    Ok(true)
+}
+
+/// Returns the number with its official form.
+fn forms<N: AsRef<number::Number> + fmt::Display>(number: N) -> (number::Number, String) {
+    (number.as_ref().clone(), format!("{:#}", number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demographics() {
+        assert_eq!(
+            years("1940:1990"),
+            Ok((Date::new(1940, 1, 1), Date::new(1990, 12, 31)))
+        );
+        assert!(years("1990:1940").is_err());
+        assert!(years("1940").is_err());
+        assert_eq!(sex("Female"), Ok(Sex::Female));
+        assert_eq!(sex("m"), Ok(Sex::Male));
+        assert!(sex("x").is_err());
+    }
+}
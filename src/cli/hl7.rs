@@ -0,0 +1,129 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Auditing of HL7 v2 messages for invalid and test identifiers.
+
+use crate::input;
+use crate::output::Reporter;
+use heidi::batch::Stats;
+use heidi::hl7::{self, Identifier};
+use heidi::number;
+use serde_json::json;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Audits the messages in the given files, reporting every identifier that
+/// is invalid or in the test range, and a summary of all of them. Returns
+/// the amount of messages reported.
+pub fn files(reporter: &mut Reporter, paths: &[PathBuf]) -> io::Result<usize> {
+    let mut stats = Stats::new();
+    let mut reported = 0;
+
+    for path in paths {
+        let mut content = Vec::new();
+
+        input::open(path, reporter.progress())?.read_to_end(&mut content)?;
+        reported += messages(reporter, &path.display().to_string(), &content, &mut stats)?;
+    }
+
+    reporter.summary(&stats, 0)?;
+
+    Ok(reported)
+}
+
+/// Audits the messages read from a stream. Returns the amount of messages
+/// reported.
+pub fn stream<R: Read>(reporter: &mut Reporter, mut reader: R) -> io::Result<usize> {
+    let mut stats = Stats::new();
+    let mut content = Vec::new();
+
+    reader.read_to_end(&mut content)?;
+
+    let reported = messages(reporter, "-", &content, &mut stats)?;
+
+    reporter.summary(&stats, 0)?;
+
+    Ok(reported)
+}
+
+/// Audits every message in the content, recording every NHS and CHI
+/// identifier in `stats`. Messages are counted from 1.
+fn messages(
+    reporter: &mut Reporter,
+    source: &str,
+    content: &[u8],
+    stats: &mut Stats,
+) -> io::Result<usize> {
+    let content = String::from_utf8_lossy(content);
+    let mut reported = 0;
+
+    for (idx, message) in hl7::messages(&content).enumerate() {
+        let control_id = hl7::control_id(message);
+        let mut flagged = false;
+
+        for identifier in hl7::extract_identifiers(message) {
+            if identifier.scheme.is_none() {
+                continue;
+            }
+
+            match &identifier.error {
+                Some(error) => stats.record::<number::Number>(&Err(error.clone())),
+                None => stats.record(&number::Number::from_str(&identifier.value)),
+            }
+
+            if identifier.error.is_some() || identifier.is_test() {
+                flagged = true;
+                report(reporter, source, idx + 1, control_id, &identifier)?;
+            }
+        }
+
+        if flagged {
+            reported += 1;
+        }
+    }
+
+    Ok(reported)
+}
+
+fn report(
+    reporter: &mut Reporter,
+    source: &str,
+    message: usize,
+    control_id: Option<&str>,
+    identifier: &Identifier,
+) -> io::Result<()> {
+    let scheme = identifier.scheme.map(|scheme| scheme.as_str());
+    let (kind, reason) = match &identifier.error {
+        Some(error) => (error.kind().as_str(), error.to_string()),
+        None => ("test", "A test number never issued to patients".to_string()),
+    };
+    let text = format!(
+        "{}:{}: {} {} '{}' {}: {}.\n",
+        source,
+        message,
+        control_id.unwrap_or("-"),
+        scheme.unwrap_or_default(),
+        identifier.value,
+        kind,
+        reason
+    );
+
+    reporter.report(
+        &text,
+        vec![
+            ("type", json!(scheme)),
+            ("source", json!(source)),
+            ("message", json!(message)),
+            ("control_id", json!(control_id)),
+            ("segment", json!(identifier.segment)),
+            ("repetition", json!(identifier.repetition)),
+            ("input", json!(identifier.value)),
+            ("valid", json!(identifier.error.is_none())),
+            ("error", json!(kind)),
+        ],
+    )
+}
@@ -0,0 +1,114 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Breakdown of a single number.
+
+use crate::check::{kind, validate};
+use crate::output::Reporter;
+use crate::Typeid;
+use heidi::any::AnyNumber;
+use heidi::number::Format;
+use heidi::privacy::{self, Date, Sex};
+use heidi::region::Region;
+use heidi::scheme::Scheme;
+use heidi::{chi, number};
+use serde_json::json;
+use std::io;
+use std::str::FromStr;
+
+/// Reports what can be told from a number: the schemes it validates under,
+/// its check digit, official format and allocation region, and for CHI
+/// numbers the date of birth and sex. Returns whether it is valid for the
+/// declared type, or for any scheme when none is declared.
+///
+/// CHI years of birth are the latest ones not after the reference date.
+pub fn number(
+    reporter: &mut Reporter,
+    declared: Option<&Typeid>,
+    input: &str,
+    reference: Date,
+) -> io::Result<bool> {
+    let mut fields = vec![("input", json!(input))];
+    let parsed = number::Number::from_str(input);
+    // Without a declared type the number is written as the scheme it is
+    // detected as.
+    let official = match declared {
+        Some(_type) => validate(_type, input).map(|(_, official)| official).ok(),
+        None => parsed
+            .as_ref()
+            .ok()
+            .map(|number| AnyNumber::from(number.clone()).to_formatted(Format::Official)),
+    };
+    let declared = declared.map(|_type| (kind(_type), validate(_type, input)));
+    let valid = match &declared {
+        Some((_, result)) => result.is_ok(),
+        None => parsed.is_ok(),
+    };
+
+    fields.push(("valid", json!(valid)));
+
+    if let Some((kind, result)) = &declared {
+        fields.push(("declared", json!(kind)));
+
+        if let Err(e) = result {
+            fields.push(("error", json!(e.kind().as_str())));
+            fields.push(("code", json!(e.code())));
+            fields.push(("message", json!(e.to_string())));
+        }
+    }
+
+    match parsed {
+        Ok(number) => {
+            let schemes: Vec<&str> = Scheme::detect(&number).iter().map(Scheme::as_str).collect();
+
+            fields.push(("schemes", json!(schemes)));
+            fields.push(("check_digit", json!(number.checkdigit())));
+            fields.push(("official", json!(official)));
+            fields.push(("region", json!(Region::of(number.digits()).as_str())));
+
+            if let Ok(chi) = chi::Number::new(*number.digits()) {
+                let sex = match privacy::sex(&chi) {
+                    Sex::Female => "female",
+                    Sex::Male => "male",
+                };
+
+                fields.push((
+                    "date_of_birth",
+                    json!(privacy::date_of_birth(&chi, reference).to_string()),
+                ));
+                fields.push(("sex", json!(sex)));
+            }
+        }
+        // The error of the declared type is more specific.
+        Err(e) if declared.is_none() => {
+            fields.push(("error", json!(e.kind().as_str())));
+            fields.push(("code", json!(e.code())));
+            fields.push(("message", json!(e.to_string())));
+        }
+        Err(_) => {}
+    }
+
+    reporter.details(fields)?;
+
+    Ok(valid)
+}
+
+/// Parses a century pivot, a two-digit year.
+pub fn pivot(value: &str) -> Result<u16, String> {
+    match value.parse::<u16>() {
+        Ok(pivot) if pivot < 100 => Ok(pivot),
+        _ => Err("expected a two-digit year from 0 to 99".to_string()),
+    }
+}
+
+/// Returns the reference date that resolves two-digit years below the pivot
+/// to the 2000s and the rest to the 1900s, or today without a pivot.
+pub fn reference(pivot: Option<u16>) -> Date {
+    match pivot {
+        Some(pivot) => Date::new(1999 + pivot, 12, 31),
+        None => Date::today(),
+    }
+}
@@ -0,0 +1,123 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Opening of inputs, decompressing gzip and Zstandard streams on the fly.
+
+use crate::progress::Progress;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+const GZIP: &[u8] = &[0x1f, 0x8b];
+const ZSTD: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Opens a file, decompressing it if it starts like a gzip or Zstandard
+/// stream regardless of its extension.
+///
+/// The progress tracks the bytes read from the file, before decompression.
+pub fn open(path: &Path, progress: &mut Progress) -> io::Result<Box<dyn BufRead>> {
+    let file = File::open(path)?;
+
+    progress.start(Some(file.metadata()?.len()));
+    decompress(BufReader::with_capacity(1 << 16, progress.wrap(file)))
+}
+
+/// Tells whether the content starts like a gzip or Zstandard stream.
+pub fn is_magic(content: &[u8]) -> bool {
+    content.starts_with(GZIP) || content.starts_with(ZSTD)
+}
+
+/// Tells whether the file starts like a gzip or Zstandard stream.
+pub fn is_compressed(path: &Path) -> io::Result<bool> {
+    let mut magic = Vec::with_capacity(ZSTD.len());
+
+    File::open(path)?
+        .take(ZSTD.len() as u64)
+        .read_to_end(&mut magic)?;
+
+    Ok(is_magic(&magic))
+}
+
+/// Splits the content into records ending with `separator`, such as `\n` or
+/// the `\0` written by `find -print0`. A carriage return before a newline
+/// separator is dropped.
+pub fn records<R: BufRead>(reader: R, separator: u8) -> impl Iterator<Item = io::Result<String>> {
+    reader.split(separator).map(move |record| {
+        let mut record = record?;
+
+        if separator == b'\n' && record.last() == Some(&b'\r') {
+            record.pop();
+        }
+
+        String::from_utf8(record).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    })
+}
+
+/// Wraps the reader with a decoder if it starts like a gzip or Zstandard
+/// stream.
+pub fn decompress<R: BufRead + 'static>(mut reader: R) -> io::Result<Box<dyn BufRead>> {
+    let magic = reader.fill_buf()?;
+
+    if magic.starts_with(GZIP) {
+        gzip(reader)
+    } else if magic.starts_with(ZSTD) {
+        zstd(reader)
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+#[cfg(feature = "compression")]
+fn gzip<R: BufRead + 'static>(reader: R) -> io::Result<Box<dyn BufRead>> {
+    // Concatenated members, as written by `pigz` or `cat a.gz b.gz`, are
+    // read as a single stream.
+    Ok(Box::new(BufReader::new(
+        flate2::bufread::MultiGzDecoder::new(reader),
+    )))
+}
+
+#[cfg(feature = "compression")]
+fn zstd<R: BufRead + 'static>(reader: R) -> io::Result<Box<dyn BufRead>> {
+    Ok(Box::new(BufReader::new(
+        zstd::stream::read::Decoder::with_buffer(reader)?,
+    )))
+}
+
+#[cfg(not(feature = "compression"))]
+fn gzip<R: BufRead + 'static>(_reader: R) -> io::Result<Box<dyn BufRead>> {
+    Err(unsupported())
+}
+
+#[cfg(not(feature = "compression"))]
+fn zstd<R: BufRead + 'static>(_reader: R) -> io::Result<Box<dyn BufRead>> {
+    Err(unsupported())
+}
+
+#[cfg(not(feature = "compression"))]
+fn unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "Compressed inputs require the compression feature",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separated_records() {
+        let split = |content: &[u8], separator| {
+            records(content, separator)
+                .collect::<io::Result<Vec<String>>>()
+                .unwrap()
+        };
+
+        assert_eq!(split(b"654\r\n100\n", b'\n'), ["654", "100"]);
+        assert_eq!(split(b"654 100\x003238\x00", b'\0'), ["654 100", "3238"]);
+        assert_eq!(split(b"654|100", b'|'), ["654", "100"]);
+    }
+}
@@ -0,0 +1,61 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Parallel processing that keeps results in input order.
+
+use std::num::NonZeroUsize;
+use std::thread;
+
+/// Amount of items read before they are processed in parallel.
+pub const BATCH: usize = 8192;
+
+/// The amount of jobs to use when none is given.
+pub fn available() -> usize {
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+/// Applies `f` to every item using up to `jobs` threads. The results are in
+/// the same order as the items.
+pub fn map<T, U, F>(jobs: usize, items: &[T], f: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> U + Sync,
+{
+    if jobs <= 1 || items.len() < 2 {
+        return items.iter().map(f).collect();
+    }
+
+    let size = items.len().div_ceil(jobs);
+
+    let f = &f;
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(f).collect::<Vec<U>>()))
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("a job panicked"))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_order() {
+        let items: Vec<usize> = (0..1000).collect();
+
+        for jobs in 1..6 {
+            assert_eq!(map(jobs, &items, |x| x * 2), map(1, &items, |x| x * 2));
+        }
+    }
+}
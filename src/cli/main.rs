@@ -4,25 +4,72 @@
 // This file may not be copied, modified, or distributed except
 // according to those terms.
 
+#[cfg(feature = "barcode-image")]
+mod barcode;
+mod check;
+mod classify;
+mod color;
+#[cfg(feature = "parquet")]
+mod columnar;
+mod columns;
+mod complete;
+mod config;
+mod convert;
+mod delimited;
+mod exit;
+mod explain;
+mod fix;
+mod gate;
+mod generate;
+mod hl7;
+mod info;
+mod input;
+mod jobs;
+#[cfg(feature = "serve")]
+mod metrics;
+mod output;
+mod paths;
+mod progress;
+mod pseudo;
+#[cfg(feature = "qr")]
+mod qr;
+mod redact;
+mod scan;
+#[cfg(feature = "serve")]
+mod serve;
+mod sink;
+#[cfg(feature = "xlsx")]
+mod spreadsheet;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod watch;
+
 use clap::arg_enum;
-use heidi::{chi, nhs};
+use exit::Code;
+use gate::Gate;
+use heidi::i18n::{self, Language, Message, Text};
+use heidi::number::Format;
+use heidi::privacy::{Date, Sex};
+use heidi::pseudo::Pseudonymizer;
+use heidi::scan::ScanOptions;
+use output::{Output, Reporter};
+use progress::Progress;
+use sink::Sink;
+use std::fs;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
 use std::process;
-use std::str::FromStr;
 use structopt::StructOpt;
 
-arg_enum! {
-    #[derive(Debug)]
-    enum Format {
-        Compact,
-        Official,
-    }
-}
+/// The names of [`Format`] the command line takes.
+const FORMATS: &[&str] = &["official", "compact", "hyphenated", "hyphen", "masked"];
 
 arg_enum! {
-    #[derive(Debug)]
-    enum Typeid {
+    #[derive(PartialEq, Debug)]
+    pub enum Typeid {
         Nhs,
         Chi,
+        Hcn,
     }
 }
 
@@ -46,21 +93,442 @@ arg_enum! {
 /// It is 10 digits long.
 ///
 /// See <https://www.ndc.scot.nhs.uk/Data-Dictionary/SMR-Datasets/Patient-Identification-and-Demographic-Information/Community-Health-Index-Number/>
+///
+///
+/// ## hcn type
+///
+/// An H&C (Health and Care) number is the health identifier for Northern Ireland.
+///
+/// It is 10 digits long, validated like an NHS number and allocated from 320 000 0010 to
+/// 399 999 9999.
+///
+///
+/// ## Exit codes
+///
+/// 0 when every number is valid, 1 when a number is invalid or `scan` finds an identifier, 2 on
+/// usage errors, 3 on I/O errors and 4 when a batch run exceeds `--max-invalid` or
+/// `--max-invalid-pct`.
+#[derive(StructOpt, Debug)]
+struct Opt {
+    /// The format to report results in. Defaults to plain.
+    #[structopt(long, global = true, possible_values=&["plain", "json", "ndjson", "csv", "sarif"], case_insensitive=true)]
+    output: Option<Output>,
+
+    /// The language of messages: `en` or `cy` (Welsh). Error kinds and codes stay the same.
+    #[structopt(long, global = true, env = "HEIDI_LANG")]
+    lang: Option<Language>,
+
+    /// The amount of threads batch subcommands use. Defaults to the available cores.
+    #[structopt(long, short = "j", global = true)]
+    jobs: Option<usize>,
+
+    /// Writes the report to the given file instead of stdout. The file is only replaced once the
+    /// run completes.
+    #[structopt(long, global = true, parse(from_os_str))]
+    output_file: Option<PathBuf>,
+
+    /// Appends the report to `--output-file` instead of replacing it.
+    #[structopt(long, global = true, requires = "output-file")]
+    append: bool,
+
+    /// Prints nothing. The exit code tells whether the numbers are valid. A report is still
+    /// written to `--output-file`.
+    #[structopt(long, short = "q", global = true)]
+    quiet: bool,
+
+    /// Does not colour plain text output. Setting the `NO_COLOR` environment variable has the
+    /// same effect.
+    #[structopt(long, global = true)]
+    no_color: bool,
+
+    /// Does not draw a progress bar on stderr for batch subcommands.
+    #[structopt(long, global = true)]
+    no_progress: bool,
+
+    /// Stops batch validation at the first invalid number, or at the first one past
+    /// `--max-invalid`.
+    #[structopt(long, global = true)]
+    fail_fast: bool,
+
+    /// The amount of invalid numbers a batch run tolerates before exiting with an error.
+    #[structopt(long, global = true)]
+    max_invalid: Option<usize>,
+
+    /// The percentage of invalid numbers a batch run tolerates before exiting with an error.
+    #[structopt(long, global = true, parse(try_from_str = percentage))]
+    max_invalid_pct: Option<f64>,
+
+    #[structopt(subcommand)]
+    command: Command,
+}
+
 #[derive(StructOpt, Debug)]
-enum Opt {
+enum Command {
     /// Validates a health identifier number for the given type.
+    ///
+    /// The type goes before the number or in `--type`, and defaults to the `HEIDI_TYPE`
+    /// environment variable, e.g. `HEIDI_TYPE=chi heidi check 0101990014`.
     Check {
+        /// The type of health identifier, or `auto` to try every type and report the ones the
+        /// number validates as.
+        #[structopt(long = "type", env = "HEIDI_TYPE", possible_values=&["nhs", "chi", "hcn", "auto"], case_insensitive=true)]
+        _type: Option<check::Selector>,
+
+        /// The type of health identifier (nhs, chi or auto) unless given in `--type`, followed by
+        /// the number to validate. Use `-` or omit the number to read numbers from stdin, one per
+        /// line unless `--delimiter` or `-0` is given.
+        #[structopt(value_names = &["type", "number"], max_values = 2)]
+        args: Vec<String>,
+
+        /// Validates a file with a number per line and reports the invalid ones. Directories are
+        /// read recursively and glob patterns like `ids/**/*.txt` are expanded. Repeat to
+        /// validate several files.
+        #[structopt(long, number_of_values = 1)]
+        file: Vec<String>,
+
+        /// How to treat blank lines in a file.
+        #[structopt(long, possible_values=&["skip", "invalid"], default_value="skip", case_insensitive=true)]
+        blank: check::Blank,
+
+        /// Lines in a file starting with this prefix are skipped. Use an empty string to disable.
+        #[structopt(long, default_value = "#")]
+        comment: String,
+
+        /// Prints only valid numbers, in the given format: `official`, `compact`, `hyphenated` (or
+        /// `hyphen`) or `masked`. Invalid numbers are told on stderr, so `check` can normalise
+        /// numbers in a pipeline.
+        #[structopt(long, possible_values=FORMATS, case_insensitive=true, conflicts_with = "file")]
+        print: Option<Format>,
+
+        /// Explains why numbers are invalid: the category of the error, the check digit given
+        /// against the one expected, and the valid numbers one mistyped or swapped digit away.
+        #[structopt(long, conflicts_with = "print")]
+        explain: bool,
+
+        /// Keeps watching the files given in `--file`, validating lines as they are appended and
+        /// files as they arrive or are rewritten, until interrupted.
+        #[structopt(long, requires = "file")]
+        watch: bool,
+
+        #[structopt(flatten)]
+        records: Records,
+    },
+    /// Tells what can be told from a number: the schemes it validates under, its check digit,
+    /// official format and region, and for CHI numbers the date of birth and sex.
+    Info {
+        /// The number to describe.
+        number: String,
+
+        /// The type the number is expected to be. Any scheme is accepted when omitted.
+        #[structopt(long = "type", possible_values=&["nhs", "chi", "hcn"], case_insensitive=true)]
+        _type: Option<Typeid>,
+
+        /// The two-digit year from which CHI years of birth are in the 1900s, e.g. with 30 `29`
+        /// is 2029 and `30` is 1930. Defaults to taking the latest year not after today.
+        #[structopt(long, parse(try_from_str = info::pivot))]
+        century_pivot: Option<u16>,
+    },
+    /// Shows how the Modulus 11 check digit of a number is derived.
+    Explain {
         /// The type of health identifier.
-        #[structopt(possible_values=&["nhs", "chi"])]
+        #[structopt(possible_values=&["nhs", "chi", "hcn"])]
+        _type: Typeid,
+
+        /// The 9 main digits or the full 10 digits of the number.
+        number: String,
+    },
+    /// Lists the valid numbers within one mistyped digit or one swap of adjacent digits of an
+    /// invalid number.
+    Fix {
+        /// The type of health identifier.
+        #[structopt(possible_values=&["nhs", "chi", "hcn"])]
+        _type: Typeid,
+
+        /// The number to correct.
+        number: String,
+    },
+    /// Appends the check digit to the 9 main digits of a number, e.g. when allocating numbers from
+    /// a local block.
+    Complete {
+        /// The output format. Official display requires a particular spacing, for example an NHS
+        /// Number requires a 3-3-4 formatting: 123 456 7890.
+        #[structopt(long, short="f", possible_values=FORMATS, default_value="compact", case_insensitive=true)]
+        format: Format,
+
+        /// The type of health identifier.
+        #[structopt(possible_values=&["nhs", "chi", "hcn"])]
         _type: Typeid,
 
-        /// The health identifier number to validate.
+        /// The 9 main digits.
+        stem: String,
+    },
+    /// Prints the check digit of the 9 main digits of a number.
+    Checkdigit {
+        /// The type of health identifier.
+        #[structopt(possible_values=&["nhs", "chi", "hcn"])]
+        _type: Typeid,
+
+        /// The 9 main digits.
+        stem: String,
+    },
+    /// Validates numbers and writes them in another format, one per line.
+    Convert {
+        /// The type the numbers are expected to be.
+        #[structopt(long, possible_values=&["any", "nhs", "chi"], default_value="any", case_insensitive=true)]
+        from: convert::Source,
+
+        /// The format to write the numbers in: `official` (`654 100 3238`), `compact`
+        /// (`6541003238`), `hyphenated` or `hyphen` (`654-100-3238`) or `masked` (`******3238`).
+        #[structopt(long, possible_values=FORMATS, case_insensitive=true)]
+        to: Format,
+
+        /// The numbers to convert. Use `-` or omit them to read numbers from stdin, one per line
+        /// unless `--delimiter` or `-0` is given.
+        numbers: Vec<String>,
+
+        #[structopt(flatten)]
+        records: Records,
+    },
+    /// Tells the schemes numbers validate under and the region they are allocated to: England and
+    /// Wales, Scotland, Northern Ireland or the test range.
+    Classify {
+        /// The numbers to classify. Use `-` or omit them to read numbers from stdin, one per line
+        /// unless `--delimiter` or `-0` is given.
+        numbers: Vec<String>,
+
+        #[structopt(flatten)]
+        records: Records,
+    },
+    /// Works with CSV files.
+    Csv(CsvOpt),
+    /// Works with HL7 v2 messages.
+    Hl7(Hl7Opt),
+    /// Finds identifiers in files of any kind, e.g. logs.
+    ///
+    /// Directories are read recursively, skipping hidden and binary files, and glob patterns
+    /// like `logs/**/*.log` are expanded. Reports the file, line, column, matched text and
+    /// confidence of each candidate, also as SARIF with `--output sarif`. Exits with 1 when any
+    /// identifier is found.
+    Scan {
+        /// The files, directories or glob patterns to scan.
+        #[structopt(required = true)]
+        paths: Vec<String>,
+
+        /// The types of health identifier, separated by commas.
+        #[structopt(long = "type", possible_values=&["nhs", "chi", "hcn"], default_value="nhs", case_insensitive=true, use_delimiter=true)]
+        _type: Vec<Typeid>,
+
+        /// Also reports candidates with an invalid check digit.
+        #[structopt(long)]
+        include_invalid: bool,
+
+        /// Only reports candidates with at least this confidence, from 0 to 1.
+        #[structopt(long, default_value = "0")]
+        min_confidence: f32,
+
+        /// Keeps watching the paths, scanning files again once they stop changing, until
+        /// interrupted.
+        #[structopt(long)]
+        watch: bool,
+    },
+    /// Rewrites a file with the identifiers found replaced.
+    ///
+    /// Plain text, CSV, JSON and NDJSON are read according to the file extension unless `--mode`
+    /// is given. The redacted content is written to stdout, to `--output-file` or over the input
+    /// with `--in-place`, and the amount of identifiers replaced to stderr.
+    Redact {
+        /// The file to redact.
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+
+        /// Replaces the input once the redacted content is complete.
+        #[structopt(long, conflicts_with = "output-file")]
+        in_place: bool,
+
+        /// How to replace identifiers: `mask` (`[REDACTED]`), `last4` (`******3238`), `tag`
+        /// (`[NHS]`) or `pseudonym` (`ID-000001`, or `PSN-…` with `--key-file`).
+        #[structopt(long, possible_values=&["mask", "last4", "tag", "pseudonym"], default_value="mask", case_insensitive=true)]
+        policy: redact::Strategy,
+
+        /// A file whose content keys pseudonyms so they are stable across runs.
+        #[structopt(long, parse(from_os_str))]
+        key_file: Option<PathBuf>,
+
+        /// How to read the input.
+        #[structopt(long, possible_values=&["text", "csv", "json", "ndjson"], case_insensitive=true)]
+        mode: Option<redact::Mode>,
+
+        /// The CSV column to redact. Repeat to redact several columns. Columns holding mostly
+        /// valid numbers are detected when omitted.
+        #[structopt(long = "column", number_of_values = 1)]
+        columns: Vec<String>,
+
+        /// The JSON key path to redact, e.g. `patient.nhsNumber`. Repeat to redact several paths.
+        /// Any value that is a whole identifier is redacted when omitted.
+        #[structopt(long = "path", number_of_values = 1)]
+        paths: Vec<String>,
+
+        /// The CSV field delimiter.
+        #[structopt(long, default_value = ",")]
+        delimiter: char,
+    },
+    /// Writes a copy of a CSV file with identifier columns replaced by stable pseudonyms.
+    ///
+    /// A pseudonym is the HMAC-SHA256 of the number under the key in `--key-file`, so the same
+    /// number always gets the same pseudonym under the same key. Invalid values are emptied and
+    /// fail the run as in `check`. The copy is written to stdout or `--output-file`.
+    Pseudo {
+        /// A file whose content is the secret key.
+        #[structopt(long, parse(from_os_str))]
+        key_file: PathBuf,
+
+        /// The column to pseudonymise. Repeat to pseudonymise several columns.
+        #[structopt(long = "column", required = true, number_of_values = 1)]
+        columns: Vec<String>,
+
+        /// The type of health identifier.
+        #[structopt(long = "type", possible_values=&["nhs", "chi", "hcn"], default_value="nhs", case_insensitive=true)]
+        _type: Typeid,
+
+        /// Mixes a dataset or purpose label into the pseudonyms so they cannot be linked with
+        /// those of datasets with another label.
+        #[structopt(long)]
+        dataset: Option<String>,
+
+        /// Keeps only the first characters of each pseudonym. Shorter pseudonyms are more likely
+        /// to collide.
+        #[structopt(long)]
+        truncate: Option<usize>,
+
+        /// Writes every pseudonym next to the number it stands for to the given file. Only use it
+        /// when reversing the pseudonymisation is authorised, and keep it apart from the dataset.
+        #[structopt(long, parse(from_os_str))]
+        crosswalk: Option<PathBuf>,
+
+        /// The field delimiter.
+        #[structopt(long, default_value = ",")]
+        delimiter: char,
+
+        /// The CSV file to pseudonymise.
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+    },
+    /// Renders the Code 128 barcode of an NHS Number as an SVG or PNG image to print on labels
+    /// and wristbands.
+    ///
+    /// The image goes to `--output-file`, e.g. `heidi barcode 6541003238 --output-file
+    /// label.svg`, in the format its extension tells, or to stdout as SVG unless `--image` says
+    /// otherwise.
+    #[cfg(feature = "barcode-image")]
+    Barcode {
+        /// The NHS Number to encode.
         number: String,
+
+        /// Encodes the number as a GS1-128 Global Service Relation Number under the given GS1
+        /// company prefix.
+        #[structopt(long)]
+        gs1_prefix: Option<String>,
+
+        /// The image format. Defaults to the extension of `--output-file`, or SVG.
+        #[structopt(long, possible_values=&["svg", "png"], case_insensitive=true)]
+        image: Option<barcode::Image>,
+
+        /// The width in pixels of the narrowest bar.
+        #[structopt(long, default_value = "2")]
+        module: u32,
+
+        /// The height in pixels of the bars.
+        #[structopt(long, default_value = "80")]
+        height: u32,
+    },
+    /// Renders the QR code of a number as an SVG image, e.g. for appointment letters and
+    /// patient-held records.
+    ///
+    /// The image goes to `--output-file` or stdout.
+    #[cfg(feature = "qr")]
+    Qr {
+        /// The number to encode.
+        number: String,
+
+        /// The type of health identifier.
+        #[structopt(long = "type", default_value = "nhs", possible_values=&["nhs", "chi", "hcn"], case_insensitive=true)]
+        _type: Typeid,
+
+        /// Encodes a FHIR Identifier, whose system tells the type, instead of the bare number.
+        #[structopt(long)]
+        fhir: bool,
+
+        /// Draws the code with Unicode blocks instead, to scan it off the terminal.
+        #[structopt(long)]
+        text: bool,
+
+        /// The size in pixels of each module.
+        #[structopt(long, default_value = "4")]
+        module: u32,
     },
+    /// Serves validation and generation over HTTP with JSON responses, e.g. as a sidecar.
+    ///
+    /// `GET /v1/{scheme}/validate/{number}` validates a number, `POST /v1/{scheme}/validate`
+    /// validates a JSON array of numbers and `POST /v1/{scheme}/generate` generates numbers as
+    /// described by a JSON object like `{"count": 10, "unique": true, "seed": 42}`. The scheme
+    /// is `nhs`, `chi`, or `auto` when validating. `GET /openapi.json` describes them and
+    /// `GET /metrics` gives request counts, outcomes and latencies to Prometheus.
+    #[cfg(feature = "serve")]
+    Serve {
+        /// The address to listen on.
+        #[structopt(long, default_value = "127.0.0.1:8080")]
+        address: String,
+
+        /// Prints the OpenAPI document describing the endpoints instead of serving them. It is
+        /// also served at `/openapi.json`.
+        #[structopt(long)]
+        openapi: bool,
+    },
+    /// Works with Parquet files.
+    #[cfg(feature = "parquet")]
+    Parquet(ParquetOpt),
+    /// Works with SQLite databases.
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteOpt),
+    /// Works with spreadsheets (XLSX, XLS and ODS).
+    #[cfg(feature = "xlsx")]
+    Xlsx(XlsxOpt),
+    /// Generates random valid numbers, one per line. Structured output also gives their other
+    /// formats, the schemes they validate under and their region.
     Generate {
+        /// The amount of numbers to generate.
+        #[structopt(long, short = "n", default_value = "1")]
+        count: usize,
+
+        /// Guarantees no number is repeated within the run.
+        #[structopt(long)]
+        unique: bool,
+
+        /// Draws the numbers from a generator seeded with the given value so the same seed gives
+        /// the same numbers on every run and machine.
+        #[structopt(long)]
+        seed: Option<u64>,
+
+        /// Only generates numbers that are never issued to patients: NHS numbers in the `999`
+        /// test range and CHI numbers born on the 30th or 31st of February.
+        #[structopt(long, conflicts_with_all = &["dob", "year-range", "sex"])]
+        test_range: bool,
+
+        /// Only generates CHI numbers of people born on the given date, e.g. `1985-03-12`.
+        #[structopt(long, parse(try_from_str = generate::dob))]
+        dob: Option<(Date, Date)>,
+
+        /// Only generates CHI numbers of people born within the given years, e.g. `1940:1990`.
+        #[structopt(long, conflicts_with = "dob", parse(try_from_str = generate::years))]
+        year_range: Option<(Date, Date)>,
+
+        /// Only generates CHI numbers of the given sex: `female` or `male`.
+        #[structopt(long, parse(try_from_str = generate::sex))]
+        sex: Option<Sex>,
+
         /// The output format. Official display requires a particular spacing, for example an NHS
         /// Number requires a 3-3-4 formatting: 123 456 7890.
-        #[structopt(long, short="f", possible_values=&["compact", "official"], default_value="compact", case_insensitive=true)]
+        #[structopt(long, short="f", possible_values=FORMATS, default_value="compact", case_insensitive=true)]
         format: Format,
 
         /// The type of health identifier.
@@ -69,56 +537,613 @@ enum Opt {
     },
 }
 
+/// How numbers read from stdin or files are separated.
+#[derive(StructOpt, Debug)]
+struct Records {
+    /// Reads numbers separated by NUL bytes, as written by `find -print0`.
+    #[structopt(long = "null", short = "0", conflicts_with = "delimiter")]
+    null: bool,
+
+    /// Reads numbers separated by the given character instead of newlines.
+    #[structopt(long)]
+    delimiter: Option<char>,
+}
+
+impl Records {
+    /// The byte separating numbers.
+    fn separator(&self) -> io::Result<u8> {
+        match (self.null, self.delimiter) {
+            (true, _) => Ok(b'\0'),
+            (false, None) => Ok(b'\n'),
+            (false, Some(delimiter)) if delimiter.is_ascii() => Ok(delimiter as u8),
+            (false, Some(_)) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                Message::new(Text::AsciiDelimiter),
+            )),
+        }
+    }
+}
+
+#[derive(StructOpt, Debug)]
+enum CsvOpt {
+    /// Validates the given columns of a CSV file with a header row and reports the invalid values.
+    Check {
+        /// The column to validate. Repeat to validate several columns.
+        #[structopt(long = "column", required = true, number_of_values = 1)]
+        columns: Vec<String>,
+
+        /// The type of health identifier.
+        #[structopt(long = "type", possible_values=&["nhs", "chi", "hcn"], case_insensitive=true)]
+        _type: Typeid,
+
+        /// The field delimiter.
+        #[structopt(long, default_value = ",")]
+        delimiter: char,
+
+        /// Writes a copy of the input with extra `valid` and `error` columns to the given path.
+        #[structopt(long, parse(from_os_str))]
+        annotate: Option<PathBuf>,
+
+        /// The CSV file to validate.
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+enum Hl7Opt {
+    /// Reports the messages whose NHS or CHI patient identifiers (PID-3) are invalid or in the
+    /// test range, together with their message control ID.
+    ///
+    /// Files hold any amount of messages, with or without MLLP framing. Directories are read
+    /// recursively and glob patterns like `feeds/**/*.hl7` are expanded. Exits with 1 when any
+    /// message is reported.
+    Audit {
+        /// The files, directories or glob patterns to audit. Use `-` or omit them to read
+        /// messages from stdin.
+        paths: Vec<String>,
+    },
+}
+
+#[cfg(feature = "parquet")]
+#[derive(StructOpt, Debug)]
+enum ParquetOpt {
+    /// Validates the given columns of a Parquet file and reports the invalid values by row.
+    Check {
+        /// The column to validate. Repeat to validate several columns.
+        #[structopt(long = "column", required = true, number_of_values = 1)]
+        columns: Vec<String>,
+
+        /// The type of health identifier.
+        #[structopt(long = "type", possible_values=&["nhs", "chi", "hcn"], case_insensitive=true)]
+        _type: Typeid,
+
+        /// The Parquet file to validate.
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+    },
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(StructOpt, Debug)]
+enum SqliteOpt {
+    /// Validates the given columns of a table and reports the invalid values by rowid.
+    Check {
+        /// The SQLite database file.
+        #[structopt(long, parse(from_os_str))]
+        db: PathBuf,
+
+        /// The table to read.
+        #[structopt(long)]
+        table: String,
+
+        /// The column to validate. Repeat to validate several columns.
+        #[structopt(long = "column", required = true, number_of_values = 1)]
+        columns: Vec<String>,
+
+        /// The type of health identifier.
+        #[structopt(long = "type", possible_values=&["nhs", "chi", "hcn"], default_value="nhs", case_insensitive=true)]
+        _type: Typeid,
+    },
+}
+
+#[cfg(feature = "xlsx")]
+#[derive(StructOpt, Debug)]
+enum XlsxOpt {
+    /// Validates the given columns of a sheet and reports the invalid values by cell.
+    Check {
+        /// The sheet to read. Defaults to the first one.
+        #[structopt(long)]
+        sheet: Option<String>,
+
+        /// The column to validate, either a letter like `B` or a header name. Repeat to validate
+        /// several columns.
+        #[structopt(long = "column", required = true, number_of_values = 1)]
+        columns: Vec<String>,
+
+        /// The type of health identifier.
+        #[structopt(long = "type", possible_values=&["nhs", "chi", "hcn"], default_value="nhs", case_insensitive=true)]
+        _type: Typeid,
+
+        /// Treats the first row as data instead of a header row.
+        #[structopt(long)]
+        no_header: bool,
+
+        /// The spreadsheet to validate.
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+    },
+}
+
+fn percentage(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(pct) if (0.0..=100.0).contains(&pct) => Ok(pct),
+        _ => Err("expected a percentage from 0 to 100".to_string()),
+    }
+}
+
+/// Reads a secret key from a file.
+fn key(path: &Path) -> io::Result<Vec<u8>> {
+    let key = fs::read(path)?;
+
+    if key.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("The key file {} is empty", path.display()),
+        ));
+    }
+
+    Ok(key)
+}
+
+/// Exits with the given code, or with the code for the error after telling
+/// it in the given language. See [`Code`].
+fn exit_with(code: io::Result<Code>, quiet: bool, language: Language) {
+    let code = code.unwrap_or_else(|e| {
+        if !quiet {
+            // Messages of the catalogue are told in the language of the run.
+            let message = e
+                .get_ref()
+                .and_then(|inner| inner.downcast_ref::<Message>())
+                .map_or_else(|| e.to_string(), |message| message.in_language(language));
+
+            eprintln!("{}: {}.", i18n::text(Text::Error, language), message);
+        }
+        Code::error(&e)
+    });
+
+    if code != Code::Success {
+        process::exit(code as i32);
+    }
+}
+
 fn main() {
-    match Opt::from_args() {
-        Opt::Check { _type, number } => match _type {
-            Typeid::Nhs => {
-                match nhs::Number::from_str(&number) {
-                    Ok(n) => {
-                        println!("NHS Number '{:#}' is valid.", &n);
+    let opt = match Opt::from_args_safe() {
+        Ok(opt) => opt,
+        Err(e) if e.use_stderr() => {
+            eprintln!("{}", e.message);
+            process::exit(Code::Usage as i32);
+        }
+        Err(e) => e.exit(),
+    };
+    let gate = Gate {
+        fail_fast: opt.fail_fast,
+        max_invalid: opt.max_invalid,
+        max_invalid_pct: opt.max_invalid_pct,
+    };
+    let quiet = opt.quiet;
+
+    let config = match config::load() {
+        Ok(config) => config,
+        Err(e) => return exit_with(Err(e), quiet, opt.lang.unwrap_or_default()),
+    };
+    let language = opt.lang.or(config.lang).unwrap_or_default();
+    let output = opt.output.or(config.output).unwrap_or(Output::Plain);
+
+    if output == Output::Sarif && !matches!(opt.command, Command::Scan { .. }) {
+        return exit_with(
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                Message::new(Text::SarifScanOnly),
+            )),
+            quiet,
+            language,
+        );
+    }
+
+    let watching = matches!(
+        opt.command,
+        Command::Check { watch: true, .. } | Command::Scan { watch: true, .. }
+    );
+
+    // Watching never ends, so results are streamed and never held back.
+    if watching && (matches!(output, Output::Json | Output::Sarif) || opt.output_file.is_some()) {
+        return exit_with(
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Watching streams results to stdout: use plain, ndjson or csv output",
+            )),
+            quiet,
+            language,
+        );
+    }
+
+    let sink = match (&opt.command, &opt.output_file) {
+        (
+            Command::Redact {
+                input,
+                in_place: true,
+                ..
+            },
+            _,
+        ) => Sink::file(input, false),
+        (_, Some(path)) => Sink::file(path, opt.append),
+        (_, None) => Ok(Sink::stdout()),
+    };
+    let mut reporter = match sink {
+        Ok(sink) => Reporter::new(
+            output,
+            Progress::new(!opt.no_progress && !quiet && !watching),
+            gate,
+        )
+        .quiet(quiet && opt.output_file.is_none())
+        .color(color::allowed(opt.no_color) && config.color.unwrap_or(true))
+        .language(language)
+        .sink(sink),
+        Err(e) => return exit_with(Err(e), quiet, language),
+    };
+    let jobs = opt
+        .jobs
+        .or(config.jobs)
+        .unwrap_or_else(jobs::available)
+        .max(1);
+
+    let code = match opt.command {
+        Command::Check {
+            _type,
+            args,
+            file,
+            blank,
+            comment,
+            print,
+            records,
+            watch,
+            explain,
+        } => records.separator().and_then(|separator| {
+            check::arguments(&args, _type.or_else(|| config._type.clone())).and_then(
+                |(_type, number)| match (file.is_empty(), number.as_deref(), _type.typeid()) {
+                    (false, Some(_), _) => Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "A number cannot be given together with --file",
+                    )),
+                    (false, None, _type) => {
+                        let options = check::LineOptions {
+                            blank,
+                            comment,
+                            separator,
+                            explain,
+                        };
+
+                        if watch {
+                            check::watch(&mut reporter, _type.as_ref(), &file, &options, jobs)
+                        } else {
+                            paths::expand(&file).and_then(|paths| {
+                                check::file(&mut reporter, _type.as_ref(), &paths, &options, jobs)
+                            })
+                        }
+                        .map(|stats| gate.code(&stats))
                     }
-                    Err(e) => {
-                        eprintln!("NHS Number '{}' is invalid.", &number);
-                        eprintln!("Error: {}.", &e);
-                        process::exit(1);
+                    (true, Some(number), _type) if number != "-" => {
+                        check::one(&mut reporter, _type.as_ref(), number, print, explain)
+                            .map(Code::from)
                     }
-                };
+                    (true, _, _type) => input::decompress(BufReader::new(io::stdin()))
+                        .and_then(|reader| {
+                            check::each(
+                                &mut reporter,
+                                _type.as_ref(),
+                                reader,
+                                separator,
+                                print,
+                                explain,
+                            )
+                        })
+                        .map(|stats| gate.code(&stats)),
+                },
+            )
+        }),
+        Command::Csv(CsvOpt::Check {
+            columns,
+            _type,
+            delimiter,
+            annotate,
+            input,
+        }) => {
+            if delimiter.is_ascii() {
+                delimited::check(
+                    &mut reporter,
+                    &_type,
+                    &input,
+                    &columns,
+                    delimiter as u8,
+                    annotate.as_deref(),
+                    jobs,
+                )
+                .map(|stats| gate.code(&stats))
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    Message::new(Text::AsciiDelimiter),
+                ))
             }
-            Typeid::Chi => {
-                match chi::Number::from_str(&number) {
-                    Ok(n) => {
-                        println!("Chi Number '{:#}' is valid.", &n);
-                    }
-                    Err(e) => {
-                        eprintln!("Chi Number '{}' is invalid.", &number);
-                        eprintln!("Error: {}.", &e);
-                        process::exit(1);
-                    }
-                };
+        }
+        Command::Hl7(Hl7Opt::Audit { paths }) => if paths.is_empty() || paths == ["-"] {
+            input::decompress(BufReader::new(io::stdin()))
+                .and_then(|reader| hl7::stream(&mut reporter, reader))
+        } else {
+            paths::expand(&paths).and_then(|paths| hl7::files(&mut reporter, &paths))
+        }
+        .map(|reported| Code::from(reported == 0)),
+        Command::Explain { _type, number } => {
+            explain::number(&mut reporter, &_type, &number).map(Code::from)
+        }
+        Command::Classify { numbers, records } => {
+            if numbers.is_empty() || numbers == ["-"] {
+                records
+                    .separator()
+                    .and_then(|separator| {
+                        input::decompress(BufReader::new(io::stdin()))
+                            .and_then(|reader| classify::each(&mut reporter, reader, separator))
+                    })
+                    .map(Code::from)
+            } else {
+                numbers
+                    .iter()
+                    .try_fold(true, |valid, number| {
+                        classify::one(&mut reporter, number).map(|ok| valid && ok)
+                    })
+                    .map(Code::from)
             }
-        },
-        Opt::Generate { _type, format } => match _type {
-            Typeid::Nhs => {
-                match nhs::lottery() {
-                    Ok(n) => match format {
-                        Format::Official => println!("{:#}", &n),
-                        _ => println!("{}", &n),
-                    },
-                    Err(e) => {
-                        eprintln!("{}", &e);
-                        process::exit(1);
-                    }
-                };
+        }
+        Command::Convert {
+            from,
+            to,
+            numbers,
+            records,
+        } => {
+            if numbers.is_empty() || numbers == ["-"] {
+                records
+                    .separator()
+                    .and_then(|separator| {
+                        input::decompress(BufReader::new(io::stdin())).and_then(|reader| {
+                            convert::each(&mut reporter, &from, to, reader, separator)
+                        })
+                    })
+                    .map(Code::from)
+            } else {
+                numbers
+                    .iter()
+                    .try_fold(true, |valid, number| {
+                        convert::one(&mut reporter, &from, to, number).map(|ok| valid && ok)
+                    })
+                    .map(Code::from)
             }
-            Typeid::Chi => {
-                match chi::lottery() {
-                    Ok(n) => println!("{}", &n),
-                    Err(e) => {
-                        eprintln!("{}", &e);
-                        process::exit(1);
-                    }
-                };
+        }
+        Command::Checkdigit { _type, stem } => {
+            complete::checkdigit(&mut reporter, &_type, &stem).map(Code::from)
+        }
+        Command::Complete {
+            format,
+            _type,
+            stem,
+        } => complete::stem(&mut reporter, &_type, &stem, format).map(Code::from),
+        Command::Fix { _type, number } => {
+            fix::number(&mut reporter, &_type, &number).map(Code::from)
+        }
+        Command::Info {
+            number,
+            _type,
+            century_pivot,
+        } => {
+            let reference = info::reference(century_pivot.or(config.century_pivot));
+
+            info::number(&mut reporter, _type.as_ref(), &number, reference).map(Code::from)
+        }
+        Command::Scan {
+            paths,
+            _type,
+            include_invalid,
+            min_confidence,
+            watch,
+        } => {
+            let options = ScanOptions {
+                valid_only: !include_invalid,
+                min_confidence,
+                ..ScanOptions::default()
+            };
+
+            if watch {
+                scan::watch(&mut reporter, &_type, &paths, options)
+            } else {
+                paths::expand(&paths)
+                    .and_then(|paths| scan::files(&mut reporter, &_type, &paths, options))
+            }
+            .map(|stats| Code::from(stats.total() == 0))
+        }
+        Command::Pseudo {
+            key_file,
+            columns,
+            _type,
+            dataset,
+            truncate,
+            crosswalk,
+            delimiter,
+            input,
+        } => {
+            if delimiter.is_ascii() {
+                key(&key_file)
+                    .and_then(|key| {
+                        let mut pseudonymizer = Pseudonymizer::new(&key);
+
+                        if let Some(label) = &dataset {
+                            pseudonymizer = pseudonymizer.dataset(label);
+                        }
+
+                        if let Some(length) = truncate {
+                            pseudonymizer = pseudonymizer.truncate(length);
+                        }
+
+                        pseudo::file(
+                            &mut reporter,
+                            &_type,
+                            &input,
+                            &columns,
+                            &pseudonymizer,
+                            delimiter as u8,
+                            crosswalk.as_deref(),
+                        )
+                    })
+                    .map(|stats| gate.code(&stats))
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    Message::new(Text::AsciiDelimiter),
+                ))
             }
-        },
+        }
+        Command::Redact {
+            input,
+            in_place,
+            policy,
+            key_file,
+            mode,
+            columns,
+            paths,
+            delimiter,
+        } => {
+            let options = redact::Options {
+                mode,
+                columns,
+                paths,
+                delimiter: delimiter as u8,
+            };
+
+            if delimiter.is_ascii() {
+                key_file
+                    .as_deref()
+                    .map(key)
+                    .transpose()
+                    .and_then(|key| {
+                        let policy = redact::policy(policy, key.as_deref());
+
+                        redact::file(&mut reporter, &input, in_place, policy, &options)
+                    })
+                    .map(|_| Code::Success)
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    Message::new(Text::AsciiDelimiter),
+                ))
+            }
+        }
+        #[cfg(feature = "barcode-image")]
+        Command::Barcode {
+            number,
+            gs1_prefix,
+            image,
+            module,
+            height,
+        } => barcode::label(
+            &mut reporter,
+            &number,
+            gs1_prefix.as_deref(),
+            barcode::Image::pick(image, opt.output_file.as_deref()),
+            module,
+            height,
+        )
+        .map(|_| Code::Success),
+        #[cfg(feature = "qr")]
+        Command::Qr {
+            number,
+            _type,
+            fhir,
+            text,
+            module,
+        } => {
+            let payload = if fhir {
+                heidi::qr::Payload::Identifier
+            } else {
+                heidi::qr::Payload::Number
+            };
+
+            qr::code(&mut reporter, &_type, &number, payload, text, module).map(|_| Code::Success)
+        }
+        #[cfg(feature = "serve")]
+        Command::Serve { address, openapi } => if openapi {
+            reporter.print(&serve::document())
+        } else {
+            serve::run(&address, jobs)
+        }
+        .map(|_| Code::Success),
+        #[cfg(feature = "parquet")]
+        Command::Parquet(ParquetOpt::Check {
+            columns,
+            _type,
+            input,
+        }) => columnar::check(&mut reporter, &_type, &input, &columns, jobs)
+            .map(|stats| gate.code(&stats)),
+        #[cfg(feature = "sqlite")]
+        Command::Sqlite(SqliteOpt::Check {
+            db,
+            table,
+            columns,
+            _type,
+        }) => sqlite::check(&mut reporter, &_type, &db, &table, &columns, jobs)
+            .map(|stats| gate.code(&stats)),
+        #[cfg(feature = "xlsx")]
+        Command::Xlsx(XlsxOpt::Check {
+            sheet,
+            columns,
+            _type,
+            no_header,
+            input,
+        }) => spreadsheet::check(
+            &mut reporter,
+            &_type,
+            &input,
+            sheet.as_deref(),
+            &columns,
+            !no_header,
+            jobs,
+        )
+        .map(|stats| gate.code(&stats)),
+        Command::Generate {
+            count,
+            unique,
+            seed,
+            test_range,
+            dob,
+            year_range,
+            sex,
+            format,
+            _type,
+        } => {
+            let options = generate::Options {
+                count,
+                unique,
+                seed,
+                test_range,
+                born: dob.or(year_range),
+                sex,
+            };
+
+            generate::numbers(&mut reporter, &_type, format, &options).map(Code::from)
+        }
     };
+
+    exit_with(
+        code.and_then(|code| reporter.finish().map(|_| code)),
+        quiet,
+        language,
+    );
 }
@@ -0,0 +1,183 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Metrics of the HTTP service in the Prometheus text format.
+//!
+//! See <https://prometheus.io/docs/instrumenting/exposition_formats/>.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The upper bounds of the latency histogram buckets, in seconds.
+const BUCKETS: [f64; 8] = [0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+
+/// The requests answered and the numbers validated since the service
+/// started.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Requests by route and status code.
+    requests: BTreeMap<(&'static str, u16), u64>,
+    /// Numbers validated by scheme and validity.
+    validations: BTreeMap<(&'static str, bool), u64>,
+    /// Latencies by route.
+    latencies: BTreeMap<&'static str, Histogram>,
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    /// The amount of observations within each bucket, not cumulative.
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Metrics::default()
+    }
+
+    /// Records a request answered with the given status after `elapsed`.
+    pub fn request(&self, route: &'static str, status: u16, elapsed: Duration) {
+        let mut state = self.state.lock().expect("metrics lock");
+        let seconds = elapsed.as_secs_f64();
+        let histogram = state.latencies.entry(route).or_default();
+
+        if let Some(idx) = BUCKETS.iter().position(|&bound| seconds <= bound) {
+            histogram.buckets[idx] += 1;
+        }
+        histogram.sum += seconds;
+        histogram.count += 1;
+        *state.requests.entry((route, status)).or_default() += 1;
+    }
+
+    /// Records the outcomes of validating numbers as the given scheme.
+    pub fn validations(&self, scheme: &'static str, valid: usize, invalid: usize) {
+        let mut state = self.state.lock().expect("metrics lock");
+
+        *state.validations.entry((scheme, true)).or_default() += valid as u64;
+        *state.validations.entry((scheme, false)).or_default() += invalid as u64;
+    }
+
+    /// Writes every metric in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+
+        self.state
+            .lock()
+            .expect("metrics lock")
+            .write(&mut text)
+            .expect("write to string");
+
+        text
+    }
+}
+
+impl State {
+    fn write(&self, text: &mut String) -> fmt::Result {
+        writeln!(
+            text,
+            "# HELP heidi_requests_total Requests answered by route and status code."
+        )?;
+        writeln!(text, "# TYPE heidi_requests_total counter")?;
+
+        for ((route, status), count) in &self.requests {
+            writeln!(
+                text,
+                "heidi_requests_total{{route=\"{}\",status=\"{}\"}} {}",
+                route, status, count
+            )?;
+        }
+
+        writeln!(
+            text,
+            "# HELP heidi_validations_total Numbers validated by scheme and outcome."
+        )?;
+        writeln!(text, "# TYPE heidi_validations_total counter")?;
+
+        for ((scheme, valid), count) in &self.validations {
+            let outcome = if *valid { "valid" } else { "invalid" };
+
+            writeln!(
+                text,
+                "heidi_validations_total{{scheme=\"{}\",outcome=\"{}\"}} {}",
+                scheme, outcome, count
+            )?;
+        }
+
+        writeln!(
+            text,
+            "# HELP heidi_request_duration_seconds Time taken to answer requests by route."
+        )?;
+        writeln!(text, "# TYPE heidi_request_duration_seconds histogram")?;
+
+        for (route, histogram) in &self.latencies {
+            let mut cumulative = 0;
+
+            for (bound, count) in BUCKETS.iter().zip(&histogram.buckets) {
+                cumulative += count;
+                writeln!(
+                    text,
+                    "heidi_request_duration_seconds_bucket{{route=\"{}\",le=\"{}\"}} {}",
+                    route, bound, cumulative
+                )?;
+            }
+
+            writeln!(
+                text,
+                "heidi_request_duration_seconds_bucket{{route=\"{}\",le=\"+Inf\"}} {}",
+                route, histogram.count
+            )?;
+            writeln!(
+                text,
+                "heidi_request_duration_seconds_sum{{route=\"{}\"}} {}",
+                route, histogram.sum
+            )?;
+            writeln!(
+                text,
+                "heidi_request_duration_seconds_count{{route=\"{}\"}} {}",
+                route, histogram.count
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exposition() {
+        let metrics = Metrics::new();
+
+        metrics.request("validate", 200, Duration::from_millis(3));
+        metrics.request("validate", 200, Duration::from_secs(10));
+        metrics.request("unknown", 404, Duration::from_micros(10));
+        metrics.validations("nhs", 1, 2);
+
+        let text = metrics.render();
+
+        assert!(text.contains("heidi_requests_total{route=\"validate\",status=\"200\"} 2\n"));
+        assert!(text.contains("heidi_validations_total{scheme=\"nhs\",outcome=\"invalid\"} 2\n"));
+        assert!(text.contains(
+            "heidi_request_duration_seconds_bucket{route=\"validate\",le=\"0.005\"} 1\n"
+        ));
+        assert!(
+            text.contains("heidi_request_duration_seconds_bucket{route=\"validate\",le=\"5\"} 1\n")
+        );
+        assert!(text
+            .contains("heidi_request_duration_seconds_bucket{route=\"validate\",le=\"+Inf\"} 2\n"));
+        assert!(text.contains("heidi_request_duration_seconds_count{route=\"unknown\"} 1\n"));
+    }
+}
@@ -0,0 +1,830 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Reporting of results in plain text or machine-readable formats.
+
+use crate::color;
+use crate::gate::Gate;
+use crate::progress::Progress;
+use crate::sink::Sink;
+use clap::arg_enum;
+use heidi::batch::Stats;
+use heidi::error::ValidationError;
+use heidi::i18n::{self, Language, Text};
+use heidi::region::Region;
+use heidi::scan::sarif::SarifLog;
+use heidi::scan::Finding;
+#[cfg(feature = "serve")]
+use schemars::JsonSchema;
+use serde::ser::{self, Impossible};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::{self, IsTerminal, Write};
+
+arg_enum! {
+    /// The format results are reported in.
+    #[derive(PartialEq, Copy, Clone, Debug)]
+    pub enum Output {
+        Plain,
+        Json,
+        Ndjson,
+        Csv,
+        Sarif,
+    }
+}
+
+/// The outcome of validating a single value.
+#[derive(Debug)]
+pub struct Outcome<'a> {
+    /// The type of identifier, e.g. `nhs`.
+    pub kind: &'a str,
+    /// The file or table the value comes from, if any.
+    pub source: Option<&'a str>,
+    /// Where the value is in the source, e.g. a line number.
+    pub location: Option<String>,
+    /// The column the value comes from, if any.
+    pub column: Option<&'a str>,
+    /// The value as given.
+    pub input: &'a str,
+    /// The number in official format or why it is invalid.
+    pub result: Result<String, ValidationError>,
+}
+
+/// A result as written to structured reports.
+#[derive(Serialize, Debug)]
+#[cfg_attr(feature = "serve", derive(JsonSchema))]
+pub struct Record<'a> {
+    /// The schemes the number validates as, separated by commas, or `any`.
+    #[serde(rename = "type")]
+    kind: &'a str,
+    /// The file or table the value comes from, if any.
+    source: Option<&'a str>,
+    /// Where the value is in the source, e.g. a line number.
+    location: Option<&'a str>,
+    /// The column the value comes from, if any.
+    column: Option<&'a str>,
+    /// The value as given.
+    input: &'a str,
+    valid: bool,
+    /// The number in official format.
+    number: Option<&'a str>,
+    /// The kind of error, e.g. `checkdigit`.
+    error: Option<&'static str>,
+    /// The stable code of the error, e.g. `HEIDI_E002`.
+    code: Option<&'static str>,
+    /// Why the number is invalid, in the language of the run.
+    message: Option<String>,
+}
+
+impl<'a> Record<'a> {
+    pub fn new(outcome: &'a Outcome, language: Language) -> Self {
+        let error = outcome.result.as_ref().err();
+
+        Record {
+            kind: outcome.kind,
+            source: outcome.source,
+            location: outcome.location.as_deref(),
+            column: outcome.column,
+            input: outcome.input,
+            valid: outcome.result.is_ok(),
+            number: outcome.result.as_deref().ok(),
+            error: error.map(|e| e.kind().as_str()),
+            code: error.map(ValidationError::code),
+            message: error.map(|e| e.message_in(language)),
+        }
+    }
+}
+
+/// The summary of a batch run as written to structured reports.
+#[derive(Serialize, Debug)]
+#[cfg_attr(feature = "serve", derive(JsonSchema))]
+pub struct Summary {
+    total: usize,
+    valid: usize,
+    invalid: usize,
+    /// Empty values, which are not counted.
+    skipped: usize,
+    /// Invalid numbers by kind of error.
+    errors: BTreeMap<&'static str, usize>,
+    duplicates: usize,
+    /// Valid numbers in the range reserved for testing.
+    test: usize,
+    /// Valid numbers by region.
+    regions: BTreeMap<&'static str, usize>,
+}
+
+/// A JSON report: every result and the summary of batch runs.
+#[derive(Serialize, Debug)]
+#[cfg_attr(feature = "serve", derive(JsonSchema))]
+pub struct Document<R> {
+    pub results: Vec<R>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<Summary>,
+}
+
+/// Reports results in the chosen format.
+///
+/// Structured formats report every result. Plain text reports results
+/// without a location as prose and only the invalid results with a location,
+/// e.g. from a file.
+#[derive(Debug)]
+pub struct Reporter {
+    format: Output,
+    rows: Vec<Value>,
+    summary: Option<Summary>,
+    header: bool,
+    progress: Progress,
+    gate: Gate,
+    quiet: bool,
+    color: bool,
+    language: Language,
+    out: Sink,
+    sarif: SarifLog,
+}
+
+impl Reporter {
+    pub fn new(format: Output, progress: Progress, gate: Gate) -> Self {
+        Reporter {
+            format,
+            rows: Vec::new(),
+            summary: None,
+            header: false,
+            progress,
+            gate,
+            quiet: false,
+            color: false,
+            language: Language::English,
+            out: Sink::stdout(),
+            sarif: SarifLog::new(),
+        }
+    }
+
+    /// Writes the report to the given sink instead of stdout.
+    pub fn sink(mut self, sink: Sink) -> Self {
+        self.out = sink;
+        self
+    }
+
+    /// Reports nothing, leaving the exit code as the only outcome.
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Colours plain text written to a terminal.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Writes messages in the given language.
+    pub fn language(mut self, language: Language) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// The language messages are written in.
+    pub fn lang(&self) -> Language {
+        self.language
+    }
+
+    /// The thresholds batch runs are judged by.
+    pub fn gate(&self) -> &Gate {
+        &self.gate
+    }
+
+    pub fn progress(&mut self) -> &mut Progress {
+        &mut self.progress
+    }
+
+    /// Where content other than results is written to, e.g. a rewritten
+    /// file.
+    pub fn writer(&mut self) -> &mut Sink {
+        &mut self.out
+    }
+
+    pub fn outcome(&mut self, outcome: &Outcome) -> io::Result<()> {
+        self.progress.record(outcome.result.is_ok());
+
+        if self.quiet {
+            return Ok(());
+        }
+
+        if self.format == Output::Plain {
+            let language = self.language;
+            let label = i18n::text(label(outcome.kind), language);
+
+            let stdout = self.color && self.out.is_stdout() && io::stdout().is_terminal();
+            let stderr = self.color && io::stderr().is_terminal();
+            match (&outcome.location, &outcome.result) {
+                (None, Ok(formatted)) if !matches!(outcome.kind, "chi" | "nhs" | "hcn") => {
+                    writeln!(
+                        self.out,
+                        "{}{}.",
+                        color::marker("PASS", color::PASS, stdout),
+                        i18n::render(
+                            Text::ValidAs,
+                            language,
+                            &[
+                                ("label", &label),
+                                ("input", formatted),
+                                ("schemes", &outcome.kind.replace(',', ", "))
+                            ]
+                        )
+                    )?
+                }
+                (None, Ok(formatted)) => writeln!(
+                    self.out,
+                    "{}{}.",
+                    color::marker("PASS", color::PASS, stdout),
+                    verdict(language, label, formatted, true)
+                )?,
+                (None, Err(e)) if self.out.is_stdout() => {
+                    eprintln!(
+                        "{}{}.",
+                        color::marker("FAIL", color::FAIL, stderr),
+                        verdict(
+                            language,
+                            label,
+                            &color::offending(outcome.input, e, stderr),
+                            false
+                        )
+                    );
+                    eprintln!(
+                        "{}: {}.",
+                        i18n::text(Text::Error, language),
+                        e.message_in(language)
+                    );
+                }
+                (None, Err(e)) => {
+                    writeln!(
+                        self.out,
+                        "{}.",
+                        verdict(language, label, outcome.input, false)
+                    )?;
+                    writeln!(
+                        self.out,
+                        "{}: {}.",
+                        i18n::text(Text::Error, language),
+                        e.message_in(language)
+                    )?;
+                }
+                (Some(_), Ok(_)) => {}
+                (Some(location), Err(e)) => {
+                    let column = outcome
+                        .column
+                        .map(|column| format!("{} ", column))
+                        .unwrap_or_default();
+
+                    let out = &mut self.out;
+
+                    self.progress.suspend(|| {
+                        writeln!(
+                            out,
+                            "{}:{}: {}'{}' {}: {}.",
+                            outcome.source.unwrap_or("-"),
+                            location,
+                            column,
+                            color::offending(outcome.input, e, stdout),
+                            i18n::kind(e.kind(), language),
+                            e.message_in(language)
+                        )
+                    })?;
+                }
+            }
+
+            return Ok(());
+        }
+
+        self.row(columns(&Record::new(outcome, self.language))?)
+    }
+
+    /// Collects the findings of a scanned file for SARIF output, which is
+    /// written as a whole once finished.
+    pub fn scanned(&mut self, uri: &str, content: &[u8], findings: &[Finding]) {
+        if self.format == Output::Sarif {
+            self.sarif.add(uri, content, findings);
+        }
+    }
+
+    /// Reports a candidate found while scanning, with how likely it is an
+    /// actual identifier.
+    ///
+    /// Plain text reports every candidate, valid or not.
+    pub fn found(&mut self, outcome: &Outcome, confidence: f32) -> io::Result<()> {
+        self.progress.record(outcome.result.is_ok());
+
+        if self.quiet {
+            return Ok(());
+        }
+
+        // Scores are multiples of 0.05.
+        let confidence = (f64::from(confidence) * 100.0).round() / 100.0;
+
+        if self.format == Output::Plain {
+            let status = match &outcome.result {
+                Ok(_) => String::new(),
+                Err(e) => format!(
+                    " {}: {}.",
+                    i18n::kind(e.kind(), self.language),
+                    e.message_in(self.language)
+                ),
+            };
+            let out = &mut self.out;
+
+            return self.progress.suspend(|| {
+                writeln!(
+                    out,
+                    "{}:{}: {} '{}' ({}){}",
+                    outcome.source.unwrap_or("-"),
+                    outcome.location.as_deref().unwrap_or("-"),
+                    outcome.kind,
+                    outcome.input,
+                    confidence,
+                    status
+                )
+            });
+        }
+
+        let mut fields = columns(&Record::new(outcome, self.language))?;
+
+        fields.push(("confidence", json!(confidence)));
+        self.row(fields)
+    }
+
+    /// Reports an outcome together with an explanation of why it is invalid:
+    /// the given text under plain invalid results, and the given fields
+    /// otherwise.
+    pub fn explained(
+        &mut self,
+        outcome: &Outcome,
+        text: &str,
+        explanation: Vec<(&str, Value)>,
+    ) -> io::Result<()> {
+        if self.format != Output::Plain {
+            self.progress.record(outcome.result.is_ok());
+
+            if self.quiet {
+                return Ok(());
+            }
+
+            let mut fields = columns(&Record::new(outcome, self.language))?;
+
+            fields.extend(explanation);
+            return self.row(fields);
+        }
+
+        self.outcome(outcome)?;
+
+        if self.quiet || outcome.result.is_ok() {
+            return Ok(());
+        }
+
+        if outcome.location.is_none() && self.out.is_stdout() {
+            eprint!("{}", text);
+            return Ok(());
+        }
+
+        // Explanations of located results are indented under their line.
+        let text: String = text.lines().map(|line| format!("  {}\n", line)).collect();
+        let out = &mut self.out;
+
+        self.progress.suspend(|| write!(out, "{}", text))
+    }
+
+    /// Reports the details of a single number. Plain text gives a field per
+    /// line.
+    pub fn details(&mut self, fields: Vec<(&str, Value)>) -> io::Result<()> {
+        if self.quiet {
+            return Ok(());
+        }
+
+        if self.format != Output::Plain {
+            return self.row(fields);
+        }
+
+        let language = self.language;
+
+        for (name, value) in &fields {
+            let value = match value {
+                Value::Null => "-".to_string(),
+                Value::Bool(true) => i18n::text(Text::Yes, language).to_string(),
+                Value::Bool(false) => i18n::text(Text::No, language).to_string(),
+                Value::String(s) => s.clone(),
+                Value::Array(values) => values
+                    .iter()
+                    .map(|value| {
+                        value
+                            .as_str()
+                            .map_or_else(|| value.to_string(), String::from)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                other => other.to_string(),
+            };
+            let label = match field(name) {
+                Some(key) => i18n::text(key, language).to_string(),
+                None => {
+                    let label = name.replace('_', " ");
+                    let mut chars = label.chars();
+
+                    match chars.next() {
+                        Some(first) => first.to_uppercase().chain(chars).collect(),
+                        None => label,
+                    }
+                }
+            };
+
+            writeln!(self.out, "{}: {}", label, value)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reports free text in plain text and the given fields otherwise.
+    pub fn report(&mut self, text: &str, fields: Vec<(&str, Value)>) -> io::Result<()> {
+        if self.quiet {
+            return Ok(());
+        }
+
+        if self.format == Output::Plain {
+            return write!(self.out, "{}", text);
+        }
+
+        self.row(fields)
+    }
+
+    /// Writes a line as is regardless of the format.
+    pub fn print(&mut self, line: &str) -> io::Result<()> {
+        if self.quiet {
+            return Ok(());
+        }
+
+        writeln!(self.out, "{}", line)
+    }
+
+    /// Tells something on stderr regardless of the format.
+    pub fn warn(&self, message: &str) {
+        if !self.quiet {
+            self.progress.suspend(|| eprintln!("{}", message));
+        }
+    }
+
+    /// Reports the summary of a batch run.
+    pub fn summary(&mut self, stats: &Stats, skipped: usize) -> io::Result<()> {
+        self.progress.finish();
+
+        if self.quiet {
+            return Ok(());
+        }
+
+        let language = self.language;
+
+        match self.format {
+            Output::Plain => {
+                writeln!(self.out)?;
+                writeln!(
+                    self.out,
+                    "{}",
+                    i18n::render(
+                        Text::Checked,
+                        language,
+                        &[
+                            ("total", &stats.total()),
+                            ("valid", &stats.valid()),
+                            ("invalid", &stats.invalid())
+                        ]
+                    )
+                )?;
+
+                for (kind, count) in stats.errors() {
+                    writeln!(self.out, "  {}: {}", i18n::kind(*kind, language), count)?;
+                }
+
+                writeln!(
+                    self.out,
+                    "{}",
+                    i18n::render(
+                        Text::Duplicates,
+                        language,
+                        &[("count", &stats.duplicates())]
+                    )
+                )?;
+                writeln!(
+                    self.out,
+                    "{}",
+                    i18n::render(Text::TestRange, language, &[("count", &test_range(stats))])
+                )?;
+                if !stats.regions().is_empty() {
+                    writeln!(self.out, "{}", i18n::text(Text::ValidByRegion, language))?;
+                }
+
+                for (region, count) in stats.regions() {
+                    writeln!(self.out, "  {}: {}", region, count)?;
+                }
+
+                if skipped > 0 {
+                    writeln!(
+                        self.out,
+                        "{}",
+                        i18n::render(Text::Skipped, language, &[("count", &skipped)])
+                    )?;
+                }
+            }
+            // CSV and SARIF output have room for results only.
+            Output::Csv | Output::Sarif => {}
+            Output::Json | Output::Ndjson => {
+                let summary = Summary {
+                    total: stats.total(),
+                    valid: stats.valid(),
+                    invalid: stats.invalid(),
+                    skipped,
+                    errors: stats
+                        .errors()
+                        .iter()
+                        .map(|(kind, count)| (kind.as_str(), *count))
+                        .collect(),
+                    duplicates: stats.duplicates(),
+                    test: test_range(stats),
+                    regions: stats
+                        .regions()
+                        .iter()
+                        .map(|(region, count)| (region.as_str(), *count))
+                        .collect(),
+                };
+
+                if self.format == Output::Ndjson {
+                    line(&mut self.out, &json!({ "summary": summary }))?;
+                } else {
+                    self.summary = Some(summary);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes anything held back until all results are known and moves the
+    /// report into place.
+    pub fn finish(self) -> io::Result<()> {
+        self.close()?.commit()
+    }
+
+    /// Writes anything held back until all results are known and returns
+    /// where the report was written to.
+    pub fn close(mut self) -> io::Result<Sink> {
+        if self.format == Output::Json && !self.quiet {
+            let document = Document {
+                results: self.rows,
+                summary: self.summary,
+            };
+
+            serde_json::to_writer_pretty(&mut self.out, &document)?;
+            writeln!(self.out)?;
+        }
+
+        if self.format == Output::Sarif && !self.quiet {
+            self.sarif.write_to(&mut self.out)?;
+        }
+
+        Ok(self.out)
+    }
+
+    fn row(&mut self, fields: Vec<(&str, Value)>) -> io::Result<()> {
+        match self.format {
+            Output::Plain | Output::Sarif => Ok(()),
+            Output::Json => {
+                self.rows.push(object(fields));
+                Ok(())
+            }
+            Output::Ndjson => {
+                let value = object(fields);
+                let out = &mut self.out;
+
+                self.progress.suspend(|| line(out, &value))
+            }
+            Output::Csv => {
+                let mut writer = csv::Writer::from_writer(&mut self.out);
+
+                if !self.header {
+                    writer.write_record(fields.iter().map(|(name, _)| name))?;
+                    self.header = true;
+                }
+
+                writer.write_record(fields.iter().map(|(_, value)| {
+                    match value {
+                        Value::Null => String::new(),
+                        Value::String(s) => s.clone(),
+                        // Lists of names, e.g. schemes, are joined by commas.
+                        Value::Array(values) if values.iter().all(Value::is_string) => values
+                            .iter()
+                            .filter_map(Value::as_str)
+                            .collect::<Vec<_>>()
+                            .join(","),
+                        other => other.to_string(),
+                    }
+                }))?;
+                writer.flush()
+            }
+        }
+    }
+}
+
+/// Valid numbers in the range reserved for testing.
+fn test_range(stats: &Stats) -> usize {
+    stats.regions().get(&Region::Test).copied().unwrap_or(0)
+}
+
+/// The heading of a field of [`Reporter::details`], if the catalogue has it.
+fn field(name: &str) -> Option<Text> {
+    match name {
+        "input" => Some(Text::Input),
+        "valid" => Some(Text::Validity),
+        "declared" => Some(Text::Declared),
+        "error" => Some(Text::Error),
+        "code" => Some(Text::Code),
+        "message" => Some(Text::Message),
+        "schemes" => Some(Text::Schemes),
+        "check_digit" => Some(Text::CheckDigitField),
+        "official" => Some(Text::Official),
+        "region" => Some(Text::Region),
+        "date_of_birth" => Some(Text::DateOfBirth),
+        "sex" => Some(Text::Sex),
+        _ => None,
+    }
+}
+
+/// How results of the given type are called.
+pub fn label(kind: &str) -> Text {
+    match kind {
+        "chi" => Text::ChiNumber,
+        "nhs" => Text::NhsNumber,
+        "hcn" => Text::HcnNumber,
+        _ => Text::Number,
+    }
+}
+
+/// States whether the input is valid, without a full stop.
+fn verdict(language: Language, label: &str, input: &str, valid: bool) -> String {
+    let key = if valid { Text::Valid } else { Text::Invalid };
+
+    i18n::render(key, language, &[("label", &label), ("input", &input)])
+}
+
+/// Returns the fields of a record in the order they are declared, which is
+/// the order of CSV columns.
+pub fn columns<T: Serialize>(record: &T) -> io::Result<Vec<(&'static str, Value)>> {
+    Ok(record.serialize(Columns(Vec::new()))?)
+}
+
+fn line<W: Write, T: Serialize>(out: &mut W, value: &T) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, value)?;
+    writeln!(out)
+}
+
+fn object(fields: Vec<(&str, Value)>) -> Value {
+    Value::Object(
+        fields
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect(),
+    )
+}
+
+/// Collects the fields of a struct as they are serialised.
+struct Columns(Vec<(&'static str, Value)>);
+
+fn unsupported() -> serde_json::Error {
+    ser::Error::custom("Only structs can be reported")
+}
+
+macro_rules! unsupported {
+    ($($method:ident($($arg:ty),*);)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<Self::Ok, Self::Error> {
+                Err(unsupported())
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for Columns {
+    type Ok = Vec<(&'static str, Value)>;
+    type Error = serde_json::Error;
+    type SerializeSeq = Impossible<Self::Ok, Self::Error>;
+    type SerializeTuple = Impossible<Self::Ok, Self::Error>;
+    type SerializeTupleStruct = Impossible<Self::Ok, Self::Error>;
+    type SerializeTupleVariant = Impossible<Self::Ok, Self::Error>;
+    type SerializeMap = Impossible<Self::Ok, Self::Error>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<Self::Ok, Self::Error>;
+
+    unsupported! {
+        serialize_bool(bool);
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+        serialize_i64(i64);
+        serialize_u8(u8);
+        serialize_u16(u16);
+        serialize_u32(u32);
+        serialize_u64(u64);
+        serialize_f32(f32);
+        serialize_f64(f64);
+        serialize_char(char);
+        serialize_str(&str);
+        serialize_bytes(&[u8]);
+        serialize_none();
+        serialize_unit();
+        serialize_unit_struct(&'static str);
+        serialize_unit_variant(&'static str, u32, &'static str);
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _: &T) -> Result<Self::Ok, Self::Error> {
+        Err(unsupported())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        Err(unsupported())
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        Err(unsupported())
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Err(unsupported())
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Err(unsupported())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Err(unsupported())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(unsupported())
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Err(unsupported())
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self, Self::Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(unsupported())
+    }
+}
+
+impl ser::SerializeStruct for Columns {
+    type Ok = Vec<(&'static str, Value)>;
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.0.push((key, serde_json::to_value(value)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.0)
+    }
+}
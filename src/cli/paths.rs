@@ -0,0 +1,154 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Expansion of directories and glob patterns into files.
+
+use crate::input;
+use heidi::i18n::{Message, Text};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Amount of bytes looked at to tell whether a file is binary.
+const SNIFF: usize = 8192;
+
+/// Expands the given paths into the files to read, in order.
+///
+/// Directories are walked recursively, skipping hidden entries, and patterns
+/// like `logs/**/*.log` are matched against the file system, leaving hidden
+/// entries out unless named explicitly. Binary files
+/// found this way are skipped, while files given explicitly are always kept.
+pub fn expand<P: AsRef<str>>(paths: &[P]) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for path in paths {
+        let path = path.as_ref();
+
+        if Path::new(path).exists() {
+            let path = PathBuf::from(path);
+
+            if path.is_dir() {
+                walk(&path, &mut files)?;
+            } else {
+                files.push(path);
+            }
+
+            continue;
+        }
+
+        let options = glob::MatchOptions {
+            require_literal_leading_dot: true,
+            ..glob::MatchOptions::new()
+        };
+        let matches = glob::glob_with(path, options).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid pattern '{}': {}", path, e.msg),
+            )
+        })?;
+        let before = files.len();
+
+        for entry in matches {
+            let entry = entry.map_err(io::Error::from)?;
+
+            if entry.is_dir() {
+                walk(&entry, &mut files)?;
+            } else if !is_binary(&entry)? {
+                files.push(entry);
+            }
+        }
+
+        if files.len() == before {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                Message::new(Text::NoFilesMatch).with_param("path", path),
+            ));
+        }
+    }
+
+    Ok(files)
+}
+
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let entries = WalkDir::new(dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !is_hidden(entry.file_name()));
+
+    for entry in entries {
+        let entry = entry?;
+
+        if entry.file_type().is_file() && !is_binary(entry.path())? {
+            files.push(entry.into_path());
+        }
+    }
+
+    Ok(())
+}
+
+fn is_hidden(name: &std::ffi::OsStr) -> bool {
+    name.to_str().is_some_and(|name| name.starts_with('.'))
+}
+
+/// Whether the file looks binary, i.e. it has a NUL byte early on and is not
+/// a compressed stream.
+pub fn is_binary(path: &Path) -> io::Result<bool> {
+    let mut buffer = Vec::with_capacity(SNIFF);
+
+    File::open(path)?
+        .take(SNIFF as u64)
+        .read_to_end(&mut buffer)?;
+
+    Ok(!input::is_magic(&buffer) && buffer.contains(&0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+
+    #[test]
+    fn expands_trees_and_patterns() {
+        let root = env::temp_dir().join(format!("heidi-paths-{}", process::id()));
+        let file = |name: &str, content: &[u8]| {
+            let path = root.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+
+        file("a.log", b"6541003238\n");
+        file("b/c.log", b"6541003238\n");
+        file("b/d.txt", b"6541003238\n");
+        file("b/e.bin", b"\x00\x01\x02");
+        file("b/f.log.gz", b"\x1f\x8b\x00");
+        file(".git/g.log", b"6541003238\n");
+
+        let names = |files: Vec<PathBuf>| -> Vec<String> {
+            files
+                .iter()
+                .map(|path| {
+                    path.strip_prefix(&root)
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect()
+        };
+        let tree = expand(&[root.to_string_lossy()]).unwrap();
+        let pattern = expand(&[root.join("**/*.log").to_string_lossy()]).unwrap();
+
+        assert_eq!(names(tree), ["a.log", "b/c.log", "b/d.txt", "b/f.log.gz"]);
+        assert_eq!(names(pattern), ["a.log", "b/c.log"]);
+        assert_eq!(
+            names(expand(&[root.join("b/e.bin").to_string_lossy()]).unwrap()),
+            ["b/e.bin"]
+        );
+        assert!(expand(&[root.join("*.csv").to_string_lossy()]).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
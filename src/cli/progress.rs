@@ -0,0 +1,102 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Progress reporting on stderr for long runs.
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::io::{self, IsTerminal, Read};
+
+/// Amount of values between message updates.
+const EVERY: u64 = 4096;
+
+/// A progress bar drawn on stderr, only when stderr is a terminal.
+///
+/// The bar tracks bytes read when the input size is known, and values
+/// checked otherwise.
+#[derive(Debug)]
+pub struct Progress {
+    enabled: bool,
+    bar: Option<ProgressBar>,
+    values: u64,
+    invalid: u64,
+}
+
+impl Progress {
+    pub fn new(enabled: bool) -> Self {
+        Progress {
+            enabled: enabled && io::stderr().is_terminal(),
+            bar: None,
+            values: 0,
+            invalid: 0,
+        }
+    }
+
+    /// Starts drawing, tracking `len` bytes if given. Does nothing if
+    /// already started.
+    pub fn start(&mut self, len: Option<u64>) {
+        if !self.enabled || self.bar.is_some() {
+            return;
+        }
+
+        let bar = match len {
+            Some(len) => ProgressBar::with_draw_target(Some(len), ProgressDrawTarget::stderr())
+                .with_style(
+                ProgressStyle::with_template(
+                    "{spinner} [{elapsed_precise}] {wide_bar} {bytes}/{total_bytes} ({eta}) {msg}",
+                )
+                .expect("valid template"),
+            ),
+            None => ProgressBar::with_draw_target(None, ProgressDrawTarget::stderr()).with_style(
+                ProgressStyle::with_template("{spinner} [{elapsed_precise}] {msg}")
+                    .expect("valid template"),
+            ),
+        };
+
+        self.bar = Some(bar);
+        self.update();
+    }
+
+    /// Wraps a reader so the bytes read from it advance the bar.
+    pub fn wrap<R: Read + 'static>(&self, reader: R) -> Box<dyn Read> {
+        match &self.bar {
+            Some(bar) if bar.length().is_some() => Box::new(bar.wrap_read(reader)),
+            _ => Box::new(reader),
+        }
+    }
+
+    /// Counts a checked value.
+    pub fn record(&mut self, valid: bool) {
+        self.values += 1;
+        if !valid {
+            self.invalid += 1;
+        }
+
+        if self.values % EVERY == 0 {
+            self.update();
+        }
+    }
+
+    /// Runs `f`, e.g. printing to the terminal, with the bar hidden.
+    pub fn suspend<F: FnOnce() -> T, T>(&self, f: F) -> T {
+        match &self.bar {
+            Some(bar) => bar.suspend(f),
+            None => f(),
+        }
+    }
+
+    /// Removes the bar.
+    pub fn finish(&mut self) {
+        if let Some(bar) = self.bar.take() {
+            bar.finish_and_clear();
+        }
+    }
+
+    fn update(&self) {
+        if let Some(bar) = &self.bar {
+            bar.set_message(format!("{} values, {} invalid", self.values, self.invalid));
+        }
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Keyed pseudonymisation of identifier columns in CSV files.
+
+use crate::check::validate;
+use crate::columns::positions;
+use crate::input;
+use crate::output::Reporter;
+use crate::sink::Sink;
+use crate::Typeid;
+use heidi::batch::Stats;
+use heidi::pseudo::Pseudonymizer;
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+
+/// Writes a copy of a delimited file with a header row to the reporter
+/// output, with the values of the given columns replaced by their
+/// pseudonyms. Returns the summary of the values replaced.
+///
+/// Empty values are kept empty. Invalid values are emptied too, so a
+/// mistyped identifier does not leak, and told by line and column on stderr
+/// without the value itself.
+///
+/// When `crosswalk` is given, every distinct pseudonym is written there next
+/// to the number it stands for, in order of appearance. The crosswalk
+/// reverses the pseudonymisation and has to be kept apart from the dataset.
+pub fn file(
+    reporter: &mut Reporter,
+    _type: &Typeid,
+    input: &Path,
+    columns: &[String],
+    pseudonymizer: &Pseudonymizer,
+    delimiter: u8,
+    crosswalk: Option<&Path>,
+) -> io::Result<Stats> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(input::open(input, reporter.progress())?);
+    let header = reader.headers()?.clone();
+    let names: Vec<&str> = header.iter().collect();
+    let idxs = positions(&names, columns)
+        .map_err(|msg| io::Error::new(io::ErrorKind::InvalidInput, msg))?;
+    let mut crosswalk = match crosswalk {
+        Some(path) => {
+            let mut writer = csv::Writer::from_writer(Sink::file(path, false)?);
+
+            writer.write_record(["pseudonym", "number"])?;
+            Some(writer)
+        }
+        None => None,
+    };
+    let mut seen = HashSet::new();
+    let mut stats = Stats::new();
+    let mut warnings = Vec::new();
+
+    {
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .flexible(true)
+            .from_writer(reporter.writer());
+
+        writer.write_record(&header)?;
+
+        for record in reader.records() {
+            let record = record?;
+            let line = record.position().map_or(0, |pos| pos.line());
+            let mut fields: Vec<String> = record.iter().map(String::from).collect();
+
+            for (column, &idx) in columns.iter().zip(&idxs) {
+                let value = match fields.get_mut(idx) {
+                    Some(value) if !value.trim().is_empty() => value,
+                    _ => continue,
+                };
+                let result = validate(_type, value).map(|(number, _)| number);
+
+                stats.record(&result);
+                *value = match &result {
+                    Ok(number) => {
+                        let pseudonym = pseudonymizer.pseudonymise(number);
+
+                        if let Some(crosswalk) = crosswalk.as_mut() {
+                            if seen.insert(pseudonym.clone()) {
+                                crosswalk.write_record([&pseudonym, &number.to_string()])?;
+                            }
+                        }
+
+                        pseudonym
+                    }
+                    Err(e) => {
+                        warnings.push(format!(
+                            "{}:{}: {} {}: {}.",
+                            input.display(),
+                            line,
+                            column,
+                            e.kind(),
+                            e
+                        ));
+                        String::new()
+                    }
+                };
+            }
+
+            writer.write_record(&fields)?;
+        }
+
+        writer.flush()?;
+    }
+
+    if let Some(crosswalk) = crosswalk {
+        crosswalk
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .commit()?;
+    }
+
+    reporter.progress().finish();
+
+    for warning in &warnings {
+        reporter.warn(warning);
+    }
+
+    reporter.warn(&format!(
+        "Pseudonymised {} values, {} invalid ones left empty.",
+        stats.valid(),
+        stats.invalid()
+    ));
+
+    Ok(stats)
+}
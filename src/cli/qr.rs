@@ -0,0 +1,40 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! QR codes of numbers.
+
+use crate::output::Reporter;
+use crate::Typeid;
+use heidi::qr::{Payload, Qr};
+use heidi::scheme::Scheme;
+use heidi::{chi, nhs};
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// Writes the QR code of a number as SVG, or with Unicode blocks when `text`
+/// is set.
+pub fn code(
+    reporter: &mut Reporter,
+    _type: &Typeid,
+    number: &str,
+    payload: Payload,
+    text: bool,
+    module: u32,
+) -> io::Result<()> {
+    let invalid = |e| io::Error::new(io::ErrorKind::InvalidInput, e);
+    let qr = match _type {
+        Typeid::Nhs => Qr::nhs(&nhs::Number::from_str(number).map_err(invalid)?, payload),
+        Typeid::Chi => Qr::chi(&chi::Number::from_str(number).map_err(invalid)?, payload),
+        Typeid::Hcn => Qr::hcn(&Scheme::Hcn.validate(number).map_err(invalid)?, payload),
+    };
+    let image = if text {
+        format!("{}\n", qr.to_text())
+    } else {
+        qr.to_svg(module)
+    };
+
+    reporter.writer().write_all(image.as_bytes())
+}
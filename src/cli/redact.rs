@@ -0,0 +1,183 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Rewriting of files with the identifiers they hold redacted.
+
+use crate::input;
+use crate::output::Reporter;
+use clap::arg_enum;
+use heidi::pseudo::audit::{Auditor, Event};
+use heidi::redact::{self, csv, json, Policy, Pseudonyms, Replacement};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+arg_enum! {
+    /// How to replace the identifiers found.
+    #[derive(PartialEq, Copy, Clone, Debug)]
+    pub enum Strategy {
+        Mask,
+        Last4,
+        Tag,
+        Pseudonym,
+    }
+}
+
+arg_enum! {
+    /// How to read the input.
+    #[derive(PartialEq, Copy, Clone, Debug)]
+    pub enum Mode {
+        Text,
+        Csv,
+        Json,
+        Ndjson,
+    }
+}
+
+/// Where to look for identifiers.
+#[derive(Debug, Default)]
+pub struct Options {
+    /// The input mode. Guessed from the file extension when missing.
+    pub mode: Option<Mode>,
+    /// The CSV columns to redact. Columns are detected when empty.
+    pub columns: Vec<String>,
+    /// The JSON key paths to redact. Any value is considered when empty.
+    pub paths: Vec<String>,
+    /// The CSV field delimiter.
+    pub delimiter: u8,
+}
+
+/// Builds the redaction policy for the given strategy.
+///
+/// Pseudonyms are keyed with `key` so they are stable across runs, or
+/// numbered in order of appearance without one.
+pub fn policy(strategy: Strategy, key: Option<&[u8]>) -> Policy {
+    let replacement = match (strategy, key) {
+        (Strategy::Mask, _) => Replacement::Mask("[REDACTED]".to_string()),
+        (Strategy::Last4, _) => Replacement::KeepLast4,
+        (Strategy::Tag, _) => Replacement::SchemeTag,
+        (Strategy::Pseudonym, Some(key)) => Replacement::Pseudonym(Pseudonyms::keyed(key)),
+        (Strategy::Pseudonym, None) => Replacement::Pseudonym(Pseudonyms::sequential()),
+    };
+
+    Policy::new(replacement)
+}
+
+/// Writes the content of `input` to the reporter output with every
+/// identifier replaced according to the policy, and tells how many were
+/// replaced on stderr.
+///
+/// Text and JSON documents are read in full while CSV and NDJSON are
+/// streamed. Compressed inputs are decompressed but cannot be rewritten in
+/// place.
+pub fn file(
+    reporter: &mut Reporter,
+    input: &Path,
+    in_place: bool,
+    mut policy: Policy,
+    options: &Options,
+) -> io::Result<()> {
+    if in_place && input::is_compressed(input)? {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Compressed inputs cannot be redacted in place",
+        ));
+    }
+
+    let count = Arc::new(AtomicUsize::new(0));
+    let counter = count.clone();
+
+    policy.audit = Some(Auditor::new(move |_: &Event| {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }));
+
+    let mut reader = input::open(input, reporter.progress())?;
+    let mode = options.mode.unwrap_or_else(|| guess(input));
+
+    match mode {
+        Mode::Text => {
+            let mut content = String::new();
+
+            reader.read_to_string(&mut content)?;
+            let redaction = redact::redact_text(&content, &policy);
+            reporter.writer().write_all(redaction.text.as_bytes())?;
+        }
+        Mode::Csv => {
+            let columns = if options.columns.is_empty() {
+                csv::Columns::Detect
+            } else {
+                csv::Columns::Named(options.columns.clone())
+            };
+
+            csv::Redactor::new(policy, columns)
+                .delimiter(options.delimiter)
+                .redact(reader, reporter.writer())?;
+        }
+        Mode::Json | Mode::Ndjson => {
+            let paths: Vec<&str> = options.paths.iter().map(String::as_str).collect();
+            let redactor = json::Redactor::new(policy).paths(&paths);
+
+            if mode == Mode::Json {
+                let mut content = String::new();
+
+                reader.read_to_string(&mut content)?;
+                reporter
+                    .writer()
+                    .write_all(redactor.redact(&content).as_bytes())?;
+            } else {
+                redactor.redact_ndjson(reader, reporter.writer())?;
+            }
+        }
+    }
+
+    reporter.progress().finish();
+    reporter.warn(&format!(
+        "Redacted {} identifiers in {}.",
+        count.load(Ordering::Relaxed),
+        input.display()
+    ));
+
+    Ok(())
+}
+
+/// Guesses the mode from the file extension, looking past `.gz` and `.zst`.
+fn guess(input: &Path) -> Mode {
+    let name = input
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let name = name
+        .trim_end_matches(".gz")
+        .trim_end_matches(".zst")
+        .to_string();
+
+    match Path::new(&name).extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => Mode::Csv,
+        Some("json") => Mode::Json,
+        Some("ndjson") | Some("jsonl") => Mode::Ndjson,
+        _ => Mode::Text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modes_from_extensions() {
+        for (name, mode) in &[
+            ("app.log", Mode::Text),
+            ("data.CSV", Mode::Csv),
+            ("data.csv.gz", Mode::Csv),
+            ("bundle.json", Mode::Json),
+            ("events.jsonl.zst", Mode::Ndjson),
+            ("README", Mode::Text),
+        ] {
+            assert_eq!(guess(Path::new(name)), *mode);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Scanning of files for identifiers.
+
+use crate::check::validate;
+use crate::input;
+use crate::output::{Outcome, Reporter};
+use crate::watch::{self, Change, Watcher};
+use crate::Typeid;
+use heidi::batch::Stats;
+use heidi::number;
+use heidi::scan::{Finding, ScanOptions, Scanner};
+use heidi::scheme::Scheme;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Scans the given files for identifiers of the given types, reporting every
+/// candidate by line and column, and a summary of all of them. Returns the
+/// summary.
+///
+/// Each file is decompressed on the fly and read in full. Columns count
+/// characters, not bytes.
+pub fn files(
+    reporter: &mut Reporter,
+    types: &[Typeid],
+    paths: &[PathBuf],
+    options: ScanOptions,
+) -> io::Result<Stats> {
+    let scanner = scanner(types, options);
+    let mut stats = Stats::new();
+
+    if paths.len() > 1 {
+        reporter.progress().start(None);
+    }
+
+    for path in paths {
+        file(reporter, &scanner, path, &mut stats)?;
+    }
+
+    reporter.summary(&stats, 0)?;
+
+    Ok(stats)
+}
+
+/// Watches the given files, scanning each in full once it stops changing,
+/// until the gate stops the run. Returns the summary.
+pub fn watch(
+    reporter: &mut Reporter,
+    types: &[Typeid],
+    patterns: &[String],
+    options: ScanOptions,
+) -> io::Result<Stats> {
+    let scanner = scanner(types, options);
+    let mut watcher = Watcher::new(patterns);
+    let mut stats = Stats::new();
+
+    while !reporter.gate().stops(&stats) {
+        for (path, change) in watcher.poll()? {
+            if change == Change::Settled {
+                file(reporter, &scanner, &path, &mut stats)?;
+            }
+        }
+
+        watch::idle();
+    }
+
+    Ok(stats)
+}
+
+fn scanner(types: &[Typeid], mut options: ScanOptions) -> Scanner {
+    options.schemes = types
+        .iter()
+        .map(|_type| match _type {
+            Typeid::Nhs => Scheme::Nhs,
+            Typeid::Chi => Scheme::Chi,
+            Typeid::Hcn => Scheme::Hcn,
+        })
+        .collect();
+
+    Scanner::new(options)
+}
+
+/// Scans a single file, recording every candidate in `stats`.
+fn file(
+    reporter: &mut Reporter,
+    scanner: &Scanner,
+    path: &Path,
+    stats: &mut Stats,
+) -> io::Result<()> {
+    let mut content = Vec::new();
+
+    input::open(path, reporter.progress())?.read_to_end(&mut content)?;
+
+    let source = path.display().to_string();
+    let findings: Vec<Finding> = scanner.find_iter(&content).collect();
+    let mut line = 1;
+    let mut line_start = 0;
+    let mut offset = 0;
+
+    reporter.scanned(&source, &content, &findings);
+
+    for finding in &findings {
+        for idx in newlines(&content[offset..finding.span.start]) {
+            line += 1;
+            line_start = offset + idx + 1;
+        }
+        offset = finding.span.start;
+
+        let text = String::from_utf8_lossy(&content[finding.span.clone()]);
+        let column = String::from_utf8_lossy(&content[line_start..finding.span.start])
+            .chars()
+            .count()
+            + 1;
+        let kind = finding
+            .schemes
+            .iter()
+            .map(Scheme::as_str)
+            .collect::<Vec<_>>()
+            .join(",");
+        let (outcome, result) = match number::Number::from_str(&finding.digits) {
+            Ok(n) => {
+                let official = match finding.schemes.first() {
+                    Some(Scheme::Chi) => validate(&Typeid::Chi, &finding.digits),
+                    _ => validate(&Typeid::Nhs, &finding.digits),
+                }
+                .map_or_else(|_| n.to_string(), |(_, official)| official);
+
+                (Ok(n), Ok(official))
+            }
+            Err(e) => (Err(e.clone()), Err(e)),
+        };
+
+        reporter.found(
+            &Outcome {
+                kind: if kind.is_empty() { "unverified" } else { &kind },
+                source: Some(&source),
+                location: Some(format!("{}:{}", line, column)),
+                column: None,
+                input: &text,
+                result,
+            },
+            finding.confidence,
+        )?;
+        stats.record(&outcome);
+    }
+
+    Ok(())
+}
+
+/// Returns the offsets of every newline.
+fn newlines(bytes: &[u8]) -> impl Iterator<Item = usize> + '_ {
+    bytes
+        .iter()
+        .enumerate()
+        .filter(|(_, &b)| b == b'\n')
+        .map(|(idx, _)| idx)
+}
@@ -0,0 +1,483 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! An HTTP service answering with the JSON reports of `check` and
+//! `generate`, e.g. to run as a sidecar.
+//!
+//! - `GET /v1/{scheme}/validate/{number}` validates a single number.
+//! - `POST /v1/{scheme}/validate` validates a JSON array of numbers and adds
+//!   a summary.
+//! - `POST /v1/{scheme}/generate` generates numbers as described by a JSON
+//!   object like `{"count": 10, "unique": true, "seed": 42}`.
+//!
+//! The scheme is `nhs`, `chi`, or `auto` when validating. Numbers are never
+//! logged. `GET /openapi.json` describes the endpoints as an OpenAPI 3
+//! document and `GET /metrics` gives the metrics of the service to
+//! Prometheus.
+
+use crate::check::{self, Selector};
+use crate::gate::Gate;
+use crate::generate::{self, Generated};
+use crate::metrics::Metrics;
+use crate::output::{Document, Output, Record, Reporter};
+use crate::progress::Progress;
+use crate::sink::Sink;
+use crate::{Format, Typeid};
+use schemars::generate::SchemaSettings;
+use schemars::{JsonSchema, Schema};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::io::{self, Cursor, Read};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// The largest request body accepted, in bytes.
+const BODY_LIMIT: u64 = 1 << 20;
+
+const JSON: &[u8] = b"application/json";
+const PROMETHEUS: &[u8] = b"text/plain; version=0.0.4";
+
+/// The most numbers a single request can generate.
+pub const COUNT_LIMIT: usize = 10_000;
+
+/// What a request asks for.
+#[derive(PartialEq, Debug)]
+enum Route {
+    Metrics,
+    OpenApi,
+    Validate(Selector, String),
+    ValidateBatch(Selector),
+    Generate(Typeid),
+}
+
+/// Why a request cannot be answered.
+#[derive(Serialize, JsonSchema, PartialEq, Debug)]
+struct Failure {
+    #[serde(skip)]
+    status: u16,
+    /// Why the request cannot be answered.
+    #[serde(rename = "error")]
+    message: String,
+}
+
+impl Route {
+    /// A short name for metrics.
+    fn name(&self) -> &'static str {
+        match self {
+            Route::Metrics => "metrics",
+            Route::OpenApi => "openapi",
+            Route::Validate(..) => "validate",
+            Route::ValidateBatch(_) => "validate_batch",
+            Route::Generate(_) => "generate",
+        }
+    }
+}
+
+impl Failure {
+    fn new(status: u16, message: &str) -> Self {
+        Failure {
+            status,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Serves requests on the given address with `jobs` threads until the
+/// process is stopped.
+pub fn run(address: &str, jobs: usize) -> io::Result<()> {
+    let server = Server::http(address)
+        .map_err(|e| io::Error::new(io::ErrorKind::AddrNotAvailable, e.to_string()))?;
+    let server = Arc::new(server);
+    let metrics = Arc::new(Metrics::new());
+
+    eprintln!("Listening on http://{}.", address);
+
+    let workers: Vec<_> = (0..jobs)
+        .map(|_| {
+            let server = server.clone();
+            let metrics = metrics.clone();
+
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    // A client hanging up is no reason to stop serving.
+                    let _ = answer(request, &metrics);
+                }
+            })
+        })
+        .collect();
+
+    for worker in workers {
+        worker
+            .join()
+            .map_err(|_| io::Error::other("A worker panicked"))?;
+    }
+
+    Ok(())
+}
+
+fn answer(mut request: Request, metrics: &Metrics) -> io::Result<()> {
+    let started = Instant::now();
+    let routed = route(request.method(), request.url());
+    let name = routed.as_ref().map_or("unknown", Route::name);
+    let (content_type, result) = match routed {
+        Ok(Route::Metrics) => (PROMETHEUS, Ok(metrics.render().into_bytes())),
+        routed => (
+            JSON,
+            routed.and_then(|route| {
+                let mut body = Vec::new();
+
+                request
+                    .as_reader()
+                    .take(BODY_LIMIT + 1)
+                    .read_to_end(&mut body)
+                    .map_err(|e| Failure::new(400, &e.to_string()))?;
+
+                if body.len() as u64 > BODY_LIMIT {
+                    return Err(Failure::new(413, "The request body is too large"));
+                }
+
+                respond(route, &body, metrics)
+            }),
+        ),
+    };
+    let (status, body) = match result {
+        Ok(body) => (200, body),
+        Err(failure) => (failure.status, serde_json::to_vec(&failure)?),
+    };
+    let header = Header::from_bytes(&b"Content-Type"[..], content_type).expect("valid header");
+
+    metrics.request(name, status, started.elapsed());
+    request.respond(
+        Response::from_data(body)
+            .with_status_code(status)
+            .with_header(header),
+    )
+}
+
+/// Tells what a request asks for from its method and URL.
+fn route(method: &Method, url: &str) -> Result<Route, Failure> {
+    let path = url.split('?').next().unwrap_or_default();
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let selector = |scheme: &str| {
+        Selector::from_str(scheme).map_err(|_| {
+            Failure::new(
+                400,
+                &format!("Unknown scheme '{}': expected nhs, chi or auto", scheme),
+            )
+        })
+    };
+
+    match (method, segments.as_slice()) {
+        (Method::Get, ["metrics"]) => Ok(Route::Metrics),
+        (Method::Get, ["openapi.json"]) => Ok(Route::OpenApi),
+        (Method::Get, ["v1", scheme, "validate", number]) => {
+            Ok(Route::Validate(selector(scheme)?, decode(number)?))
+        }
+        (Method::Post, ["v1", scheme, "validate"]) => Ok(Route::ValidateBatch(selector(scheme)?)),
+        (Method::Post, ["v1", scheme, "generate"]) => {
+            // H&C numbers cannot be generated.
+            match Typeid::from_str(scheme) {
+                Ok(Typeid::Hcn) | Err(_) => Err(Failure::new(
+                    400,
+                    &format!("Unknown scheme '{}': expected nhs or chi", scheme),
+                )),
+                Ok(_type) => Ok(Route::Generate(_type)),
+            }
+        }
+        (_, ["v1", _, "validate", _]) | (_, ["v1", _, "validate"]) | (_, ["v1", _, "generate"]) => {
+            Err(Failure::new(405, "Method not allowed"))
+        }
+        _ => Err(Failure::new(404, "Not found")),
+    }
+}
+
+/// Answers a route with the JSON report of the matching subcommand.
+fn respond(route: Route, body: &[u8], metrics: &Metrics) -> Result<Vec<u8>, Failure> {
+    let mut reporter =
+        Reporter::new(Output::Json, Progress::new(false), Gate::default()).sink(Sink::memory());
+    let invalid = |e: io::Error| Failure::new(400, &e.to_string());
+
+    match route {
+        Route::Metrics => return Ok(metrics.render().into_bytes()),
+        Route::OpenApi => return Ok(document().into_bytes()),
+        Route::Validate(selector, number) => {
+            let _type = selector.typeid();
+            let valid =
+                check::one(&mut reporter, _type.as_ref(), &number, None, false).map_err(invalid)?;
+
+            metrics.validations(scheme(_type.as_ref()), valid as usize, !valid as usize);
+        }
+        Route::ValidateBatch(selector) => {
+            let numbers: Vec<String> = serde_json::from_slice(body)
+                .map_err(|_| Failure::new(400, "Expected a JSON array of numbers"))?;
+            let reader = Cursor::new(numbers.join("\n"));
+            let _type = selector.typeid();
+            let stats = check::each(&mut reporter, _type.as_ref(), reader, b'\n', None, false)
+                .map_err(invalid)?;
+
+            metrics.validations(scheme(_type.as_ref()), stats.valid(), stats.invalid());
+
+            reporter.summary(&stats, 0).map_err(invalid)?;
+        }
+        Route::Generate(_type) => {
+            let options = options(body)?;
+            let format = Format::Compact;
+
+            generate::numbers(&mut reporter, &_type, format, &options).map_err(invalid)?;
+        }
+    }
+
+    reporter
+        .close()
+        .map(|mut sink| sink.take())
+        .map_err(|e| Failure::new(500, &e.to_string()))
+}
+
+/// The scheme numbers are validated as, or `auto` without a type.
+fn scheme(_type: Option<&Typeid>) -> &'static str {
+    _type.map_or("auto", check::kind)
+}
+
+/// Describes the endpoints as an OpenAPI 3 document. The schemes, limits and
+/// options are taken from the ones the handlers use, and the schemas from
+/// the types they read and write.
+pub fn document() -> String {
+    let mut settings = SchemaSettings::draft2020_12();
+    settings.definitions_path = "/components/schemas".into();
+    let mut generator = settings.into_generator();
+    let mut schemas = Map::new();
+    let mut component = |name: &str, schema: Schema| {
+        schemas.insert(name.to_string(), schema.to_value());
+        json!({ "$ref": format!("#/components/schemas/{}", name) })
+    };
+    let validation = component(
+        "Validation",
+        Document::<Record>::json_schema(&mut generator),
+    );
+    let generated = component(
+        "Generation",
+        Document::<Generated>::json_schema(&mut generator),
+    );
+    let options = component("Options", generate::Options::json_schema(&mut generator));
+    let failure = component("Failure", Failure::json_schema(&mut generator));
+
+    schemas.extend(generator.take_definitions(true));
+
+    let lowercase =
+        |names: &[&str]| -> Vec<String> { names.iter().map(|name| name.to_lowercase()).collect() };
+    let scheme = |names: Vec<String>| {
+        json!({
+            "name": "scheme",
+            "in": "path",
+            "required": true,
+            "schema": { "type": "string", "enum": names },
+        })
+    };
+    let json = |schema: &Value| json!({ "application/json": { "schema": schema } });
+    let report = |description: &str, schema: &Value| {
+        json!({
+            "200": {
+                "description": description,
+                "content": json(schema),
+            },
+            "400": {
+                "description": "The scheme or the request body is invalid.",
+                "content": json(&failure),
+            },
+        })
+    };
+    let document = json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "heidi",
+            "description": "Validation and generation of health identifiers such as NHS and CHI numbers.",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": {
+            "/metrics": {
+                "get": {
+                    "summary": "Gives request counts, validation outcomes and latencies to Prometheus.",
+                    "responses": {
+                        "200": {
+                            "description": "The metrics in the Prometheus text format.",
+                            "content": { "text/plain": { "schema": { "type": "string" } } },
+                        },
+                    },
+                },
+            },
+            "/v1/{scheme}/validate/{number}": {
+                "get": {
+                    "summary": "Validates a number.",
+                    "parameters": [
+                        scheme(lowercase(&Selector::variants())),
+                        { "name": "number", "in": "path", "required": true, "schema": { "type": "string" } },
+                    ],
+                    "responses": report("The outcome, valid or not.", &validation),
+                },
+            },
+            "/v1/{scheme}/validate": {
+                "post": {
+                    "summary": "Validates several numbers and summarises them. Empty numbers are skipped.",
+                    "parameters": [scheme(lowercase(&Selector::variants()))],
+                    "requestBody": {
+                        "required": true,
+                        "content": json(&json!({ "type": "array", "items": { "type": "string" } })),
+                    },
+                    "responses": report("Every outcome and their summary.", &validation),
+                },
+            },
+            "/v1/{scheme}/generate": {
+                "post": {
+                    "summary": "Generates random valid numbers.",
+                    "parameters": [scheme(lowercase(&["Nhs", "Chi"]))],
+                    "requestBody": {
+                        "required": false,
+                        "content": json(&options),
+                    },
+                    "responses": report("The numbers generated.", &generated),
+                },
+            },
+        },
+        "components": { "schemas": schemas },
+    });
+
+    serde_json::to_string_pretty(&document).expect("serialisable document")
+}
+
+/// Reads the generation options from a JSON object. An empty body asks for
+/// a single number.
+fn options(body: &[u8]) -> Result<generate::Options, Failure> {
+    let value: Value = if body.is_empty() {
+        json!({})
+    } else {
+        serde_json::from_slice(body).map_err(|_| Failure::new(400, "Expected a JSON object"))?
+    };
+    let field = |name: &str| value.get(name).filter(|value| !value.is_null());
+    let invalid = |name: &str| Failure::new(400, &format!("Invalid value for '{}'", name));
+    let flag = |name: &str| match field(name) {
+        None => Ok(false),
+        Some(value) => value.as_bool().ok_or_else(|| invalid(name)),
+    };
+    let count = match field("count") {
+        None => generate::Options::default().count,
+        Some(value) => value
+            .as_u64()
+            .map(|count| count as usize)
+            .filter(|count| (1..=COUNT_LIMIT).contains(count))
+            .ok_or_else(|| invalid("count"))?,
+    };
+    let seed = match field("seed") {
+        None => None,
+        Some(value) => Some(value.as_u64().ok_or_else(|| invalid("seed"))?),
+    };
+
+    Ok(generate::Options {
+        count,
+        unique: flag("unique")?,
+        seed,
+        test_range: flag("test_range")?,
+        ..generate::Options::default()
+    })
+}
+
+/// Decodes the percent-encoded characters of a path segment, e.g. `%20`.
+fn decode(segment: &str) -> Result<String, Failure> {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'%' => {
+                let byte = segment
+                    .get(idx + 1..idx + 3)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| Failure::new(400, "Invalid percent-encoding"))?;
+
+                decoded.push(byte);
+                idx += 3;
+            }
+            byte => {
+                decoded.push(byte);
+                idx += 1;
+            }
+        }
+    }
+
+    String::from_utf8(decoded).map_err(|_| Failure::new(400, "Invalid percent-encoding"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes() {
+        assert_eq!(
+            route(&Method::Get, "/v1/nhs/validate/654%20100%203238"),
+            Ok(Route::Validate(Selector::Nhs, "654 100 3238".to_string()))
+        );
+        assert_eq!(
+            route(&Method::Post, "/v1/auto/validate?pretty"),
+            Ok(Route::ValidateBatch(Selector::Auto))
+        );
+        assert_eq!(
+            route(&Method::Post, "/v1/chi/generate"),
+            Ok(Route::Generate(Typeid::Chi))
+        );
+        assert_eq!(
+            route(&Method::Post, "/v1/auto/generate")
+                .unwrap_err()
+                .status,
+            400
+        );
+        assert_eq!(
+            route(&Method::Get, "/v1/nhs/generate").unwrap_err().status,
+            405
+        );
+        assert_eq!(
+            route(&Method::Get, "/v2/nhs/validate/1")
+                .unwrap_err()
+                .status,
+            404
+        );
+    }
+
+    #[test]
+    fn openapi_document() {
+        let document: Value = serde_json::from_str(&document()).unwrap();
+        let generate = &document["paths"]["/v1/{scheme}/generate"]["post"];
+
+        assert_eq!(
+            generate["parameters"][0]["schema"]["enum"],
+            json!(["nhs", "chi"])
+        );
+        assert_eq!(
+            document["components"]["schemas"]["Options"]["properties"]["count"]["maximum"],
+            json!(COUNT_LIMIT)
+        );
+        assert_eq!(
+            document["components"]["schemas"]["Generation"]["properties"]["results"]["items"],
+            json!({ "$ref": "#/components/schemas/Generated" })
+        );
+    }
+
+    #[test]
+    fn generation_options() {
+        let parsed = options(br#"{"count": 3, "unique": true, "seed": 42}"#).unwrap();
+
+        assert_eq!(
+            (parsed.count, parsed.unique, parsed.seed),
+            (3, true, Some(42))
+        );
+        assert_eq!(options(b"").unwrap().count, 1);
+        assert!(options(br#"{"count": 0}"#).is_err());
+        assert!(options(br#"{"unique": "yes"}"#).is_err());
+    }
+}
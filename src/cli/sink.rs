@@ -0,0 +1,168 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Destinations for reports.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Where a report is written to.
+///
+/// A file is written to a temporary sibling and only renamed over the
+/// destination on [`Sink::commit`], so a failed or interrupted run never
+/// leaves a partial report behind.
+#[derive(Debug)]
+pub enum Sink {
+    Stdout(io::Stdout),
+    File {
+        writer: BufWriter<File>,
+        /// The file being written, until it is renamed.
+        temp: Option<PathBuf>,
+        path: PathBuf,
+    },
+    /// Content kept in memory, e.g. to answer a request.
+    #[cfg(feature = "serve")]
+    Memory(Vec<u8>),
+}
+
+impl Sink {
+    pub fn stdout() -> Self {
+        Sink::Stdout(io::stdout())
+    }
+
+    /// Creates a sink writing to the given path. When appending, the
+    /// existing content is copied to the temporary file first.
+    pub fn file(path: &Path, append: bool) -> io::Result<Self> {
+        let name = path
+            .file_name()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("'{}' is not a file path", path.display()),
+                )
+            })?
+            .to_string_lossy();
+        let temp = path.with_file_name(format!(".{}.{}.tmp", name, process::id()));
+        let mut file = File::create(&temp)?;
+
+        if append && path.exists() {
+            if let Err(e) = io::copy(&mut File::open(path)?, &mut file) {
+                let _ = fs::remove_file(&temp);
+                return Err(e);
+            }
+        }
+
+        Ok(Sink::File {
+            writer: BufWriter::new(file),
+            temp: Some(temp),
+            path: path.to_path_buf(),
+        })
+    }
+
+    #[cfg(feature = "serve")]
+    pub fn memory() -> Self {
+        Sink::Memory(Vec::new())
+    }
+
+    /// Takes the content written so far to memory. Other sinks give nothing.
+    #[cfg(feature = "serve")]
+    pub fn take(&mut self) -> Vec<u8> {
+        match self {
+            Sink::Memory(buffer) => std::mem::take(buffer),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn is_stdout(&self) -> bool {
+        matches!(self, Sink::Stdout(_))
+    }
+
+    /// Flushes the report and moves it into place.
+    pub fn commit(mut self) -> io::Result<()> {
+        self.flush()?;
+
+        if let Sink::File { writer, temp, path } = &mut self {
+            writer.get_ref().sync_all()?;
+
+            if let Some(file) = temp {
+                fs::rename(file, path)?;
+            }
+            *temp = None;
+        }
+
+        Ok(())
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Sink::Stdout(stdout) => stdout.write(buf),
+            Sink::File { writer, .. } => writer.write(buf),
+            #[cfg(feature = "serve")]
+            Sink::Memory(buffer) => buffer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Sink::Stdout(stdout) => stdout.flush(),
+            Sink::File { writer, .. } => writer.flush(),
+            #[cfg(feature = "serve")]
+            Sink::Memory(_) => Ok(()),
+        }
+    }
+}
+
+impl Drop for Sink {
+    fn drop(&mut self) {
+        if let Sink::File {
+            temp: Some(temp), ..
+        } = self
+        {
+            let _ = fs::remove_file(temp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn writes_on_commit_only() {
+        let dir = env::temp_dir().join(format!("heidi-sink-{}", process::id()));
+        let path = dir.join("report.txt");
+
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut sink = Sink::file(&path, false).unwrap();
+        writeln!(sink, "first").unwrap();
+        drop(sink);
+        assert!(!path.exists());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        let mut sink = Sink::file(&path, false).unwrap();
+        writeln!(sink, "first").unwrap();
+        sink.commit().unwrap();
+
+        let mut sink = Sink::file(&path, true).unwrap();
+        writeln!(sink, "second").unwrap();
+        sink.commit().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first\nsecond\n");
+
+        let mut sink = Sink::file(&path, false).unwrap();
+        writeln!(sink, "third").unwrap();
+        sink.commit().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "third\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
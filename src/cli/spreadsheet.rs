@@ -0,0 +1,149 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Validation of columns in spreadsheets (XLSX, XLS, ODS).
+
+use crate::columns::{positions, Checker};
+use crate::output::Reporter;
+use crate::Typeid;
+use calamine::{open_workbook_auto, Data, Reader};
+use heidi::batch::Stats;
+use std::io;
+use std::path::Path;
+
+/// Validates the given columns of a sheet, reporting the invalid values by
+/// cell reference, e.g. `B7`.
+///
+/// A column is either the name of a column in the header row or a letter
+/// reference like `B`, in that order. Without a header row only letter
+/// references can be used.
+///
+/// Empty cells are not validated and numeric cells are padded with leading
+/// zeros to 10 digits.
+pub fn check(
+    reporter: &mut Reporter,
+    _type: &Typeid,
+    input: &Path,
+    sheet: Option<&str>,
+    columns: &[String],
+    has_header: bool,
+    jobs: usize,
+) -> io::Result<Stats> {
+    let mut workbook = open_workbook_auto(input).map_err(error)?;
+    let sheet = match sheet {
+        Some(sheet) => sheet.to_string(),
+        None => workbook
+            .sheet_names()
+            .first()
+            .cloned()
+            .ok_or_else(|| invalid("The workbook has no sheets".to_string()))?,
+    };
+    let range = workbook.worksheet_range(&sheet).map_err(error)?;
+    let (first_row, first_col) = range.start().unwrap_or((0, 0));
+    let mut rows = range.rows().enumerate();
+    let header: Vec<String> = if has_header {
+        rows.next()
+            .map(|(_, row)| row.iter().map(|cell| cell.to_string()).collect())
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    let names: Vec<&str> = header.iter().map(String::as_str).collect();
+    let mut idxs = Vec::new();
+
+    for column in columns {
+        let idx = match (names.contains(&column.as_str()), letter_index(column)) {
+            // Letters are absolute while the range starts at its first
+            // non-empty cell.
+            (false, Some(idx)) => idx
+                .checked_sub(first_col as usize)
+                .ok_or_else(|| invalid(format!("Column '{}' is outside the data range", column)))?,
+            _ => positions(&names, std::slice::from_ref(column)).map_err(invalid)?[0],
+        };
+
+        idxs.push(idx);
+    }
+
+    let source = format!("{}:{}", input.display(), sheet);
+    let mut checker = Checker::new(reporter, _type, &source, jobs);
+
+    for (offset, row) in rows {
+        let number = first_row as usize + offset + 1;
+
+        for (column, &idx) in columns.iter().zip(&idxs) {
+            let value = match row.get(idx) {
+                None | Some(Data::Empty) => continue,
+                Some(Data::Int(value)) => format!("{:010}", value),
+                Some(Data::Float(value)) if value.fract() == 0.0 => {
+                    format!("{:010}", *value as i64)
+                }
+                Some(cell) => cell.to_string(),
+            };
+            let reference = format!("{}{}", letters(first_col as usize + idx), number);
+
+            checker.push((reference, vec![(column.clone(), value)]))?;
+        }
+
+        if checker.stopped() {
+            break;
+        }
+    }
+
+    checker.finish()
+}
+
+/// Returns the 0-based index of a column reference like `A` or `AB`.
+fn letter_index(column: &str) -> Option<usize> {
+    if column.is_empty() || column.len() > 3 || !column.bytes().all(|b| b.is_ascii_uppercase()) {
+        return None;
+    }
+
+    Some(
+        column
+            .bytes()
+            .fold(0, |acc, b| acc * 26 + (b - b'A' + 1) as usize)
+            - 1,
+    )
+}
+
+/// Returns the column reference of a 0-based index.
+fn letters(mut idx: usize) -> String {
+    let mut letters = Vec::new();
+
+    loop {
+        letters.push(b'A' + (idx % 26) as u8);
+        if idx < 26 {
+            break;
+        }
+        idx = idx / 26 - 1;
+    }
+    letters.reverse();
+
+    String::from_utf8(letters).expect("ASCII letters")
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+fn error<E: std::error::Error + Send + Sync + 'static>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn column_references() {
+        for (reference, idx) in &[("A", 0), ("Z", 25), ("AA", 26), ("AZ", 51), ("XFD", 16383)] {
+            assert_eq!(letter_index(reference), Some(*idx));
+            assert_eq!(&letters(*idx), reference);
+        }
+
+        assert_eq!(letter_index("nhs"), None);
+    }
+}
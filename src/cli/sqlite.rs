@@ -0,0 +1,77 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Validation of columns in SQLite tables.
+
+use crate::columns::Checker;
+use crate::output::Reporter;
+use crate::Typeid;
+use heidi::batch::Stats;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+use std::io;
+use std::path::Path;
+
+/// Validates the given columns of a table, reporting the invalid values by
+/// rowid.
+///
+/// The database is opened read-only. Null values are not validated and
+/// integer values are padded with leading zeros to 10 digits.
+pub fn check(
+    reporter: &mut Reporter,
+    _type: &Typeid,
+    db: &Path,
+    table: &str,
+    columns: &[String],
+    jobs: usize,
+) -> io::Result<Stats> {
+    let connection =
+        Connection::open_with_flags(db, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(error)?;
+    let selection: Vec<String> = columns.iter().map(|column| quote(column)).collect();
+    let sql = format!(
+        "SELECT rowid, {} FROM {} ORDER BY rowid",
+        selection.join(", "),
+        quote(table)
+    );
+    let mut statement = connection.prepare(&sql).map_err(error)?;
+    let mut rows = statement.query([]).map_err(error)?;
+    let source = format!("{}:{}", db.display(), table);
+    let mut checker = Checker::new(reporter, _type, &source, jobs);
+
+    while let Some(row) = rows.next().map_err(error)? {
+        let rowid: i64 = row.get(0).map_err(error)?;
+        let mut values = Vec::new();
+
+        for (idx, column) in columns.iter().enumerate() {
+            let value = match row.get_ref(idx + 1).map_err(error)? {
+                ValueRef::Null => continue,
+                ValueRef::Integer(value) => format!("{:010}", value),
+                ValueRef::Real(value) => value.to_string(),
+                ValueRef::Text(value) | ValueRef::Blob(value) => {
+                    String::from_utf8_lossy(value).into_owned()
+                }
+            };
+
+            values.push((column.clone(), value));
+        }
+
+        checker.push((rowid.to_string(), values))?;
+        if checker.stopped() {
+            break;
+        }
+    }
+
+    checker.finish()
+}
+
+/// Quotes an SQL identifier.
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+fn error(err: rusqlite::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
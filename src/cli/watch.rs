@@ -0,0 +1,243 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Polling of files so batch subcommands can keep validating them as they
+//! change, e.g. in a landing directory where extracts arrive.
+//!
+//! Files are polled rather than subscribed to so watching behaves the same on
+//! every platform and on network shares, where change notifications are
+//! unreliable.
+
+use crate::paths;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// The time between polls.
+pub const INTERVAL: Duration = Duration::from_secs(1);
+
+/// The size and modification time of a file when polled.
+#[derive(PartialEq, Clone, Copy, Debug)]
+struct Stamp {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+/// What is known of a file from the previous poll.
+#[derive(PartialEq, Clone, Copy, Debug)]
+struct Seen {
+    stamp: Stamp,
+    /// Whether it changed in the previous poll.
+    changing: bool,
+}
+
+/// How a file changed since the previous poll.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Change {
+    /// The file is new, or was truncated or rewritten, and has to be read
+    /// from the start.
+    Created,
+    /// Content was added at the end.
+    Appended,
+    /// The file has not changed since the previous poll, when it did, so
+    /// whatever was written is likely complete.
+    Settled,
+}
+
+/// Tells the files matching a set of paths that changed between polls.
+#[derive(Debug)]
+pub struct Watcher {
+    patterns: Vec<String>,
+    seen: HashMap<PathBuf, Seen>,
+}
+
+impl Watcher {
+    /// Watches the files the given paths expand to. See [`paths::expand`].
+    pub fn new(patterns: &[String]) -> Self {
+        Watcher {
+            patterns: patterns.to_vec(),
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Expands the paths again and returns the files that changed since the
+    /// previous poll, in order. Every file is new to the first poll.
+    ///
+    /// Paths matching nothing yet are not an error, as files may arrive later.
+    pub fn poll(&mut self) -> io::Result<Vec<(PathBuf, Change)>> {
+        let mut files = Vec::new();
+
+        for pattern in &self.patterns {
+            match paths::expand(&[pattern]) {
+                Ok(matches) => files.extend(matches),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut seen = HashMap::with_capacity(files.len());
+        let mut changes = Vec::new();
+
+        for path in files {
+            // Files removed since they were expanded are left for the next poll.
+            let metadata = match fs::metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            let stamp = Stamp {
+                len: metadata.len(),
+                modified: metadata.modified().ok(),
+            };
+            let (changed, now) = change(self.seen.get(&path), stamp);
+
+            if let Some(changed) = changed {
+                changes.push((path.clone(), changed));
+            }
+            seen.insert(path, now);
+        }
+
+        self.seen = seen;
+
+        Ok(changes)
+    }
+}
+
+/// Compares a file with what was seen of it in the previous poll.
+fn change(before: Option<&Seen>, stamp: Stamp) -> (Option<Change>, Seen) {
+    let changing = Seen {
+        stamp,
+        changing: true,
+    };
+
+    match before {
+        None => (Some(Change::Created), changing),
+        Some(before) if before.stamp == stamp => {
+            let change = if before.changing {
+                Some(Change::Settled)
+            } else {
+                None
+            };
+
+            (
+                change,
+                Seen {
+                    stamp,
+                    changing: false,
+                },
+            )
+        }
+        Some(before) if stamp.len > before.stamp.len => (Some(Change::Appended), changing),
+        Some(_) => (Some(Change::Created), changing),
+    }
+}
+
+/// Waits until the next poll.
+pub fn idle() {
+    thread::sleep(INTERVAL);
+}
+
+/// How far a file has been read.
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub struct Position {
+    /// The byte offset after the last record read.
+    pub offset: u64,
+    /// The amount of records read.
+    pub records: usize,
+}
+
+/// The records of a file past a position, ending with `separator`.
+///
+/// A last record without a separator is only read when `settled`, as it may
+/// still be being written. The position moves past every record read.
+pub fn tail<'a>(
+    path: &Path,
+    position: &'a mut Position,
+    separator: u8,
+    settled: bool,
+) -> io::Result<impl Iterator<Item = io::Result<String>> + 'a> {
+    let mut file = File::open(path)?;
+
+    file.seek(SeekFrom::Start(position.offset))?;
+
+    let mut reader = BufReader::new(file);
+
+    Ok(std::iter::from_fn(move || {
+        let mut record = Vec::new();
+
+        match reader.read_until(separator, &mut record) {
+            Ok(0) => None,
+            Ok(_) if record.last() != Some(&separator) && !settled => None,
+            Ok(len) => {
+                position.offset += len as u64;
+                position.records += 1;
+
+                if record.last() == Some(&separator) {
+                    record.pop();
+                }
+                if separator == b'\n' && record.last() == Some(&b'\r') {
+                    record.pop();
+                }
+
+                Some(
+                    String::from_utf8(record)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+                )
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes() {
+        let stamp = |len| Stamp {
+            len,
+            modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(len)),
+        };
+        let (created, seen) = change(None, stamp(10));
+        let (appended, seen) = change(Some(&seen), stamp(20));
+        let (settled, seen) = change(Some(&seen), stamp(20));
+        let (unchanged, seen) = change(Some(&seen), stamp(20));
+        let (truncated, _) = change(Some(&seen), stamp(5));
+
+        assert_eq!(created, Some(Change::Created));
+        assert_eq!(appended, Some(Change::Appended));
+        assert_eq!(settled, Some(Change::Settled));
+        assert_eq!(unchanged, None);
+        assert_eq!(truncated, Some(Change::Created));
+    }
+
+    #[test]
+    fn tails() {
+        let path = std::env::temp_dir().join(format!("heidi-tail-{}", std::process::id()));
+        let mut position = Position::default();
+        let mut read = |settled| {
+            tail(&path, &mut position, b'\n', settled)
+                .unwrap()
+                .collect::<io::Result<Vec<String>>>()
+                .unwrap()
+        };
+
+        fs::write(&path, "6541003238\r\n65410").unwrap();
+        assert_eq!(read(false), ["6541003238"]);
+        assert_eq!(read(false), Vec::<String>::new());
+
+        fs::write(&path, "6541003238\r\n6541003238\n010199").unwrap();
+        assert_eq!(read(false), ["6541003238"]);
+        assert_eq!(read(true), ["010199"]);
+        assert_eq!(position.records, 3);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Value parsers for clap 4 so other command line tools can take numbers as
+//! arguments and reject invalid ones while parsing arguments.
+//!
+//! Numbers implement [`ValueParserFactory`] so clap derives pick the parser
+//! on their own:
+//!
+//! ```
+//! use clap4 as clap;
+//! use heidi::nhs;
+//!
+//! let command = clap::Command::new("tool").arg(
+//!     clap::Arg::new("nhs-number")
+//!         .long("nhs-number")
+//!         .value_parser(clap::value_parser!(nhs::Number)),
+//! );
+//! let matches = command
+//!     .clone()
+//!     .try_get_matches_from(["tool", "--nhs-number", "654 100 3238"])
+//!     .unwrap();
+//!
+//! assert_eq!(
+//!     matches.get_one::<nhs::Number>("nhs-number").unwrap().to_string(),
+//!     "6541003238"
+//! );
+//! assert!(command
+//!     .try_get_matches_from(["tool", "--nhs-number", "6541003239"])
+//!     .is_err());
+//! ```
+//!
+//! Requires the `cli-support` feature.
+
+use crate::error::ValidationError;
+use crate::{chi, nhs};
+use clap4::builder::{TypedValueParser, ValueParserFactory};
+use clap4::error::{Error, ErrorKind};
+use clap4::{Arg, Command};
+use std::ffi::OsStr;
+use std::str::FromStr;
+
+/// Parses a valid NHS Number.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NhsNumberParser;
+
+impl TypedValueParser for NhsNumberParser {
+    type Value = nhs::Number;
+
+    fn parse_ref(
+        &self,
+        cmd: &Command,
+        arg: Option<&Arg>,
+        value: &OsStr,
+    ) -> Result<Self::Value, Error> {
+        parse(cmd, arg, value, nhs::Number::from_str)
+    }
+}
+
+impl ValueParserFactory for nhs::Number {
+    type Parser = NhsNumberParser;
+
+    fn value_parser() -> Self::Parser {
+        NhsNumberParser
+    }
+}
+
+/// Parses a valid CHI Number.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ChiNumberParser;
+
+impl TypedValueParser for ChiNumberParser {
+    type Value = chi::Number;
+
+    fn parse_ref(
+        &self,
+        cmd: &Command,
+        arg: Option<&Arg>,
+        value: &OsStr,
+    ) -> Result<Self::Value, Error> {
+        parse(cmd, arg, value, chi::Number::from_str)
+    }
+}
+
+impl ValueParserFactory for chi::Number {
+    type Parser = ChiNumberParser;
+
+    fn value_parser() -> Self::Parser {
+        ChiNumberParser
+    }
+}
+
+fn parse<T>(
+    cmd: &Command,
+    arg: Option<&Arg>,
+    value: &OsStr,
+    from_str: fn(&str) -> Result<T, ValidationError>,
+) -> Result<T, Error> {
+    let name = arg.map_or_else(|| "...".to_string(), |arg| arg.to_string());
+    let value = value.to_str().ok_or_else(|| {
+        Error::raw(
+            ErrorKind::InvalidUtf8,
+            format!("invalid UTF-8 value for '{}'\n", name),
+        )
+        .with_cmd(cmd)
+    })?;
+
+    from_str(value).map_err(|err| {
+        Error::raw(
+            ErrorKind::ValueValidation,
+            format!("invalid value '{}' for '{}': {}\n", value, name, err),
+        )
+        .with_cmd(cmd)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_arguments() {
+        let command = Command::new("tool")
+            .arg(Arg::new("chi").long("chi").value_parser(ChiNumberParser))
+            .arg(Arg::new("nhs").long("nhs").value_parser(NhsNumberParser));
+        let matches = command
+            .clone()
+            .try_get_matches_from(["tool", "--chi", "0101990014"])
+            .unwrap();
+
+        assert_eq!(
+            matches.get_one::<chi::Number>("chi").unwrap().to_string(),
+            "0101990014"
+        );
+
+        let err = command
+            .try_get_matches_from(["tool", "--nhs", "65410"])
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::ValueValidation);
+        assert!(err
+            .to_string()
+            .contains("invalid value '65410' for '--nhs <nhs>'"));
+    }
+}
@@ -0,0 +1,215 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Codecs to embed numbers in event payloads such as Avro or Protobuf with
+//! the same rules at every serialisation boundary.
+//!
+//! There are two encodings:
+//!
+//! - The canonical string of exactly 10 ASCII digits, for Avro `string` and
+//!   Protobuf `string` fields.
+//! - The packed integer, for Avro `long` and Protobuf `uint64` or `fixed64`
+//!   fields. Leading zeroes are implied.
+//!
+//! Decoding always validates the number. Protobuf messages generated by
+//! `prost` have plain fields so use the functions directly. Avro records
+//! read through serde use the [`canonical`] or [`packed`] modules with
+//! `#[serde(with = "...")]`.
+//!
+//! ```
+//! use heidi::nhs::Number;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Admission {
+//!     #[serde(with = "heidi::codec::packed")]
+//!     nhs_number: Number,
+//! }
+//!
+//! let event: Admission = serde_json::from_str(r#"{"nhs_number":6541003238}"#).unwrap();
+//!
+//! assert_eq!(event.nhs_number.to_string(), "6541003238");
+//! assert!(serde_json::from_str::<Admission>(r#"{"nhs_number":6541003239}"#).is_err());
+//! ```
+
+use crate::error::{ErrorKind, ValidationError};
+use crate::number::{self, Digit};
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+/// Encodes a number as its canonical string of 10 digits.
+pub fn to_canonical<N: AsRef<number::Number>>(number: &N) -> String {
+    number.as_ref().to_string()
+}
+
+/// Decodes a canonical string. Unlike `FromStr`, whitespace and any other
+/// formatting is rejected.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::codec::from_canonical;
+/// use heidi::nhs::Number;
+///
+/// assert!(from_canonical::<Number>("6541003238").is_ok());
+/// assert!(from_canonical::<Number>("654 100 3238").is_err());
+/// ```
+pub fn from_canonical<N>(input: &str) -> Result<N, ValidationError>
+where
+    N: FromStr<Err = ValidationError>,
+{
+    if let Some(c) = input.chars().find(|c| !c.is_ascii_digit()) {
+        return Err(ValidationError::with_kind(
+            ErrorKind::Digit,
+            &format!("The given character '{}' is not a digit", c),
+        )
+        .with_param("character", c));
+    }
+
+    if input.len() != number::LENGTH {
+        return Err(ValidationError::with_kind(
+            ErrorKind::Length,
+            "Canonical numbers must be of ten-digit long",
+        )
+        .with_param("length", input.len()));
+    }
+
+    N::from_str(input)
+}
+
+/// Encodes a number as a packed integer.
+pub fn to_packed<N: AsRef<number::Number>>(number: &N) -> u64 {
+    let number = number.as_ref();
+
+    number
+        .digits()
+        .iter()
+        .chain(Some(number.checkdigit()))
+        .fold(0, |packed, &digit| packed * 10 + u64::from(digit))
+}
+
+/// Decodes a packed integer.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::chi::Number;
+/// use heidi::codec::{from_packed, to_packed};
+///
+/// let number: Number = from_packed(101990014).unwrap();
+///
+/// assert_eq!(number.to_string(), "0101990014");
+/// assert_eq!(to_packed(&number), 101990014);
+/// ```
+pub fn from_packed<N>(value: u64) -> Result<N, ValidationError>
+where
+    N: for<'a> TryFrom<&'a [Digit; 10], Error = ValidationError>,
+{
+    N::try_from(&number::split(value)?)
+}
+
+/// Serde functions for the canonical string encoding.
+pub mod canonical {
+    use crate::error::ValidationError;
+    use crate::number;
+    use serde::de::{Deserialize, Deserializer, Error};
+    use serde::ser::Serializer;
+    use std::borrow::Cow;
+    use std::str::FromStr;
+
+    pub fn serialize<N, S>(number: &N, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        N: AsRef<number::Number>,
+        S: Serializer,
+    {
+        serializer.serialize_str(&super::to_canonical(number))
+    }
+
+    pub fn deserialize<'de, N, D>(deserializer: D) -> Result<N, D::Error>
+    where
+        N: FromStr<Err = ValidationError>,
+        D: Deserializer<'de>,
+    {
+        let input = Cow::<str>::deserialize(deserializer)?;
+
+        super::from_canonical(&input).map_err(D::Error::custom)
+    }
+}
+
+/// Serde functions for the packed integer encoding.
+pub mod packed {
+    use crate::error::ValidationError;
+    use crate::number::{self, Digit};
+    use serde::de::{Deserialize, Deserializer, Error};
+    use serde::ser::Serializer;
+    use std::convert::TryFrom;
+
+    pub fn serialize<N, S>(number: &N, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        N: AsRef<number::Number>,
+        S: Serializer,
+    {
+        serializer.serialize_u64(super::to_packed(number))
+    }
+
+    pub fn deserialize<'de, N, D>(deserializer: D) -> Result<N, D::Error>
+    where
+        N: for<'a> TryFrom<&'a [Digit; 10], Error = ValidationError>,
+        D: Deserializer<'de>,
+    {
+        let value = u64::deserialize(deserializer)?;
+
+        super::from_packed(value).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chi, nhs};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Event {
+        #[serde(with = "canonical")]
+        nhs: nhs::Number,
+        #[serde(with = "packed")]
+        chi: chi::Number,
+    }
+
+    #[test]
+    fn round_trips() {
+        let event = Event {
+            nhs: nhs::Number::from_str("6541003238").unwrap(),
+            chi: chi::Number::from_str("0101990014").unwrap(),
+        };
+        let json = serde_json::to_string(&event).unwrap();
+
+        assert_eq!(json, r#"{"nhs":"6541003238","chi":101990014}"#);
+        assert_eq!(serde_json::from_str::<Event>(&json).unwrap(), event);
+    }
+
+    #[test]
+    fn rejects_on_decode() {
+        assert_eq!(
+            from_canonical::<nhs::Number>("654-100-3238")
+                .unwrap_err()
+                .kind(),
+            ErrorKind::Digit
+        );
+        assert_eq!(
+            from_canonical::<nhs::Number>("654100323")
+                .unwrap_err()
+                .kind(),
+            ErrorKind::Length
+        );
+        assert_eq!(
+            from_packed::<nhs::Number>(16541003238).unwrap_err().kind(),
+            ErrorKind::Length
+        );
+        assert!(serde_json::from_str::<Event>(r#"{"nhs":"6541003239","chi":101990014}"#).is_err());
+    }
+}
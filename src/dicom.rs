@@ -0,0 +1,402 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Patient identifiers from DICOM files, e.g. to audit PACS exports.
+//!
+//! Reads Patient ID (0010,0020), Other Patient IDs (0010,1000) and the
+//! Patient IDs in Other Patient IDs Sequence (0010,1002) from DICOM Part 10
+//! files and validates them as NHS or CHI numbers. Reading stops once the
+//! patient module is over so pixel data is never touched. Deflated transfer
+//! syntaxes are not supported.
+//!
+//! Requires the `dicom` feature.
+
+use crate::any::AnyNumber;
+use crate::error::ValidationError;
+use memmap2::Mmap;
+use std::convert::TryInto;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+type Tag = (u16, u16);
+
+const TRANSFER_SYNTAX: Tag = (0x0002, 0x0010);
+const PATIENT_ID: Tag = (0x0010, 0x0020);
+const ISSUER: Tag = (0x0010, 0x0021);
+const OTHER_PATIENT_IDS: Tag = (0x0010, 0x1000);
+const OTHER_PATIENT_IDS_SEQUENCE: Tag = (0x0010, 0x1002);
+const ITEM: Tag = (0xFFFE, 0xE000);
+const ITEM_END: Tag = (0xFFFE, 0xE00D);
+const SEQUENCE_END: Tag = (0xFFFE, 0xE0DD);
+const UNDEFINED: u32 = 0xFFFF_FFFF;
+
+/// Value representations with a 4-byte length in explicit VR encodings.
+const LONG_VRS: [&[u8; 2]; 13] = [
+    b"OB", b"OD", b"OF", b"OL", b"OV", b"OW", b"SQ", b"SV", b"UC", b"UN", b"UR", b"UT", b"UV",
+];
+
+/// The element a patient identifier was read from.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Source {
+    /// Patient ID (0010,0020).
+    PatientId,
+    /// Other Patient IDs (0010,1000).
+    OtherPatientIds,
+    /// An item of Other Patient IDs Sequence (0010,1002).
+    OtherPatientIdsSequence,
+}
+
+/// A patient identifier and whether it is a valid number.
+#[derive(PartialEq, Clone, Debug)]
+pub struct PatientId {
+    pub source: Source,
+    /// The value without padding.
+    pub value: String,
+    /// The Issuer of Patient ID (0010,0021) next to the value, if any.
+    pub issuer: Option<String>,
+    /// The number with the schemes it validates under, or why it is invalid.
+    pub result: Result<AnyNumber, ValidationError>,
+}
+
+impl PatientId {
+    fn new(source: Source, value: &str, issuer: Option<&String>) -> Self {
+        PatientId {
+            source,
+            value: value.to_string(),
+            issuer: issuer.cloned(),
+            result: AnyNumber::from_str(value),
+        }
+    }
+}
+
+/// Reads the patient identifiers of a DICOM file.
+pub fn read(path: &Path) -> io::Result<Vec<PatientId>> {
+    let file = File::open(path)?;
+    // SAFETY: The map is read-only and dropped before returning. Concurrent
+    // truncation by another process is outside of what a read can guard
+    // against.
+    let map = unsafe { Mmap::map(&file)? };
+
+    patient_ids(&map)
+}
+
+/// Reads the patient identifiers of the content of a DICOM file.
+///
+/// # Errors
+///
+/// Fails with [`io::ErrorKind::InvalidData`] when the content is not a DICOM
+/// Part 10 file or is truncated before the patient module ends.
+pub fn patient_ids(bytes: &[u8]) -> io::Result<Vec<PatientId>> {
+    if bytes.get(128..132) != Some(b"DICM") {
+        return Err(invalid("Not a DICOM file"));
+    }
+
+    let mut parser = Parser {
+        bytes,
+        pos: 132,
+        explicit: true,
+        big_endian: false,
+    };
+    let mut syntax = String::new();
+
+    while bytes.get(parser.pos..parser.pos + 2) == Some(&[0x02, 0x00]) {
+        let (tag, _, length) = parser.header()?;
+        let value = parser.take(length)?;
+
+        if tag == TRANSFER_SYNTAX {
+            syntax = text(value);
+        }
+    }
+
+    match syntax.as_str() {
+        "1.2.840.10008.1.2" => parser.explicit = false,
+        "1.2.840.10008.1.2.2" => parser.big_endian = true,
+        "1.2.840.10008.1.2.1.99" => return Err(invalid("Deflated DICOM files are not supported")),
+        _ => {}
+    }
+
+    let mut sequence = Vec::new();
+    let values = parser.dataset(bytes.len(), None, true, &mut sequence)?;
+    let mut ids = Vec::new();
+
+    if let Some(id) = value(&values, PATIENT_ID) {
+        ids.push(PatientId::new(
+            Source::PatientId,
+            id,
+            value(&values, ISSUER),
+        ));
+    }
+
+    if let Some(others) = value(&values, OTHER_PATIENT_IDS) {
+        let others = others
+            .split('\\')
+            .map(str::trim)
+            .filter(|id| !id.is_empty());
+
+        for id in others {
+            ids.push(PatientId::new(Source::OtherPatientIds, id, None));
+        }
+    }
+
+    ids.extend(sequence);
+
+    Ok(ids)
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    explicit: bool,
+    big_endian: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn take(&mut self, length: u32) -> io::Result<&'a [u8]> {
+        let end = self.pos + length as usize;
+        let value = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or_else(|| invalid("The DICOM file is truncated"))?;
+
+        self.pos = end;
+
+        Ok(value)
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let bytes = self.take(2)?.try_into().expect("2 bytes");
+
+        Ok(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let bytes = self.take(4)?.try_into().expect("4 bytes");
+
+        Ok(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    /// Reads the tag, value representation and length of an element.
+    fn header(&mut self) -> io::Result<(Tag, Option<&'a [u8]>, u32)> {
+        let tag = (self.u16()?, self.u16()?);
+
+        if tag.0 == 0xFFFE || !self.explicit {
+            return Ok((tag, None, self.u32()?));
+        }
+
+        let vr = self.take(2)?;
+
+        if LONG_VRS.iter().any(|long| &long[..] == vr) {
+            self.take(2)?;
+            Ok((tag, Some(vr), self.u32()?))
+        } else {
+            Ok((tag, Some(vr), u32::from(self.u16()?)))
+        }
+    }
+
+    /// Reads the elements of a data set up to `end`, or up to the
+    /// `delimiter` when its length is undefined, and returns the values of
+    /// the identifier elements. Patient IDs in sequences are added to `ids`.
+    ///
+    /// The top level data set stops after the patient module.
+    fn dataset(
+        &mut self,
+        end: usize,
+        delimiter: Option<Tag>,
+        top: bool,
+        ids: &mut Vec<PatientId>,
+    ) -> io::Result<Vec<(Tag, String)>> {
+        let mut values = Vec::new();
+
+        while self.pos < end {
+            let (tag, vr, length) = self.header()?;
+
+            if Some(tag) == delimiter || (top && tag.0 > 0x0010) {
+                break;
+            }
+
+            if vr == Some(b"SQ") || length == UNDEFINED || tag == OTHER_PATIENT_IDS_SEQUENCE {
+                self.sequence(tag, length, ids)?;
+            } else {
+                let value = self.take(length)?;
+
+                if [PATIENT_ID, ISSUER, OTHER_PATIENT_IDS].contains(&tag) {
+                    values.push((tag, text(value)));
+                }
+            }
+        }
+
+        Ok(values)
+    }
+
+    /// Reads the items of a sequence, adding the Patient IDs of Other
+    /// Patient IDs Sequence to `ids`.
+    fn sequence(&mut self, tag: Tag, length: u32, ids: &mut Vec<PatientId>) -> io::Result<()> {
+        let end = if length == UNDEFINED {
+            self.bytes.len()
+        } else {
+            self.pos + length as usize
+        };
+
+        while self.pos < end {
+            let (item, _, length) = self.header()?;
+
+            match item {
+                SEQUENCE_END => break,
+                ITEM => {
+                    let (end, delimiter) = if length == UNDEFINED {
+                        (self.bytes.len(), Some(ITEM_END))
+                    } else {
+                        (self.pos + length as usize, None)
+                    };
+                    let values = self.dataset(end, delimiter, false, ids)?;
+
+                    if tag == OTHER_PATIENT_IDS_SEQUENCE {
+                        if let Some(id) = value(&values, PATIENT_ID) {
+                            ids.push(PatientId::new(
+                                Source::OtherPatientIdsSequence,
+                                id,
+                                value(&values, ISSUER),
+                            ));
+                        }
+                    }
+                }
+                _ => return Err(invalid("Unexpected element in a DICOM sequence")),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn value(values: &[(Tag, String)], tag: Tag) -> Option<&String> {
+    values
+        .iter()
+        .find(|(found, _)| *found == tag)
+        .map(|(_, value)| value)
+}
+
+/// Decodes a text value without its padding.
+fn text(value: &[u8]) -> String {
+    String::from_utf8_lossy(value)
+        .trim_matches(|c: char| c == ' ' || c == '\0')
+        .to_string()
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::scheme::Scheme;
+
+    /// Writes an explicit VR little endian element.
+    fn element(out: &mut Vec<u8>, tag: Tag, vr: &[u8; 2], value: &[u8]) {
+        out.extend(&tag.0.to_le_bytes());
+        out.extend(&tag.1.to_le_bytes());
+        out.extend(vr);
+
+        if LONG_VRS.contains(&vr) {
+            out.extend(&[0, 0]);
+            out.extend(&(value.len() as u32).to_le_bytes());
+        } else {
+            out.extend(&(value.len() as u16).to_le_bytes());
+        }
+
+        out.extend(value);
+    }
+
+    fn file(syntax: &[u8]) -> Vec<u8> {
+        let mut out = vec![0; 128];
+
+        out.extend(b"DICM");
+        element(&mut out, TRANSFER_SYNTAX, b"UI", syntax);
+
+        out
+    }
+
+    #[test]
+    fn explicit_little_endian() {
+        let mut out = file(b"1.2.840.10008.1.2.1\0");
+        let mut item = Vec::new();
+
+        element(&mut item, PATIENT_ID, b"LO", b"6541003239");
+        element(&mut item, ISSUER, b"LO", b"NHS ");
+
+        // A sequence of undefined length before the patient module.
+        element(&mut out, (0x0008, 0x1110), b"SQ", &[]);
+        out.truncate(out.len() - 4);
+        out.extend(&UNDEFINED.to_le_bytes());
+        out.extend(&[0xFE, 0xFF, 0x00, 0xE0]);
+        out.extend(&UNDEFINED.to_le_bytes());
+        element(&mut out, (0x0008, 0x1150), b"UI", b"1.2\0");
+        out.extend(&[0xFE, 0xFF, 0x0D, 0xE0, 0, 0, 0, 0]);
+        out.extend(&[0xFE, 0xFF, 0xDD, 0xE0, 0, 0, 0, 0]);
+
+        element(&mut out, PATIENT_ID, b"LO", b"0101990014");
+        element(&mut out, OTHER_PATIENT_IDS, b"LO", b"6541003238\\MRN1 ");
+
+        let mut sequence = vec![0xFE, 0xFF, 0x00, 0xE0];
+
+        sequence.extend(&(item.len() as u32).to_le_bytes());
+        sequence.extend(&item);
+        element(&mut out, OTHER_PATIENT_IDS_SEQUENCE, b"SQ", &sequence);
+        // Pixel data is never read.
+        element(&mut out, (0x7FE0, 0x0010), b"OB", &[]);
+        out.truncate(out.len() - 4);
+        out.extend(&1000u32.to_le_bytes());
+
+        let ids = patient_ids(&out).unwrap();
+        let summary: Vec<_> = ids
+            .iter()
+            .map(|id| (id.source, id.value.as_str(), id.issuer.as_deref()))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                (Source::PatientId, "0101990014", None),
+                (Source::OtherPatientIds, "6541003238", None),
+                (Source::OtherPatientIds, "MRN1", None),
+                (Source::OtherPatientIdsSequence, "6541003239", Some("NHS")),
+            ]
+        );
+        assert!(ids[0].result.as_ref().unwrap().is(Scheme::Chi));
+        assert_eq!(
+            ids[3].result.as_ref().unwrap_err().kind(),
+            ErrorKind::CheckDigit
+        );
+    }
+
+    #[test]
+    fn implicit_little_endian() {
+        let mut out = file(b"1.2.840.10008.1.2\0");
+
+        out.extend(&[0x10, 0x00, 0x20, 0x00, 10, 0, 0, 0]);
+        out.extend(b"6541003238");
+
+        let ids = patient_ids(&out).unwrap();
+
+        assert_eq!(ids.len(), 1);
+        assert!(ids[0].result.is_ok());
+        assert_eq!(
+            patient_ids(b"DICM").unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
@@ -0,0 +1,158 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Diesel SQL types for NHS and CHI numbers so models can have number
+//! fields that are validated when loaded.
+//!
+//! Numbers are stored as `Text` of 10 digits or as `BigInt`, where leading
+//! zeroes are lost, in Postgres, MySQL and SQLite. Loading fails when the
+//! stored number is not valid.
+//!
+//! ```ignore
+//! #[derive(Queryable, Insertable)]
+//! #[diesel(table_name = patient)]
+//! struct Patient {
+//!     id: i32,
+//!     nhs_number: heidi::nhs::Number,
+//! }
+//! ```
+//!
+//! Requires the `diesel` feature.
+
+use crate::number::from_bigint;
+use crate::{chi, nhs};
+use diesel::backend::Backend;
+use diesel::deserialize::{self, FromSql};
+use diesel::mysql::Mysql;
+use diesel::pg::Pg;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::{BigInt, Text};
+use diesel::sqlite::Sqlite;
+use std::str::FromStr;
+
+/// Converts a number to store as an integer.
+fn to_bigint(number: &str) -> i64 {
+    number.parse().expect("10 digits fit in a BigInt")
+}
+
+macro_rules! sql_type {
+    ($number:ty) => {
+        impl<DB: Backend> FromSql<Text, DB> for $number
+        where
+            String: FromSql<Text, DB>,
+        {
+            fn from_sql(bytes: DB::RawValue<'_>) -> deserialize::Result<Self> {
+                let text = <String as FromSql<Text, DB>>::from_sql(bytes)?;
+
+                Ok(<$number>::from_str(&text)?)
+            }
+        }
+
+        impl<DB: Backend> FromSql<BigInt, DB> for $number
+        where
+            i64: FromSql<BigInt, DB>,
+        {
+            fn from_sql(bytes: DB::RawValue<'_>) -> deserialize::Result<Self> {
+                let value = <i64 as FromSql<BigInt, DB>>::from_sql(bytes)?;
+
+                Ok(from_bigint(value)?)
+            }
+        }
+
+        impl ToSql<Text, Sqlite> for $number {
+            fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Sqlite>) -> serialize::Result {
+                out.set_value(self.to_string());
+
+                Ok(IsNull::No)
+            }
+        }
+
+        impl ToSql<BigInt, Sqlite> for $number {
+            fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Sqlite>) -> serialize::Result {
+                out.set_value(to_bigint(&self.to_string()));
+
+                Ok(IsNull::No)
+            }
+        }
+
+        sql_type!($number, Pg);
+        sql_type!($number, Mysql);
+    };
+    ($number:ty, $backend:ty) => {
+        impl ToSql<Text, $backend> for $number {
+            fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, $backend>) -> serialize::Result {
+                <str as ToSql<Text, $backend>>::to_sql(&self.to_string(), &mut out.reborrow())
+            }
+        }
+
+        impl ToSql<BigInt, $backend> for $number {
+            fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, $backend>) -> serialize::Result {
+                let value = to_bigint(&self.to_string());
+
+                <i64 as ToSql<BigInt, $backend>>::to_sql(&value, &mut out.reborrow())
+            }
+        }
+    };
+}
+
+sql_type!(nhs::Number);
+sql_type!(chi::Number);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use diesel::prelude::*;
+    use diesel::sql_query;
+
+    diesel::table! {
+        patient (id) {
+            id -> Integer,
+            nhs_number -> Text,
+            chi_number -> BigInt,
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let mut conn = SqliteConnection::establish(":memory:").unwrap();
+        let nhs = nhs::Number::from_str("6541003238").unwrap();
+        let chi = chi::Number::from_str("0101990014").unwrap();
+
+        sql_query("CREATE TABLE patient (id INTEGER PRIMARY KEY, nhs_number TEXT NOT NULL, chi_number BIGINT NOT NULL)")
+            .execute(&mut conn)
+            .unwrap();
+        diesel::insert_into(patient::table)
+            .values((
+                patient::id.eq(1),
+                patient::nhs_number.eq(&nhs),
+                patient::chi_number.eq(&chi),
+            ))
+            .execute(&mut conn)
+            .unwrap();
+
+        let loaded: (nhs::Number, chi::Number) = patient::table
+            .select((patient::nhs_number, patient::chi_number))
+            .filter(patient::nhs_number.eq(&nhs))
+            .first(&mut conn)
+            .unwrap();
+
+        assert_eq!(loaded, (nhs, chi));
+    }
+
+    #[test]
+    fn rejects_invalid_numbers() {
+        let mut conn = SqliteConnection::establish(":memory:").unwrap();
+        let loaded = diesel::select(diesel::dsl::sql::<Text>("'6541003239'"))
+            .get_result::<nhs::Number>(&mut conn);
+
+        assert!(loaded.is_err());
+        assert_eq!(
+            from_bigint::<nhs::Number>(-6541003238).unwrap_err().kind(),
+            ErrorKind::Digit
+        );
+    }
+}
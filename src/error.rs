@@ -4,16 +4,134 @@
 // This file may not be copied, modified, or distributed except
 // according to those terms.
 
+use crate::i18n::{self, Language};
 use std::error::Error;
 use std::fmt;
 
+/// The category of a [`ValidationError`].
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone, Debug)]
+pub enum ErrorKind {
+    /// The input does not have the expected amount of digits.
+    Length,
+    /// The input has characters other than digits and whitespace.
+    Digit,
+    /// The given check digit does not match the computed one.
+    CheckDigit,
+    /// The main digits compute a check digit of 10 which is never issued.
+    Unusable,
+    /// The embedded date of birth is not a valid date.
+    Date,
+    /// Any other failure.
+    Other,
+}
+
+impl ErrorKind {
+    /// A short lowercase name for the category.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::Length => "length",
+            ErrorKind::Digit => "digit",
+            ErrorKind::CheckDigit => "checkdigit",
+            ErrorKind::Unusable => "unusable",
+            ErrorKind::Date => "date",
+            ErrorKind::Other => "other",
+        }
+    }
+
+    /// A stable code for the category, for API responses and logs to key off
+    /// rather than the English message. Codes are never reused.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heidi::error::ErrorKind;
+    ///
+    /// assert_eq!(ErrorKind::CheckDigit.code(), "HEIDI_E002");
+    /// ```
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorKind::Length => "HEIDI_E001",
+            ErrorKind::CheckDigit => "HEIDI_E002",
+            ErrorKind::Digit => "HEIDI_E003",
+            ErrorKind::Unusable => "HEIDI_E004",
+            ErrorKind::Date => "HEIDI_E005",
+            ErrorKind::Other => "HEIDI_E999",
+        }
+    }
+
+    /// The numeric part of [`code`](ErrorKind::code).
+    pub fn number(&self) -> u16 {
+        match self {
+            ErrorKind::Length => 1,
+            ErrorKind::CheckDigit => 2,
+            ErrorKind::Digit => 3,
+            ErrorKind::Unusable => 4,
+            ErrorKind::Date => 5,
+            ErrorKind::Other => 999,
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
 /// Represents an error after validating the integrity of a number.
 #[derive(PartialEq, Debug, Clone)]
-pub struct ValidationError(String);
+pub struct ValidationError {
+    kind: ErrorKind,
+    message: String,
+    params: Vec<(&'static str, String)>,
+}
 
 impl ValidationError {
     pub fn new(msg: &str) -> Self {
-        Self(msg.to_string())
+        Self::with_kind(ErrorKind::Other, msg)
+    }
+
+    pub fn with_kind(kind: ErrorKind, msg: &str) -> Self {
+        Self {
+            kind,
+            message: msg.to_string(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Adds a named value from the message, such as the `given` and
+    /// `expected` check digits, so translations can keep the detail.
+    pub fn with_param<T: fmt::Display>(mut self, name: &'static str, value: T) -> Self {
+        self.params.push((name, value.to_string()));
+        self
+    }
+
+    /// The value of the named parameter, if the error has it.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// The stable code of the error kind, e.g. `HEIDI_E001`.
+    pub fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+
+    /// The message in the given language. English keeps the original
+    /// message while other languages fill the parameters of the error into
+    /// the catalogue, or describe the error kind when it has none.
+    pub fn message_in(&self, language: Language) -> String {
+        match language {
+            Language::English => self.message.clone(),
+            _ => i18n::detail(self.kind, language, &self.params)
+                .unwrap_or_else(|| i18n::message(self.kind, language).to_string()),
+        }
     }
 }
 
@@ -21,6 +139,30 @@ impl Error for ValidationError {}
 
 impl fmt::Display for ValidationError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.0)
+        write!(f, "{}", self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes() {
+        let error = ValidationError::with_kind(ErrorKind::Length, "Too short");
+
+        assert_eq!(error.code(), "HEIDI_E001");
+        assert_eq!(ErrorKind::Other.number(), 999);
+        assert_eq!(ErrorKind::Date.number(), 5);
+        assert!([
+            ErrorKind::Length,
+            ErrorKind::Digit,
+            ErrorKind::CheckDigit,
+            ErrorKind::Unusable,
+            ErrorKind::Date,
+            ErrorKind::Other,
+        ]
+        .iter()
+        .all(|kind| kind.code() == format!("HEIDI_E{:03}", kind.number())));
     }
 }
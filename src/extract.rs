@@ -0,0 +1,280 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Request extractors for axum and actix-web so routes taking a number in
+//! their path only run with a valid one.
+//!
+//! The number is read from the route parameter named `nhs` or `chi`, e.g.
+//! `/patients/{nhs}/records/{page}`. An invalid number, or a route without
+//! that parameter, is rejected with a `400 Bad Request` and a JSON body
+//! before the handler runs:
+//!
+//! ```json
+//! { "error": "The given check digit 9 does not match the actual check digit 8", "kind": "checkdigit" }
+//! ```
+//!
+//! Requires the `axum` or the `actix` feature.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! use heidi::extract::NhsNumberParam;
+//!
+//! async fn records(NhsNumberParam(number): NhsNumberParam) -> String {
+//!     number.to_string()
+//! }
+//!
+//! // axum
+//! let app = axum::Router::new().route("/patients/{nhs}/records", axum::routing::get(records));
+//!
+//! // actix-web
+//! let app = actix_web::App::new().route("/patients/{nhs}/records", actix_web::web::get().to(records));
+//! ```
+
+use crate::error::{ErrorKind, ValidationError};
+use crate::{chi, nhs};
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+/// A valid NHS Number taken from the `nhs` parameter of the route.
+#[derive(PartialEq, Clone, Debug)]
+pub struct NhsNumberParam(pub nhs::Number);
+
+impl NhsNumberParam {
+    /// The name of the route parameter the number is read from.
+    pub const NAME: &'static str = "nhs";
+}
+
+/// A valid CHI Number taken from the `chi` parameter of the route.
+#[derive(PartialEq, Clone, Debug)]
+pub struct ChiNumberParam(pub chi::Number);
+
+impl ChiNumberParam {
+    /// The name of the route parameter the number is read from.
+    pub const NAME: &'static str = "chi";
+}
+
+/// The rejection of a request whose route parameter is not a valid number.
+#[derive(PartialEq, Clone, Debug)]
+pub struct InvalidNumber(pub ValidationError);
+
+impl error::Error for InvalidNumber {}
+
+impl fmt::Display for InvalidNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl InvalidNumber {
+    fn body(&self) -> String {
+        serde_json::json!({
+            "error": self.0.to_string(),
+            "kind": self.0.kind().as_str(),
+        })
+        .to_string()
+    }
+}
+
+/// Validates the route parameter with the given name.
+fn parse<'a, N, I>(params: I, name: &str) -> Result<N, InvalidNumber>
+where
+    N: FromStr<Err = ValidationError>,
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let (_, value) = params
+        .into_iter()
+        .find(|(param, _)| *param == name)
+        .ok_or_else(|| {
+            InvalidNumber(ValidationError::with_kind(
+                ErrorKind::Other,
+                &format!("The route has no parameter named '{}'", name),
+            ))
+        })?;
+
+    N::from_str(value).map_err(InvalidNumber)
+}
+
+#[cfg(feature = "axum")]
+mod axum_support {
+    use super::*;
+    use axum::body::Body;
+    use axum::extract::{FromRequestParts, RawPathParams};
+    use axum::response::{IntoResponse, Response};
+    use http::header::CONTENT_TYPE;
+    use http::request::Parts;
+    use http::StatusCode;
+
+    impl IntoResponse for InvalidNumber {
+        fn into_response(self) -> Response {
+            Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(self.body()))
+                .expect("valid response")
+        }
+    }
+
+    async fn params<S: Send + Sync>(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<RawPathParams, InvalidNumber> {
+        RawPathParams::from_request_parts(parts, state)
+            .await
+            .map_err(|e| {
+                InvalidNumber(ValidationError::with_kind(ErrorKind::Other, &e.body_text()))
+            })
+    }
+
+    impl<S: Send + Sync> FromRequestParts<S> for NhsNumberParam {
+        type Rejection = InvalidNumber;
+
+        async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+            parse(&params(parts, state).await?, NhsNumberParam::NAME).map(NhsNumberParam)
+        }
+    }
+
+    impl<S: Send + Sync> FromRequestParts<S> for ChiNumberParam {
+        type Rejection = InvalidNumber;
+
+        async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+            parse(&params(parts, state).await?, ChiNumberParam::NAME).map(ChiNumberParam)
+        }
+    }
+}
+
+#[cfg(feature = "actix")]
+mod actix_support {
+    use super::*;
+    use actix_web::dev::Payload;
+    use actix_web::http::header::ContentType;
+    use actix_web::http::StatusCode;
+    use actix_web::{FromRequest, HttpRequest, HttpResponse, ResponseError};
+    use std::future::{ready, Ready};
+
+    impl ResponseError for InvalidNumber {
+        fn status_code(&self) -> StatusCode {
+            StatusCode::BAD_REQUEST
+        }
+
+        fn error_response(&self) -> HttpResponse {
+            HttpResponse::BadRequest()
+                .content_type(ContentType::json())
+                .body(self.body())
+        }
+    }
+
+    impl FromRequest for NhsNumberParam {
+        type Error = InvalidNumber;
+        type Future = Ready<Result<Self, Self::Error>>;
+
+        fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+            ready(parse(req.match_info().iter(), NhsNumberParam::NAME).map(NhsNumberParam))
+        }
+    }
+
+    impl FromRequest for ChiNumberParam {
+        type Error = InvalidNumber;
+        type Future = Ready<Result<Self, Self::Error>>;
+
+        fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+            ready(parse(req.match_info().iter(), ChiNumberParam::NAME).map(ChiNumberParam))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_named_parameter() {
+        let params = [("trust", "RX1"), ("nhs", "654 100 3238"), ("page", "2")];
+        let number: nhs::Number = parse(params, NhsNumberParam::NAME).unwrap();
+
+        assert_eq!(number.to_string(), "6541003238");
+        assert_eq!(
+            parse::<chi::Number, _>([("chi", "0101990015")], ChiNumberParam::NAME)
+                .unwrap_err()
+                .0
+                .kind(),
+            ErrorKind::CheckDigit
+        );
+
+        let missing = parse::<chi::Number, _>([("id", "0101990014")], ChiNumberParam::NAME);
+
+        assert_eq!(
+            missing.unwrap_err().to_string(),
+            "The route has no parameter named 'chi'"
+        );
+    }
+
+    #[cfg(feature = "axum")]
+    #[test]
+    fn axum_routes() {
+        use axum::body::{to_bytes, Body};
+        use axum::routing::get;
+        use axum::Router;
+        use http::StatusCode;
+        use std::future::Future;
+        use std::pin::pin;
+        use std::task::{Context, Poll, Waker};
+        use tower_service::Service;
+
+        fn block_on<F: Future>(future: F) -> F::Output {
+            match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+                Poll::Ready(output) => output,
+                Poll::Pending => panic!("the route does not wait"),
+            }
+        }
+
+        let mut app = Router::new().route(
+            "/patients/{nhs}/records/{page}",
+            get(|NhsNumberParam(number): NhsNumberParam| async move { number.to_string() }),
+        );
+        let mut request = |path: &str| {
+            let response =
+                block_on(app.call(http::Request::get(path).body(Body::empty()).unwrap())).unwrap();
+            let status = response.status();
+            let body = block_on(to_bytes(response.into_body(), 1024)).unwrap();
+
+            (status, String::from_utf8(body.to_vec()).unwrap())
+        };
+
+        assert_eq!(
+            request("/patients/654%20100%203238/records/2"),
+            (StatusCode::OK, "6541003238".to_string())
+        );
+
+        let (status, body) = request("/patients/6541003239/records/2");
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains(r#""kind":"checkdigit""#));
+    }
+
+    #[cfg(feature = "actix")]
+    #[test]
+    fn actix_routes() {
+        use actix_web::test::TestRequest;
+        use actix_web::{FromRequest, ResponseError};
+
+        let extract = |value: &str| {
+            let (req, mut payload) = TestRequest::default()
+                .param("chi", value.to_string())
+                .param("page", "2")
+                .to_http_parts();
+
+            ChiNumberParam::from_request(&req, &mut payload).into_inner()
+        };
+
+        assert_eq!(extract("0101990014").unwrap().0.to_string(), "0101990014");
+        assert_eq!(
+            extract("0101990015").unwrap_err().status_code(),
+            actix_web::http::StatusCode::BAD_REQUEST
+        );
+    }
+}
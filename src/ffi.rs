@@ -0,0 +1,282 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! C-compatible functions so systems written in other languages can link
+//! against heidi.
+//!
+//! Numbers are passed as NUL-terminated strings and outcomes are returned as
+//! a [`HeidiStatus`]. The header in `include/heidi.h` is generated from this
+//! module with cbindgen when building with the `ffi` feature, and the tests
+//! fail when the tracked copy is stale.
+//!
+//! The crate builds as a Rust library only. Build the shared or the static
+//! library to link against with:
+//!
+//! ```sh
+//! cargo rustc --release --lib --features ffi --crate-type cdylib
+//! cargo rustc --release --lib --features ffi --crate-type staticlib
+//! ```
+//!
+//! Requires the `ffi` feature.
+
+use crate::error::{ErrorKind, ValidationError};
+use crate::number::Digit;
+use crate::{chi, nhs};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::str::FromStr;
+
+/// The outcome of a call.
+#[repr(C)]
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum HeidiStatus {
+    /// The number is valid, or the check digit was computed.
+    Valid = 0,
+    /// The input does not have the expected amount of digits.
+    Length = 1,
+    /// The input has characters other than digits and whitespace.
+    Digit = 2,
+    /// The given check digit does not match the computed one.
+    CheckDigit = 3,
+    /// The main digits compute a check digit of 10 which is never issued.
+    Unusable = 4,
+    /// The embedded date of birth is not a valid date.
+    Date = 5,
+    /// Any other failure.
+    Other = 6,
+    /// A pointer is null or the input is not UTF-8.
+    Argument = 7,
+}
+
+impl From<ErrorKind> for HeidiStatus {
+    fn from(kind: ErrorKind) -> Self {
+        match kind {
+            ErrorKind::Length => HeidiStatus::Length,
+            ErrorKind::Digit => HeidiStatus::Digit,
+            ErrorKind::CheckDigit => HeidiStatus::CheckDigit,
+            ErrorKind::Unusable => HeidiStatus::Unusable,
+            ErrorKind::Date => HeidiStatus::Date,
+            ErrorKind::Other => HeidiStatus::Other,
+        }
+    }
+}
+
+impl<T> From<Result<T, ValidationError>> for HeidiStatus {
+    fn from(result: Result<T, ValidationError>) -> Self {
+        match result {
+            Ok(_) => HeidiStatus::Valid,
+            Err(err) => err.kind().into(),
+        }
+    }
+}
+
+/// Validates a NUL-terminated NHS Number, e.g. `"654 100 3238"`.
+///
+/// # Safety
+///
+/// `input` must be null or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn heidi_nhs_validate(input: *const c_char) -> HeidiStatus {
+    match text(input) {
+        Some(input) => nhs::Number::from_str(input).into(),
+        None => HeidiStatus::Argument,
+    }
+}
+
+/// Validates a NUL-terminated CHI Number, e.g. `"010199 0014"`.
+///
+/// # Safety
+///
+/// `input` must be null or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn heidi_chi_validate(input: *const c_char) -> HeidiStatus {
+    match text(input) {
+        Some(input) => chi::Number::from_str(input).into(),
+        None => HeidiStatus::Argument,
+    }
+}
+
+/// Computes the NHS check digit for the NUL-terminated 9 main digits and
+/// writes it to `checkdigit` when the status is valid.
+///
+/// # Safety
+///
+/// `stem` must be null or point to a NUL-terminated string, and `checkdigit`
+/// must be null or point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn heidi_nhs_checkdigit(
+    stem: *const c_char,
+    checkdigit: *mut u8,
+) -> HeidiStatus {
+    checkdigit_with(stem, checkdigit, |digits| {
+        nhs::Number::new(digits).map(|number| *number.checkdigit())
+    })
+}
+
+/// Computes the CHI check digit for the NUL-terminated 9 main digits and
+/// writes it to `checkdigit` when the status is valid.
+///
+/// # Safety
+///
+/// `stem` must be null or point to a NUL-terminated string, and `checkdigit`
+/// must be null or point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn heidi_chi_checkdigit(
+    stem: *const c_char,
+    checkdigit: *mut u8,
+) -> HeidiStatus {
+    checkdigit_with(stem, checkdigit, |digits| {
+        chi::Number::new(digits).map(|number| *number.checkdigit())
+    })
+}
+
+/// A static NUL-terminated lowercase name for the status, e.g. `"checkdigit"`.
+#[no_mangle]
+pub extern "C" fn heidi_status_str(status: HeidiStatus) -> *const c_char {
+    let name: &'static [u8] = match status {
+        HeidiStatus::Valid => b"valid\0",
+        HeidiStatus::Length => b"length\0",
+        HeidiStatus::Digit => b"digit\0",
+        HeidiStatus::CheckDigit => b"checkdigit\0",
+        HeidiStatus::Unusable => b"unusable\0",
+        HeidiStatus::Date => b"date\0",
+        HeidiStatus::Other => b"other\0",
+        HeidiStatus::Argument => b"argument\0",
+    };
+
+    name.as_ptr() as *const c_char
+}
+
+/// A static NUL-terminated version of the library, e.g. `"0.2.0"`.
+#[no_mangle]
+pub extern "C" fn heidi_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
+
+unsafe fn text<'a>(input: *const c_char) -> Option<&'a str> {
+    if input.is_null() {
+        return None;
+    }
+
+    CStr::from_ptr(input).to_str().ok()
+}
+
+unsafe fn checkdigit_with<F>(stem: *const c_char, checkdigit: *mut u8, compute: F) -> HeidiStatus
+where
+    F: FnOnce([Digit; 9]) -> Result<Digit, ValidationError>,
+{
+    if checkdigit.is_null() {
+        return HeidiStatus::Argument;
+    }
+
+    let digits = match text(stem) {
+        Some(stem) => digits(stem),
+        None => return HeidiStatus::Argument,
+    };
+
+    match digits.and_then(compute) {
+        Ok(digit) => {
+            *checkdigit = digit;
+
+            HeidiStatus::Valid
+        }
+        Err(err) => err.kind().into(),
+    }
+}
+
+/// Reads the 9 main digits of a number, ignoring whitespace.
+fn digits(stem: &str) -> Result<[Digit; 9], ValidationError> {
+    let mut digits = [0; 9];
+    let mut len = 0;
+
+    for c in stem.chars().filter(|c| !c.is_whitespace()) {
+        let digit = c.to_digit(10).ok_or_else(|| {
+            ValidationError::with_kind(
+                ErrorKind::Digit,
+                &format!("The given character '{}' is not a digit", c),
+            )
+        })?;
+
+        if len == digits.len() {
+            len += 1;
+            break;
+        }
+
+        digits[len] = digit as Digit;
+        len += 1;
+    }
+
+    if len != digits.len() {
+        return Err(ValidationError::with_kind(
+            ErrorKind::Length,
+            "The main digits must be nine-digit long",
+        ));
+    }
+
+    Ok(digits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+
+    #[test]
+    fn validates() {
+        let status = |input: &[u8]| unsafe { heidi_nhs_validate(input.as_ptr() as *const c_char) };
+
+        assert_eq!(status(b"654 100 3238\0"), HeidiStatus::Valid);
+        assert_eq!(status(b"6541003239\0"), HeidiStatus::CheckDigit);
+        assert_eq!(status(b"65410\0"), HeidiStatus::Length);
+        assert_eq!(status(b"65410O3238\0"), HeidiStatus::Digit);
+        assert_eq!(
+            unsafe { heidi_chi_validate(b"0101990014\0".as_ptr() as *const c_char) },
+            HeidiStatus::Valid
+        );
+        assert_eq!(
+            unsafe { heidi_nhs_validate(ptr::null()) },
+            HeidiStatus::Argument
+        );
+    }
+
+    #[test]
+    fn checkdigits() {
+        let mut digit = 0;
+        let status =
+            unsafe { heidi_nhs_checkdigit(b"654100323\0".as_ptr() as *const c_char, &mut digit) };
+
+        assert_eq!(status, HeidiStatus::Valid);
+        assert_eq!(digit, 8);
+        assert_eq!(
+            unsafe { heidi_chi_checkdigit(b"0101990014\0".as_ptr() as *const c_char, &mut digit) },
+            HeidiStatus::Length
+        );
+        assert_eq!(
+            unsafe {
+                heidi_nhs_checkdigit(b"654100323\0".as_ptr() as *const c_char, ptr::null_mut())
+            },
+            HeidiStatus::Argument
+        );
+
+        let name = unsafe { CStr::from_ptr(heidi_status_str(HeidiStatus::CheckDigit)) };
+
+        assert_eq!(name.to_str(), Ok("checkdigit"));
+    }
+
+    #[test]
+    fn header_is_current() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/heidi.h"));
+        let tracked = include_str!("../include/heidi.h");
+
+        assert!(
+            generated == tracked,
+            "include/heidi.h is stale: copy it from {}/heidi.h",
+            env!("OUT_DIR")
+        );
+        // `MIN` and `MAX` would clash with the macros of <sys/param.h>.
+        assert!(!tracked.contains("#define MIN") && !tracked.contains("#define MAX"));
+    }
+}
@@ -0,0 +1,200 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Pre-flight checks of the identifiers in FHIR R4 Patient resources.
+//!
+//! Identifiers are recognised by their `system`. Values must be the
+//! canonical 10 digits without spaces, as the NHS and NHS Scotland profiles
+//! require. [`identifier`] builds them the same way.
+
+use crate::codec::from_canonical;
+use crate::error::{ErrorKind, ValidationError};
+use crate::number;
+use crate::scheme::Scheme;
+use crate::{chi, nhs};
+use serde_json::{json, Value};
+
+/// The identifier system of NHS Numbers.
+pub const NHS_SYSTEM: &str = "https://fhir.nhs.uk/Id/nhs-number";
+
+/// The identifier system of CHI Numbers.
+pub const CHI_SYSTEM: &str = "https://fhir.nhs.scot/Id/chi-number";
+
+/// The identifier system of H&C Numbers.
+pub const HCN_SYSTEM: &str = "https://fhir.hscni.net/Id/hcn";
+
+/// The FHIR Identifier of a number under the given scheme.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::nhs::Number;
+/// use heidi::scheme::Scheme;
+/// use serde_json::json;
+/// use std::str::FromStr;
+///
+/// let number = Number::from_str("654 100 3238").unwrap();
+///
+/// assert_eq!(
+///     heidi::fhir::identifier(Scheme::Nhs, number.as_ref()),
+///     json!({ "system": "https://fhir.nhs.uk/Id/nhs-number", "value": "6541003238" })
+/// );
+/// ```
+pub fn identifier(scheme: Scheme, number: &number::Number) -> Value {
+    let system = match scheme {
+        Scheme::Nhs => NHS_SYSTEM,
+        Scheme::Chi => CHI_SYSTEM,
+        Scheme::Hcn => HCN_SYSTEM,
+    };
+
+    json!({ "system": system, "value": number.to_string() })
+}
+
+/// A Patient identifier that does not hold a valid number.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Finding {
+    /// JSON Pointer to the identifier, such as
+    /// `/entry/0/resource/identifier/1`.
+    pub pointer: String,
+    /// The scheme the identifier system stands for.
+    pub scheme: Scheme,
+    /// The value as given, if it is a string.
+    pub value: Option<String>,
+    pub error: ValidationError,
+}
+
+/// Checks every NHS, CHI and H&C identifier of a Patient, or of every Patient in
+/// a Bundle.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::error::ErrorKind;
+/// use serde_json::json;
+///
+/// let patient = json!({
+///     "resourceType": "Patient",
+///     "identifier": [
+///         { "system": "https://fhir.nhs.uk/Id/nhs-number", "value": "6541003238" },
+///         { "system": "https://fhir.nhs.uk/Id/nhs-number", "value": "6541003239" }
+///     ]
+/// });
+/// let findings = heidi::fhir::validate_patient(&patient);
+///
+/// assert_eq!(findings.len(), 1);
+/// assert_eq!(findings[0].pointer, "/identifier/1");
+/// assert_eq!(findings[0].error.kind(), ErrorKind::CheckDigit);
+/// ```
+pub fn validate_patient(resource: &Value) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    walk(resource, "", &mut findings);
+
+    findings
+}
+
+fn walk(resource: &Value, pointer: &str, findings: &mut Vec<Finding>) {
+    match resource["resourceType"].as_str() {
+        Some("Patient") => {
+            let identifiers = resource["identifier"].as_array().into_iter().flatten();
+
+            for (idx, identifier) in identifiers.enumerate() {
+                let pointer = format!("{}/identifier/{}", pointer, idx);
+
+                findings.extend(check(identifier, pointer));
+            }
+        }
+        Some("Bundle") => {
+            let entries = resource["entry"].as_array().into_iter().flatten();
+
+            for (idx, entry) in entries.enumerate() {
+                let pointer = format!("{}/entry/{}/resource", pointer, idx);
+
+                walk(&entry["resource"], &pointer, findings);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check(identifier: &Value, pointer: String) -> Option<Finding> {
+    let scheme = match identifier["system"].as_str()? {
+        NHS_SYSTEM => Scheme::Nhs,
+        CHI_SYSTEM => Scheme::Chi,
+        HCN_SYSTEM => Scheme::Hcn,
+        _ => return None,
+    };
+    let value = identifier["value"].as_str();
+    let result = match value {
+        Some(value) => match scheme {
+            Scheme::Nhs => from_canonical::<nhs::Number>(value).map(|_| ()),
+            Scheme::Chi => from_canonical::<chi::Number>(value).map(|_| ()),
+            Scheme::Hcn => {
+                from_canonical::<number::Number>(value).and_then(|n| Scheme::Hcn.check(&n))
+            }
+        },
+        None => Err(ValidationError::with_kind(
+            ErrorKind::Other,
+            "The identifier value is missing or not a string",
+        )),
+    };
+
+    result.err().map(|error| Finding {
+        pointer,
+        scheme,
+        value: value.map(String::from),
+        error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundles() {
+        let bundle = json!({
+            "resourceType": "Bundle",
+            "entry": [
+                { "resource": { "resourceType": "Observation" } },
+                { "resource": {
+                    "resourceType": "Patient",
+                    "identifier": [
+                        { "system": "https://example.org/mrn", "value": "x" },
+                        { "system": NHS_SYSTEM, "value": "654 100 3238" },
+                        { "system": CHI_SYSTEM, "value": "6541003238" },
+                        { "system": CHI_SYSTEM, "value": 101990014 }
+                    ]
+                } }
+            ]
+        });
+        let kinds: Vec<_> = validate_patient(&bundle)
+            .into_iter()
+            .map(|finding| (finding.pointer, finding.scheme, finding.error.kind()))
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                (
+                    "/entry/1/resource/identifier/1".to_string(),
+                    Scheme::Nhs,
+                    ErrorKind::Digit
+                ),
+                (
+                    "/entry/1/resource/identifier/2".to_string(),
+                    Scheme::Chi,
+                    ErrorKind::Date
+                ),
+                (
+                    "/entry/1/resource/identifier/3".to_string(),
+                    Scheme::Chi,
+                    ErrorKind::Other
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,165 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Fields for serde-based readers, such as the `csv` crate, that keep
+//! invalid numbers instead of rejecting the whole record.
+//!
+//! # Examples
+//!
+//! ```
+//! use heidi::fields::NhsField;
+//!
+//! let data = "name,nhs\nAda,654-100-3238\nBob,6541003239\n";
+//! let mut reader = csv::Reader::from_reader(data.as_bytes());
+//! let patients: Vec<(String, NhsField)> = reader.deserialize().collect::<Result<_, _>>().unwrap();
+//!
+//! assert!(patients[0].1.is_valid());
+//! assert_eq!(patients[1].1.input(), "6541003239");
+//! ```
+
+use crate::error::ValidationError;
+use crate::{chi, nhs};
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+use std::fmt;
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+/// A number read from a field, valid or not.
+#[derive(PartialEq, Clone, Debug)]
+pub enum Field<N> {
+    Valid(N),
+    /// The field as given and why it is not a valid number.
+    Invalid {
+        input: String,
+        error: ValidationError,
+    },
+}
+
+/// An NHS Number read from a field.
+pub type NhsField = Field<nhs::Number>;
+
+/// A CHI Number read from a field.
+pub type ChiField = Field<chi::Number>;
+
+impl<N> Field<N>
+where
+    N: FromStr<Err = ValidationError> + fmt::Display,
+{
+    /// Reads a number leniently, ignoring whitespace and hyphens.
+    pub fn parse(input: &str) -> Self {
+        let cleaned: String = input.chars().filter(|&c| c != '-').collect();
+
+        match N::from_str(&cleaned) {
+            Ok(number) => Field::Valid(number),
+            Err(error) => Field::Invalid {
+                input: input.to_string(),
+                error,
+            },
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Field::Valid(_))
+    }
+
+    /// The number when valid.
+    pub fn valid(&self) -> Option<&N> {
+        match self {
+            Field::Valid(number) => Some(number),
+            Field::Invalid { .. } => None,
+        }
+    }
+
+    /// The number in compact form when valid, or the field as given.
+    pub fn input(&self) -> String {
+        match self {
+            Field::Valid(number) => number.to_string(),
+            Field::Invalid { input, .. } => input.clone(),
+        }
+    }
+
+    pub fn into_result(self) -> Result<N, ValidationError> {
+        match self {
+            Field::Valid(number) => Ok(number),
+            Field::Invalid { error, .. } => Err(error),
+        }
+    }
+}
+
+impl<'de, N> Deserialize<'de> for Field<N>
+where
+    N: FromStr<Err = ValidationError> + fmt::Display,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(FieldVisitor(PhantomData))
+    }
+}
+
+struct FieldVisitor<N>(PhantomData<N>);
+
+impl<'de, N> Visitor<'de> for FieldVisitor<N>
+where
+    N: FromStr<Err = ValidationError> + fmt::Display,
+{
+    type Value = Field<N>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string of 10 digits")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(Field::parse(value))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(Field::parse(&format!("{:010}", value)))
+    }
+}
+
+impl<N> Serialize for Field<N>
+where
+    N: FromStr<Err = ValidationError> + fmt::Display,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.input())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn reads_records() {
+        let data = "nhs,chi\n654 100 3238,0101990014\n65410,\n";
+        let mut reader = csv::Reader::from_reader(data.as_bytes());
+        let records: Vec<(NhsField, Option<ChiField>)> =
+            reader.deserialize().collect::<Result<_, _>>().unwrap();
+
+        assert_eq!(records[0].0.input(), "6541003238");
+        assert!(records[0].1.as_ref().unwrap().is_valid());
+        assert_eq!(
+            records[1].0.clone().into_result().unwrap_err().kind(),
+            ErrorKind::Length
+        );
+        assert_eq!(records[1].1, None);
+    }
+
+    #[test]
+    fn writes_records() {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+
+        writer
+            .serialize((NhsField::parse("654-100-3238"), ChiField::parse("x")))
+            .unwrap();
+
+        let data = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+
+        assert_eq!(data, "6541003238,x\n");
+    }
+}
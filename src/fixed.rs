@@ -0,0 +1,289 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Fixed-width flat files, as national data submissions lay out every
+//! record with each field at exact character positions.
+//!
+//! A [`Layout`] declares the columns by their start position, counted from 1
+//! as submission specifications do, and their width. Identifier columns hold
+//! NHS or CHI numbers as the canonical 10 digits: they are validated when a
+//! record is parsed and normalised when one is formatted.
+//!
+//! ```
+//! use heidi::fixed::{Layout, Padding};
+//! use heidi::scheme::Scheme;
+//!
+//! let layout = Layout::new()
+//!     .column("provider", 1, 5, Padding::Space)
+//!     .identifier("nhs_number", 6, Scheme::Nhs)
+//!     .column("episodes", 16, 4, Padding::Zero);
+//! let record = layout.format(&["RX1", "654 100 3238", "7"]).unwrap();
+//!
+//! assert_eq!(record, "RX1  65410032380007");
+//! assert_eq!(layout.parse(&record).unwrap(), vec!["RX1", "6541003238", "7"]);
+//! ```
+
+use crate::codec::from_canonical;
+use crate::error::{ErrorKind, ValidationError};
+use crate::scheme::Scheme;
+use crate::{chi, nhs, number};
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+/// How a value fills a column wider than itself.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Padding {
+    /// Aligned to the left and followed by spaces.
+    Space,
+    /// Aligned to the right and preceded by zeroes.
+    Zero,
+}
+
+/// A column of a layout.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Column {
+    pub name: String,
+    /// The position of the first character, counted from 1.
+    pub start: usize,
+    pub width: usize,
+    pub padding: Padding,
+    /// The scheme of the numbers in an identifier column.
+    pub scheme: Option<Scheme>,
+}
+
+/// A value that does not fit its column.
+#[derive(PartialEq, Clone, Debug)]
+pub struct FieldError {
+    /// The name of the column.
+    pub column: String,
+    pub error: ValidationError,
+}
+
+impl error::Error for FieldError {}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.column, self.error)
+    }
+}
+
+/// The columns of a fixed-width record.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Layout {
+    columns: Vec<Column>,
+}
+
+impl Layout {
+    pub fn new() -> Self {
+        Layout::default()
+    }
+
+    /// Adds a column of the given width starting at the given position.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `start` is 0.
+    pub fn column(self, name: &str, start: usize, width: usize, padding: Padding) -> Self {
+        self.push(Column {
+            name: name.to_string(),
+            start,
+            width,
+            padding,
+            scheme: None,
+        })
+    }
+
+    /// Adds a column of 10 characters for numbers of the given scheme.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `start` is 0.
+    pub fn identifier(self, name: &str, start: usize, scheme: Scheme) -> Self {
+        self.push(Column {
+            name: name.to_string(),
+            start,
+            width: number::LENGTH,
+            padding: Padding::Zero,
+            scheme: Some(scheme),
+        })
+    }
+
+    fn push(mut self, column: Column) -> Self {
+        assert!(column.start > 0, "Column positions are counted from 1");
+        self.columns.push(column);
+        self
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// The length of a record: the end of the column that ends last.
+    pub fn width(&self) -> usize {
+        self.columns
+            .iter()
+            .map(|column| column.start + column.width - 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Reads the value of every column from a record, without its padding.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the record is too short for a column or an identifier
+    /// column does not hold a valid number.
+    pub fn parse(&self, record: &str) -> Result<Vec<String>, FieldError> {
+        let record = record.trim_end_matches(['\r', '\n']);
+
+        self.columns
+            .iter()
+            .map(|column| {
+                let field: String = record
+                    .chars()
+                    .skip(column.start - 1)
+                    .take(column.width)
+                    .collect();
+
+                if field.chars().count() != column.width {
+                    return Err(
+                        column.error(ErrorKind::Length, "The record ends before the column")
+                    );
+                }
+
+                match column.scheme {
+                    Some(Scheme::Nhs) => from_canonical::<nhs::Number>(&field).map(|_| ()),
+                    Some(Scheme::Chi) => from_canonical::<chi::Number>(&field).map(|_| ()),
+                    Some(Scheme::Hcn) => {
+                        from_canonical::<number::Number>(&field).and_then(|n| Scheme::Hcn.check(&n))
+                    }
+                    None => Ok(()),
+                }
+                .map_err(|error| FieldError {
+                    column: column.name.clone(),
+                    error,
+                })?;
+
+                Ok(column.unpad(&field))
+            })
+            .collect()
+    }
+
+    /// Writes a record with the given values in column order, padding them
+    /// to their width. Identifier columns take numbers in any format
+    /// `FromStr` does and write the canonical 10 digits. Gaps between
+    /// columns are filled with spaces.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a value is missing or longer than its column, or an
+    /// identifier column is given an invalid number.
+    pub fn format(&self, values: &[&str]) -> Result<String, FieldError> {
+        let mut record = vec![' '; self.width()];
+
+        for (idx, column) in self.columns.iter().enumerate() {
+            let value = values
+                .get(idx)
+                .ok_or_else(|| column.error(ErrorKind::Length, "The value is missing"))?;
+            let value = match column.scheme {
+                Some(Scheme::Nhs) => nhs::Number::from_str(value).map(|n| n.to_string()),
+                Some(Scheme::Chi) => chi::Number::from_str(value).map(|n| n.to_string()),
+                Some(Scheme::Hcn) => Scheme::Hcn.validate(value).map(|n| n.to_string()),
+                None => Ok(value.to_string()),
+            }
+            .map_err(|error| FieldError {
+                column: column.name.clone(),
+                error,
+            })?;
+            let length = value.chars().count();
+
+            if length > column.width {
+                return Err(column.error(
+                    ErrorKind::Length,
+                    &format!("'{}' is longer than {} characters", value, column.width),
+                ));
+            }
+
+            let fill = std::iter::repeat_n(
+                match column.padding {
+                    Padding::Space => ' ',
+                    Padding::Zero => '0',
+                },
+                column.width - length,
+            );
+            let field: Vec<char> = match column.padding {
+                Padding::Space => value.chars().chain(fill).collect(),
+                Padding::Zero => fill.chain(value.chars()).collect(),
+            };
+
+            record[column.start - 1..column.start - 1 + column.width].copy_from_slice(&field);
+        }
+
+        Ok(record.into_iter().collect())
+    }
+}
+
+impl Column {
+    fn error(&self, kind: ErrorKind, message: &str) -> FieldError {
+        FieldError {
+            column: self.name.clone(),
+            error: ValidationError::with_kind(kind, message),
+        }
+    }
+
+    fn unpad(&self, field: &str) -> String {
+        match (self.padding, self.scheme) {
+            (_, Some(_)) => field.to_string(),
+            (Padding::Space, None) => field.trim_end_matches(' ').to_string(),
+            (Padding::Zero, None) => {
+                let value = field.trim_start_matches('0');
+
+                if value.is_empty() && !field.is_empty() {
+                    "0".to_string()
+                } else {
+                    value.to_string()
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_records() {
+        let layout = Layout::new()
+            .identifier("chi_number", 1, Scheme::Chi)
+            .column("count", 13, 3, Padding::Zero);
+
+        assert_eq!(layout.width(), 15);
+        assert_eq!(
+            layout.format(&["0101990014", "0"]).unwrap(),
+            "0101990014  000"
+        );
+        assert_eq!(
+            layout.parse("0101990014  000\r\n").unwrap(),
+            vec!["0101990014", "0"]
+        );
+        assert_eq!(
+            layout.parse("0101990015  000").unwrap_err().error.kind(),
+            ErrorKind::CheckDigit
+        );
+        assert_eq!(layout.parse("0101990014  00").unwrap_err().column, "count");
+        assert_eq!(
+            layout
+                .format(&["0101990014", "1000"])
+                .unwrap_err()
+                .error
+                .kind(),
+            ErrorKind::Length
+        );
+        assert!(layout.format(&["0101990014"]).is_err());
+    }
+}
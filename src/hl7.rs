@@ -0,0 +1,309 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Patient identifiers from HL7 v2 messages.
+//!
+//! The separators are read from the MSH segment. Every repetition of PID-3
+//! is reported and those whose assigning authority (CX.4) or identifier type
+//! (CX.5) stands for NHS or CHI numbers are validated. Values must be the
+//! canonical 10 digits.
+//!
+//! [`audit`] goes through a stream of messages, such as an interface log,
+//! and tells the messages with invalid or test identifiers.
+
+use crate::codec::from_canonical;
+use crate::error::ValidationError;
+use crate::number;
+use crate::region::Region;
+use crate::scheme::Scheme;
+use crate::{chi, nhs};
+use std::str::FromStr;
+
+/// A repetition of PID-3.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Identifier {
+    /// The position of the PID segment in the message, starting from 0 for
+    /// MSH.
+    pub segment: usize,
+    /// The position of the repetition in PID-3, starting from 0.
+    pub repetition: usize,
+    /// The ID number (CX.1).
+    pub value: String,
+    /// The namespace of the assigning authority (CX.4.1).
+    pub authority: String,
+    /// The identifier type code (CX.5).
+    pub kind: String,
+    /// The scheme the identifier stands for, if any.
+    pub scheme: Option<Scheme>,
+    /// Why the value is not a valid number under [`Identifier::scheme`].
+    pub error: Option<ValidationError>,
+}
+
+impl Identifier {
+    /// Whether the identifier is a valid number never issued to patients:
+    /// NHS numbers in the `999` range and CHI numbers born on the 30th or
+    /// 31st of February.
+    pub fn is_test(&self) -> bool {
+        if self.scheme.is_none() || self.error.is_some() {
+            return false;
+        }
+
+        let number = match number::Number::from_str(&self.value) {
+            Ok(number) => number,
+            Err(_) => return false,
+        };
+        let digits = number.digits();
+        let day = digits[0] * 10 + digits[1];
+        let month = digits[2] * 10 + digits[3];
+
+        Region::of(digits) == Region::Test
+            || (self.scheme == Some(Scheme::Chi) && month == 2 && day >= 30)
+    }
+}
+
+/// A message with identifiers that are invalid or in a test range.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Audit {
+    /// The position of the message in the stream, starting from 0.
+    pub index: usize,
+    /// The message control ID (MSH-10), if any.
+    pub control_id: Option<String>,
+    /// The identifiers that are invalid or in a test range.
+    pub findings: Vec<Identifier>,
+}
+
+struct Separators {
+    field: char,
+    component: char,
+    repetition: char,
+    subcomponent: char,
+}
+
+impl Separators {
+    fn from_msh(segment: &str) -> Option<Self> {
+        let mut chars = segment.strip_prefix("MSH")?.chars();
+        let field = chars.next()?;
+        let encoding: Vec<char> = chars.take_while(|&c| c != field).collect();
+
+        Some(Separators {
+            field,
+            component: encoding.first().copied().unwrap_or('^'),
+            repetition: encoding.get(1).copied().unwrap_or('~'),
+            subcomponent: encoding.get(3).copied().unwrap_or('&'),
+        })
+    }
+}
+
+/// Splits a stream of messages at every MSH segment, dropping MLLP framing
+/// and anything before the first MSH segment.
+pub fn messages(content: &str) -> impl Iterator<Item = &str> {
+    let mut starts: Vec<usize> = content
+        .match_indices("MSH")
+        .map(|(idx, _)| idx)
+        .filter(|&idx| idx == 0 || content[..idx].ends_with(['\r', '\n', '\x0b']))
+        .collect();
+
+    starts.push(content.len());
+
+    (1..starts.len()).map(move |idx| {
+        content[starts[idx - 1]..starts[idx]].trim_end_matches(['\r', '\n', '\x0b', '\x1c'])
+    })
+}
+
+/// The message control ID (MSH-10) of a message.
+pub fn control_id(message: &str) -> Option<&str> {
+    let separators = Separators::from_msh(message)?;
+    let id = message
+        .split(['\r', '\n'])
+        .next()?
+        .split(separators.field)
+        .nth(9)?;
+
+    Some(id).filter(|id| !id.is_empty())
+}
+
+/// Audits every message in a stream and returns the ones with identifiers
+/// that are invalid or in a test range.
+///
+/// # Examples
+///
+/// ```
+/// let log = "MSH|^~\\&|PAS|RX1|EPR|RX1|20200101||ADT^A01|A1|P|2.4\r\
+///            PID|||6541003238^^^NHS^NH\r\
+///            MSH|^~\\&|PAS|RX1|EPR|RX1|20200101||ADT^A08|A2|P|2.4\r\
+///            PID|||9990001235^^^NHS^NH\r";
+/// let audits = heidi::hl7::audit(log);
+///
+/// assert_eq!(audits.len(), 1);
+/// assert_eq!(audits[0].control_id.as_deref(), Some("A2"));
+/// assert!(audits[0].findings[0].is_test());
+/// ```
+pub fn audit(content: &str) -> Vec<Audit> {
+    messages(content)
+        .enumerate()
+        .filter_map(|(index, message)| {
+            let findings: Vec<Identifier> = extract_identifiers(message)
+                .into_iter()
+                .filter(|identifier| identifier.error.is_some() || identifier.is_test())
+                .collect();
+
+            if findings.is_empty() {
+                return None;
+            }
+
+            Some(Audit {
+                index,
+                control_id: control_id(message).map(String::from),
+                findings,
+            })
+        })
+        .collect()
+}
+
+/// Extracts the patient identifiers of every PID segment in a message.
+///
+/// A message without an MSH segment has no identifiers.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::error::ErrorKind;
+/// use heidi::scheme::Scheme;
+///
+/// let message = "MSH|^~\\&|PAS|RX1|EPR|RX1|20200101||ADT^A01|1|P|2.4\r\
+///                PID|||12345^^^RX1^MR~6541003239^^^NHS^NH||Doe^Jane";
+/// let identifiers = heidi::hl7::extract_identifiers(message);
+///
+/// assert_eq!(identifiers.len(), 2);
+/// assert_eq!(identifiers[0].scheme, None);
+/// assert_eq!(identifiers[1].scheme, Some(Scheme::Nhs));
+/// assert_eq!(identifiers[1].error.as_ref().unwrap().kind(), ErrorKind::CheckDigit);
+/// ```
+pub fn extract_identifiers(message: &str) -> Vec<Identifier> {
+    let mut segments = message
+        .split(['\r', '\n'])
+        .filter(|segment| !segment.is_empty());
+    let separators = match segments.next().and_then(Separators::from_msh) {
+        Some(separators) => separators,
+        None => return Vec::new(),
+    };
+    let mut identifiers = Vec::new();
+
+    for (idx, segment) in segments.enumerate() {
+        let mut fields = segment.split(separators.field);
+
+        if fields.next() != Some("PID") {
+            continue;
+        }
+
+        let pid3 = fields.nth(2).unwrap_or_default();
+        let repetitions = pid3.split(separators.repetition).filter(|r| !r.is_empty());
+
+        for (repetition, cx) in repetitions.enumerate() {
+            identifiers.push(identifier(&separators, idx + 1, repetition, cx));
+        }
+    }
+
+    identifiers
+}
+
+fn identifier(separators: &Separators, segment: usize, repetition: usize, cx: &str) -> Identifier {
+    let components: Vec<&str> = cx.split(separators.component).collect();
+    let component = |idx: usize| components.get(idx).copied().unwrap_or_default();
+    let value = component(0);
+    let authority = component(3)
+        .split(separators.subcomponent)
+        .next()
+        .unwrap_or_default();
+    let kind = component(4);
+    let scheme = scheme(authority, kind);
+    let error = scheme.and_then(|scheme| {
+        match scheme {
+            Scheme::Nhs => from_canonical::<nhs::Number>(value).map(|_| ()),
+            Scheme::Chi => from_canonical::<chi::Number>(value).map(|_| ()),
+            Scheme::Hcn => {
+                from_canonical::<number::Number>(value).and_then(|n| Scheme::Hcn.check(&n))
+            }
+        }
+        .err()
+    });
+
+    Identifier {
+        segment,
+        repetition,
+        value: value.to_string(),
+        authority: authority.to_string(),
+        kind: kind.to_string(),
+        scheme,
+        error,
+    }
+}
+
+/// Tells the scheme from the assigning authority, or from the identifier
+/// type when the authority is not conclusive.
+fn scheme(authority: &str, kind: &str) -> Option<Scheme> {
+    if authority.eq_ignore_ascii_case("NHS") {
+        Some(Scheme::Nhs)
+    } else if authority.eq_ignore_ascii_case("CHI") {
+        Some(Scheme::Chi)
+    } else if authority.eq_ignore_ascii_case("HCN") {
+        Some(Scheme::Hcn)
+    } else if kind.eq_ignore_ascii_case("NH") {
+        Some(Scheme::Nhs)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn custom_separators() {
+        let message = "MSH#*!\\@#PAS\nEVN#A01\nPID###6541003238***NHS@2.16.840.1.113883.2.1.4.1@ISO*NH!0101990014***CHI!1***RX1*MR";
+        let identifiers = extract_identifiers(message);
+
+        assert_eq!(identifiers.len(), 3);
+        assert_eq!(identifiers[0].segment, 2);
+        assert_eq!(identifiers[0].authority, "NHS");
+        assert_eq!(identifiers[0].error, None);
+        assert_eq!(identifiers[1].scheme, Some(Scheme::Chi));
+        assert_eq!(identifiers[1].error, None);
+        assert_eq!(identifiers[2].repetition, 2);
+        assert_eq!(identifiers[2].scheme, None);
+    }
+
+    #[test]
+    fn malformed_values() {
+        let message = "MSH|^~\\&\rPID|||654 100 3238^^^^NH";
+        let identifiers = extract_identifiers(message);
+
+        assert_eq!(
+            identifiers[0].error.as_ref().map(ValidationError::kind),
+            Some(ErrorKind::Digit)
+        );
+        assert!(extract_identifiers("PID|||6541003238^^^NHS").is_empty());
+    }
+
+    #[test]
+    fn audits_streams() {
+        let log = "\x0bMSH|^~\\&|||||||ADT^A01|A1\rPID|||3002001236^^^CHI\r\x1c\r\
+                   \x0bMSH|^~\\&|||||||ADT^A01\rPID|||6541003239^^^NHS~0101990014^^^CHI\r\x1c\r\
+                   \x0bMSH|^~\\&|||||||ADT^A01|A3\rPID|||0101990014^^^CHI\r\x1c\r";
+        let audits = audit(log);
+
+        assert_eq!(messages(log).count(), 3);
+        assert_eq!(audits.len(), 2);
+        assert_eq!(audits[0].control_id.as_deref(), Some("A1"));
+        assert!(audits[0].findings[0].is_test());
+        assert_eq!(audits[1].index, 1);
+        assert_eq!(audits[1].control_id, None);
+        assert_eq!(audits[1].findings.len(), 1);
+        assert_eq!(audits[1].findings[0].value, "6541003239");
+    }
+}
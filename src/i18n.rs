@@ -0,0 +1,523 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Message catalogues to show validation errors and reports in English or
+//! Welsh, as suppliers to NHS Wales must offer services in both languages.
+//!
+//! Only the wording changes: error kinds and their codes are the same in
+//! every language. The parameters of an error, such as the given and
+//! expected check digits, are filled into the messages that mention them.
+//!
+//! ```
+//! use heidi::i18n::Language;
+//! use heidi::nhs::Number;
+//! use std::str::FromStr;
+//!
+//! let error = Number::from_str("6541003239").unwrap_err();
+//!
+//! assert_eq!(
+//!     error.message_in(Language::Welsh),
+//!     "Nid yw'r digid gwirio 9 yn cyfateb i'r digid gwirio disgwyliedig 8"
+//! );
+//! assert_eq!(error.code(), "HEIDI_E002");
+//! ```
+
+use crate::error::ErrorKind;
+use std::error::Error;
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+/// The languages messages are available in.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum Language {
+    #[default]
+    English,
+    Welsh,
+}
+
+impl Language {
+    /// The ISO 639-1 code of the language.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Welsh => "cy",
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Takes ISO 639-1 codes, optionally with a region as in `cy-GB` or
+/// `cy_GB.UTF-8`, or the English names.
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = s
+            .split(['-', '_', '.'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        match code.as_str() {
+            "en" | "english" => Ok(Language::English),
+            "cy" | "welsh" => Ok(Language::Welsh),
+            _ => Err(format!("Unknown language '{}', expected 'en' or 'cy'", s)),
+        }
+    }
+}
+
+/// The name of the error kind in the given language, as shown in reports.
+/// English names are the ones of [`ErrorKind::as_str`].
+pub fn kind(kind: ErrorKind, language: Language) -> &'static str {
+    match language {
+        Language::English => kind.as_str(),
+        Language::Welsh => match kind {
+            ErrorKind::Length => "hyd",
+            ErrorKind::Digit => "digid",
+            ErrorKind::CheckDigit => "digid-gwirio",
+            ErrorKind::Unusable => "anaddas",
+            ErrorKind::Date => "dyddiad",
+            ErrorKind::Other => "arall",
+        },
+    }
+}
+
+/// A description of the error kind in the given language.
+pub fn message(kind: ErrorKind, language: Language) -> &'static str {
+    match language {
+        Language::English => match kind {
+            ErrorKind::Length => "The number does not have 10 digits",
+            ErrorKind::Digit => "The number has characters other than digits",
+            ErrorKind::CheckDigit => "The check digit does not match",
+            ErrorKind::Unusable => "The check digit would be 10 so the number is never issued",
+            ErrorKind::Date => "The date of birth is not a valid date",
+            ErrorKind::Other => "The number is not valid",
+        },
+        Language::Welsh => match kind {
+            ErrorKind::Length => "Nid oes gan y rhif 10 digid",
+            ErrorKind::Digit => "Mae gan y rhif nodau heblaw digidau",
+            ErrorKind::CheckDigit => "Nid yw'r digid gwirio yn cyfateb",
+            ErrorKind::Unusable => "Byddai'r digid gwirio yn 10 felly ni chaiff y rhif ei roi byth",
+            ErrorKind::Date => "Nid yw'r dyddiad geni yn ddyddiad dilys",
+            ErrorKind::Other => "Nid yw'r rhif yn ddilys",
+        },
+    }
+}
+
+/// A description of the error kind in the given language with the given
+/// parameters filled in, or `None` when the kind has no such message or a
+/// parameter it mentions is missing.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::error::ErrorKind;
+/// use heidi::i18n::{self, Language};
+///
+/// let params = [("length", "3".to_string())];
+///
+/// assert_eq!(
+///     i18n::detail(ErrorKind::Length, Language::Welsh, &params).unwrap(),
+///     "Mae gan y rhif 3 digid yn lle 10"
+/// );
+/// assert_eq!(i18n::detail(ErrorKind::Length, Language::Welsh, &[]), None);
+/// ```
+pub fn detail(kind: ErrorKind, language: Language, params: &[(&str, String)]) -> Option<String> {
+    let template = match language {
+        Language::English => match kind {
+            ErrorKind::Length => "The number has {length} digits instead of 10",
+            ErrorKind::Digit => "The character '{character}' is not a digit",
+            ErrorKind::CheckDigit => {
+                "The check digit {given} does not match the expected check digit {expected}"
+            }
+            _ => return None,
+        },
+        Language::Welsh => match kind {
+            ErrorKind::Length => "Mae gan y rhif {length} digid yn lle 10",
+            ErrorKind::Digit => "Nid yw'r nod '{character}' yn ddigid",
+            ErrorKind::CheckDigit => {
+                "Nid yw'r digid gwirio {given} yn cyfateb i'r digid gwirio disgwyliedig {expected}"
+            }
+            _ => return None,
+        },
+    };
+
+    fill(template, params)
+}
+
+/// The messages of reports, such as the verdict on a number or the summary
+/// of a batch run. Messages with values take them as `{name}` placeholders
+/// filled in by [`render`].
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum Text {
+    Number,
+    NhsNumber,
+    ChiNumber,
+    HcnNumber,
+    /// `{label} '{input}' is valid`
+    Valid,
+    /// `{label} '{input}' is valid as {schemes}`
+    ValidAs,
+    /// `{label} '{input}' is invalid`
+    Invalid,
+    Error,
+    /// `Checked {total} numbers: {valid} valid, {invalid} invalid.`
+    Checked,
+    /// `Duplicates: {count}.`
+    Duplicates,
+    /// `Test range: {count}.`
+    TestRange,
+    ValidByRegion,
+    /// `Skipped {count} lines.`
+    Skipped,
+    /// `Category: {kind}`
+    Category,
+    /// `Check digit: {given} given, {expected} expected from the main digits.`
+    CheckDigitGiven,
+    /// `Check digit: {given} given, none expected as the main digits compute 10.`
+    CheckDigitUnusable,
+    NoCandidates,
+    /// `No valid {label} is one substitution or transposition away.`
+    NoCandidatesOf,
+    Candidates,
+    /// `There are {count} candidates. Confirm the right one with the source record.`
+    Ambiguous,
+    /// `position {position}: {to} instead of {from}`
+    Substitution,
+    /// `positions {position} and {next} swapped`
+    Transposition,
+    Position,
+    Digit,
+    Weight,
+    Product,
+    /// `Sum: {sum}`
+    Sum,
+    /// `Remainder: {sum} mod 11 = {remainder}`
+    Remainder,
+    /// `Check digit: 11 - {remainder} = {checkdigit}`
+    CheckDigit,
+    CheckDigitZero,
+    CheckDigitTen,
+    /// `Supplied check digit: {checkdigit}`
+    Supplied,
+    /// `The {label} {number} is valid.`
+    NumberValid,
+    /// `The {label} is invalid: {error}.`
+    NumberInvalid,
+    /// `The stem {stem} is unusable: {error}.`
+    UnusableStem,
+    /// `No files match '{path}'`
+    NoFilesMatch,
+    AsciiDelimiter,
+    SarifScanOnly,
+    Input,
+    Validity,
+    Declared,
+    Schemes,
+    CheckDigitField,
+    Official,
+    Region,
+    DateOfBirth,
+    Sex,
+    Code,
+    Message,
+    Yes,
+    No,
+}
+
+/// The message in the given language, placeholders included.
+pub fn text(key: Text, language: Language) -> &'static str {
+    match language {
+        Language::English => match key {
+            Text::Number => "Number",
+            Text::NhsNumber => "NHS Number",
+            Text::ChiNumber => "Chi Number",
+            Text::HcnNumber => "H&C Number",
+            Text::Valid => "{label} '{input}' is valid",
+            Text::ValidAs => "{label} '{input}' is valid as {schemes}",
+            Text::Invalid => "{label} '{input}' is invalid",
+            Text::Error => "Error",
+            Text::Checked => "Checked {total} numbers: {valid} valid, {invalid} invalid.",
+            Text::Duplicates => "Duplicates: {count}.",
+            Text::TestRange => "Test range: {count}.",
+            Text::ValidByRegion => "Valid by region:",
+            Text::Skipped => "Skipped {count} lines.",
+            Text::Category => "Category: {kind}",
+            Text::CheckDigitGiven => {
+                "Check digit: {given} given, {expected} expected from the main digits."
+            }
+            Text::CheckDigitUnusable => {
+                "Check digit: {given} given, none expected as the main digits compute 10."
+            }
+            Text::NoCandidates => "No valid number is one substitution or transposition away.",
+            Text::NoCandidatesOf => {
+                "No valid {label} is one substitution or transposition away."
+            }
+            Text::Candidates => "Nearest valid numbers:",
+            Text::Ambiguous => {
+                "There are {count} candidates. Confirm the right one with the source record."
+            }
+            Text::Substitution => "position {position}: {to} instead of {from}",
+            Text::Transposition => "positions {position} and {next} swapped",
+            Text::Position => "Position",
+            Text::Digit => "Digit",
+            Text::Weight => "Weight",
+            Text::Product => "Product",
+            Text::Sum => "Sum: {sum}",
+            Text::Remainder => "Remainder: {sum} mod 11 = {remainder}",
+            Text::CheckDigit => "Check digit: 11 - {remainder} = {checkdigit}",
+            Text::CheckDigitZero => "Check digit: 11 - 0 = 11, which is written as 0",
+            Text::CheckDigitTen => {
+                "Check digit: 11 - 1 = 10, which is never issued so no number has these main digits"
+            }
+            Text::Supplied => "Supplied check digit: {checkdigit}",
+            Text::NumberValid => "The {label} {number} is valid.",
+            Text::NumberInvalid => "The {label} is invalid: {error}.",
+            Text::UnusableStem => "The stem {stem} is unusable: {error}.",
+            Text::NoFilesMatch => "No files match '{path}'",
+            Text::AsciiDelimiter => "The delimiter must be an ASCII character",
+            Text::SarifScanOnly => "SARIF output is only available for scan",
+            Text::Input => "Input",
+            Text::Validity => "Valid",
+            Text::Declared => "Declared",
+            Text::Schemes => "Schemes",
+            Text::CheckDigitField => "Check digit",
+            Text::Official => "Official",
+            Text::Region => "Region",
+            Text::DateOfBirth => "Date of birth",
+            Text::Sex => "Sex",
+            Text::Code => "Code",
+            Text::Message => "Message",
+            Text::Yes => "yes",
+            Text::No => "no",
+        },
+        Language::Welsh => match key {
+            Text::Number => "Rhif",
+            Text::NhsNumber => "Rhif GIG",
+            Text::ChiNumber => "Rhif CHI",
+            Text::HcnNumber => "Rhif H&C",
+            Text::Valid => "Mae {label} '{input}' yn ddilys",
+            Text::ValidAs => "Mae {label} '{input}' yn ddilys fel {schemes}",
+            Text::Invalid => "Mae {label} '{input}' yn annilys",
+            Text::Error => "Gwall",
+            Text::Checked => "Gwiriwyd {total} rhif: {valid} yn ddilys, {invalid} yn annilys.",
+            Text::Duplicates => "Dyblygion: {count}.",
+            Text::TestRange => "Ystod prawf: {count}.",
+            Text::ValidByRegion => "Dilys fesul rhanbarth:",
+            Text::Skipped => "Hepgorwyd {count} llinell.",
+            Text::Category => "Categori: {kind}",
+            Text::CheckDigitGiven => {
+                "Digid gwirio: rhoddwyd {given}, disgwylir {expected} o'r prif ddigidau."
+            }
+            Text::CheckDigitUnusable => {
+                "Digid gwirio: rhoddwyd {given}, ni ddisgwylir un gan fod y prif ddigidau'n rhoi 10."
+            }
+            Text::NoCandidates => "Nid oes rhif dilys un amnewidiad neu drawsddodiad i ffwrdd.",
+            Text::NoCandidatesOf => {
+                "Nid oes {label} dilys un amnewidiad neu drawsddodiad i ffwrdd."
+            }
+            Text::Candidates => "Y rhifau dilys agosaf:",
+            Text::Ambiguous => {
+                "Mae {count} ymgeisydd. Cadarnhewch yr un cywir gyda'r cofnod ffynhonnell."
+            }
+            Text::Substitution => "safle {position}: {to} yn lle {from}",
+            Text::Transposition => "safleoedd {position} a {next} wedi'u cyfnewid",
+            Text::Position => "Safle",
+            Text::Digit => "Digid",
+            Text::Weight => "Pwysau",
+            Text::Product => "Lluoswm",
+            Text::Sum => "Swm: {sum}",
+            Text::Remainder => "Gweddill: {sum} mod 11 = {remainder}",
+            Text::CheckDigit => "Digid gwirio: 11 - {remainder} = {checkdigit}",
+            Text::CheckDigitZero => "Digid gwirio: 11 - 0 = 11, a ysgrifennir fel 0",
+            Text::CheckDigitTen => {
+                "Digid gwirio: 11 - 1 = 10, na chaiff ei roi byth felly nid oes gan unrhyw rif y prif ddigidau hyn"
+            }
+            Text::Supplied => "Digid gwirio a roddwyd: {checkdigit}",
+            Text::NumberValid => "Mae'r {label} {number} yn ddilys.",
+            Text::NumberInvalid => "Mae'r {label} yn annilys: {error}.",
+            Text::UnusableStem => "Ni ellir defnyddio'r bôn {stem}: {error}.",
+            Text::NoFilesMatch => "Nid oes ffeiliau'n cyfateb i '{path}'",
+            Text::AsciiDelimiter => "Rhaid i'r amffinydd fod yn nod ASCII",
+            Text::SarifScanOnly => "Dim ond gyda scan y mae allbwn SARIF ar gael",
+            Text::Input => "Mewnbwn",
+            Text::Validity => "Dilys",
+            Text::Declared => "Datganedig",
+            Text::Schemes => "Cynlluniau",
+            Text::CheckDigitField => "Digid gwirio",
+            Text::Official => "Swyddogol",
+            Text::Region => "Rhanbarth",
+            Text::DateOfBirth => "Dyddiad geni",
+            Text::Sex => "Rhyw",
+            Text::Code => "Cod",
+            Text::Message => "Neges",
+            Text::Yes => "ie",
+            Text::No => "na",
+        },
+    }
+}
+
+/// The message in the given language with the given values filled in.
+/// Placeholders without a value are left as they are.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::i18n::{self, Language, Text};
+///
+/// assert_eq!(
+///     i18n::render(Text::Duplicates, Language::Welsh, &[("count", &2)]),
+///     "Dyblygion: 2."
+/// );
+/// ```
+pub fn render(key: Text, language: Language, params: &[(&str, &dyn fmt::Display)]) -> String {
+    let template = text(key, language);
+
+    fill(template, params).unwrap_or_else(|| template.to_string())
+}
+
+/// A message of the catalogue together with its values, for errors raised
+/// before the language they are shown in is known. It displays in English
+/// and [`Message::in_language`] gives any other.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::i18n::{Language, Message, Text};
+///
+/// let message = Message::new(Text::NoFilesMatch).with_param("path", "*.csv");
+///
+/// assert_eq!(message.to_string(), "No files match '*.csv'");
+/// assert_eq!(
+///     message.in_language(Language::Welsh),
+///     "Nid oes ffeiliau'n cyfateb i '*.csv'"
+/// );
+/// ```
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Message {
+    key: Text,
+    params: Vec<(&'static str, String)>,
+}
+
+impl Message {
+    pub fn new(key: Text) -> Self {
+        Message {
+            key,
+            params: Vec::new(),
+        }
+    }
+
+    /// Adds the value of a placeholder.
+    pub fn with_param<T: fmt::Display>(mut self, name: &'static str, value: T) -> Self {
+        self.params.push((name, value.to_string()));
+        self
+    }
+
+    /// The message in the given language.
+    pub fn in_language(&self, language: Language) -> String {
+        let template = text(self.key, language);
+
+        fill(template, &self.params).unwrap_or_else(|| template.to_string())
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.in_language(Language::English))
+    }
+}
+
+impl Error for Message {}
+
+/// Replaces the `{name}` placeholders of the template with the values of the
+/// parameters in a single pass, or gives `None` when one is missing.
+fn fill<T: fmt::Display>(template: &str, params: &[(&str, T)]) -> Option<String> {
+    let mut text = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let end = start + rest[start..].find('}')?;
+        let (_, value) = params
+            .iter()
+            .find(|(name, _)| *name == &rest[start + 1..end])?;
+
+        text.push_str(&rest[..start]);
+        write!(text, "{}", value).expect("write to string");
+        rest = &rest[end + 1..];
+    }
+    text.push_str(rest);
+
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ValidationError;
+
+    #[test]
+    fn languages() {
+        assert_eq!(Language::from_str("cy_GB.UTF-8").unwrap(), Language::Welsh);
+        assert_eq!(Language::from_str("EN").unwrap(), Language::English);
+        assert!(Language::from_str("fr").is_err());
+
+        let error = ValidationError::with_kind(ErrorKind::Length, "Expected 10 digits, got 3");
+
+        assert_eq!(error.message_in(Language::English), error.to_string());
+        assert_eq!(
+            error.message_in(Language::Welsh),
+            message(ErrorKind::Length, Language::Welsh)
+        );
+    }
+
+    #[test]
+    fn placeholders() {
+        assert_eq!(
+            render(
+                Text::Valid,
+                Language::Welsh,
+                &[("label", &"Rhif GIG"), ("input", &"{input}")]
+            ),
+            "Mae Rhif GIG '{input}' yn ddilys"
+        );
+        assert_eq!(
+            render(Text::Skipped, Language::English, &[]),
+            "Skipped {count} lines."
+        );
+        assert_eq!(fill("{a} and {b}", &[("a", 1)]), None);
+        assert_eq!(kind(ErrorKind::CheckDigit, Language::Welsh), "digid-gwirio");
+        assert_eq!(kind(ErrorKind::CheckDigit, Language::English), "checkdigit");
+    }
+
+    #[test]
+    fn welsh_keeps_the_detail() {
+        let error = crate::nhs::Number::from_str("6541003237").unwrap_err();
+
+        assert_eq!(error.param("given"), Some("7"));
+        assert_eq!(error.param("expected"), Some("8"));
+        assert_eq!(
+            error.message_in(Language::Welsh),
+            "Nid yw'r digid gwirio 7 yn cyfateb i'r digid gwirio disgwyliedig 8"
+        );
+        assert_eq!(
+            crate::nhs::Number::from_str("654x003238")
+                .unwrap_err()
+                .message_in(Language::Welsh),
+            "Nid yw'r nod 'x' yn ddigid"
+        );
+        assert_eq!(
+            crate::nhs::Number::from_str("654")
+                .unwrap_err()
+                .message_in(Language::Welsh),
+            "Mae gan y rhif 3 digid yn lle 10"
+        );
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! JSON Schema for NHS and CHI numbers so schemas and OpenAPI documents
+//! generated from Rust types describe number fields properly.
+//!
+//! Numbers are strings of 10 digits with a `format` of `nhs-number` or
+//! `chi-number`. The pattern does not check the check digit.
+//!
+//! Requires the `schemars` feature.
+
+use crate::{chi, nhs};
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use std::borrow::Cow;
+
+impl JsonSchema for nhs::Number {
+    fn inline_schema() -> bool {
+        true
+    }
+
+    fn schema_name() -> Cow<'static, str> {
+        "NhsNumber".into()
+    }
+
+    fn schema_id() -> Cow<'static, str> {
+        "heidi::nhs::Number".into()
+    }
+
+    fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "format": "nhs-number",
+            "pattern": "^[0-9]{10}$",
+            "description": "An NHS Number of 10 digits where the last one is the check digit.",
+            "examples": ["6541003238"],
+        })
+    }
+}
+
+impl JsonSchema for chi::Number {
+    fn inline_schema() -> bool {
+        true
+    }
+
+    fn schema_name() -> Cow<'static, str> {
+        "ChiNumber".into()
+    }
+
+    fn schema_id() -> Cow<'static, str> {
+        "heidi::chi::Number".into()
+    }
+
+    fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "format": "chi-number",
+            "pattern": "^(0[1-9]|[12][0-9]|3[01])(0[1-9]|1[0-2])[0-9]{6}$",
+            "description": "A CHI Number of 10 digits starting with the date of birth as DDMMYY where the last one is the check digit.",
+            "examples": ["0101990014"],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schemars::schema_for;
+
+    #[test]
+    fn schemas() {
+        let nhs = schema_for!(nhs::Number);
+        let chi = schema_for!(Vec<chi::Number>);
+
+        assert_eq!(nhs.get("format").unwrap(), "nhs-number");
+        assert_eq!(nhs.get("title").unwrap(), "NhsNumber");
+        assert_eq!(chi.get("items").unwrap()["format"], "chi-number");
+    }
+}
@@ -7,7 +7,50 @@
 //! `heidi` (health identifiers) is a library to work with health identifiers
 //! such as NHS Numbers.
 
+pub mod any;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod barcode;
+pub mod batch;
 pub mod chi;
+#[cfg(feature = "cli-support")]
+pub mod cli_support;
+pub mod codec;
+#[cfg(feature = "dicom")]
+pub mod dicom;
+#[cfg(feature = "diesel")]
+pub mod diesel_support;
 pub mod error;
+#[cfg(any(feature = "axum", feature = "actix"))]
+pub mod extract;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fhir;
+pub mod fields;
+pub mod fixed;
+pub mod hl7;
+pub mod i18n;
+#[cfg(feature = "schemars")]
+pub mod json_schema;
 pub mod nhs;
 pub mod number;
+#[cfg(feature = "pds")]
+pub mod pds;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod privacy;
+pub mod pseudo;
+#[cfg(feature = "qr")]
+pub mod qr;
+pub mod redact;
+pub mod region;
+pub mod scan;
+pub mod scheme;
+#[cfg(feature = "sea-orm")]
+pub mod sea_orm_support;
+pub mod set;
+#[cfg(feature = "sqlx")]
+pub mod sql;
+#[cfg(feature = "store")]
+pub mod store;
+pub mod validator;
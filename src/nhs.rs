@@ -19,12 +19,29 @@
 
 use crate::error::ValidationError;
 use crate::number;
+use crate::region::{self, Region};
+use crate::validator;
 use std::convert::TryFrom;
 use std::fmt;
+use std::ops::{Index, RangeInclusive};
 use std::str::FromStr;
 
 /// A digit can be from 0 to 9.
-pub type Digit = u16;
+pub type Digit = u8;
+
+/// The amount of digits of an NHS Number, check digit included.
+pub const LENGTH: usize = number::LENGTH;
+
+/// The numbers issued in England, Wales and the Isle of Man, in two ranges
+/// as the `5xx` block between them is not allocated. The same as
+/// [`region::ENGLAND_WALES`].
+pub const VALID_RANGE: [RangeInclusive<u64>; 2] = region::ENGLAND_WALES;
+
+/// The numbers set aside for tests, never issued to patients.
+pub const TEST_RANGE: RangeInclusive<u64> = region::TEST;
+
+/// Validates NHS Numbers with additional local rules.
+pub type Validator = validator::Validator<Number>;
 
 /// Represents an NHS Number as a list of 9 digits (`Number.digits()`) plus 1
 /// check digit (`Number.checkdigit()`).
@@ -53,18 +70,26 @@ pub type Digit = u16;
 /// assert_eq!(*number.unwrap().checkdigit(), 8);
 /// ```
 ///
-/// Finally, with a `u16` slice:
+/// Finally, with a `u8` slice:
 ///
 /// ```
 /// use std::convert::TryFrom;
 /// use heidi::nhs::Number;
 ///
-/// let n: [u16; 10] = [6, 5, 4, 1, 0, 0, 3, 2, 3, 8];
+/// let n: [u8; 10] = [6, 5, 4, 1, 0, 0, 3, 2, 3, 8];
 /// let number = Number::try_from(&n);
 ///
 /// assert_eq!(*number.unwrap().checkdigit(), 8);
 /// ```
 #[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(
+    feature = "diesel",
+    derive(diesel::expression::AsExpression, diesel::deserialize::FromSqlRow)
+)]
+#[cfg_attr(
+    feature = "diesel",
+    diesel(sql_type = diesel::sql_types::Text, sql_type = diesel::sql_types::BigInt)
+)]
 pub struct Number(number::Number);
 
 impl Number {
@@ -77,7 +102,7 @@ impl Number {
     /// ```
     /// use heidi::nhs::Number;
     ///
-    /// let n: [u16; 9] = [3, 7, 8, 3, 9, 5, 5, 6, 0];
+    /// let n: [u8; 9] = [3, 7, 8, 3, 9, 5, 5, 6, 0];
     /// let number = Number::new(n);
     ///
     /// assert_eq!(*number.unwrap().checkdigit(), 2);
@@ -86,44 +111,93 @@ impl Number {
         Ok(Number(number::Number::new(digits)?))
     }
 
+    /// See [`number::Number::from_digits`].
+    pub fn from_digits<I: IntoIterator<Item = Digit>>(digits: I) -> Result<Self, ValidationError> {
+        Ok(Number(number::Number::from_digits(digits)?))
+    }
+
+    /// See [`number::Number::from_u16_digits`].
+    pub fn from_u16_digits(value: &[u16; 10]) -> Result<Self, ValidationError> {
+        Ok(Number(number::Number::from_u16_digits(value)?))
+    }
+
+    /// See [`number::Number::get`].
+    pub fn get(&self, index: usize) -> Option<Digit> {
+        self.0.get(index)
+    }
+
     pub fn checkdigit(&self) -> &Digit {
         self.0.checkdigit()
     }
 
+    /// See [`number::Number::explain_checkdigit`].
+    pub fn explain_checkdigit(&self) -> number::Explanation {
+        self.0.explain_checkdigit()
+    }
+
     pub fn digits(&self) -> &[Digit; 9] {
         self.0.digits()
     }
+
+    /// See [`number::Number::to_uuid`].
+    #[cfg(feature = "uuid")]
+    pub fn to_uuid(&self, namespace: uuid::Uuid) -> uuid::Uuid {
+        self.0.to_uuid(namespace)
+    }
+
+    /// See [`number::Number::to_be_bytes`].
+    pub fn to_be_bytes(&self) -> [u8; 5] {
+        self.0.to_be_bytes()
+    }
+
+    /// See [`number::Number::to_formatted`].
+    pub fn to_formatted(&self, format: number::Format) -> String {
+        self.0.to_formatted(format)
+    }
+
+    /// See [`number::Number::format_with`].
+    pub fn format_with(&self, options: &number::FormatOptions) -> String {
+        self.0.format_with(options)
+    }
+
+    /// See [`number::Number::from_be_bytes`].
+    pub fn from_be_bytes(bytes: [u8; 5]) -> Result<Self, ValidationError> {
+        Ok(Number(number::Number::from_be_bytes(bytes)?))
+    }
+}
+
+impl AsRef<number::Number> for Number {
+    fn as_ref(&self) -> &number::Number {
+        &self.0
+    }
+}
+
+impl Index<usize> for Number {
+    type Output = Digit;
+
+    fn index(&self, index: usize) -> &Digit {
+        &self.0[index]
+    }
 }
 
 impl fmt::Display for Number {
+    /// Writes the 10 digits, in groups of 3, 3 and 4 with the alternate
+    /// flag, honouring the width, fill and alignment flags.
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        let digits = self.0.digits();
-
-        write!(formatter, "{}", &digits[0])?;
-        write!(formatter, "{}", &digits[1])?;
-        write!(formatter, "{}", &digits[2])?;
-        if formatter.alternate() {
-            write!(formatter, " ")?;
-        }
-        write!(formatter, "{}", &digits[3])?;
-        write!(formatter, "{}", &digits[4])?;
-        write!(formatter, "{}", &digits[5])?;
-        if formatter.alternate() {
-            write!(formatter, " ")?;
-        }
-        write!(formatter, "{}", &digits[6])?;
-        write!(formatter, "{}", &digits[7])?;
-        write!(formatter, "{}", &digits[8])?;
-        write!(formatter, "{}", self.0.checkdigit())?;
+        let buffer = if formatter.alternate() {
+            self.0.format_with(&number::FormatOptions::new())
+        } else {
+            self.0.to_string()
+        };
 
-        Ok(())
+        formatter.pad(&buffer)
     }
 }
 
 impl TryFrom<&[Digit; 10]> for Number {
     type Error = ValidationError;
 
-    /// Converts an array slice of 10 decimal `u16` into a [`Number`].
+    /// Converts an array slice of 10 decimal `u8` into a [`Number`].
     ///
     /// # Examples
     ///
@@ -131,7 +205,7 @@ impl TryFrom<&[Digit; 10]> for Number {
     /// use heidi::nhs::Number;
     /// use std::convert::TryFrom;
     ///
-    /// let n: [u16; 10] = [6, 5, 4, 1, 0, 0, 3, 2, 3, 8];
+    /// let n: [u8; 10] = [6, 5, 4, 1, 0, 0, 3, 2, 3, 8];
     /// let number = Number::try_from(&n);
     ///
     /// assert_eq!(*number.unwrap().checkdigit(), 8);
@@ -147,6 +221,15 @@ impl TryFrom<&[Digit; 10]> for Number {
     }
 }
 
+impl TryFrom<&[Digit]> for Number {
+    type Error = ValidationError;
+
+    /// See [`number::Number`]'s `TryFrom<&[Digit]>`.
+    fn try_from(value: &[Digit]) -> Result<Self, Self::Error> {
+        Ok(Number(number::Number::try_from(value)?))
+    }
+}
+
 impl TryFrom<String> for Number {
     type Error = ValidationError;
 
@@ -222,7 +305,24 @@ impl FromStr for Number {
     }
 }
 
-/// Returns a random NHS Number.
+/// Parses an NHS Number leniently, ignoring whitespace and hyphens, and
+/// returns its compact form.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(heidi::nhs::normalize("654 100 3238").unwrap(), "6541003238");
+/// ```
+///
+/// # Errors
+///
+/// Fails with [ValidationError] when the number is not valid.
+pub fn normalize(input: &str) -> Result<String, ValidationError> {
+    number::normalize_as::<Number>(input)
+}
+
+/// Returns a random NHS Number allocated in England, Wales and the Isle of
+/// Man, see [`region::ENGLAND_WALES`].
 ///
 /// If the result is not valid (e.g. the modulus 11 is 10) it will generate a new one.
 ///
@@ -235,25 +335,91 @@ impl FromStr for Number {
 /// assert!(number.is_ok());
 /// ```
 pub fn lottery() -> Result<Number, ValidationError> {
-    use rand::prelude::*;
+    lottery_with(&mut rand::thread_rng())
+}
 
-    let mut rng = rand::thread_rng();
-    let mut digits = [0u16; 9];
+/// Returns a random NHS Number drawn from the given source of randomness,
+/// within [`region::ENGLAND_WALES`].
+///
+/// A seeded source gives the same numbers every time, e.g. for test fixtures.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::nhs::lottery_with;
+/// use rand::SeedableRng;
+/// use rand_chacha::ChaCha8Rng;
+///
+/// let a = lottery_with(&mut ChaCha8Rng::seed_from_u64(42));
+/// let b = lottery_with(&mut ChaCha8Rng::seed_from_u64(42));
+/// assert_eq!(a, b);
+/// ```
+pub fn lottery_with<R: rand::Rng + ?Sized>(rng: &mut R) -> Result<Number, ValidationError> {
     let distr = rand::distributions::Uniform::new_inclusive(0, 9);
 
-    for x in &mut digits {
-        *x = rng.sample(distr);
+    loop {
+        let mut digits: [Digit; 9] = [0; 9];
+
+        for x in &mut digits {
+            *x = rng.sample(distr);
+        }
+
+        // Numbers of other regions are drawn again.
+        if Region::of(&digits) != Region::EnglandWales {
+            continue;
+        }
+
+        if let Ok(number) = Number::new(digits) {
+            return Ok(number);
+        }
     }
+}
+
+/// Returns a random NHS Number from the range reserved for testing, `999 000
+/// 0000` to `999 999 9999`, which is never issued to patients.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::nhs::test_lottery_with;
+/// use heidi::region::Region;
+///
+/// let number = test_lottery_with(&mut rand::thread_rng()).unwrap();
+/// assert_eq!(Region::of(number.digits()), Region::Test);
+/// ```
+pub fn test_lottery_with<R: rand::Rng + ?Sized>(rng: &mut R) -> Result<Number, ValidationError> {
+    let distr = rand::distributions::Uniform::new_inclusive(0, 9);
+
+    loop {
+        let mut digits: [Digit; 9] = [9; 9];
 
-    match Number::new(digits) {
-        Err(_) => lottery(),
-        number => number,
+        for x in &mut digits[3..] {
+            *x = rng.sample(distr);
+        }
+
+        if let Ok(number) = Number::new(digits) {
+            return Ok(number);
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn seeded_lottery() -> Result<(), ValidationError> {
+        // Documented fixtures rely on these staying the same.
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+
+        assert_eq!(lottery_with(&mut rng)?.to_string(), "7420480648");
+        assert_eq!(lottery_with(&mut rng)?.to_string(), "7987028991");
+        assert!((0..100)
+            .all(|_| Region::of(lottery_with(&mut rng).unwrap().digits()) == Region::EnglandWales));
+        Ok(())
+    }
 
     #[test]
     fn valid_formatted_string() -> Result<(), ValidationError> {
@@ -277,6 +443,17 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn display_padded() -> Result<(), ValidationError> {
+        let number = Number::from_str("8931774583")?;
+
+        assert_eq!(format!("[{:>12}]", number), "[  8931774583]");
+        assert_eq!(format!("[{:*^14}]", number), "[**8931774583**]");
+        assert_eq!(format!("[{:<#14}]", number), "[893 177 4583  ]");
+
+        Ok(())
+    }
+
     #[test]
     fn display_alternate() -> Result<(), ValidationError> {
         let n = String::from("893 177 4583");
@@ -6,13 +6,156 @@
 
 //! A generic identifier of 9 digits plus a check digit.
 
-use crate::error::ValidationError;
+use crate::error::{ErrorKind, ValidationError};
 use std::convert::TryFrom;
-use std::fmt;
+use std::fmt::{self, Write};
+use std::ops::Index;
 use std::str::FromStr;
 
+/// The amount of digits of a number, check digit included.
+pub const LENGTH: usize = 10;
+
+/// The lowest number as an integer.
+pub const MIN: u64 = 0;
+
+/// The highest number as an integer.
+pub const MAX: u64 = 9_999_999_999;
+
 /// A digit can be from 0 to 9.
-pub type Digit = u16;
+///
+/// Digits used to be `u16`. Arrays of 10 `u16` convert with [`Number::from_u16_digits`].
+pub type Digit = u8;
+
+/// The named formats numbers are written in, with the same meaning in the
+/// library and the command line.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Format {
+    /// The 10 digits, e.g. `6541003238`.
+    Compact,
+    /// The official display, e.g. `654 100 3238` for NHS Numbers. CHI
+    /// Numbers are officially written compact.
+    Official,
+    /// Groups of 3, 3 and 4 digits separated by hyphens, e.g. `654-100-3238`.
+    Hyphenated,
+    /// Every digit but the last 4 replaced with `*`, e.g. `******3238`.
+    Masked,
+}
+
+impl Format {
+    /// A short lowercase name for the format.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Format::Compact => "compact",
+            Format::Official => "official",
+            Format::Hyphenated => "hyphenated",
+            Format::Masked => "masked",
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    /// Parses a format name, ignoring case. `hyphen` is taken for
+    /// `hyphenated`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "compact" => Ok(Format::Compact),
+            "official" => Ok(Format::Official),
+            "hyphenated" | "hyphen" => Ok(Format::Hyphenated),
+            "masked" => Ok(Format::Masked),
+            _ => Err(format!("Unknown format '{}'", s)),
+        }
+    }
+}
+
+/// How [`Number::format_with`] writes a number.
+///
+/// Defaults to groups of 3, 3 and 4 digits separated by spaces, like the
+/// alternate `{:#}` format.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::number::{FormatOptions, Number};
+/// use std::str::FromStr;
+///
+/// let number = Number::from_str("6541003238").unwrap();
+///
+/// assert_eq!(number.format_with(&FormatOptions::new().separator('-')), "654-100-3238");
+/// assert_eq!(number.format_with(&FormatOptions::new().groups(&[2, 4, 4])), "65 4100 3238");
+/// assert_eq!(number.format_with(&FormatOptions::new().mask('*', 4)), "*** *** 3238");
+/// assert_eq!(number.format_with(&FormatOptions::new().groups(&[])), "6541003238");
+/// ```
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct FormatOptions {
+    separator: char,
+    groups: Vec<usize>,
+    mask: Option<(char, usize)>,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            separator: ' ',
+            groups: vec![3, 3, 4],
+            mask: None,
+        }
+    }
+}
+
+impl FormatOptions {
+    pub fn new() -> Self {
+        FormatOptions::default()
+    }
+
+    /// The character between groups.
+    pub fn separator(mut self, separator: char) -> Self {
+        self.separator = separator;
+        self
+    }
+
+    /// The sizes of the groups of digits from the left. Digits past the
+    /// given groups make up a last group, so no groups means no separators.
+    pub fn groups(mut self, sizes: &[usize]) -> Self {
+        self.groups = sizes.to_vec();
+        self
+    }
+
+    /// Replaces every digit with `mask` except the last `visible` ones.
+    pub fn mask(mut self, mask: char, visible: usize) -> Self {
+        self.mask = Some((mask, visible));
+        self
+    }
+}
+
+/// A main digit multiplied by the weight of its position.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Term {
+    pub digit: Digit,
+    /// From 10 for the first digit down to 2 for the ninth.
+    pub weight: u16,
+    pub product: u16,
+}
+
+/// The working of the Modulus 11 check digit, see [`explain`].
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Explanation {
+    pub terms: [Term; 9],
+    /// The sum of the products.
+    pub sum: u16,
+    /// The sum modulo 11.
+    pub remainder: u16,
+    /// 11 minus the remainder, written as 0 when 11. `None` when it is 10,
+    /// which is never issued.
+    pub checkdigit: Option<Digit>,
+}
 
 #[derive(PartialEq, Clone, Debug)]
 pub struct Number {
@@ -30,18 +173,131 @@ impl Number {
     /// ```
     /// use heidi::number::Number;
     ///
-    /// let n: [u16; 9] = [0, 1, 0, 1, 9, 9, 0, 0, 1];
+    /// let n: [u8; 9] = [0, 1, 0, 1, 9, 9, 0, 0, 1];
     /// let number = Number::new(n);
     ///
     /// assert_eq!(*number.unwrap().checkdigit(), 4);
     /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with [ValidationError] when a value is over 9 or the check
+    /// digit would be 10.
     pub fn new(digits: [Digit; 9]) -> Result<Self, ValidationError> {
+        if let Some(digit) = digits.iter().find(|&&digit| digit > 9) {
+            return Err(ValidationError::with_kind(
+                ErrorKind::Digit,
+                &format!("The given value {} is not a digit", digit),
+            ));
+        }
+
         Ok(Number {
             checkdigit: check_digit(&digits)?,
             digits,
         })
     }
 
+    /// Collects either the 9 main digits or the full 10 digits from an
+    /// iterator, e.g. one of digits streamed out of a parser.
+    ///
+    /// `FromIterator` cannot be implemented for a `Result` of a number, so
+    /// this stands for `collect`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heidi::number::Number;
+    ///
+    /// let digits = "654-100-3238".chars().filter_map(|c| c.to_digit(10)).map(|d| d as u8);
+    /// let number = Number::from_digits(digits).unwrap();
+    ///
+    /// assert_eq!(number.to_string(), "6541003238");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with [ValidationError] like `TryFrom<&[Digit]>` does.
+    pub fn from_digits<I: IntoIterator<Item = Digit>>(digits: I) -> Result<Self, ValidationError> {
+        let mut buffer: [Digit; 10] = [0; 10];
+        let mut len = 0;
+
+        for digit in digits {
+            if len == buffer.len() {
+                return Err(ValidationError::with_kind(
+                    ErrorKind::Length,
+                    "The given digits are more than 10",
+                ));
+            }
+
+            buffer[len] = digit;
+            len += 1;
+        }
+
+        Number::try_from(&buffer[..len])
+    }
+
+    /// Converts 10 digits held as `u16`, as digits used to be, into a
+    /// [`Number`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heidi::number::Number;
+    ///
+    /// let number = Number::from_u16_digits(&[6, 5, 4, 1, 0, 0, 3, 2, 3, 8]).unwrap();
+    ///
+    /// assert_eq!(number.to_string(), "6541003238");
+    /// assert!(Number::from_u16_digits(&[6, 5, 4, 1, 0, 0, 3, 2, 300, 8])
+    ///     .unwrap_err()
+    ///     .to_string()
+    ///     .contains("300"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with [ValidationError] when a value is over 9 or the check
+    /// digit cannot be verified.
+    pub fn from_u16_digits(value: &[u16; 10]) -> Result<Self, ValidationError> {
+        let mut digits: [Digit; 10] = [0; 10];
+
+        for (digit, &wide) in digits.iter_mut().zip(value) {
+            *digit = match Digit::try_from(wide) {
+                Ok(narrow) if narrow <= 9 => narrow,
+                _ => {
+                    return Err(ValidationError::with_kind(
+                        ErrorKind::Digit,
+                        &format!("The given value {} is not a digit", wide),
+                    ))
+                }
+            };
+        }
+
+        Number::try_from(&digits)
+    }
+
+    /// The digit at the given position from 0 to 9, where 9 is the check
+    /// digit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heidi::number::Number;
+    /// use std::str::FromStr;
+    ///
+    /// let number = Number::from_str("6541003238").unwrap();
+    ///
+    /// assert_eq!(number.get(0), Some(6));
+    /// assert_eq!(number.get(9), Some(8));
+    /// assert_eq!(number[9], 8);
+    /// assert_eq!(number.get(10), None);
+    /// ```
+    pub fn get(&self, index: usize) -> Option<Digit> {
+        match index {
+            9 => Some(self.checkdigit),
+            _ => self.digits.get(index).copied(),
+        }
+    }
+
     pub fn checkdigit(&self) -> &Digit {
         &self.checkdigit
     }
@@ -49,29 +305,175 @@ impl Number {
     pub fn digits(&self) -> &[Digit; 9] {
         &self.digits
     }
+
+    /// How the check digit is derived from the main digits, see [`explain`].
+    pub fn explain_checkdigit(&self) -> Explanation {
+        explain(&self.digits)
+    }
+
+    /// Derives a UUIDv5 from the canonical compact form under the given
+    /// namespace. The same number always gets the same UUID in a namespace.
+    ///
+    /// Requires the `uuid` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heidi::number::Number;
+    /// use std::str::FromStr;
+    /// use uuid::Uuid;
+    ///
+    /// let number = Number::from_str("654 100 3238").unwrap();
+    /// let uuid = number.to_uuid(Uuid::NAMESPACE_OID);
+    ///
+    /// assert_eq!(uuid, Uuid::new_v5(&Uuid::NAMESPACE_OID, b"6541003238"));
+    /// ```
+    #[cfg(feature = "uuid")]
+    pub fn to_uuid(&self, namespace: uuid::Uuid) -> uuid::Uuid {
+        uuid::Uuid::new_v5(&namespace, self.to_string().as_bytes())
+    }
+
+    /// Packs the 10 digits as binary-coded decimal, two digits per byte with
+    /// the first digit in the high nibble. Packed numbers sort in the same
+    /// order as their compact form.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heidi::number::Number;
+    /// use std::str::FromStr;
+    ///
+    /// let number = Number::from_str("6541003238").unwrap();
+    ///
+    /// assert_eq!(number.to_be_bytes(), [0x65, 0x41, 0x00, 0x32, 0x38]);
+    /// ```
+    pub fn to_be_bytes(&self) -> [u8; 5] {
+        let mut bytes = [0; 5];
+
+        for (idx, byte) in bytes.iter_mut().enumerate() {
+            let high = self.digits[idx * 2];
+            let low = self.digits.get(idx * 2 + 1).unwrap_or(&self.checkdigit);
+
+            *byte = high << 4 | low;
+        }
+
+        bytes
+    }
+
+    /// Writes the number in the given format. Numbers of unknown scheme are
+    /// officially written like NHS Numbers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heidi::number::{Format, Number};
+    /// use std::str::FromStr;
+    ///
+    /// let number = Number::from_str("6541003238").unwrap();
+    ///
+    /// assert_eq!(number.to_formatted(Format::Official), "654 100 3238");
+    /// assert_eq!(number.to_formatted(Format::Hyphenated), "654-100-3238");
+    /// assert_eq!(number.to_formatted(Format::Masked), "******3238");
+    /// ```
+    pub fn to_formatted(&self, format: Format) -> String {
+        let options = match format {
+            Format::Compact => FormatOptions::new().groups(&[]),
+            Format::Official => FormatOptions::new(),
+            Format::Hyphenated => FormatOptions::new().separator('-'),
+            Format::Masked => FormatOptions::new().groups(&[]).mask('*', 4),
+        };
+
+        self.format_with(&options)
+    }
+
+    /// Writes the number as the given options tell.
+    pub fn format_with(&self, options: &FormatOptions) -> String {
+        let masked = options.mask.map_or(0, |(_, visible)| 10 - visible.min(10));
+        let boundaries: Vec<usize> = options
+            .groups
+            .iter()
+            .scan(0, |end, size| {
+                *end += size;
+                Some(*end)
+            })
+            .collect();
+        let mut output = String::new();
+
+        for (idx, digit) in self.to_string().chars().enumerate() {
+            if idx > 0 && boundaries.contains(&idx) {
+                output.push(options.separator);
+            }
+
+            match options.mask {
+                Some((mask, _)) if idx < masked => output.push(mask),
+                _ => output.push(digit),
+            }
+        }
+
+        output
+    }
+
+    /// Unpacks a number from [`Number::to_be_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Fails with [ValidationError] when a nibble is not a decimal digit or
+    /// the check digit cannot be verified.
+    pub fn from_be_bytes(bytes: [u8; 5]) -> Result<Self, ValidationError> {
+        let mut digits: [Digit; 10] = [0; 10];
+
+        for (idx, byte) in bytes.iter().enumerate() {
+            for (offset, nibble) in [byte >> 4, byte & 0x0f].iter().enumerate() {
+                if *nibble > 9 {
+                    return Err(ValidationError::with_kind(
+                        ErrorKind::Digit,
+                        &format!("The given nibble {:#x} is not a digit", nibble),
+                    ));
+                }
+
+                digits[idx * 2 + offset] = Digit::from(*nibble);
+            }
+        }
+
+        Number::try_from(&digits)
+    }
+}
+
+impl AsRef<Number> for Number {
+    fn as_ref(&self) -> &Number {
+        self
+    }
+}
+
+impl Index<usize> for Number {
+    type Output = Digit;
+
+    /// Panics when the index is over 9. See [`Number::get`].
+    fn index(&self, index: usize) -> &Digit {
+        match index {
+            9 => &self.checkdigit,
+            _ => &self.digits[index],
+        }
+    }
 }
 
 impl fmt::Display for Number {
+    /// Writes the 10 digits, honouring the width, fill and alignment flags.
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        write!(formatter, "{}", &self.digits[0])?;
-        write!(formatter, "{}", &self.digits[1])?;
-        write!(formatter, "{}", &self.digits[2])?;
-        write!(formatter, "{}", &self.digits[3])?;
-        write!(formatter, "{}", &self.digits[4])?;
-        write!(formatter, "{}", &self.digits[5])?;
-        write!(formatter, "{}", &self.digits[6])?;
-        write!(formatter, "{}", &self.digits[7])?;
-        write!(formatter, "{}", &self.digits[8])?;
-        write!(formatter, "{}", &self.checkdigit)?;
+        let mut buffer = String::with_capacity(10);
 
-        Ok(())
+        for digit in self.digits.iter().chain(Some(&self.checkdigit)) {
+            write!(buffer, "{}", digit)?;
+        }
+
+        formatter.pad(&buffer)
     }
 }
 
 impl TryFrom<&[Digit; 10]> for Number {
     type Error = ValidationError;
 
-    /// Converts an array slice of 10 decimal `u16` into a [`Number`].
+    /// Converts an array slice of 10 decimal `u8` into a [`Number`].
     ///
     /// # Examples
     ///
@@ -79,7 +481,7 @@ impl TryFrom<&[Digit; 10]> for Number {
     /// use heidi::number::Number;
     /// use std::convert::TryFrom;
     ///
-    /// let n: [u16; 10] = [3, 1, 0, 1, 0, 0, 3, 2, 3, 7];
+    /// let n: [u8; 10] = [3, 1, 0, 1, 0, 0, 3, 2, 3, 7];
     /// let number = Number::try_from(&n);
     ///
     /// assert_eq!(*number.unwrap().checkdigit(), 7);
@@ -97,17 +499,76 @@ impl TryFrom<&[Digit; 10]> for Number {
         let number = Number::new(digits)?;
 
         if number.checkdigit() != control {
-            return Err(ValidationError::new(&format!(
-                "The given check digit {} does not match the actual check digit {}",
-                control,
-                number.checkdigit()
-            )));
+            return Err(ValidationError::with_kind(
+                ErrorKind::CheckDigit,
+                &format!(
+                    "The given check digit {} does not match the actual check digit {}",
+                    control,
+                    number.checkdigit()
+                ),
+            )
+            .with_param("given", control)
+            .with_param("expected", number.checkdigit()));
         }
 
         Ok(number)
     }
 }
 
+impl TryFrom<&[Digit]> for Number {
+    type Error = ValidationError;
+
+    /// Converts a slice of either the 9 main digits, completed with their
+    /// check digit, or the full 10 digits into a [`Number`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heidi::number::Number;
+    /// use std::convert::TryFrom;
+    ///
+    /// let digits: Vec<u8> = vec![6, 5, 4, 1, 0, 0, 3, 2, 3];
+    /// let number = Number::try_from(digits.as_slice()).unwrap();
+    ///
+    /// assert_eq!(*number.checkdigit(), 8);
+    /// assert!(Number::try_from(&digits[..8]).is_err());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with [ValidationError] when the slice is not 9 or 10 digits
+    /// long, has values over 9 or the check digit cannot be verified.
+    fn try_from(value: &[Digit]) -> Result<Self, Self::Error> {
+        if let Some(digit) = value.iter().find(|&&digit| digit > 9) {
+            return Err(ValidationError::with_kind(
+                ErrorKind::Digit,
+                &format!("The given value {} is not a digit", digit),
+            ));
+        }
+
+        match value.len() {
+            9 => {
+                let mut digits: [Digit; 9] = [0; 9];
+
+                digits.copy_from_slice(value);
+
+                Number::new(digits)
+            }
+            10 => {
+                let mut digits: [Digit; 10] = [0; 10];
+
+                digits.copy_from_slice(value);
+
+                Number::try_from(&digits)
+            }
+            len => Err(ValidationError::with_kind(
+                ErrorKind::Length,
+                &format!("The given slice has {} digits instead of 9 or 10", len),
+            )),
+        }
+    }
+}
+
 impl TryFrom<String> for Number {
     type Error = ValidationError;
 
@@ -152,25 +613,7 @@ impl TryFrom<usize> for Number {
     ///
     /// Fails with [ValidationError] when the check digit cannot be verified.
     fn try_from(value: usize) -> Result<Self, Self::Error> {
-        let mut digits: [Digit; 10] = [0; 10];
-        let mut idx: usize = 0;
-        let mut div = 1_000_000_000;
-
-        if (value / div * 10) % 10 != 0 {
-            return Err(ValidationError::new(&format!(
-                "The given number {} has more than 10 digits.",
-                &value
-            )));
-        }
-
-        while idx <= 9 {
-            digits[idx] = ((value / div) % 10) as u16;
-
-            div = div / 10;
-            idx = idx + 1;
-        }
-
-        Number::try_from(&digits)
+        Number::try_from(&split(value as u64)?)
     }
 }
 
@@ -196,19 +639,23 @@ impl FromStr for Number {
         let mut digits: [Digit; 10] = [0; 10];
         let vec: Vec<Digit> = s
             .chars()
-            .filter_map(|d| {
-                if d.is_whitespace() {
-                    None
-                } else {
-                    Some(d.to_digit(10).unwrap() as u16)
-                }
+            .filter(|d| !d.is_whitespace())
+            .map(|d| match d.to_digit(10) {
+                Some(digit) => Ok(digit as Digit),
+                None => Err(ValidationError::with_kind(
+                    ErrorKind::Digit,
+                    &format!("The given character '{}' is not a digit", d),
+                )
+                .with_param("character", d)),
             })
-            .collect();
+            .collect::<Result<_, _>>()?;
 
         if vec.len() != 10 {
-            return Err(ValidationError::new(
+            return Err(ValidationError::with_kind(
+                ErrorKind::Length,
                 "NHS Numbers must be of ten-digit long",
-            ));
+            )
+            .with_param("length", vec.len()));
         }
 
         digits.copy_from_slice(&vec);
@@ -217,28 +664,253 @@ impl FromStr for Number {
     }
 }
 
-fn check_digit(digits: &[u16; 9]) -> Result<Digit, ValidationError> {
-    let weighted_sum = digits
-        .iter()
-        .enumerate()
-        .fold(0, |sum, (idx, digit)| sum + (digit * (10 - (idx as u16))));
-    let chi = 11 - (weighted_sum % 11);
-
-    match chi {
-        11 => Ok(0),
-        d if d >= 10 => {
-            return Err(ValidationError::new(
-                "Modulus 11 numbers cannot have a check digit of 10",
-            ));
-        }
-        d => Ok(d),
+fn check_digit(digits: &[Digit; 9]) -> Result<Digit, ValidationError> {
+    explain(digits).checkdigit.ok_or_else(|| {
+        ValidationError::with_kind(
+            ErrorKind::Unusable,
+            "Modulus 11 numbers cannot have a check digit of 10",
+        )
+    })
+}
+
+/// Works out the Modulus 11 check digit of the given main digits step by
+/// step: every digit is multiplied by a weight from 10 down to 2, the
+/// products are added up and the check digit is 11 minus the remainder of
+/// dividing the sum by 11.
+///
+/// Unlike [`Number::new`] it takes main digits that no number can have,
+/// for which the check digit is `None`.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::number::explain;
+///
+/// let explanation = explain(&[6, 5, 4, 1, 0, 0, 3, 2, 3]);
+///
+/// assert_eq!(explanation.terms[0].product, 60);
+/// assert_eq!(explanation.sum, 168);
+/// assert_eq!(explanation.remainder, 3);
+/// assert_eq!(explanation.checkdigit, Some(8));
+/// ```
+pub fn explain(digits: &[Digit; 9]) -> Explanation {
+    let mut terms = [Term {
+        digit: 0,
+        weight: 0,
+        product: 0,
+    }; 9];
+
+    for (idx, (term, &digit)) in terms.iter_mut().zip(digits).enumerate() {
+        let weight = 10 - idx as u16;
+
+        *term = Term {
+            digit,
+            weight,
+            product: u16::from(digit) * weight,
+        };
+    }
+
+    let sum = terms.iter().map(|term| term.product).sum();
+    let remainder = sum % 11;
+    let checkdigit = match 11 - remainder {
+        11 => Some(0),
+        10 => None,
+        digit => Some(digit as Digit),
+    };
+
+    Explanation {
+        terms,
+        sum,
+        remainder,
+        checkdigit,
     }
 }
 
+/// Parses a number leniently, ignoring whitespace and hyphens, and returns
+/// its compact form. Suits ETL jobs that only need cleaned strings.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(heidi::number::normalize(" 654-100-3238 ").unwrap(), "6541003238");
+/// assert!(heidi::number::normalize("654-100-3239").is_err());
+/// ```
+///
+/// # Errors
+///
+/// Fails with [ValidationError] when the number is not valid.
+pub fn normalize(input: &str) -> Result<String, ValidationError> {
+    normalize_as::<Number>(input)
+}
+
+/// Normalises a number validated as `N`.
+pub(crate) fn normalize_as<N>(input: &str) -> Result<String, ValidationError>
+where
+    N: FromStr<Err = ValidationError> + fmt::Display,
+{
+    let cleaned: String = input.chars().filter(|&c| c != '-').collect();
+
+    N::from_str(&cleaned).map(|number| number.to_string())
+}
+
+/// Splits an unsigned integer into the 10 digits of a number, leading zeroes
+/// included.
+pub(crate) fn split(value: u64) -> Result<[Digit; 10], ValidationError> {
+    if value > MAX {
+        return Err(ValidationError::with_kind(
+            ErrorKind::Length,
+            &format!("The given number {} has more than 10 digits.", value),
+        ));
+    }
+
+    let mut digits = [0; 10];
+    let mut rest = value;
+
+    for digit in digits.iter_mut().rev() {
+        *digit = (rest % 10) as Digit;
+        rest /= 10;
+    }
+
+    Ok(digits)
+}
+
+/// Converts a number stored as an integer by a database, where leading zeroes
+/// are lost.
+#[cfg(any(
+    feature = "diesel",
+    feature = "postgres",
+    feature = "sea-orm",
+    feature = "sqlx"
+))]
+pub(crate) fn from_bigint<T>(value: i64) -> Result<T, ValidationError>
+where
+    T: for<'a> TryFrom<&'a [Digit; 10], Error = ValidationError>,
+{
+    let value = u64::try_from(value).map_err(|_| {
+        ValidationError::with_kind(
+            ErrorKind::Digit,
+            &format!("The given number {} is negative", value),
+        )
+    })?;
+
+    T::try_from(&split(value)?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn packs_bytes() -> Result<(), ValidationError> {
+        let number = Number::from_str("0101990014")?;
+        let bytes = number.to_be_bytes();
+
+        assert_eq!(bytes, [0x01, 0x01, 0x99, 0x00, 0x14]);
+        assert_eq!(Number::from_be_bytes(bytes)?, number);
+        assert_eq!(
+            Number::from_be_bytes([0x01, 0x01, 0x99, 0x00, 0x15])
+                .unwrap_err()
+                .kind(),
+            ErrorKind::CheckDigit
+        );
+        assert_eq!(
+            Number::from_be_bytes([0x0a, 0x01, 0x99, 0x00, 0x14])
+                .unwrap_err()
+                .kind(),
+            ErrorKind::Digit
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn custom_formats() -> Result<(), ValidationError> {
+        let number = Number::from_str("6541003238")?;
+
+        assert_eq!(number.format_with(&FormatOptions::new()), "654 100 3238");
+        assert_eq!(
+            number.format_with(&FormatOptions::new().groups(&[0, 2, 2]).separator('.')),
+            "65.41.003238"
+        );
+        assert_eq!(
+            number.format_with(&FormatOptions::new().groups(&[]).mask('X', 12)),
+            "6541003238"
+        );
+        assert_eq!(
+            number.format_with(&FormatOptions::new().mask('*', 0)),
+            "*** *** ****"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn explanations() {
+        let number = Number::from_str("0101990014").unwrap();
+        let explanation = number.explain_checkdigit();
+
+        assert_eq!(
+            explanation.terms[3],
+            Term {
+                digit: 1,
+                weight: 7,
+                product: 7
+            }
+        );
+        assert_eq!(explanation.checkdigit, Some(*number.checkdigit()));
+        assert_eq!(explain(&[0, 0, 0, 0, 0, 0, 0, 0, 0]).checkdigit, Some(0));
+        assert_eq!(explain(&[0, 0, 0, 0, 0, 0, 0, 0, 6]).checkdigit, None);
+    }
+
+    #[test]
+    fn digit_slices() -> Result<(), ValidationError> {
+        let digits: Vec<Digit> = vec![6, 5, 4, 1, 0, 0, 3, 2, 3, 8];
+
+        assert_eq!(
+            Number::try_from(&digits[..])?,
+            Number::try_from(&digits[..9])?
+        );
+        assert_eq!(
+            Number::try_from(&[6, 5, 4, 1, 0, 0, 3, 2, 3, 9][..])
+                .unwrap_err()
+                .kind(),
+            ErrorKind::CheckDigit
+        );
+        assert_eq!(
+            Number::try_from(&digits[..3]).unwrap_err().kind(),
+            ErrorKind::Length
+        );
+        assert_eq!(
+            Number::from_u16_digits(&[6, 5, 4, 1, 0, 0, 3, 2, 3, 8])?,
+            Number::try_from(&digits[..])?
+        );
+        assert_eq!(
+            Number::from_u16_digits(&[6, 5, 4, 1, 0, 0, 3, 2, 3, 256])
+                .unwrap_err()
+                .kind(),
+            ErrorKind::Digit
+        );
+        assert_eq!(
+            Number::try_from(&[6, 5, 4, 1, 0, 0, 3, 2, 10][..])
+                .unwrap_err()
+                .kind(),
+            ErrorKind::Digit
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn digit_iterators() {
+        assert!(Number::from_digits(vec![3, 1, 0, 1, 0, 0, 3, 2, 3, 7]).is_ok());
+        assert_eq!(
+            Number::from_digits(std::iter::repeat(1))
+                .unwrap_err()
+                .kind(),
+            ErrorKind::Length
+        );
+    }
+
     #[test]
     fn valid_checkdigit() -> Result<(), ValidationError> {
         assert_eq!(3, check_digit(&[8, 9, 3, 1, 7, 7, 4, 5, 8])?);
@@ -252,6 +924,23 @@ mod tests {
         assert!(Number::new([8, 9, 3, 1, 7, 7, 4, 5, 8]).is_ok());
     }
 
+    #[test]
+    fn values_over_nine() {
+        let wide = [30, 1, 0, 1, 9, 9, 0, 0, 1];
+
+        assert_eq!(Number::new(wide).unwrap_err().kind(), ErrorKind::Digit);
+        assert_eq!(
+            crate::chi::Number::new(wide).unwrap_err().kind(),
+            ErrorKind::Digit
+        );
+        assert_eq!(
+            Number::from_u16_digits(&[6, 5, 4, 1, 0, 0, 3, 2, 3, 256])
+                .unwrap_err()
+                .to_string(),
+            "The given value 256 is not a digit"
+        );
+    }
+
     #[test]
     fn valid_number_from_slice10() {
         assert!(Number::try_from(&[8, 9, 3, 1, 7, 7, 4, 5, 8, 3]).is_ok());
@@ -269,6 +958,14 @@ mod tests {
     }
 
     #[test]
+    fn invalid_character() {
+        let error = Number::from_str("654 100 323X").unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::Digit);
+    }
+
+    #[test]
+    #[allow(clippy::inconsistent_digit_grouping)]
     fn valid_usize() -> Result<(), ValidationError> {
         let n = 893_177_4583;
         let number = Number::try_from(n)?;
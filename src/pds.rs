@@ -0,0 +1,239 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! A client for the NHS Personal Demographics Service (PDS) FHIR API to
+//! confirm an NHS Number is allocated to a patient, beyond having a valid
+//! check digit.
+//!
+//! Authentication is left to the caller through a [`TokenProvider`], e.g.
+//! one that runs the signed JWT flow of the NHS API platform and caches the
+//! access token.
+//!
+//! ```no_run
+//! use heidi::nhs::Number;
+//! use heidi::pds::{Client, Verification, SANDBOX};
+//! use std::str::FromStr;
+//!
+//! # async fn run() -> Result<(), heidi::pds::Error> {
+//! let client = Client::new(SANDBOX, String::from("token"));
+//! let number = Number::from_str("9000000009").unwrap();
+//!
+//! assert_eq!(client.verify(&number).await?, Verification::Allocated);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Requires the `pds` feature.
+
+use crate::nhs;
+use serde_json::Value;
+use std::error;
+use std::fmt;
+use std::future::{self, Future};
+use std::str::FromStr;
+
+/// The production environment.
+pub const PRODUCTION: &str = "https://api.service.nhs.uk/personal-demographics/FHIR/R4";
+
+/// The integration test environment.
+pub const INTEGRATION: &str = "https://int.api.service.nhs.uk/personal-demographics/FHIR/R4";
+
+/// The sandbox, which needs no authentication and knows a few test patients.
+pub const SANDBOX: &str = "https://sandbox.api.service.nhs.uk/personal-demographics/FHIR/R4";
+
+/// Provides the access token sent with every request.
+pub trait TokenProvider {
+    fn token(&self) -> impl Future<Output = Result<String, Error>> + Send;
+}
+
+/// A fixed token, e.g. for the sandbox.
+impl TokenProvider for String {
+    fn token(&self) -> impl Future<Output = Result<String, Error>> + Send {
+        future::ready(Ok(self.clone()))
+    }
+}
+
+/// What PDS knows about a number.
+#[derive(PartialEq, Clone, Debug)]
+pub enum Verification {
+    /// The number is allocated to a patient.
+    Allocated,
+    /// The record was merged and the patient now has the given number.
+    Superseded(nhs::Number),
+    /// No patient has the number.
+    NotFound,
+}
+
+/// Represents a failure to verify a number.
+#[derive(Debug)]
+pub enum Error {
+    /// The token provider failed.
+    Token(String),
+    /// The token was rejected or does not grant access.
+    Unauthorized,
+    /// The request could not be sent or the response could not be read.
+    Http(reqwest::Error),
+    /// PDS answered with an unexpected status and the given body.
+    Status(u16, String),
+    /// The response is not a Patient resource.
+    Response(String),
+}
+
+impl error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Token(message) => write!(f, "Could not get an access token: {}", message),
+            Error::Unauthorized => write!(f, "The access token was rejected"),
+            Error::Http(e) => write!(f, "The request to PDS failed: {}", e),
+            Error::Status(status, _) => write!(f, "PDS answered with status {}", status),
+            Error::Response(message) => write!(f, "Unexpected PDS response: {}", message),
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        Error::Http(error)
+    }
+}
+
+/// Verifies numbers against a PDS environment.
+#[derive(Clone, Debug)]
+pub struct Client<T> {
+    http: reqwest::Client,
+    base: String,
+    tokens: T,
+}
+
+impl<T: TokenProvider> Client<T> {
+    /// Creates a client for the given base URL, such as [`PRODUCTION`].
+    pub fn new(base: &str, tokens: T) -> Self {
+        Client {
+            http: reqwest::Client::new(),
+            base: base.trim_end_matches('/').to_string(),
+            tokens,
+        }
+    }
+
+    /// Sends requests with the given HTTP client, e.g. one with timeouts or
+    /// a proxy.
+    pub fn http(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Looks up the patient with the given number.
+    pub async fn verify(&self, number: &nhs::Number) -> Result<Verification, Error> {
+        let token = self.tokens.token().await?;
+        let response = self
+            .http
+            .get(format!("{}/Patient/{}", self.base, number))
+            .bearer_auth(token)
+            .header("Accept", "application/fhir+json")
+            .header("X-Request-ID", request_id())
+            .send()
+            .await?;
+        let status = response.status().as_u16();
+        let body = response.text().await?;
+
+        verification(number, status, &body)
+    }
+}
+
+/// Interprets a response to a Patient read.
+fn verification(number: &nhs::Number, status: u16, body: &str) -> Result<Verification, Error> {
+    match status {
+        200 => {
+            let patient: Value =
+                serde_json::from_str(body).map_err(|e| Error::Response(e.to_string()))?;
+            let id = patient["id"]
+                .as_str()
+                .ok_or_else(|| Error::Response("The patient has no id".to_string()))?;
+
+            if id == number.to_string() {
+                return Ok(Verification::Allocated);
+            }
+
+            nhs::Number::from_str(id)
+                .map(Verification::Superseded)
+                .map_err(|e| Error::Response(e.to_string()))
+        }
+        // An NHS Number PDS considers invalid is not allocated either.
+        400 | 404 => Ok(Verification::NotFound),
+        401 | 403 => Err(Error::Unauthorized),
+        _ => Err(Error::Status(status, body.to_string())),
+    }
+}
+
+/// A random UUID v4 to trace a request.
+fn request_id() -> String {
+    let mut bytes: [u8; 16] = rand::random();
+
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifications() {
+        let number = nhs::Number::from_str("9000000009").unwrap();
+
+        assert_eq!(
+            verification(
+                &number,
+                200,
+                r#"{"resourceType":"Patient","id":"9000000009"}"#
+            )
+            .unwrap(),
+            Verification::Allocated
+        );
+        assert_eq!(
+            verification(
+                &number,
+                200,
+                r#"{"resourceType":"Patient","id":"9000000017"}"#
+            )
+            .unwrap(),
+            Verification::Superseded(nhs::Number::from_str("9000000017").unwrap())
+        );
+        assert_eq!(
+            verification(&number, 404, "").unwrap(),
+            Verification::NotFound
+        );
+        assert!(matches!(
+            verification(&number, 403, ""),
+            Err(Error::Unauthorized)
+        ));
+        assert!(matches!(
+            verification(&number, 200, "{}"),
+            Err(Error::Response(_))
+        ));
+    }
+
+    #[test]
+    fn request_ids() {
+        let id = request_id();
+
+        assert_eq!(id.len(), 36);
+        assert_eq!(&id[14..15], "4");
+    }
+}
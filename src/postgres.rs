@@ -0,0 +1,107 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Postgres types so tokio-postgres and postgres can bind and read NHS and
+//! CHI numbers as parameters.
+//!
+//! Numbers are sent as `TEXT`, `VARCHAR` or `BPCHAR` of 10 digits, or as
+//! `INT8` where leading zeroes are lost. Reading a number that is not valid
+//! fails with a conversion error.
+//!
+//! ```ignore
+//! let number: heidi::nhs::Number = client
+//!     .query_one("SELECT nhs_number FROM patient WHERE id = $1", &[&id])
+//!     .await?
+//!     .try_get(0)?;
+//! ```
+//!
+//! Requires the `postgres` feature.
+
+use crate::number::from_bigint;
+use crate::{chi, nhs};
+use bytes::BytesMut;
+use postgres_types::{to_sql_checked, FromSql, IsNull, ToSql, Type};
+use std::error::Error;
+use std::str::FromStr;
+
+macro_rules! postgres_type {
+    ($number:ty) => {
+        impl ToSql for $number {
+            fn to_sql(
+                &self,
+                ty: &Type,
+                out: &mut BytesMut,
+            ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+                let number = self.to_string();
+
+                if *ty == Type::INT8 {
+                    number.parse::<i64>()?.to_sql(ty, out)
+                } else {
+                    number.to_sql(ty, out)
+                }
+            }
+
+            fn accepts(ty: &Type) -> bool {
+                *ty == Type::INT8 || <String as ToSql>::accepts(ty)
+            }
+
+            to_sql_checked!();
+        }
+
+        impl<'a> FromSql<'a> for $number {
+            fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+                if *ty == Type::INT8 {
+                    Ok(from_bigint(i64::from_sql(ty, raw)?)?)
+                } else {
+                    Ok(<$number>::from_str(<&str>::from_sql(ty, raw)?)?)
+                }
+            }
+
+            fn accepts(ty: &Type) -> bool {
+                *ty == Type::INT8 || <&str as FromSql>::accepts(ty)
+            }
+        }
+    };
+}
+
+postgres_type!(nhs::Number);
+postgres_type!(chi::Number);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ValidationError;
+
+    #[test]
+    fn round_trips() {
+        let nhs = nhs::Number::from_str("6541003238").unwrap();
+        let chi = chi::Number::from_str("0101990014").unwrap();
+
+        for ty in &[Type::TEXT, Type::VARCHAR, Type::INT8] {
+            let mut buf = BytesMut::new();
+
+            nhs.to_sql_checked(ty, &mut buf).unwrap();
+            assert_eq!(nhs::Number::from_sql(ty, &buf).unwrap(), nhs);
+
+            buf.clear();
+            chi.to_sql_checked(ty, &mut buf).unwrap();
+            assert_eq!(chi::Number::from_sql(ty, &buf).unwrap(), chi);
+        }
+
+        assert!(nhs
+            .to_sql_checked(&Type::INT4, &mut BytesMut::new())
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_numbers() {
+        let err = nhs::Number::from_sql(&Type::TEXT, b"6541003239").unwrap_err();
+        let negative = (-6541003238i64).to_be_bytes();
+
+        assert!(err.downcast_ref::<ValidationError>().is_some());
+        assert!(nhs::Number::from_sql(&Type::INT8, &negative).is_err());
+    }
+}
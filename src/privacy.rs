@@ -0,0 +1,457 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Generalisation of identifiers for statistical disclosure control.
+//!
+//! Released data should carry coarse attributes (a number prefix, a year of
+//! birth, an age band) instead of identifiers so each combination of
+//! attributes is shared by enough records. A [`Generaliser`] builds a key out
+//! of those attributes that can be grouped on to check k-anonymity.
+//!
+//! CHI numbers only give the last two digits of the year of birth, so the
+//! century is resolved relative to a reference date: the year of birth is
+//! the latest one not after the reference date. People older than 100 are
+//! taken as 100 years younger. Reference dates before the year 100 have no
+//! earlier century to resolve to, so births after them get an age of 0.
+//!
+//! Number prefixes are capped at [`MAX_PREFIX`] digits.
+
+use crate::error::{ErrorKind, ValidationError};
+use crate::{chi, number};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A calendar date.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Date {
+    pub fn new(year: u16, month: u8, day: u8) -> Self {
+        Date { year, month, day }
+    }
+
+    /// Returns the current date in UTC.
+    pub fn today() -> Self {
+        let days = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs() / 86_400);
+
+        Date::from_days(days as i64)
+    }
+
+    /// Tells whether the date exists in the calendar, e.g. `2020-02-29` does
+    /// but `2021-02-29` does not.
+    pub fn is_valid(&self) -> bool {
+        (1..=12).contains(&self.month)
+            && (1..=31).contains(&self.day)
+            && Date::from_days(self.days()) == *self
+    }
+
+    /// Returns the amount of days from 1970-01-01 to this date.
+    ///
+    /// See `days_from_civil` in <https://howardhinnant.github.io/date_algorithms.html>.
+    pub fn days(&self) -> i64 {
+        let month = i64::from(self.month);
+        let year = i64::from(self.year) - if month <= 2 { 1 } else { 0 };
+        let era = year.div_euclid(400);
+        let yoe = year - era * 400;
+        let mp = if month > 2 { month - 3 } else { month + 9 };
+        let doy = (153 * mp + 2) / 5 + i64::from(self.day) - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+        era * 146_097 + doe - 719_468
+    }
+
+    /// Returns the date the given amount of days after 1970-01-01.
+    ///
+    /// See `civil_from_days` in <https://howardhinnant.github.io/date_algorithms.html>.
+    pub(crate) fn from_days(days: i64) -> Self {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        Date::new(year as u16, month as u8, day as u8)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl FromStr for Date {
+    type Err = ValidationError;
+
+    /// Parses a date written as `YYYY-MM-DD`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heidi::privacy::Date;
+    /// use std::str::FromStr;
+    ///
+    /// assert_eq!(Date::from_str("1985-03-12"), Ok(Date::new(1985, 3, 12)));
+    /// assert!(Date::from_str("1985-02-30").is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            ValidationError::with_kind(
+                ErrorKind::Date,
+                &format!("'{}' is not a date in the YYYY-MM-DD format", s),
+            )
+        };
+        let mut parts = s.trim().splitn(3, '-');
+        let mut part = |len: usize| {
+            parts
+                .next()
+                .filter(|part| part.len() == len && part.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|part| part.parse::<u16>().ok())
+                .ok_or_else(invalid)
+        };
+        let year = part(4)?;
+        let month = part(2)? as u8;
+        let day = part(2)? as u8;
+        let date = Date::new(year, month, day);
+
+        if date.is_valid() {
+            Ok(date)
+        } else {
+            Err(invalid())
+        }
+    }
+}
+
+/// The sex encoded in a CHI number.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Sex {
+    Female,
+    Male,
+}
+
+impl Sex {
+    /// A single letter, `F` or `M`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Sex::Female => "F",
+            Sex::Male => "M",
+        }
+    }
+}
+
+/// An inclusive range of ages.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug)]
+pub struct AgeBand {
+    pub from: u16,
+    pub to: u16,
+}
+
+impl fmt::Display for AgeBand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.from, self.to)
+    }
+}
+
+/// The longest prefix [`truncate`] and [`Generaliser::prefix`] give. The 9
+/// main digits determine the check digit, so a longer prefix would release
+/// the whole identifier.
+pub const MAX_PREFIX: usize = 8;
+
+/// Returns the first `len` digits of the number, at most [`MAX_PREFIX`].
+///
+/// # Examples
+///
+/// ```
+/// use heidi::nhs::Number;
+/// use heidi::privacy::truncate;
+/// use std::str::FromStr;
+///
+/// let number = Number::from_str("654 100 3238").unwrap();
+///
+/// assert_eq!(truncate(&number, 3), "654");
+/// assert_eq!(truncate(&number, 10), "65410032");
+/// ```
+pub fn truncate<N: AsRef<number::Number>>(number: &N, len: usize) -> String {
+    number
+        .as_ref()
+        .to_string()
+        .chars()
+        .take(len.min(MAX_PREFIX))
+        .collect()
+}
+
+/// Returns the year of birth of a CHI number, resolving the century relative
+/// to the reference date.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::chi::Number;
+/// use heidi::privacy::{year_of_birth, Date};
+/// use std::str::FromStr;
+///
+/// let number = Number::from_str("3011203237").unwrap();
+///
+/// assert_eq!(year_of_birth(&number, Date::new(2020, 4, 1)), 1920);
+/// assert_eq!(year_of_birth(&number, Date::new(2021, 4, 1)), 2020);
+/// ```
+pub fn year_of_birth(number: &chi::Number, reference: Date) -> u16 {
+    date_of_birth(number, reference).year
+}
+
+/// Returns the sex of a CHI number, given by its 9th digit: even for
+/// females and odd for males.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::chi::Number;
+/// use heidi::privacy::{sex, Sex};
+/// use std::str::FromStr;
+///
+/// let number = Number::from_str("0101990014").unwrap();
+///
+/// assert_eq!(sex(&number), Sex::Male);
+/// ```
+pub fn sex(number: &chi::Number) -> Sex {
+    if number.digits()[8] % 2 == 0 {
+        Sex::Female
+    } else {
+        Sex::Male
+    }
+}
+
+/// Returns the age band of a CHI number on the reference date. Bands are
+/// `width` years wide and aligned to multiples of `width`.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::chi::Number;
+/// use heidi::privacy::{age_band, AgeBand, Date};
+/// use std::str::FromStr;
+///
+/// let number = Number::from_str("0101990014").unwrap();
+///
+/// assert_eq!(
+///     age_band(&number, Date::new(2020, 4, 1), 5),
+///     AgeBand { from: 20, to: 24 }
+/// );
+/// ```
+pub fn age_band(number: &chi::Number, reference: Date, width: u16) -> AgeBand {
+    let width = width.max(1);
+    let from = age(number, reference) / width * width;
+
+    AgeBand {
+        from,
+        to: from + width - 1,
+    }
+}
+
+/// Returns the age in whole years of a CHI number on the reference date.
+pub fn age(number: &chi::Number, reference: Date) -> u16 {
+    let birth = date_of_birth(number, reference);
+    let had_birthday = (reference.month, reference.day) >= (birth.month, birth.day);
+
+    reference
+        .year
+        .saturating_sub(birth.year)
+        .saturating_sub(if had_birthday { 0 } else { 1 })
+}
+
+/// Returns the date of birth of a CHI number, resolving the century
+/// relative to the reference date.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::chi::Number;
+/// use heidi::privacy::{date_of_birth, Date};
+/// use std::str::FromStr;
+///
+/// let number = Number::from_str("0101990014").unwrap();
+///
+/// assert_eq!(date_of_birth(&number, Date::new(2020, 4, 1)).to_string(), "1999-01-01");
+/// ```
+pub fn date_of_birth(number: &chi::Number, reference: Date) -> Date {
+    let digits = number.digits();
+    let day = digits[0] * 10 + digits[1];
+    let month = digits[2] * 10 + digits[3];
+    let yy = u16::from(digits[4] * 10 + digits[5]);
+    let mut year = reference.year / 100 * 100 + yy;
+
+    if Date::new(year, month, day) > reference {
+        year = year.checked_sub(100).unwrap_or(year);
+    }
+
+    Date::new(year, month, day)
+}
+
+/// How the date of birth is generalised in a key.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+enum Birth {
+    Omit,
+    Year,
+    Band(u16),
+}
+
+/// Builds keys out of generalised attributes of CHI numbers.
+///
+/// Attributes are joined with `|` in a fixed order: prefix, birth, sex.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::chi::Number;
+/// use heidi::privacy::{Date, Generaliser};
+/// use std::str::FromStr;
+///
+/// let generaliser = Generaliser::new(Date::new(2020, 4, 1))
+///     .prefix(2)
+///     .age_band(10)
+///     .sex();
+/// let number = Number::from_str("0101990014").unwrap();
+///
+/// assert_eq!(generaliser.key(&number), "01|20-29|M");
+/// ```
+#[derive(PartialEq, Clone, Debug)]
+pub struct Generaliser {
+    reference: Date,
+    prefix: usize,
+    birth: Birth,
+    sex: bool,
+}
+
+impl Generaliser {
+    /// Creates a generaliser with no attributes. Ages are computed on the
+    /// reference date.
+    pub fn new(reference: Date) -> Self {
+        Generaliser {
+            reference,
+            prefix: 0,
+            birth: Birth::Omit,
+            sex: false,
+        }
+    }
+
+    /// Includes the first `len` digits of the number, at most
+    /// [`MAX_PREFIX`].
+    pub fn prefix(mut self, len: usize) -> Self {
+        self.prefix = len.min(MAX_PREFIX);
+        self
+    }
+
+    /// Includes the year of birth.
+    pub fn year_of_birth(mut self) -> Self {
+        self.birth = Birth::Year;
+        self
+    }
+
+    /// Includes the age band of the given width.
+    pub fn age_band(mut self, width: u16) -> Self {
+        self.birth = Birth::Band(width);
+        self
+    }
+
+    /// Includes the sex, `F` or `M`, given by the 9th digit.
+    pub fn sex(mut self) -> Self {
+        self.sex = true;
+        self
+    }
+
+    /// Returns the key for the given number.
+    pub fn key(&self, number: &chi::Number) -> String {
+        let mut parts = Vec::new();
+
+        if self.prefix > 0 {
+            parts.push(truncate(number, self.prefix));
+        }
+
+        match self.birth {
+            Birth::Omit => {}
+            Birth::Year => parts.push(year_of_birth(number, self.reference).to_string()),
+            Birth::Band(width) => parts.push(age_band(number, self.reference, width).to_string()),
+        }
+
+        if self.sex {
+            parts.push(sex(number).as_str().to_string());
+        }
+
+        parts.join("|")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn ages_around_birthdays() {
+        let number = chi::Number::from_str("3011203237").unwrap();
+
+        assert_eq!(age(&number, Date::new(2020, 11, 29)), 99);
+        assert_eq!(age(&number, Date::new(2020, 11, 30)), 0);
+        assert_eq!(age(&number, Date::new(2021, 11, 29)), 0);
+        assert_eq!(age(&number, Date::new(2021, 11, 30)), 1);
+        assert_eq!(
+            age_band(&number, Date::new(2020, 11, 29), 10),
+            AgeBand { from: 90, to: 99 }
+        );
+    }
+
+    #[test]
+    fn references_without_an_earlier_century() {
+        let number = chi::Number::from_str("0101990014").unwrap();
+        let reference = Date::new(50, 1, 1);
+
+        assert_eq!(date_of_birth(&number, reference), Date::new(99, 1, 1));
+        assert_eq!(age(&number, reference), 0);
+        assert_eq!(
+            date_of_birth(&number, Date::new(150, 1, 1)),
+            Date::new(99, 1, 1)
+        );
+    }
+
+    #[test]
+    fn dates_from_days() {
+        assert_eq!(Date::from_days(0), Date::new(1970, 1, 1));
+        assert_eq!(Date::from_days(11_016), Date::new(2000, 2, 29));
+        assert_eq!(Date::from_days(18_353), Date::new(2020, 4, 1));
+        assert_eq!(Date::new(1900, 3, 1).days(), -25_508);
+        assert_eq!(Date::new(2000, 2, 29).days(), 11_016);
+        assert!(Date::today() > Date::new(2020, 1, 1));
+        assert!(!Date::new(1900, 2, 29).is_valid());
+        assert!(!Date::new(2021, 4, 31).is_valid());
+    }
+
+    #[test]
+    fn keys() {
+        let number = chi::Number::from_str("3011203237").unwrap();
+        let reference = Date::new(2020, 4, 1);
+
+        assert_eq!(Generaliser::new(reference).key(&number), "");
+        assert_eq!(
+            Generaliser::new(reference)
+                .prefix(20)
+                .year_of_birth()
+                .sex()
+                .key(&number),
+            "30112032|1920|M"
+        );
+    }
+}
@@ -0,0 +1,339 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Keyed pseudonymisation of identifiers.
+//!
+//! A pseudonym is the HMAC-SHA256 of the canonical compact form of a number
+//! (e.g. `6541003238`) under a secret key. The same number always gets the
+//! same pseudonym under the same key, and the number cannot be recovered
+//! from the pseudonym without brute forcing the key.
+//!
+//! See [`vault`] for tokens that can be reversed.
+//!
+//! [`hash_with_salt`] is a plain salted hash for settings where HMAC has not
+//! been signed off.
+
+pub mod audit;
+pub mod vault;
+
+use crate::number;
+use crate::scheme::Scheme;
+use audit::{Auditor, Event, Operation};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256, Sha512};
+use std::fmt;
+
+/// How to encode the digest of a pseudonym.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Encoding {
+    /// Lowercase hexadecimal.
+    Hex,
+    /// Standard base64 with padding.
+    Base64,
+    /// URL-safe base64 without padding.
+    Base64Url,
+}
+
+impl Encoding {
+    pub(crate) fn encode(&self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Hex => bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+            Encoding::Base64 => STANDARD.encode(bytes),
+            Encoding::Base64Url => URL_SAFE_NO_PAD.encode(bytes),
+        }
+    }
+}
+
+/// A hash function for [`hash_with_salt`].
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Algorithm {
+    Sha256,
+    Sha512,
+    Blake3,
+}
+
+/// Returns the hash of the salt followed by the canonical compact form of
+/// the number.
+///
+/// Unlike a [`Pseudonymizer`], anyone holding the salt can confirm whether a
+/// hash belongs to a given number, and with only 10^9 possible numbers the
+/// salt must be kept as secret as a key.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::nhs::Number;
+/// use heidi::pseudo::{hash_with_salt, Algorithm, Encoding};
+/// use std::str::FromStr;
+///
+/// let number = Number::from_str("654 100 3238").unwrap();
+/// let hash = hash_with_salt(&number, b"salt", Algorithm::Sha256, Encoding::Hex);
+///
+/// assert_eq!(hash.len(), 64);
+/// ```
+pub fn hash_with_salt<N: AsRef<number::Number>>(
+    number: &N,
+    salt: &[u8],
+    algorithm: Algorithm,
+    encoding: Encoding,
+) -> String {
+    let canonical = number.as_ref().to_string();
+
+    match algorithm {
+        Algorithm::Sha256 => encoding.encode(
+            &Sha256::new()
+                .chain_update(salt)
+                .chain_update(&canonical)
+                .finalize(),
+        ),
+        Algorithm::Sha512 => encoding.encode(
+            &Sha512::new()
+                .chain_update(salt)
+                .chain_update(&canonical)
+                .finalize(),
+        ),
+        Algorithm::Blake3 => encoding.encode(
+            blake3::Hasher::new()
+                .update(salt)
+                .update(canonical.as_bytes())
+                .finalize()
+                .as_bytes(),
+        ),
+    }
+}
+
+/// Derives stable, non-reversible pseudonyms under a study key.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::nhs::Number;
+/// use heidi::pseudo::{Encoding, Pseudonymizer};
+/// use std::str::FromStr;
+///
+/// let pseudonymizer = Pseudonymizer::new(b"study key")
+///     .encoding(Encoding::Hex)
+///     .truncate(16);
+/// let a = Number::from_str("654 100 3238").unwrap();
+/// let b = Number::from_str("6541003238").unwrap();
+///
+/// assert_eq!(pseudonymizer.pseudonymise(&a).len(), 16);
+/// assert_eq!(pseudonymizer.pseudonymise(&a), pseudonymizer.pseudonymise(&b));
+/// ```
+#[derive(PartialEq, Clone)]
+pub struct Pseudonymizer {
+    key: Vec<u8>,
+    encoding: Encoding,
+    length: Option<usize>,
+    dataset: Option<String>,
+    key_id: Option<String>,
+    audit: Option<Auditor>,
+}
+
+impl Pseudonymizer {
+    /// Creates a pseudonymizer with the given key. Pseudonyms are the full
+    /// digest in hexadecimal unless configured otherwise.
+    pub fn new(key: &[u8]) -> Self {
+        Pseudonymizer {
+            key: key.to_vec(),
+            encoding: Encoding::Hex,
+            length: None,
+            dataset: None,
+            key_id: None,
+            audit: None,
+        }
+    }
+
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Keeps only the first `length` characters of the encoded digest.
+    ///
+    /// Shorter pseudonyms are more likely to collide.
+    pub fn truncate(mut self, length: usize) -> Self {
+        self.length = Some(length);
+        self
+    }
+
+    /// Mixes a dataset or purpose label into the derivation so the same
+    /// number gets unrelated pseudonyms in datasets with different labels,
+    /// even under the same key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heidi::nhs::Number;
+    /// use heidi::pseudo::Pseudonymizer;
+    /// use std::str::FromStr;
+    ///
+    /// let number = Number::from_str("6541003238").unwrap();
+    /// let audit = Pseudonymizer::new(b"key").dataset("audit-2020");
+    /// let research = Pseudonymizer::new(b"key").dataset("research-2020");
+    ///
+    /// assert_ne!(audit.pseudonymise(&number), research.pseudonymise(&number));
+    /// ```
+    pub fn dataset(mut self, label: &str) -> Self {
+        self.dataset = Some(label.to_string());
+        self
+    }
+
+    /// Names the key for audit events. The id is not mixed into the
+    /// derivation.
+    pub fn key_id(mut self, id: &str) -> Self {
+        self.key_id = Some(id.to_string());
+        self
+    }
+
+    /// Reports every pseudonymisation to the given auditor.
+    pub fn audit(mut self, auditor: Auditor) -> Self {
+        self.audit = Some(auditor);
+        self
+    }
+
+    /// Returns the pseudonym for the given number.
+    pub fn pseudonymise<N: AsRef<number::Number>>(&self, number: &N) -> String {
+        let number = number.as_ref();
+
+        if let Some(auditor) = &self.audit {
+            let mut event = Event::new(Operation::Pseudonymise, Scheme::detect(number));
+            event.key_id = self.key_id.clone();
+            event.dataset = self.dataset.clone();
+            auditor.record(&event);
+        }
+
+        self.derive(&number.to_string())
+    }
+
+    /// Returns the pseudonym for the given canonical form.
+    pub(crate) fn derive(&self, canonical: &str) -> String {
+        let digest = match &self.dataset {
+            // The dataset key is derived from the main key so labels do not
+            // have to be kept secret.
+            Some(label) => hmac(
+                &hmac(&self.key, format!("dataset:{}", label).as_bytes()),
+                canonical.as_bytes(),
+            ),
+            None => hmac(&self.key, canonical.as_bytes()),
+        };
+        let mut token = self.encoding.encode(&digest);
+
+        if let Some(length) = self.length {
+            token.truncate(length);
+        }
+
+        token
+    }
+}
+
+fn hmac(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(message);
+
+    mac.finalize().into_bytes().to_vec()
+}
+
+impl fmt::Debug for Pseudonymizer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Pseudonymizer")
+            .field("encoding", &self.encoding)
+            .field("length", &self.length)
+            .field("dataset", &self.dataset)
+            .field("key_id", &self.key_id)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chi, nhs};
+    use std::str::FromStr;
+
+    #[test]
+    fn known_digest() {
+        // HMAC-SHA256 test case 2 from RFC 4231.
+        let pseudonymizer = Pseudonymizer::new(b"Jefe");
+
+        assert_eq!(
+            pseudonymizer.derive("what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn encodings_and_truncation() {
+        let number = nhs::Number::from_str("6541003238").unwrap();
+        let hex = Pseudonymizer::new(b"k").pseudonymise(&number);
+        let base64 = Pseudonymizer::new(b"k")
+            .encoding(Encoding::Base64Url)
+            .pseudonymise(&number);
+
+        assert_eq!(hex.len(), 64);
+        assert_eq!(base64.len(), 43);
+        assert!(!base64.contains('='));
+        assert_eq!(
+            Pseudonymizer::new(b"k").truncate(10).pseudonymise(&number),
+            &hex[..10]
+        );
+        assert_ne!(Pseudonymizer::new(b"j").pseudonymise(&number), hex);
+    }
+
+    #[test]
+    fn datasets_are_separate() {
+        let number = nhs::Number::from_str("6541003238").unwrap();
+        let plain = Pseudonymizer::new(b"k");
+        let a = plain.clone().dataset("a");
+
+        assert_ne!(a.pseudonymise(&number), plain.pseudonymise(&number));
+        assert_ne!(
+            a.pseudonymise(&number),
+            plain.clone().dataset("b").pseudonymise(&number)
+        );
+        assert_eq!(
+            a.pseudonymise(&number),
+            plain.dataset("a").pseudonymise(&number)
+        );
+    }
+
+    #[test]
+    fn salted_hashes() {
+        let number = nhs::Number::from_str("6541003238").unwrap();
+        let hash = |algorithm, encoding| hash_with_salt(&number, b"s", algorithm, encoding);
+
+        // `echo -n s6541003238 | sha256sum`
+        assert_eq!(
+            hash(Algorithm::Sha256, Encoding::Hex),
+            "0ab86b7a2f344f182bbe51b0ed3741f2b0dbc8db0270a1627a71d829effe2833"
+        );
+        assert_eq!(hash(Algorithm::Sha512, Encoding::Hex).len(), 128);
+        assert_eq!(hash(Algorithm::Blake3, Encoding::Hex).len(), 64);
+        assert_eq!(hash(Algorithm::Sha256, Encoding::Base64).len(), 44);
+        assert_ne!(
+            hash(Algorithm::Sha256, Encoding::Hex),
+            hash_with_salt(&number, b"t", Algorithm::Sha256, Encoding::Hex)
+        );
+    }
+
+    #[test]
+    fn schemes_share_pseudonyms() {
+        let pseudonymizer = Pseudonymizer::new(b"k");
+        let chi = chi::Number::from_str("0101990014").unwrap();
+        let nhs = nhs::Number::from_str("0101990014").unwrap();
+
+        assert_eq!(
+            pseudonymizer.pseudonymise(&chi),
+            pseudonymizer.pseudonymise(&nhs)
+        );
+        assert_eq!(
+            format!("{:?}", pseudonymizer),
+            "Pseudonymizer { encoding: Hex, length: None, dataset: None, key_id: None }"
+        );
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Audit hooks for privacy operations.
+//!
+//! An [`Auditor`] attached to a [`Pseudonymizer`](super::Pseudonymizer), a
+//! [`TokenVault`](super::vault::TokenVault) or a redaction
+//! [`Policy`](crate::redact::Policy) is told about every operation they
+//! perform. Events never carry the numbers or tokens involved.
+
+use crate::scheme::Scheme;
+use std::fmt;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// A privacy operation.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Operation {
+    Pseudonymise,
+    Tokenise,
+    Detokenise,
+    Retokenise,
+    Redact,
+}
+
+impl Operation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operation::Pseudonymise => "pseudonymise",
+            Operation::Tokenise => "tokenise",
+            Operation::Detokenise => "detokenise",
+            Operation::Retokenise => "retokenise",
+            Operation::Redact => "redact",
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Non-identifying metadata about an operation.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Event {
+    pub operation: Operation,
+    /// The schemes the number validates under. Empty when the number has an
+    /// invalid check digit or could not be recovered.
+    pub schemes: Vec<Scheme>,
+    pub timestamp: SystemTime,
+    /// The id of the key used, if known.
+    pub key_id: Option<String>,
+    /// The dataset label, if any.
+    pub dataset: Option<String>,
+    /// Whether the operation succeeded.
+    pub success: bool,
+}
+
+impl Event {
+    pub(crate) fn new(operation: Operation, schemes: Vec<Scheme>) -> Self {
+        Event {
+            operation,
+            schemes,
+            timestamp: SystemTime::now(),
+            key_id: None,
+            dataset: None,
+            success: true,
+        }
+    }
+}
+
+/// A receiver of audit events.
+///
+/// Implemented for closures taking an [`Event`].
+pub trait PseudoAudit: Send + Sync {
+    fn record(&self, event: &Event);
+}
+
+impl<F> PseudoAudit for F
+where
+    F: Fn(&Event) + Send + Sync,
+{
+    fn record(&self, event: &Event) {
+        self(event)
+    }
+}
+
+/// A shareable handle to a [`PseudoAudit`].
+///
+/// # Examples
+///
+/// ```
+/// use heidi::nhs::Number;
+/// use heidi::pseudo::audit::{Auditor, Event, Operation};
+/// use heidi::pseudo::Pseudonymizer;
+/// use std::str::FromStr;
+/// use std::sync::{Arc, Mutex};
+///
+/// let log = Arc::new(Mutex::new(Vec::new()));
+/// let sink = log.clone();
+/// let auditor = Auditor::new(move |event: &Event| sink.lock().unwrap().push(event.clone()));
+/// let pseudonymizer = Pseudonymizer::new(b"key").key_id("k1").audit(auditor);
+///
+/// pseudonymizer.pseudonymise(&Number::from_str("6541003238").unwrap());
+///
+/// let log = log.lock().unwrap();
+/// assert_eq!(log[0].operation, Operation::Pseudonymise);
+/// assert_eq!(log[0].key_id.as_deref(), Some("k1"));
+/// ```
+#[derive(Clone)]
+pub struct Auditor(Arc<dyn PseudoAudit>);
+
+impl Auditor {
+    pub fn new<A: PseudoAudit + 'static>(audit: A) -> Self {
+        Auditor(Arc::new(audit))
+    }
+
+    pub(crate) fn record(&self, event: &Event) {
+        self.0.record(event)
+    }
+}
+
+impl PartialEq for Auditor {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for Auditor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Auditor")
+    }
+}
@@ -0,0 +1,418 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Reversible tokenisation under named, rotatable keys.
+//!
+//! Tokens are the deterministic AES-256-GCM-SIV encryption of the canonical
+//! compact form of a number, so the same number always gets the same token
+//! under the same key and tokens can be linked without being reversed.
+//!
+//! A token is tagged with the format version and the id of the key it was
+//! issued under, e.g. `v1.k2020.<base64url>`. The key id is authenticated so
+//! a token cannot be relabelled to another key.
+
+use super::audit::{Auditor, Event, Operation};
+use super::Encoding;
+use crate::number;
+use crate::scheme::Scheme;
+use aes_gcm_siv::aead::{Aead, KeyInit, Payload};
+use aes_gcm_siv::{Aes256GcmSiv, Nonce};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// The format version tag of the tokens issued by this module.
+const VERSION: &str = "v1";
+
+/// Errors when managing keys or reversing tokens.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum VaultError {
+    /// The key id is not in the vault.
+    UnknownKey(String),
+    /// The key id is empty or contains characters other than ASCII
+    /// alphanumerics, `-` and `_`.
+    InvalidKeyId(String),
+    /// The key id is already in the vault.
+    DuplicateKey(String),
+    /// The current key cannot be removed.
+    CurrentKey(String),
+    /// The token is not shaped like a token of a known version.
+    Malformed,
+    /// The token was not issued under the key it is tagged with.
+    Unauthentic,
+}
+
+impl Error for VaultError {}
+
+impl fmt::Display for VaultError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VaultError::UnknownKey(id) => write!(f, "Unknown key '{}'", id),
+            VaultError::InvalidKeyId(id) => write!(f, "Invalid key id '{}'", id),
+            VaultError::DuplicateKey(id) => write!(f, "Key '{}' already exists", id),
+            VaultError::CurrentKey(id) => write!(f, "Key '{}' is the current key", id),
+            VaultError::Malformed => write!(f, "Malformed token"),
+            VaultError::Unauthentic => write!(f, "Token does not verify under its key"),
+        }
+    }
+}
+
+/// A keystore issuing and reversing tokens.
+///
+/// New tokens are issued under the current key. Tokens issued under any
+/// other key in the vault can still be reversed or re-issued under the
+/// current key, which allows rotating a compromised key without losing
+/// linkage.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::nhs::Number;
+/// use heidi::pseudo::vault::TokenVault;
+/// use std::str::FromStr;
+///
+/// let mut vault = TokenVault::new("k2020", [1; 32]).unwrap();
+/// let number = Number::from_str("6541003238").unwrap();
+/// let old = vault.tokenise(&number);
+///
+/// vault.add_key("k2021", [2; 32]).unwrap();
+/// vault.rotate("k2021").unwrap();
+///
+/// let new = vault.retokenise(&old).unwrap();
+///
+/// assert!(new.starts_with("v1.k2021."));
+/// assert_eq!(new, vault.tokenise(&number));
+/// assert_eq!(vault.detokenise(&old).unwrap().to_string(), "6541003238");
+/// ```
+#[derive(Clone)]
+pub struct TokenVault {
+    keys: BTreeMap<String, [u8; 32]>,
+    current: String,
+    audit: Option<Auditor>,
+}
+
+impl TokenVault {
+    /// Creates a vault with a single key, which becomes the current key.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [VaultError::InvalidKeyId] when the id cannot be used in a
+    /// token.
+    pub fn new(id: &str, key: [u8; 32]) -> Result<Self, VaultError> {
+        let mut vault = TokenVault {
+            keys: BTreeMap::new(),
+            current: id.to_string(),
+            audit: None,
+        };
+        vault.add_key(id, key)?;
+
+        Ok(vault)
+    }
+
+    /// Reports every tokenisation, detokenisation and retokenisation to the
+    /// given auditor.
+    pub fn audit(mut self, auditor: Auditor) -> Self {
+        self.audit = Some(auditor);
+        self
+    }
+
+    /// Adds a key without making it current.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [VaultError] when the id is invalid or already taken.
+    pub fn add_key(&mut self, id: &str, key: [u8; 32]) -> Result<(), VaultError> {
+        let valid = !id.is_empty()
+            && id
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+
+        if !valid {
+            return Err(VaultError::InvalidKeyId(id.to_string()));
+        }
+        if self.keys.contains_key(id) {
+            return Err(VaultError::DuplicateKey(id.to_string()));
+        }
+        self.keys.insert(id.to_string(), key);
+
+        Ok(())
+    }
+
+    /// Makes the given key the one new tokens are issued under.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [VaultError::UnknownKey] when the key is not in the vault.
+    pub fn rotate(&mut self, id: &str) -> Result<(), VaultError> {
+        if !self.keys.contains_key(id) {
+            return Err(VaultError::UnknownKey(id.to_string()));
+        }
+        self.current = id.to_string();
+
+        Ok(())
+    }
+
+    /// Removes a key. Tokens issued under it can no longer be reversed.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [VaultError] when the key is unknown or current.
+    pub fn remove_key(&mut self, id: &str) -> Result<(), VaultError> {
+        if id == self.current {
+            return Err(VaultError::CurrentKey(id.to_string()));
+        }
+        self.keys
+            .remove(id)
+            .map(|_| ())
+            .ok_or_else(|| VaultError::UnknownKey(id.to_string()))
+    }
+
+    /// The id of the key new tokens are issued under.
+    pub fn current_key(&self) -> &str {
+        &self.current
+    }
+
+    /// Returns the token for the given number under the current key.
+    pub fn tokenise<N: AsRef<number::Number>>(&self, number: &N) -> String {
+        let number = number.as_ref();
+
+        self.record(Operation::Tokenise, Some(number), Some(&self.current));
+        self.encrypt(number)
+    }
+
+    /// Returns the number the token was issued for.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [VaultError] when the token is malformed, its key is not in
+    /// the vault or it does not verify under its key.
+    pub fn detokenise(&self, token: &str) -> Result<number::Number, VaultError> {
+        let result = self.decrypt(token);
+
+        self.record(
+            Operation::Detokenise,
+            result.as_ref().ok(),
+            key_id(token).ok(),
+        );
+
+        result
+    }
+
+    /// Returns the token for the same number under the current key.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`TokenVault::detokenise`].
+    pub fn retokenise(&self, token: &str) -> Result<String, VaultError> {
+        let result = self.decrypt(token);
+
+        self.record(
+            Operation::Retokenise,
+            result.as_ref().ok(),
+            Some(&self.current),
+        );
+
+        result.map(|number| self.encrypt(&number))
+    }
+
+    fn encrypt(&self, number: &number::Number) -> String {
+        let canonical = number.to_string();
+        let ciphertext = self
+            .cipher(&self.current)
+            .encrypt(
+                &Nonce::default(),
+                Payload {
+                    msg: canonical.as_bytes(),
+                    aad: self.current.as_bytes(),
+                },
+            )
+            .expect("encryption of a 10 byte message never fails");
+
+        format!(
+            "{}.{}.{}",
+            VERSION,
+            self.current,
+            Encoding::Base64Url.encode(&ciphertext)
+        )
+    }
+
+    fn decrypt(&self, token: &str) -> Result<number::Number, VaultError> {
+        let (id, payload) = split(token)?;
+
+        if !self.keys.contains_key(id) {
+            return Err(VaultError::UnknownKey(id.to_string()));
+        }
+
+        let plaintext = self
+            .cipher(id)
+            .decrypt(
+                &Nonce::default(),
+                Payload {
+                    msg: &payload,
+                    aad: id.as_bytes(),
+                },
+            )
+            .map_err(|_| VaultError::Unauthentic)?;
+
+        std::str::from_utf8(&plaintext)
+            .ok()
+            .and_then(|canonical| number::Number::from_str(canonical).ok())
+            .ok_or(VaultError::Unauthentic)
+    }
+
+    /// Reports an operation to the auditor, if any. A missing number means
+    /// the operation failed.
+    fn record(&self, operation: Operation, number: Option<&number::Number>, key: Option<&str>) {
+        if let Some(auditor) = &self.audit {
+            let mut event = Event::new(operation, number.map(Scheme::detect).unwrap_or_default());
+            event.key_id = key.map(String::from);
+            event.success = number.is_some();
+            auditor.record(&event);
+        }
+    }
+
+    fn cipher(&self, id: &str) -> Aes256GcmSiv {
+        Aes256GcmSiv::new(&self.keys[id].into())
+    }
+}
+
+impl fmt::Debug for TokenVault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TokenVault")
+            .field("keys", &self.keys.keys().collect::<Vec<_>>())
+            .field("current", &self.current)
+            .field("audit", &self.audit)
+            .finish()
+    }
+}
+
+/// Returns the key id a token was issued under.
+///
+/// # Errors
+///
+/// Fails with [VaultError::Malformed] when the token is not shaped like a
+/// token.
+pub fn key_id(token: &str) -> Result<&str, VaultError> {
+    split(token).map(|(id, _)| id)
+}
+
+/// Splits a token into its key id and decoded payload.
+fn split(token: &str) -> Result<(&str, Vec<u8>), VaultError> {
+    let mut parts = token.splitn(3, '.');
+
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(VERSION), Some(id), Some(payload)) if !id.is_empty() => URL_SAFE_NO_PAD
+            .decode(payload)
+            .map(|payload| (id, payload))
+            .map_err(|_| VaultError::Malformed),
+        _ => Err(VaultError::Malformed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number() -> number::Number {
+        number::Number::from_str("6541003238").unwrap()
+    }
+
+    #[test]
+    fn deterministic_and_reversible() {
+        let vault = TokenVault::new("a", [7; 32]).unwrap();
+        let token = vault.tokenise(&number());
+
+        assert_eq!(token, vault.tokenise(&number()));
+        assert!(!token.contains("6541003238"));
+        assert_eq!(key_id(&token), Ok("a"));
+        assert_eq!(vault.detokenise(&token), Ok(number()));
+    }
+
+    #[test]
+    fn rejects_tampering() {
+        let mut vault = TokenVault::new("a", [7; 32]).unwrap();
+        vault.add_key("b", [8; 32]).unwrap();
+
+        let token = vault.tokenise(&number());
+        let relabelled = token.replacen(".a.", ".b.", 1);
+
+        assert_eq!(vault.detokenise(&relabelled), Err(VaultError::Unauthentic));
+        assert_eq!(vault.detokenise("v2.a.AAAA"), Err(VaultError::Malformed));
+        assert_eq!(
+            vault.detokenise("v1.c.AAAA"),
+            Err(VaultError::UnknownKey("c".to_string()))
+        );
+    }
+
+    #[test]
+    fn audit_events() {
+        use std::sync::{Arc, Mutex};
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let sink = log.clone();
+        let vault =
+            TokenVault::new("a", [7; 32])
+                .unwrap()
+                .audit(Auditor::new(move |event: &Event| {
+                    sink.lock().unwrap().push(event.clone())
+                }));
+        let token = vault.tokenise(&number());
+
+        vault.retokenise(&token).unwrap();
+        vault.detokenise("v1.b.AAAA").unwrap_err();
+
+        let log = log.lock().unwrap();
+        let summary: Vec<_> = log
+            .iter()
+            .map(|e| (e.operation, e.key_id.as_deref(), e.success, e.schemes.len()))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                (Operation::Tokenise, Some("a"), true, 1),
+                (Operation::Retokenise, Some("a"), true, 1),
+                (Operation::Detokenise, Some("b"), false, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn key_management() {
+        let mut vault = TokenVault::new("a", [7; 32]).unwrap();
+        let token = vault.tokenise(&number());
+
+        assert_eq!(
+            vault.add_key("a", [0; 32]),
+            Err(VaultError::DuplicateKey("a".to_string()))
+        );
+        assert_eq!(
+            vault.add_key("b.c", [0; 32]),
+            Err(VaultError::InvalidKeyId("b.c".to_string()))
+        );
+        assert_eq!(
+            vault.remove_key("a"),
+            Err(VaultError::CurrentKey("a".to_string()))
+        );
+
+        vault.add_key("b", [8; 32]).unwrap();
+        vault.rotate("b").unwrap();
+
+        let rotated = vault.retokenise(&token).unwrap();
+
+        vault.remove_key("a").unwrap();
+
+        assert_eq!(vault.current_key(), "b");
+        assert_eq!(vault.detokenise(&rotated), Ok(number()));
+        assert_eq!(
+            vault.detokenise(&token),
+            Err(VaultError::UnknownKey("a".to_string()))
+        );
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! QR codes of NHS and CHI Numbers for appointment letters and patient-held
+//! records.
+//!
+//! A code carries either the bare 10 digits, which any scanner can type into
+//! a search box, or a FHIR Identifier that tells the scheme apart as well.
+//!
+//! ```
+//! use heidi::nhs::Number;
+//! use heidi::qr::{Payload, Qr};
+//! use std::str::FromStr;
+//!
+//! let number = Number::from_str("6541003238").unwrap();
+//! let qr = Qr::nhs(&number, Payload::Identifier);
+//!
+//! assert_eq!(
+//!     qr.data(),
+//!     r#"{"system":"https://fhir.nhs.uk/Id/nhs-number","value":"6541003238"}"#
+//! );
+//! assert!(qr.to_svg(4).starts_with("<?xml"));
+//! ```
+//!
+//! Requires the `qr` feature.
+
+use crate::fhir;
+use crate::number;
+use crate::scheme::Scheme;
+use crate::{chi, nhs};
+use qrcode::render::{svg, unicode};
+use qrcode::{EcLevel, QrCode};
+
+/// What a QR code carries.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Payload {
+    /// The 10 digits.
+    Number,
+    /// A FHIR Identifier as compact JSON.
+    Identifier,
+}
+
+/// A QR code of a number.
+#[derive(Clone)]
+pub struct Qr {
+    data: String,
+    code: QrCode,
+}
+
+impl Qr {
+    /// Encodes an NHS Number.
+    pub fn nhs(number: &nhs::Number, payload: Payload) -> Self {
+        Qr::new(Scheme::Nhs, number.as_ref(), payload)
+    }
+
+    /// Encodes a CHI Number.
+    pub fn chi(number: &chi::Number, payload: Payload) -> Self {
+        Qr::new(Scheme::Chi, number.as_ref(), payload)
+    }
+
+    /// Encodes a Health and Care Number, as given by
+    /// [`Scheme::validate`](crate::scheme::Scheme::validate).
+    pub fn hcn(number: &number::Number, payload: Payload) -> Self {
+        Qr::new(Scheme::Hcn, number, payload)
+    }
+
+    fn new(scheme: Scheme, number: &number::Number, payload: Payload) -> Self {
+        let data = match payload {
+            Payload::Number => number.to_string(),
+            Payload::Identifier => fhir::identifier(scheme, number).to_string(),
+        };
+        // Medium error correction survives creased and smudged letters, and
+        // the longest identifier still fits in a version 5 code.
+        let code = QrCode::with_error_correction_level(&data, EcLevel::M)
+            .expect("Identifiers are far below the capacity of a QR code");
+
+        Qr { data, code }
+    }
+
+    /// The data a scanner reads.
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+
+    /// The amount of modules on each side, without the quiet zone.
+    pub fn width(&self) -> usize {
+        self.code.width()
+    }
+
+    /// Renders the code as an SVG document, taking `module` pixels for each
+    /// module and including the quiet zone.
+    pub fn to_svg(&self, module: u32) -> String {
+        self.code
+            .render::<svg::Color>()
+            .module_dimensions(module, module)
+            .build()
+    }
+
+    /// Renders the code with Unicode half blocks, two modules per character
+    /// vertically, so it can be scanned off a terminal.
+    pub fn to_text(&self) -> String {
+        self.code
+            .render::<unicode::Dense1x2>()
+            .dark_color(unicode::Dense1x2::Light)
+            .light_color(unicode::Dense1x2::Dark)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn payloads() {
+        let number = chi::Number::from_str("0101990014").unwrap();
+        let bare = Qr::chi(&number, Payload::Number);
+        let identifier = Qr::chi(&number, Payload::Identifier);
+
+        assert_eq!(bare.data(), "0101990014");
+        assert_eq!(bare.width(), 21);
+        assert!(identifier
+            .data()
+            .contains("https://fhir.nhs.scot/Id/chi-number"));
+        assert!(identifier.width() <= 37);
+        assert_eq!(
+            bare.to_text().lines().count(),
+            (bare.width() + 8).div_ceil(2)
+        );
+    }
+}
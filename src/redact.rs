@@ -0,0 +1,301 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Redaction of identifiers found in arbitrary content.
+//!
+//! Redaction relies on the [scanner](crate::scan) to find candidates and
+//! replaces them according to a [`Policy`].
+
+pub mod csv;
+pub mod json;
+
+use crate::any::AnyNumber;
+use crate::pseudo::audit::{Auditor, Event, Operation};
+use crate::pseudo::Pseudonymizer;
+use crate::scan::{Finding, ScanOptions, Scanner};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+/// How to replace a candidate.
+#[derive(PartialEq, Clone, Debug)]
+pub enum Replacement {
+    /// Replaces the whole candidate with the given text.
+    Mask(String),
+    /// Replaces all digits but the last 4 with `*`, keeping separators.
+    KeepLast4,
+    /// Replaces the candidate with the schemes it validates under, e.g.
+    /// `[NHS]` or `[NHS,CHI]`. Candidates that do not validate become
+    /// `[REDACTED]`.
+    SchemeTag,
+    /// Replaces the candidate with a deterministic pseudonym so the same
+    /// identifier always gets the same token.
+    Pseudonym(Pseudonyms),
+}
+
+/// A source of deterministic pseudonyms.
+///
+/// Candidates are compared by their digits so `654 100 3238` and
+/// `6541003238` get the same token.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::redact::{redact_text, Policy, Pseudonyms, Replacement};
+///
+/// let policy = Policy::new(Replacement::Pseudonym(Pseudonyms::sequential()));
+/// let redaction = redact_text("6541003238 0101990014 654 100 3238", &policy);
+///
+/// assert_eq!(redaction.text, "ID-000001 ID-000002 ID-000001");
+/// ```
+#[derive(Clone)]
+pub struct Pseudonyms(Source);
+
+#[derive(Clone)]
+enum Source {
+    Sequential(Arc<Mutex<HashMap<String, usize>>>),
+    Keyed(Pseudonymizer),
+}
+
+impl Pseudonyms {
+    /// Tokens numbered in order of appearance. Consistent within the life of
+    /// this value and its clones, e.g. a single run.
+    pub fn sequential() -> Self {
+        Pseudonyms(Source::Sequential(Arc::new(Mutex::new(HashMap::new()))))
+    }
+
+    /// Tokens derived with HMAC-SHA256 under the given key. Consistent for
+    /// as long as the same key is used.
+    ///
+    /// See [`Pseudonymizer`] for finer control over the tokens.
+    pub fn keyed(key: &[u8]) -> Self {
+        Pseudonyms::with(Pseudonymizer::new(key).truncate(16))
+    }
+
+    /// Tokens derived by the given pseudonymizer.
+    pub fn with(pseudonymizer: Pseudonymizer) -> Self {
+        Pseudonyms(Source::Keyed(pseudonymizer))
+    }
+
+    /// Returns the token for the given digits.
+    pub fn token(&self, digits: &str) -> String {
+        match &self.0 {
+            Source::Sequential(seen) => {
+                let mut seen = seen.lock().unwrap_or_else(|err| err.into_inner());
+                let next = seen.len() + 1;
+                let id = *seen.entry(digits.to_string()).or_insert(next);
+
+                format!("ID-{:06}", id)
+            }
+            Source::Keyed(pseudonymizer) => format!("PSN-{}", pseudonymizer.derive(digits)),
+        }
+    }
+}
+
+impl PartialEq for Pseudonyms {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Source::Sequential(a), Source::Sequential(b)) => Arc::ptr_eq(a, b),
+            (Source::Keyed(a), Source::Keyed(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Debug for Pseudonyms {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.0 {
+            Source::Sequential(_) => write!(f, "Pseudonyms::Sequential"),
+            Source::Keyed(_) => write!(f, "Pseudonyms::Keyed"),
+        }
+    }
+}
+
+/// What to redact and how.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Policy {
+    pub replacement: Replacement,
+    /// Which candidates to redact. By default every candidate is redacted,
+    /// even those with an invalid check digit.
+    pub options: ScanOptions,
+    /// Receives an event for every replaced candidate.
+    pub audit: Option<Auditor>,
+}
+
+impl Policy {
+    pub fn new(replacement: Replacement) -> Self {
+        Policy {
+            replacement,
+            options: ScanOptions::default(),
+            audit: None,
+        }
+    }
+
+    /// Returns the text to use instead of the given candidate.
+    pub(crate) fn replace(&self, original: &str) -> String {
+        let digits: String = original.chars().filter(|c| c.is_ascii_digit()).collect();
+
+        if let Some(auditor) = &self.audit {
+            let schemes = AnyNumber::from_str(&digits)
+                .map(|number| number.schemes().to_vec())
+                .unwrap_or_default();
+            auditor.record(&Event::new(Operation::Redact, schemes));
+        }
+
+        match &self.replacement {
+            Replacement::Mask(mask) => mask.clone(),
+            Replacement::KeepLast4 => {
+                let hidden = digits.len().saturating_sub(4);
+                let mut seen = 0;
+
+                original
+                    .chars()
+                    .map(|c| {
+                        if c.is_ascii_digit() {
+                            seen += 1;
+                            if seen <= hidden {
+                                return '*';
+                            }
+                        }
+                        c
+                    })
+                    .collect()
+            }
+            Replacement::SchemeTag => match AnyNumber::from_str(&digits) {
+                Ok(number) if !number.schemes().is_empty() => {
+                    let tags: Vec<String> = number
+                        .schemes()
+                        .iter()
+                        .map(|scheme| scheme.as_str().to_uppercase())
+                        .collect();
+
+                    format!("[{}]", tags.join(","))
+                }
+                _ => "[REDACTED]".to_string(),
+            },
+            Replacement::Pseudonym(pseudonyms) => pseudonyms.token(&digits),
+        }
+    }
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy::new(Replacement::Mask("[REDACTED]".to_string()))
+    }
+}
+
+/// The outcome of a redaction.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Redaction {
+    /// The redacted content.
+    pub text: String,
+    /// The redacted candidates with their spans in the original content.
+    pub findings: Vec<Finding>,
+}
+
+/// Replaces the identifiers found in the given text according to the policy.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::redact::{redact_text, Policy, Replacement};
+///
+/// let policy = Policy::new(Replacement::KeepLast4);
+/// let redaction = redact_text("Patient 654 100 3238 called.", &policy);
+///
+/// assert_eq!(redaction.text, "Patient *** *** 3238 called.");
+/// assert_eq!(redaction.findings.len(), 1);
+/// ```
+pub fn redact_text(input: &str, policy: &Policy) -> Redaction {
+    let scanner = Scanner::new(policy.options.clone());
+    let mut text = String::with_capacity(input.len());
+    let mut findings = Vec::new();
+    let mut cursor = 0;
+
+    for finding in scanner.find_iter(input.as_bytes()) {
+        let original = &input[finding.span.clone()];
+
+        text.push_str(&input[cursor..finding.span.start]);
+        text.push_str(&policy.replace(original));
+        cursor = finding.span.end;
+        findings.push(finding);
+    }
+
+    text.push_str(&input[cursor..]);
+
+    Redaction { text, findings }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheme::Scheme;
+
+    #[test]
+    fn mask() {
+        let redaction = redact_text("a 6541003238 b 6541003237", &Policy::default());
+
+        assert_eq!(redaction.text, "a [REDACTED] b [REDACTED]");
+    }
+
+    #[test]
+    fn scheme_tag() {
+        let policy = Policy::new(Replacement::SchemeTag);
+        let redaction = redact_text("6541003238 0101990014 6541003237", &policy);
+
+        assert_eq!(redaction.text, "[NHS] [CHI] [REDACTED]");
+    }
+
+    #[test]
+    fn scheme_tag_outside_every_scheme() {
+        let policy = Policy::new(Replacement::SchemeTag);
+
+        assert_eq!(policy.replace("8931774583"), "[REDACTED]");
+    }
+
+    #[test]
+    fn valid_only() {
+        let mut policy = Policy::new(Replacement::KeepLast4);
+        policy.options.valid_only = true;
+
+        let redaction = redact_text("6541003238 6541003237", &policy);
+
+        assert_eq!(redaction.text, "******3238 6541003237");
+    }
+
+    #[test]
+    fn keyed_pseudonyms() {
+        let policy = Policy::new(Replacement::Pseudonym(Pseudonyms::keyed(b"study-1")));
+        let first = redact_text("6541003238", &policy);
+        let second = redact_text("Seen 654-100-3238 again", &policy);
+        let other = Policy::new(Replacement::Pseudonym(Pseudonyms::keyed(b"study-2")));
+        let third = redact_text("6541003238", &other);
+
+        assert!(first.text.starts_with("PSN-"));
+        assert_eq!(second.text, format!("Seen {} again", first.text));
+        assert_ne!(first.text, third.text);
+    }
+
+    #[test]
+    fn audit() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let sink = log.clone();
+        let policy = Policy {
+            audit: Some(Auditor::new(move |event: &Event| {
+                sink.lock().unwrap().push(event.schemes.clone())
+            })),
+            ..Policy::default()
+        };
+
+        redact_text("6541003238 0101990014 6541003237", &policy);
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![vec![Scheme::Nhs], vec![Scheme::Chi], Vec::new()]
+        );
+    }
+}
@@ -0,0 +1,366 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Redaction of identifier columns in CSV content.
+//!
+//! Only the values of the identifier columns are rewritten. Every other byte
+//! of the input, quoting and line terminators included, is copied verbatim.
+
+use super::Policy;
+use crate::number;
+use csv_core::{ReadFieldResult, Reader, ReaderBuilder};
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+
+/// Amount of records inspected to detect identifier columns.
+const SAMPLE: usize = 64;
+
+/// Which columns hold identifiers.
+#[derive(PartialEq, Clone, Debug)]
+pub enum Columns {
+    /// Columns where most values in the first records are valid numbers.
+    Detect,
+    /// Columns with the given header names.
+    Named(Vec<String>),
+    /// Columns in the given zero-based positions.
+    Indices(Vec<usize>),
+}
+
+/// The outcome of a CSV redaction.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Report {
+    /// Amount of records read, the header excluded.
+    pub records: usize,
+    /// Amount of values replaced.
+    pub redacted: usize,
+    /// Zero-based positions of the redacted columns.
+    pub columns: Vec<usize>,
+}
+
+/// Rewrites CSV content with the values of identifier columns redacted.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::redact::csv::{Columns, Redactor};
+/// use heidi::redact::{Policy, Replacement};
+///
+/// let input = "id,nhs_number,note\n1,654 100 3238,\"a, b\"\r\n";
+/// let redactor = Redactor::new(
+///     Policy::new(Replacement::KeepLast4),
+///     Columns::Named(vec!["nhs_number".to_string()]),
+/// );
+/// let mut output = Vec::new();
+/// let report = redactor.redact(input.as_bytes(), &mut output).unwrap();
+///
+/// assert_eq!(
+///     String::from_utf8(output).unwrap(),
+///     "id,nhs_number,note\n1,*** *** 3238,\"a, b\"\r\n"
+/// );
+/// assert_eq!(report.redacted, 1);
+/// ```
+#[derive(PartialEq, Clone, Debug)]
+pub struct Redactor {
+    policy: Policy,
+    columns: Columns,
+    delimiter: u8,
+    has_headers: bool,
+}
+
+/// A field as found in the input.
+#[derive(Clone, Debug, Default)]
+struct Field {
+    /// The bytes as they were in the input, delimiters included.
+    raw: Vec<u8>,
+    /// The unescaped value.
+    value: Vec<u8>,
+}
+
+impl Redactor {
+    pub fn new(policy: Policy, columns: Columns) -> Self {
+        Redactor {
+            policy,
+            columns,
+            delimiter: b',',
+            has_headers: true,
+        }
+    }
+
+    /// Sets the field delimiter. Defaults to `,`.
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Sets whether the first record is a header. Defaults to `true`.
+    pub fn has_headers(mut self, has_headers: bool) -> Self {
+        self.has_headers = has_headers;
+        self
+    }
+
+    /// Reads CSV content from `reader` and writes it redacted to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [io::Error] when reading or writing fails, or when
+    /// [`Columns::Named`] refers to a column missing in the header.
+    pub fn redact<R: Read, W: Write>(&self, reader: R, mut writer: W) -> io::Result<Report> {
+        let mut records = Records::new(reader, self.delimiter);
+        let mut report = Report::default();
+        let mut pending = Vec::new();
+
+        if self.has_headers {
+            if let Some(header) = records.next_record()? {
+                if let Columns::Named(names) = &self.columns {
+                    report.columns = names
+                        .iter()
+                        .map(|name| {
+                            header
+                                .iter()
+                                .position(|field| field.value == name.as_bytes())
+                                .ok_or_else(|| {
+                                    io::Error::new(
+                                        io::ErrorKind::InvalidInput,
+                                        format!("Column '{}' not found in the header", name),
+                                    )
+                                })
+                        })
+                        .collect::<io::Result<_>>()?;
+                }
+                write_record(&mut writer, &header)?;
+            }
+        }
+
+        match &self.columns {
+            Columns::Named(_) => {}
+            Columns::Indices(indices) => report.columns = indices.clone(),
+            Columns::Detect => {
+                while pending.len() < SAMPLE {
+                    match records.next_record()? {
+                        Some(record) => pending.push(record),
+                        None => break,
+                    }
+                }
+                report.columns = detect(&pending);
+            }
+        }
+
+        for record in pending {
+            self.write_redacted(&mut writer, record, &mut report)?;
+        }
+
+        while let Some(record) = records.next_record()? {
+            self.write_redacted(&mut writer, record, &mut report)?;
+        }
+
+        writer.write_all(&records.trailing)?;
+        writer.flush()?;
+
+        Ok(report)
+    }
+
+    fn write_redacted<W: Write>(
+        &self,
+        writer: &mut W,
+        mut record: Vec<Field>,
+        report: &mut Report,
+    ) -> io::Result<()> {
+        report.records += 1;
+
+        for &idx in &report.columns {
+            if let Some(field) = record.get_mut(idx) {
+                let value = String::from_utf8_lossy(&field.value);
+
+                if value.bytes().any(|b| b.is_ascii_digit()) {
+                    let replacement = self.policy.replace(&value);
+
+                    field.raw = rewrite(&field.raw, &replacement, self.delimiter);
+                    report.redacted += 1;
+                }
+            }
+        }
+
+        write_record(writer, &record)
+    }
+}
+
+/// Returns the columns where most non-empty sampled values are valid numbers.
+fn detect(records: &[Vec<Field>]) -> Vec<usize> {
+    let width = records.iter().map(|r| r.len()).max().unwrap_or(0);
+
+    (0..width)
+        .filter(|&idx| {
+            let values: Vec<&[u8]> = records
+                .iter()
+                .filter_map(|record| record.get(idx))
+                .map(|field| field.value.as_slice())
+                .filter(|value| !value.is_empty())
+                .collect();
+            let valid = values
+                .iter()
+                .filter(|value| {
+                    std::str::from_utf8(value)
+                        .ok()
+                        .and_then(|value| number::Number::from_str(value).ok())
+                        .is_some()
+                })
+                .count();
+
+            !values.is_empty() && valid * 5 >= values.len() * 4
+        })
+        .collect()
+}
+
+/// Returns the raw bytes of a field with its value replaced, keeping the
+/// surrounding line terminators and delimiter as they were.
+fn rewrite(raw: &[u8], value: &str, delimiter: u8) -> Vec<u8> {
+    let is_terminator = |b: &u8| *b == b'\r' || *b == b'\n';
+    let lead = raw.iter().take_while(|b| is_terminator(b)).count();
+    let body = &raw[lead..];
+    let trail = match body.last() {
+        Some(&b) if b == delimiter => 1,
+        _ => body.iter().rev().take_while(|b| is_terminator(b)).count(),
+    };
+    let quoted = body.first() == Some(&b'"')
+        || value
+            .bytes()
+            .any(|b| b == delimiter || b == b'"' || is_terminator(&b));
+
+    let mut out = raw[..lead].to_vec();
+
+    if quoted {
+        out.push(b'"');
+        out.extend(value.replace('"', "\"\"").bytes());
+        out.push(b'"');
+    } else {
+        out.extend(value.bytes());
+    }
+    out.extend(&body[body.len() - trail..]);
+
+    out
+}
+
+fn write_record<W: Write>(writer: &mut W, record: &[Field]) -> io::Result<()> {
+    for field in record {
+        writer.write_all(&field.raw)?;
+    }
+
+    Ok(())
+}
+
+/// Splits the input into records keeping the raw bytes of every field.
+struct Records<R> {
+    reader: R,
+    parser: Reader,
+    input: Vec<u8>,
+    pos: usize,
+    eof: bool,
+    field: Field,
+    /// Bytes consumed after the last field, e.g. a final line terminator.
+    trailing: Vec<u8>,
+}
+
+impl<R: Read> Records<R> {
+    fn new(reader: R, delimiter: u8) -> Self {
+        Records {
+            reader,
+            parser: ReaderBuilder::new().delimiter(delimiter).build(),
+            input: Vec::new(),
+            pos: 0,
+            eof: false,
+            field: Field::default(),
+            trailing: Vec::new(),
+        }
+    }
+
+    fn next_record(&mut self) -> io::Result<Option<Vec<Field>>> {
+        let mut record = Vec::new();
+        let mut output = [0; 1024];
+
+        loop {
+            if self.pos == self.input.len() && !self.eof {
+                self.input.resize(64 * 1024, 0);
+                let read = self.reader.read(&mut self.input)?;
+
+                self.input.truncate(read);
+                self.pos = 0;
+                self.eof = read == 0;
+            }
+
+            let (result, nin, nout) = self.parser.read_field(&self.input[self.pos..], &mut output);
+
+            self.field
+                .raw
+                .extend_from_slice(&self.input[self.pos..self.pos + nin]);
+            self.field.value.extend_from_slice(&output[..nout]);
+            self.pos += nin;
+
+            match result {
+                ReadFieldResult::InputEmpty | ReadFieldResult::OutputFull => {}
+                ReadFieldResult::Field { record_end } => {
+                    record.push(std::mem::take(&mut self.field));
+
+                    if record_end {
+                        return Ok(Some(record));
+                    }
+                }
+                ReadFieldResult::End => {
+                    self.trailing.append(&mut self.field.raw);
+
+                    return Ok(None);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redact::Replacement;
+
+    fn run(redactor: &Redactor, input: &str) -> (String, Report) {
+        let mut output = Vec::new();
+        let report = redactor.redact(input.as_bytes(), &mut output).unwrap();
+
+        (String::from_utf8(output).unwrap(), report)
+    }
+
+    #[test]
+    fn detects_identifier_columns() {
+        let input = "a,b,c\r\n1,6541003238,\"x,\"\"y\"\"\"\r\n\r\n2,\"0101990014\",z";
+        let redactor = Redactor::new(Policy::default(), Columns::Detect);
+        let (output, report) = run(&redactor, input);
+
+        assert_eq!(
+            output,
+            "a,b,c\r\n1,[REDACTED],\"x,\"\"y\"\"\"\r\n\r\n2,\"[REDACTED]\",z"
+        );
+        assert_eq!(report.columns, vec![1]);
+        assert_eq!(report.records, 2);
+    }
+
+    #[test]
+    fn indices_without_headers() {
+        let input = "6541003238;1\n";
+        let redactor = Redactor::new(
+            Policy::new(Replacement::Mask("X;Y".to_string())),
+            Columns::Indices(vec![0]),
+        )
+        .delimiter(b';')
+        .has_headers(false);
+        let (output, _) = run(&redactor, input);
+
+        assert_eq!(output, "\"X;Y\";1\n");
+    }
+
+    #[test]
+    fn missing_column() {
+        let redactor = Redactor::new(Policy::default(), Columns::Named(vec!["nhs".into()]));
+
+        assert!(redactor.redact(&b"a,b\n"[..], Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,322 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Redaction of identifier values in JSON and NDJSON content.
+//!
+//! The input is walked token by token instead of being parsed into a tree so
+//! whitespace, key order and number formatting are preserved exactly. Only
+//! the redacted values change.
+//!
+//! Key paths are dot-separated object keys from the root of the document,
+//! e.g. `patient.nhsNumber`. Arrays are transparent so `identifier.value`
+//! matches every `value` in an `identifier` array. A `*` segment matches any
+//! key.
+
+use super::Policy;
+use crate::scan::Scanner;
+use std::io::{self, BufRead, Write};
+
+/// The outcome of an NDJSON redaction.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Report {
+    /// Amount of documents (lines) read.
+    pub documents: usize,
+    /// Amount of values replaced.
+    pub redacted: usize,
+}
+
+/// Rewrites JSON content with identifier values redacted.
+///
+/// Without key paths, every string (or number) whose whole value is a
+/// candidate identifier is redacted. With key paths, every value holding
+/// digits under those paths is redacted instead.
+///
+/// Redacted numbers become strings.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::redact::json::Redactor;
+/// use heidi::redact::Policy;
+///
+/// let input = r#"{"patient": {"nhsNumber": "654 100 3238", "ref": "6541003238"}}"#;
+/// let redactor = Redactor::new(Policy::default()).paths(&["patient.nhsNumber"]);
+///
+/// assert_eq!(
+///     redactor.redact(input),
+///     r#"{"patient": {"nhsNumber": "[REDACTED]", "ref": "6541003238"}}"#
+/// );
+/// ```
+#[derive(PartialEq, Clone, Debug)]
+pub struct Redactor {
+    policy: Policy,
+    paths: Vec<Vec<String>>,
+}
+
+/// An open object or array while walking the input.
+#[derive(Debug)]
+struct Frame {
+    object: bool,
+    key: Option<String>,
+    expects_key: bool,
+}
+
+impl Redactor {
+    pub fn new(policy: Policy) -> Self {
+        Redactor {
+            policy,
+            paths: Vec::new(),
+        }
+    }
+
+    /// Restricts redaction to the given key paths.
+    pub fn paths(mut self, paths: &[&str]) -> Self {
+        self.paths = paths
+            .iter()
+            .map(|path| path.split('.').map(String::from).collect())
+            .collect();
+        self
+    }
+
+    /// Returns the given JSON content with identifier values redacted.
+    ///
+    /// Malformed content is copied as is from the point it stops making
+    /// sense.
+    pub fn redact(&self, input: &str) -> String {
+        self.redact_counting(input).0
+    }
+
+    /// Reads NDJSON content from `reader` and writes it redacted to
+    /// `writer`, one document per line.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [io::Error] when reading or writing fails.
+    pub fn redact_ndjson<R: BufRead, W: Write>(
+        &self,
+        mut reader: R,
+        mut writer: W,
+    ) -> io::Result<Report> {
+        let mut report = Report::default();
+        let mut line = String::new();
+
+        while reader.read_line(&mut line)? > 0 {
+            let (text, redacted) = self.redact_counting(&line);
+
+            writer.write_all(text.as_bytes())?;
+            report.redacted += redacted;
+            if !line.trim().is_empty() {
+                report.documents += 1;
+            }
+            line.clear();
+        }
+
+        writer.flush()?;
+
+        Ok(report)
+    }
+
+    fn redact_counting(&self, input: &str) -> (String, usize) {
+        let bytes = input.as_bytes();
+        let mut out = String::with_capacity(input.len());
+        let mut stack: Vec<Frame> = Vec::new();
+        let mut redacted = 0;
+        let mut idx = 0;
+
+        while idx < bytes.len() {
+            match bytes[idx] {
+                b'{' | b'[' => {
+                    stack.push(Frame {
+                        object: bytes[idx] == b'{',
+                        key: None,
+                        expects_key: bytes[idx] == b'{',
+                    });
+                    out.push(bytes[idx] as char);
+                    idx += 1;
+                }
+                b'}' | b']' => {
+                    stack.pop();
+                    out.push(bytes[idx] as char);
+                    idx += 1;
+                }
+                b',' => {
+                    if let Some(frame) = stack.last_mut() {
+                        frame.expects_key = frame.object;
+                    }
+                    out.push(',');
+                    idx += 1;
+                }
+                b':' => {
+                    if let Some(frame) = stack.last_mut() {
+                        frame.expects_key = false;
+                    }
+                    out.push(':');
+                    idx += 1;
+                }
+                b'"' => {
+                    let end = match string_end(bytes, idx) {
+                        Some(end) => end,
+                        None => {
+                            out.push_str(&input[idx..]);
+                            break;
+                        }
+                    };
+                    let literal = &input[idx..end];
+                    let value: Option<String> = serde_json::from_str(literal).ok();
+
+                    match stack.last_mut() {
+                        Some(frame) if frame.expects_key => {
+                            frame.key = value;
+                            out.push_str(literal);
+                        }
+                        _ => match value.and_then(|value| self.replace(&stack, &value)) {
+                            Some(replacement) => {
+                                out.push_str(&replacement);
+                                redacted += 1;
+                            }
+                            None => out.push_str(literal),
+                        },
+                    }
+                    idx = end;
+                }
+                b'-' | b'0'..=b'9' => {
+                    let end = idx
+                        + bytes[idx..]
+                            .iter()
+                            .take_while(|b| {
+                                matches!(b, b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+                            })
+                            .count();
+                    let literal = &input[idx..end];
+
+                    match self.replace(&stack, literal) {
+                        Some(replacement) => {
+                            out.push_str(&replacement);
+                            redacted += 1;
+                        }
+                        None => out.push_str(literal),
+                    }
+                    idx = end;
+                }
+                _ => {
+                    let end = idx
+                        + input[idx..]
+                            .find(|c| "{}[],:\"-0123456789".contains(c))
+                            .unwrap_or(input.len() - idx);
+
+                    out.push_str(&input[idx..end]);
+                    idx = end;
+                }
+            }
+        }
+
+        (out, redacted)
+    }
+
+    /// Returns the JSON string literal to use instead of the given value, if
+    /// it has to be redacted.
+    fn replace(&self, stack: &[Frame], value: &str) -> Option<String> {
+        let redact = if self.paths.is_empty() {
+            let trimmed = value.trim();
+
+            Scanner::new(self.policy.options.clone())
+                .find_iter(trimmed.as_bytes())
+                .next()
+                .map(|finding| finding.span == (0..trimmed.len()))
+                .unwrap_or(false)
+        } else {
+            let path: Vec<&str> = stack
+                .iter()
+                .filter(|frame| frame.object)
+                .filter_map(|frame| frame.key.as_deref())
+                .collect();
+
+            self.paths.iter().any(|pattern| matches(pattern, &path))
+                && value.bytes().any(|b| b.is_ascii_digit())
+        };
+
+        if redact {
+            serde_json::to_string(&self.policy.replace(value)).ok()
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether the key path matches the pattern.
+fn matches(pattern: &[String], path: &[&str]) -> bool {
+    pattern.len() == path.len()
+        && pattern
+            .iter()
+            .zip(path)
+            .all(|(expected, key)| expected == "*" || expected == key)
+}
+
+/// Returns the offset right after the string literal starting at `start`.
+fn string_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut idx = start + 1;
+
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'\\' => idx += 2,
+            b'"' => return Some(idx + 1),
+            _ => idx += 1,
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preserves_layout() {
+        let input = "{\n  \"a\" : [ \"6541003238\", 6541003238, 12.5e3, true ],\n  \"b\": \"x\\\"6541003238\"\n}";
+        let output = Redactor::new(Policy::default()).redact(input);
+
+        assert_eq!(
+            output,
+            "{\n  \"a\" : [ \"[REDACTED]\", \"[REDACTED]\", 12.5e3, true ],\n  \"b\": \"x\\\"6541003238\"\n}"
+        );
+    }
+
+    #[test]
+    fn paths_through_arrays() {
+        let input = r#"{"identifier": [{"system": "x", "value": "123"}], "value": "6541003238"}"#;
+        let output = Redactor::new(Policy::default())
+            .paths(&["identifier.value"])
+            .redact(input);
+
+        assert_eq!(
+            output,
+            r#"{"identifier": [{"system": "x", "value": "[REDACTED]"}], "value": "6541003238"}"#
+        );
+    }
+
+    #[test]
+    fn ndjson() -> io::Result<()> {
+        let input = "{\"n\":\"6541003238\"}\n\n{\"n\":\"0101990014\"}";
+        let mut output = Vec::new();
+        let report =
+            Redactor::new(Policy::default()).redact_ndjson(input.as_bytes(), &mut output)?;
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"n\":\"[REDACTED]\"}\n\n{\"n\":\"[REDACTED]\"}"
+        );
+        assert_eq!(
+            report,
+            Report {
+                documents: 2,
+                redacted: 2
+            }
+        );
+
+        Ok(())
+    }
+}
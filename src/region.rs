@@ -0,0 +1,159 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Allocation regions of the shared 10-digit number space.
+//!
+//! NHS, CHI and H&C numbers share the same format and check digit so the
+//! region where a number was allocated is given by its leading digits:
+//!
+//! | From           | To             | Region                         |
+//! |----------------|----------------|--------------------------------|
+//! | `010 101 0000` | `311 299 9999` | Scotland (CHI)                 |
+//! | `320 000 0010` | `399 999 9999` | Northern Ireland (H&C)         |
+//! | `400 000 0000` | `499 999 9999` | England, Wales and Isle of Man |
+//! | `600 000 0000` | `799 999 9999` | England, Wales and Isle of Man |
+//! | `999 000 0000` | `999 999 9999` | Test numbers, never issued     |
+//!
+//! The constants give the same ranges as integers, e.g. to generate SQL
+//! `BETWEEN` clauses.
+
+use crate::number::Digit;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/// The numbers allocated in Scotland (CHI).
+pub const SCOTLAND: RangeInclusive<u64> = 101_010_000..=3_112_999_999;
+
+/// The numbers allocated in Northern Ireland (H&C).
+pub const NORTHERN_IRELAND: RangeInclusive<u64> = 3_200_000_010..=3_999_999_999;
+
+/// The numbers allocated in England, Wales and the Isle of Man.
+pub const ENGLAND_WALES: [RangeInclusive<u64>; 2] =
+    [4_000_000_000..=4_999_999_999, 6_000_000_000..=7_999_999_999];
+
+/// The numbers set aside for tests, never issued to patients.
+pub const TEST: RangeInclusive<u64> = 9_990_000_000..=9_999_999_999;
+
+/// The region a number was allocated for.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone, Debug)]
+pub enum Region {
+    EnglandWales,
+    Scotland,
+    NorthernIreland,
+    Test,
+    Unallocated,
+}
+
+impl Region {
+    /// Classifies the given main digits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heidi::nhs::Number;
+    /// use heidi::region::Region;
+    /// use std::str::FromStr;
+    ///
+    /// let number = Number::from_str("6541003238").unwrap();
+    ///
+    /// assert_eq!(Region::of(number.digits()), Region::EnglandWales);
+    /// ```
+    pub fn of(digits: &[Digit; 9]) -> Region {
+        // The check digit does not change the region so it is taken as 0.
+        let number = digits
+            .iter()
+            .fold(0u64, |acc, &digit| acc * 10 + u64::from(digit))
+            * 10;
+
+        [
+            Region::Scotland,
+            Region::NorthernIreland,
+            Region::EnglandWales,
+            Region::Test,
+        ]
+        .iter()
+        .copied()
+        .find(|region| region.ranges().iter().any(|range| range.contains(&number)))
+        .unwrap_or(Region::Unallocated)
+    }
+
+    /// The numbers allocated for the region, check digit included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heidi::region::Region;
+    ///
+    /// assert_eq!(Region::Test.ranges(), &[9_990_000_000..=9_999_999_999]);
+    /// assert!(Region::Unallocated.ranges().is_empty());
+    /// ```
+    pub fn ranges(&self) -> &'static [RangeInclusive<u64>] {
+        match self {
+            Region::EnglandWales => &ENGLAND_WALES,
+            Region::Scotland => std::slice::from_ref(&SCOTLAND),
+            Region::NorthernIreland => std::slice::from_ref(&NORTHERN_IRELAND),
+            Region::Test => std::slice::from_ref(&TEST),
+            Region::Unallocated => &[],
+        }
+    }
+
+    /// A short lowercase name for the region.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Region::EnglandWales => "england-wales",
+            Region::Scotland => "scotland",
+            Region::NorthernIreland => "northern-ireland",
+            Region::Test => "test",
+            Region::Unallocated => "unallocated",
+        }
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boundaries() {
+        assert_eq!(Region::of(&[0, 1, 0, 1, 0, 1, 0, 0, 0]), Region::Scotland);
+        assert_eq!(Region::of(&[3, 1, 1, 2, 9, 9, 9, 9, 9]), Region::Scotland);
+        assert_eq!(
+            Region::of(&[3, 2, 0, 0, 0, 0, 0, 0, 0]),
+            Region::Unallocated
+        );
+        assert_eq!(
+            Region::of(&[3, 2, 0, 0, 0, 0, 0, 0, 1]),
+            Region::NorthernIreland
+        );
+        assert_eq!(
+            Region::of(&[5, 0, 0, 0, 0, 0, 0, 0, 0]),
+            Region::Unallocated
+        );
+        assert_eq!(Region::of(&[9, 9, 9, 1, 2, 3, 4, 5, 6]), Region::Test);
+    }
+
+    #[test]
+    fn ranges() {
+        use crate::{chi, nhs};
+
+        assert!(nhs::VALID_RANGE
+            .iter()
+            .any(|range| range.contains(&6_541_003_238)));
+        assert!(!nhs::VALID_RANGE
+            .iter()
+            .any(|range| range.contains(&5_000_000_013)));
+        assert!(nhs::TEST_RANGE.contains(&9_990_001_235));
+        assert!(chi::VALID_RANGE.contains(&101_990_014));
+        assert_eq!(nhs::VALID_RANGE, ENGLAND_WALES);
+        assert!(!NORTHERN_IRELAND.contains(&3_200_000_009));
+    }
+}
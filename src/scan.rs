@@ -0,0 +1,692 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Scanning of arbitrary content for candidate identifiers.
+//!
+//! A candidate is a sequence of 10 digits not surrounded by other digits. It
+//! can be written compact (`6541003238`) or in groups of 3-3-4 separated by
+//! a single space or hyphen (`654 100 3238`, `654-100-3238`).
+//!
+//! Every finding carries a confidence score between 0 and 1 derived from its
+//! check digit and its context:
+//!
+//! * A preceding token such as “NHS No” or “CHI” raises the score.
+//! * A preceding token such as “Tel” or “+44”, or digits right before or
+//!   after the candidate, suggest a phone number or a longer code and lower
+//!   the score.
+//! * Candidates taken from inside a longer run of digits (only reported when
+//!   [`ScanOptions::longer_runs`] is set) score low.
+
+mod engine;
+pub mod sarif;
+
+use crate::any::AnyNumber;
+use crate::number::{self, Digit};
+use crate::scheme::Scheme;
+use engine::{digit_run, find_digit, Checksum};
+use memmap2::Mmap;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Read};
+use std::ops::Range;
+use std::path::Path;
+
+/// Options to tune what a scan reports.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct ScanOptions {
+    /// Only report candidates with a valid check digit.
+    pub valid_only: bool,
+    /// Also report valid candidates found inside longer runs of digits.
+    pub longer_runs: bool,
+    /// Only report candidates with at least this confidence.
+    pub min_confidence: f32,
+    /// Only report valid candidates for these schemes. All schemes when
+    /// empty.
+    pub schemes: Vec<Scheme>,
+}
+
+/// The way a candidate was written.
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
+pub enum Style {
+    /// `6541003238`
+    Compact,
+    /// `654 100 3238`
+    Spaced,
+    /// `654-100-3238`
+    Hyphenated,
+}
+
+/// A candidate identifier found in the scanned content.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Finding {
+    /// Byte offsets of the candidate in the scanned content.
+    pub span: Range<usize>,
+    /// The way the candidate was written.
+    pub style: Style,
+    /// The 10 digits of the candidate without any separator.
+    pub digits: String,
+    /// Whether the check digit validates.
+    pub valid: bool,
+    /// The schemes the candidate validates under, restricted to the ones
+    /// selected in [`ScanOptions::schemes`]. Empty when the check digit does
+    /// not validate.
+    pub schemes: Vec<Scheme>,
+    /// How likely the candidate is an actual identifier, from 0 to 1.
+    pub confidence: f32,
+}
+
+/// Finds candidate identifiers in arbitrary text.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::scan::{Scanner, Style};
+///
+/// let scanner = Scanner::default();
+/// let findings = scanner.scan("Patient 654-100-3238 was seen today.");
+///
+/// assert_eq!(findings[0].span, 8..20);
+/// assert_eq!(findings[0].style, Style::Hyphenated);
+/// assert!(findings[0].valid);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Scanner {
+    options: ScanOptions,
+}
+
+impl Scanner {
+    pub fn new(options: ScanOptions) -> Self {
+        Scanner { options }
+    }
+
+    /// Returns all candidates in the given text.
+    pub fn scan(&self, text: &str) -> Vec<Finding> {
+        self.find_iter(text.as_bytes()).collect()
+    }
+
+    /// Returns an iterator over the candidates read incrementally from the
+    /// given reader.
+    ///
+    /// Spans are byte offsets from the start of the stream. Candidates split
+    /// across reads are found as long as the reader eventually yields the
+    /// rest of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heidi::scan::Scanner;
+    ///
+    /// let input = "first 654 100 3238\nsecond 6541003238\n".as_bytes();
+    /// let findings: Vec<_> = Scanner::default()
+    ///     .scan_reader(input)
+    ///     .collect::<Result<_, _>>()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(findings[1].span, 26..36);
+    /// ```
+    pub fn scan_reader<R: Read>(&self, reader: R) -> StreamFindings<R> {
+        StreamFindings {
+            reader,
+            scanner: self.clone(),
+            buffer: Vec::new(),
+            base: 0,
+            emitted: 0,
+            pending: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Returns an iterator over the candidates in the given bytes.
+    pub fn find_iter<'s, 'h>(&'s self, haystack: &'h [u8]) -> Findings<'s, 'h> {
+        Findings {
+            scanner: self,
+            haystack,
+            idx: 0,
+            nested: Vec::new(),
+        }
+    }
+}
+
+/// An iterator over the candidates found by a [`Scanner`].
+#[derive(Debug)]
+pub struct Findings<'s, 'h> {
+    scanner: &'s Scanner,
+    haystack: &'h [u8],
+    idx: usize,
+    nested: Vec<Finding>,
+}
+
+impl<'s, 'h> Iterator for Findings<'s, 'h> {
+    type Item = Finding;
+
+    fn next(&mut self) -> Option<Finding> {
+        let haystack = self.haystack;
+        let options = &self.scanner.options;
+
+        if let Some(finding) = self.nested.pop() {
+            return Some(finding);
+        }
+
+        while let Some(start) = find_digit(haystack, self.idx) {
+            let run = digit_run(haystack, start);
+
+            match candidate(haystack, start, run) {
+                Some((end, style, digits)) => {
+                    self.idx = end;
+
+                    let detected = detect(&digits, options);
+                    let valid = detected.is_some();
+                    let wanted = match &detected {
+                        Some(schemes) => !schemes.is_empty(),
+                        None => !options.valid_only,
+                    };
+
+                    if !wanted {
+                        continue;
+                    }
+
+                    let confidence = confidence(haystack, start..end, valid, false);
+
+                    if confidence >= options.min_confidence {
+                        return Some(Finding {
+                            span: start..end,
+                            style,
+                            digits: digits.iter().map(|&d| (b'0' + d) as char).collect(),
+                            valid,
+                            schemes: detected.unwrap_or_default(),
+                            confidence,
+                        });
+                    }
+                }
+                None => {
+                    self.idx = start + run;
+
+                    if options.longer_runs && run > 10 {
+                        self.nested = nested(haystack, start..start + run, options);
+                        self.nested.reverse();
+
+                        if let Some(finding) = self.nested.pop() {
+                            return Some(finding);
+                        }
+                    }
+                }
+            }
+        }
+
+        self.idx = haystack.len();
+
+        None
+    }
+}
+
+/// Amount of bytes read from the underlying reader at once.
+const CHUNK: usize = 64 * 1024;
+
+/// Amount of bytes at the end of the buffer held back until more input
+/// arrives. Covers the longest candidate plus the context after it.
+const LOOKAHEAD: usize = 32;
+
+/// Amount of bytes kept before the first unprocessed byte so the context of
+/// the next candidates is still available.
+const LOOKBEHIND: usize = CONTEXT + 8;
+
+/// An iterator over the candidates found by a [`Scanner`] in a reader.
+///
+/// Only a sliding window of the input is kept in memory.
+#[derive(Debug)]
+pub struct StreamFindings<R> {
+    reader: R,
+    scanner: Scanner,
+    buffer: Vec<u8>,
+    /// Stream offset of the first byte in the buffer.
+    base: usize,
+    /// Stream offset up to which candidates have been reported.
+    emitted: usize,
+    pending: VecDeque<Finding>,
+    done: bool,
+}
+
+impl<R: Read> StreamFindings<R> {
+    /// Reads the next chunk and queues the candidates that can be safely
+    /// reported.
+    fn fill(&mut self) -> io::Result<()> {
+        let filled = self.buffer.len();
+        self.buffer.resize(filled + CHUNK, 0);
+
+        let read = loop {
+            match self.reader.read(&mut self.buffer[filled..]) {
+                Ok(read) => break read,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    self.buffer.truncate(filled);
+                    return Err(err);
+                }
+            }
+        };
+        self.buffer.truncate(filled + read);
+
+        let eof = read == 0;
+        let limit = if eof {
+            self.buffer.len()
+        } else {
+            self.buffer.len().saturating_sub(LOOKAHEAD)
+        };
+
+        for mut finding in self.scanner.find_iter(&self.buffer) {
+            if finding.span.start >= limit {
+                break;
+            }
+
+            finding.span = finding.span.start + self.base..finding.span.end + self.base;
+
+            if finding.span.start >= self.emitted {
+                self.pending.push_back(finding);
+            }
+        }
+
+        self.emitted = self.emitted.max(self.base + limit);
+
+        if eof {
+            self.done = true;
+        } else {
+            let keep = limit.saturating_sub(LOOKBEHIND);
+
+            self.buffer.drain(..keep);
+            self.base += keep;
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for StreamFindings<R> {
+    type Item = io::Result<Finding>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(finding) = self.pending.pop_front() {
+                return Some(Ok(finding));
+            }
+
+            if self.done {
+                return None;
+            }
+
+            if let Err(err) = self.fill() {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+/// Scans a file for candidate identifiers.
+///
+/// The file is memory-mapped so large files are never loaded in full.
+///
+/// # Examples
+///
+/// ```no_run
+/// use heidi::scan::{scan_file, ScanOptions};
+///
+/// let findings = scan_file("/var/log/app.log", &ScanOptions::default()).unwrap();
+///
+/// for finding in findings {
+///     println!("{:?} {}", finding.span, finding.digits);
+/// }
+/// ```
+///
+/// # Errors
+///
+/// Fails with [io::Error] when the file cannot be opened or mapped.
+pub fn scan_file<P: AsRef<Path>>(path: P, options: &ScanOptions) -> io::Result<Vec<Finding>> {
+    let file = File::open(path)?;
+
+    if file.metadata()?.len() == 0 {
+        return Ok(Vec::new());
+    }
+
+    // SAFETY: The map is read-only and dropped before returning. Concurrent
+    // truncation by another process is outside of what a scan can guard
+    // against.
+    let map = unsafe { Mmap::map(&file)? };
+
+    Ok(scan_bytes(&map, options))
+}
+
+/// Scans a byte slice for candidate identifiers.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::scan::{scan_bytes, ScanOptions};
+///
+/// let findings = scan_bytes(b"NHS: 654 100 3238.", &ScanOptions::default());
+///
+/// assert_eq!(findings[0].span, 5..17);
+/// assert_eq!(findings[0].digits, "6541003238");
+/// assert!(findings[0].valid);
+/// ```
+pub fn scan_bytes(haystack: &[u8], options: &ScanOptions) -> Vec<Finding> {
+    Scanner::new(options.clone()).find_iter(haystack).collect()
+}
+
+/// Tokens that announce an identifier right after them.
+const IDENTIFIER_TOKENS: &[&str] = &["nhs", "nhsno", "nhs no", "chi", "patient", "pt"];
+
+/// Tokens that announce a phone number right after them.
+const PHONE_TOKENS: &[&str] = &["tel", "phone", "mobile", "mob", "fax", "+44"];
+
+/// Amount of bytes before a candidate considered as its context.
+const CONTEXT: usize = 24;
+
+/// Scores how likely the candidate in `span` is an actual identifier.
+fn confidence(haystack: &[u8], span: Range<usize>, valid: bool, nested: bool) -> f32 {
+    if nested {
+        return 0.2;
+    }
+
+    let mut score: f32 = if valid { 0.6 } else { 0.1 };
+    let context = haystack[span.start.saturating_sub(CONTEXT)..span.start].to_ascii_lowercase();
+
+    if IDENTIFIER_TOKENS
+        .iter()
+        .any(|token| contains_token(&context, token.as_bytes()))
+    {
+        score += 0.35;
+    }
+
+    if PHONE_TOKENS
+        .iter()
+        .any(|token| contains_token(&context, token.as_bytes()))
+    {
+        score -= 0.4;
+    }
+
+    let before = &haystack[span.start.saturating_sub(2)..span.start];
+    let after = &haystack[span.end..haystack.len().min(span.end + 2)];
+    let adjacent = |bytes: &[u8]| {
+        bytes.len() == 2
+            && matches!(bytes[0], b' ' | b'-' | b'.' | b'(' | b')')
+            && bytes[1].is_ascii_digit()
+    };
+    let flipped: Vec<u8> = before.iter().rev().cloned().collect();
+
+    if adjacent(&flipped) || adjacent(after) || before.last() == Some(&b'+') {
+        score -= 0.3;
+    }
+
+    score.clamp(0.0, 1.0)
+}
+
+/// Whether `token` appears in `context` not glued to other letters.
+fn contains_token(context: &[u8], token: &[u8]) -> bool {
+    context
+        .windows(token.len())
+        .enumerate()
+        .any(|(idx, window)| {
+            let end = idx + token.len();
+
+            window == token
+                && (idx == 0 || !context[idx - 1].is_ascii_alphabetic())
+                && (end == context.len() || !context[end].is_ascii_alphabetic())
+        })
+}
+
+/// Returns the selected schemes the digits validate under, or `None` when the
+/// check digit does not validate.
+fn detect(digits: &[u8; 10], options: &ScanOptions) -> Option<Vec<Scheme>> {
+    let mut checksum = Checksum::default();
+    let mut main: [Digit; 9] = [0; 9];
+
+    for (slot, &digit) in main.iter_mut().zip(digits) {
+        checksum.push(digit);
+        *slot = Digit::from(digit);
+    }
+
+    if !checksum.verifies(digits[9]) {
+        return None;
+    }
+
+    let number = AnyNumber::from(number::Number::new(main).ok()?);
+
+    Some(
+        number
+            .schemes()
+            .iter()
+            .filter(|scheme| options.schemes.is_empty() || options.schemes.contains(scheme))
+            .cloned()
+            .collect(),
+    )
+}
+
+/// Returns the valid candidates inside a run of more than 10 digits.
+fn nested(haystack: &[u8], run: Range<usize>, options: &ScanOptions) -> Vec<Finding> {
+    let values: Vec<u8> = haystack[run.clone()].iter().map(|b| b - b'0').collect();
+
+    engine::valid_windows(&values)
+        .into_iter()
+        .filter_map(|offset| {
+            let span = run.start + offset..run.start + offset + 10;
+            let mut digits = [0; 10];
+            digits.copy_from_slice(&values[offset..offset + 10]);
+
+            let schemes = detect(&digits, options)?;
+            let confidence = confidence(haystack, span.clone(), true, true);
+
+            if schemes.is_empty() || confidence < options.min_confidence {
+                return None;
+            }
+
+            Some(Finding {
+                digits: String::from_utf8_lossy(&haystack[span.clone()]).into_owned(),
+                span,
+                style: Style::Compact,
+                valid: true,
+                schemes,
+                confidence,
+            })
+        })
+        .collect()
+}
+
+/// Returns the end, style and digit values of the candidate starting at
+/// `start` if any.
+fn candidate(haystack: &[u8], start: usize, run: usize) -> Option<(usize, Style, [u8; 10])> {
+    let mut digits = [0; 10];
+
+    match run {
+        10 => {
+            for (slot, byte) in digits.iter_mut().zip(&haystack[start..start + 10]) {
+                *slot = byte - b'0';
+            }
+
+            Some((start + 10, Style::Compact, digits))
+        }
+        3 => {
+            let sep = *haystack.get(start + 3)?;
+            let style = match sep {
+                b' ' => Style::Spaced,
+                b'-' => Style::Hyphenated,
+                _ => return None,
+            };
+
+            let second = start + 4;
+            if second >= haystack.len() || digit_run(haystack, second) != 3 {
+                return None;
+            }
+            if haystack.get(second + 3) != Some(&sep) {
+                return None;
+            }
+
+            let third = second + 4;
+            if third >= haystack.len() || digit_run(haystack, third) != 4 {
+                return None;
+            }
+
+            let groups = haystack[start..start + 3]
+                .iter()
+                .chain(&haystack[second..second + 3])
+                .chain(&haystack[third..third + 4]);
+
+            for (slot, byte) in digits.iter_mut().zip(groups) {
+                *slot = byte - b'0';
+            }
+
+            Some((third + 4, style, digits))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_all_styles() {
+        let text = b"a 6541003238 b 654 100 3238 c 654-100-3238";
+        let findings = scan_bytes(text, &ScanOptions::default());
+        let spans: Vec<_> = findings.iter().map(|f| (f.span.clone(), f.style)).collect();
+
+        assert_eq!(
+            spans,
+            vec![
+                (2..12, Style::Compact),
+                (15..27, Style::Spaced),
+                (30..42, Style::Hyphenated)
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_longer_runs() {
+        let text = b"65410032381 654 100 32381 654-100 3238";
+        let findings = scan_bytes(text, &ScanOptions::default());
+
+        assert!(findings.is_empty());
+    }
+
+    #[test]
+    fn text_spans_are_byte_offsets() {
+        let findings = Scanner::default().scan("naïve 6541003238");
+
+        assert_eq!(findings[0].span, 7..17);
+    }
+
+    #[test]
+    fn valid_only() {
+        let text = b"6541003238 6541003237";
+        let all = scan_bytes(text, &ScanOptions::default());
+        let valid = scan_bytes(
+            text,
+            &ScanOptions {
+                valid_only: true,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(all.len(), 2);
+        assert!(!all[1].valid);
+        assert_eq!(valid.len(), 1);
+    }
+
+    #[test]
+    fn context_raises_confidence() {
+        let scanner = Scanner::default();
+        let plain = scanner.scan("ref 6541003238");
+        let labelled = scanner.scan("NHS No: 6541003238");
+
+        assert!(labelled[0].confidence > plain[0].confidence);
+    }
+
+    #[test]
+    fn phone_context_lowers_confidence() {
+        let scanner = Scanner::default();
+        let plain = scanner.scan("ref 654 100 3238");
+        let phone = scanner.scan("Tel: +44 654 100 3238");
+
+        assert!(phone[0].confidence < plain[0].confidence);
+    }
+
+    #[test]
+    fn longer_runs() {
+        let scanner = Scanner::new(ScanOptions {
+            longer_runs: true,
+            ..Default::default()
+        });
+        let findings = scanner.scan("id 99654100323855");
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].span, 5..15);
+        assert!(findings[0].confidence < 0.5);
+    }
+
+    #[test]
+    fn min_confidence() {
+        let scanner = Scanner::new(ScanOptions {
+            min_confidence: 0.9,
+            ..Default::default()
+        });
+        let findings = scanner.scan("6541003238 NHS: 6541003238");
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].span, 16..26);
+    }
+
+    /// Yields the input a few bytes at a time to split candidates.
+    struct Trickle<'a>(&'a [u8], usize);
+
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let size = self.1.min(self.0.len()).min(buf.len());
+
+            buf[..size].copy_from_slice(&self.0[..size]);
+            self.0 = &self.0[size..];
+
+            Ok(size)
+        }
+    }
+
+    #[test]
+    fn stream_matches_in_memory_scan() {
+        let text = "NHS No 654 100 3238, tel 01234 6541003238 and 6541003237 end. ".repeat(20);
+        let scanner = Scanner::default();
+        let expected = scanner.scan(&text);
+
+        for size in &[1, 3, 7, 13, 64] {
+            let findings: Vec<_> = scanner
+                .scan_reader(Trickle(text.as_bytes(), *size))
+                .collect::<io::Result<_>>()
+                .unwrap();
+
+            assert_eq!(findings, expected, "reads of {} bytes", size);
+        }
+    }
+
+    #[test]
+    fn scheme_tagging() {
+        let findings = Scanner::default().scan("6541003238 0101990014 3200000015");
+
+        assert_eq!(findings[0].schemes, vec![Scheme::Nhs]);
+        assert_eq!(findings[1].schemes, vec![Scheme::Chi]);
+        assert_eq!(findings[2].schemes, vec![Scheme::Hcn]);
+    }
+
+    #[test]
+    fn restricted_schemes() {
+        let scanner = Scanner::new(ScanOptions {
+            schemes: vec![Scheme::Chi],
+            valid_only: true,
+            ..Default::default()
+        });
+        let findings = scanner.scan("6541003238 0101990014 6541003237");
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].digits, "0101990014");
+        assert_eq!(findings[0].schemes, vec![Scheme::Chi]);
+    }
+}
@@ -0,0 +1,191 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Low-level primitives for the scanner.
+//!
+//! Digits are located 8 bytes at a time (SWAR) and check digits are verified
+//! while digits are consumed, so no candidate is parsed from a string.
+
+/// `0x01` in every byte.
+const LO: u64 = 0x0101_0101_0101_0101;
+/// `0x80` in every byte.
+const HI: u64 = 0x8080_8080_8080_8080;
+
+/// Returns a word with the high bit set on every byte that is an ASCII digit.
+///
+/// See “Determine if a word has a byte between m and n” in
+/// <https://graphics.stanford.edu/~seander/bithacks.html>. Each byte is
+/// reduced to 7 bits first so no carry or borrow crosses byte boundaries and
+/// the result is exact per byte.
+#[inline]
+fn digit_mask(word: u64) -> u64 {
+    let low = word & (LO * 127);
+
+    ((LO * (127 + u64::from(b'9' + 1))) - low)
+        & !word
+        & (low + LO * (127 - u64::from(b'0' - 1)))
+        & HI
+}
+
+#[inline]
+fn load(haystack: &[u8], idx: usize) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&haystack[idx..idx + 8]);
+
+    u64::from_le_bytes(bytes)
+}
+
+/// Returns the offset of the first ASCII digit at or after `from`.
+#[inline]
+pub(crate) fn find_digit(haystack: &[u8], from: usize) -> Option<usize> {
+    let mut idx = from;
+
+    while idx + 8 <= haystack.len() {
+        let mask = digit_mask(load(haystack, idx));
+
+        if mask != 0 {
+            return Some(idx + (mask.trailing_zeros() / 8) as usize);
+        }
+        idx += 8;
+    }
+
+    haystack[idx.min(haystack.len())..]
+        .iter()
+        .position(u8::is_ascii_digit)
+        .map(|pos| idx + pos)
+}
+
+/// Returns the length of the run of ASCII digits starting at `start`.
+#[inline]
+pub(crate) fn digit_run(haystack: &[u8], start: usize) -> usize {
+    let mut idx = start;
+
+    while idx + 8 <= haystack.len() {
+        let mask = !digit_mask(load(haystack, idx)) & HI;
+
+        if mask != 0 {
+            return idx - start + (mask.trailing_zeros() / 8) as usize;
+        }
+        idx += 8;
+    }
+
+    idx - start
+        + haystack[idx.min(haystack.len())..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count()
+}
+
+/// A Modulus 11 weighted sum computed as digits are consumed.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Checksum {
+    sum: u32,
+    count: u32,
+}
+
+impl Checksum {
+    /// Consumes the next main digit.
+    #[inline]
+    pub(crate) fn push(&mut self, digit: u8) {
+        self.sum += u32::from(digit) * (10 - self.count);
+        self.count += 1;
+    }
+
+    /// Whether the given check digit matches the consumed main digits.
+    #[inline]
+    pub(crate) fn verifies(&self, checkdigit: u8) -> bool {
+        expected(self.sum) == Some(checkdigit)
+    }
+}
+
+/// Returns the check digit for a weighted sum, if any.
+#[inline]
+fn expected(sum: u32) -> Option<u8> {
+    match 11 - sum % 11 {
+        11 => Some(0),
+        10 => None,
+        digit => Some(digit as u8),
+    }
+}
+
+/// Returns the offsets of every 10-digit window with a valid check digit in
+/// a run of digit values.
+///
+/// The weighted sum of each window is derived from the previous one in
+/// constant time.
+pub(crate) fn valid_windows(run: &[u8]) -> Vec<usize> {
+    let mut found = Vec::new();
+
+    if run.len() < 10 {
+        return found;
+    }
+
+    let mut checksum = Checksum::default();
+    let mut total: u32 = 0;
+
+    for &digit in &run[..9] {
+        checksum.push(digit);
+        total += u32::from(digit);
+    }
+
+    let mut sum = checksum.sum;
+
+    for start in 0..=run.len() - 10 {
+        if expected(sum) == Some(run[start + 9]) {
+            found.push(start);
+        }
+
+        if start + 10 < run.len() {
+            let (first, next) = (u32::from(run[start]), u32::from(run[start + 9]));
+
+            sum = sum + total + 2 * next - 11 * first;
+            total = total - first + next;
+        }
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swar_matches_scalar() {
+        let haystack: Vec<u8> = (0..=255u8).chain(b"ab/:09xyz".iter().cloned()).collect();
+
+        for from in 0..haystack.len() {
+            let expected = haystack[from..]
+                .iter()
+                .position(u8::is_ascii_digit)
+                .map(|pos| from + pos);
+
+            assert_eq!(find_digit(&haystack, from), expected, "from {}", from);
+        }
+
+        let digits = b"x0123456789012345678y";
+        assert_eq!(digit_run(digits, 1), 19);
+        assert_eq!(digit_run(digits, 18), 2);
+    }
+
+    #[test]
+    fn sliding_windows_match_brute_force() {
+        let run: Vec<u8> = b"9965410032385501019900148"
+            .iter()
+            .map(|b| b - b'0')
+            .collect();
+        let brute: Vec<usize> = (0..=run.len() - 10)
+            .filter(|&start| {
+                let mut checksum = Checksum::default();
+                run[start..start + 9].iter().for_each(|&d| checksum.push(d));
+                checksum.verifies(run[start + 9])
+            })
+            .collect();
+
+        assert_eq!(valid_windows(&run), brute);
+        assert!(brute.contains(&2));
+    }
+}
@@ -0,0 +1,275 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! SARIF 2.1.0 output for scan findings.
+//!
+//! See <https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html>
+//!
+//! Results never include the matched text so the log can be shared with
+//! dashboards without leaking the identifiers it reports.
+
+use super::Finding;
+use crate::scheme::Scheme;
+use serde_json::{json, Value};
+use std::io::{self, Write};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// A rule a finding can be reported under.
+struct Rule {
+    id: &'static str,
+    name: &'static str,
+    description: &'static str,
+}
+
+const RULES: &[Rule] = &[
+    Rule {
+        id: "heidi/nhs-number",
+        name: "NhsNumber",
+        description: "A valid NHS number.",
+    },
+    Rule {
+        id: "heidi/chi-number",
+        name: "ChiNumber",
+        description: "A valid CHI number.",
+    },
+    Rule {
+        id: "heidi/hcn-number",
+        name: "HcnNumber",
+        description: "A valid Health and Care Number.",
+    },
+    Rule {
+        id: "heidi/unverified-number",
+        name: "UnverifiedNumber",
+        description:
+            "A 10-digit number shaped like a health identifier with an invalid check digit.",
+    },
+];
+
+/// Accumulates findings for several files into a single SARIF log.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::scan::sarif::SarifLog;
+/// use heidi::scan::Scanner;
+///
+/// let content = "line one\nNHS: 654 100 3238\n";
+/// let findings = Scanner::default().scan(content);
+/// let mut log = SarifLog::new();
+///
+/// log.add("logs/app.log", content.as_bytes(), &findings);
+///
+/// let json = log.to_json();
+/// let region = &json["runs"][0]["results"][0]["locations"][0]["physicalLocation"]["region"];
+///
+/// assert_eq!(region["startLine"], 2);
+/// assert_eq!(region["startColumn"], 6);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SarifLog {
+    results: Vec<Value>,
+}
+
+impl SarifLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the findings of a file. The content is used to compute lines
+    /// and columns from the finding spans.
+    pub fn add(&mut self, uri: &str, content: &[u8], findings: &[Finding]) {
+        let mut cursor = Cursor::default();
+
+        for finding in findings {
+            let (start_line, start_column) = cursor.advance(content, finding.span.start);
+            let (end_line, end_column) = cursor.clone().advance(content, finding.span.end);
+            let rule = rule_for(finding);
+
+            self.results.push(json!({
+                "ruleId": RULES[rule].id,
+                "ruleIndex": rule,
+                "level": level(finding.confidence),
+                "rank": (f64::from(finding.confidence) * 100.0).round(),
+                "message": { "text": message(finding) },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": uri },
+                        "region": {
+                            "startLine": start_line,
+                            "startColumn": start_column,
+                            "endLine": end_line,
+                            "endColumn": end_column,
+                            "byteOffset": finding.span.start,
+                            "byteLength": finding.span.len(),
+                        }
+                    }
+                }],
+                "properties": { "confidence": finding.confidence },
+            }));
+        }
+    }
+
+    /// Returns the SARIF log as a JSON value.
+    pub fn to_json(&self) -> Value {
+        let rules: Vec<Value> = RULES
+            .iter()
+            .map(|rule| {
+                json!({
+                    "id": rule.id,
+                    "name": rule.name,
+                    "shortDescription": { "text": rule.description },
+                })
+            })
+            .collect();
+
+        json!({
+            "$schema": SCHEMA,
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "heidi",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "informationUri": env!("CARGO_PKG_HOMEPAGE"),
+                        "rules": rules,
+                    }
+                },
+                "columnKind": "unicodeCodePoints",
+                "results": self.results,
+            }]
+        })
+    }
+
+    /// Writes the SARIF log as pretty-printed JSON.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [io::Error] when the writer fails.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut writer, &self.to_json())?;
+        writeln!(writer)
+    }
+}
+
+/// Tracks the line and column of a byte offset while moving forward.
+#[derive(Clone, Debug)]
+struct Cursor {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Default for Cursor {
+    fn default() -> Self {
+        Cursor {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
+impl Cursor {
+    /// Moves to `offset` and returns its 1-based line and column.
+    fn advance(&mut self, content: &[u8], offset: usize) -> (usize, usize) {
+        let offset = offset.min(content.len());
+
+        for &byte in &content[self.offset.min(offset)..offset] {
+            if byte == b'\n' {
+                self.line += 1;
+                self.column = 1;
+            } else if byte & 0xC0 != 0x80 {
+                // Continuation bytes of a UTF-8 sequence do not start a new
+                // code point.
+                self.column += 1;
+            }
+        }
+        self.offset = offset;
+
+        (self.line, self.column)
+    }
+}
+
+/// Returns the most specific rule for the finding.
+fn rule_for(finding: &Finding) -> usize {
+    if finding.schemes.contains(&Scheme::Chi) {
+        1
+    } else if finding.schemes.contains(&Scheme::Nhs) {
+        0
+    } else if finding.schemes.contains(&Scheme::Hcn) {
+        2
+    } else {
+        3
+    }
+}
+
+fn level(confidence: f32) -> &'static str {
+    if confidence >= 0.8 {
+        "error"
+    } else if confidence >= 0.5 {
+        "warning"
+    } else {
+        "note"
+    }
+}
+
+fn message(finding: &Finding) -> String {
+    format!(
+        "Possible health identifier ({:.0}% confidence).",
+        finding.confidence * 100.0
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::Scanner;
+
+    #[test]
+    fn rules_and_levels() {
+        let content = "NHS No 6541003238\n0101990014 6541003237 3200000015";
+        let findings = Scanner::default().scan(content);
+        let mut log = SarifLog::new();
+
+        log.add("a.txt", content.as_bytes(), &findings);
+
+        let json = log.to_json();
+        let results = json["runs"][0]["results"].as_array().unwrap();
+        let rules: Vec<&str> = results
+            .iter()
+            .map(|r| r["ruleId"].as_str().unwrap())
+            .collect();
+
+        assert_eq!(
+            rules,
+            vec![
+                "heidi/nhs-number",
+                "heidi/chi-number",
+                "heidi/unverified-number",
+                "heidi/hcn-number"
+            ]
+        );
+        assert_eq!(results[0]["level"], "error");
+        assert_eq!(results[2]["level"], "note");
+        assert!(!json.to_string().contains("6541003238"));
+    }
+
+    #[test]
+    fn columns_count_code_points() {
+        let content = "naïve 6541003238";
+        let findings = Scanner::default().scan(content);
+        let mut log = SarifLog::new();
+
+        log.add("a.txt", content.as_bytes(), &findings);
+
+        let json = log.to_json();
+        let region = &json["runs"][0]["results"][0]["locations"][0]["physicalLocation"]["region"];
+
+        assert_eq!(region["startColumn"], 7);
+        assert_eq!(region["byteOffset"], 7);
+    }
+}
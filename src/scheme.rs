@@ -0,0 +1,140 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! The health identifier schemes supported by `heidi`.
+
+use crate::codec::to_packed;
+use crate::error::{ErrorKind, ValidationError};
+use crate::{chi, nhs, number, region};
+use std::fmt;
+
+/// A health identifier scheme.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone, Debug)]
+pub enum Scheme {
+    Nhs,
+    Chi,
+    /// The Health and Care Number of Northern Ireland.
+    Hcn,
+}
+
+impl Scheme {
+    /// Returns the schemes the given number validates under.
+    ///
+    /// A number with a valid check digit belongs to the schemes whose ranges
+    /// hold it: [`nhs::VALID_RANGE`] and [`nhs::TEST_RANGE`] for NHS,
+    /// [`chi::VALID_RANGE`] with a plausible date of birth for CHI and
+    /// [`region::NORTHERN_IRELAND`] for H&C. Numbers outside all of them
+    /// give no scheme.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heidi::number::Number;
+    /// use heidi::scheme::Scheme;
+    /// use std::str::FromStr;
+    ///
+    /// let number = Number::from_str("0101990014").unwrap();
+    ///
+    /// assert_eq!(Scheme::detect(&number), vec![Scheme::Chi]);
+    /// ```
+    pub fn detect(number: &number::Number) -> Vec<Scheme> {
+        [Scheme::Nhs, Scheme::Chi, Scheme::Hcn]
+            .iter()
+            .copied()
+            .filter(|scheme| scheme.allocates(number))
+            .collect()
+    }
+
+    /// Whether the number is in a range the scheme allocates from.
+    pub fn allocates(&self, number: &number::Number) -> bool {
+        let value = to_packed(number);
+
+        match self {
+            Scheme::Nhs => {
+                nhs::VALID_RANGE.iter().any(|range| range.contains(&value))
+                    || nhs::TEST_RANGE.contains(&value)
+            }
+            Scheme::Chi => {
+                chi::VALID_RANGE.contains(&value) && chi::Number::new(*number.digits()).is_ok()
+            }
+            Scheme::Hcn => region::NORTHERN_IRELAND.contains(&value),
+        }
+    }
+
+    /// Validates a number of the scheme, which must be both well formed and
+    /// in the range the scheme allocates from. H&C numbers have no type of
+    /// their own so they are returned as a plain [`number::Number`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heidi::scheme::Scheme;
+    ///
+    /// assert!(Scheme::Hcn.validate("320 000 0015").is_ok());
+    /// assert!(Scheme::Hcn.validate("6541003238").is_err());
+    /// ```
+    pub fn validate(&self, input: &str) -> Result<number::Number, ValidationError> {
+        let number: number::Number = input.parse()?;
+
+        self.check(&number)?;
+
+        Ok(number)
+    }
+
+    /// Fails when a number with a valid check digit is outside the ranges
+    /// of the scheme, see [`Scheme::allocates`].
+    pub fn check(&self, number: &number::Number) -> Result<(), ValidationError> {
+        if self.allocates(number) {
+            Ok(())
+        } else {
+            Err(ValidationError::with_kind(
+                ErrorKind::Other,
+                &format!("{} is not in the range of {} numbers", number, self.label()),
+            ))
+        }
+    }
+
+    /// A short lowercase name for the scheme.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scheme::Nhs => "nhs",
+            Scheme::Chi => "chi",
+            Scheme::Hcn => "hcn",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Scheme::Nhs => "NHS",
+            Scheme::Chi => "CHI",
+            Scheme::Hcn => "H&C",
+        }
+    }
+}
+
+impl fmt::Display for Scheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn detects_by_range() {
+        let detect = |input: &str| Scheme::detect(&number::Number::from_str(input).unwrap());
+
+        assert_eq!(detect("6541003238"), vec![Scheme::Nhs]);
+        assert_eq!(detect("9990001235"), vec![Scheme::Nhs]);
+        assert_eq!(detect("0101990014"), vec![Scheme::Chi]);
+        assert_eq!(detect("3200000015"), vec![Scheme::Hcn]);
+        assert_eq!(detect("8931774583"), vec![]);
+        assert_eq!(detect("5000000013"), vec![]);
+    }
+}
@@ -0,0 +1,103 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! SeaORM value types so entities can have NHS and CHI number columns that
+//! are validated when read.
+//!
+//! Numbers are stored as strings of 10 digits. Reading a number that is not
+//! valid fails with a type error.
+//!
+//! ```ignore
+//! #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+//! #[sea_orm(table_name = "patient")]
+//! pub struct Model {
+//!     #[sea_orm(primary_key)]
+//!     pub id: i32,
+//!     pub nhs_number: heidi::nhs::Number,
+//! }
+//! ```
+//!
+//! Requires the `sea-orm` feature.
+
+use crate::number::from_bigint;
+use crate::{chi, nhs};
+use sea_orm::sea_query::{
+    ArrayType, ColumnType, Nullable, StringLen, Value, ValueType, ValueTypeErr,
+};
+use sea_orm::{ColIdx, DbErr, QueryResult, TryGetError, TryGetable};
+use std::str::FromStr;
+
+macro_rules! value_type {
+    ($number:ty, $name:expr) => {
+        impl From<$number> for Value {
+            fn from(number: $number) -> Self {
+                Value::String(Some(Box::new(number.to_string())))
+            }
+        }
+
+        impl Nullable for $number {
+            fn null() -> Value {
+                Value::String(None)
+            }
+        }
+
+        impl ValueType for $number {
+            fn try_from(value: Value) -> Result<Self, ValueTypeErr> {
+                match value {
+                    Value::String(Some(text)) => {
+                        <$number>::from_str(&text).map_err(|_| ValueTypeErr)
+                    }
+                    Value::BigInt(Some(value)) => from_bigint(value).map_err(|_| ValueTypeErr),
+                    _ => Err(ValueTypeErr),
+                }
+            }
+
+            fn type_name() -> String {
+                $name.to_string()
+            }
+
+            fn array_type() -> ArrayType {
+                ArrayType::String
+            }
+
+            fn column_type() -> ColumnType {
+                ColumnType::String(StringLen::N(10))
+            }
+        }
+
+        impl TryGetable for $number {
+            fn try_get_by<I: ColIdx>(res: &QueryResult, index: I) -> Result<Self, TryGetError> {
+                let text = String::try_get_by(res, index)?;
+
+                <$number>::from_str(&text)
+                    .map_err(|err| TryGetError::DbErr(DbErr::Type(err.to_string())))
+            }
+        }
+    };
+}
+
+value_type!(nhs::Number, "nhs::Number");
+value_type!(chi::Number, "chi::Number");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values() {
+        let nhs = nhs::Number::from_str("6541003238").unwrap();
+        let value = Value::from(nhs.clone());
+
+        assert_eq!(value, Value::String(Some(Box::new("6541003238".into()))));
+        assert_eq!(<nhs::Number as ValueType>::try_from(value).ok(), Some(nhs));
+        assert_eq!(
+            <chi::Number as ValueType>::unwrap(Value::BigInt(Some(101990014))).to_string(),
+            "0101990014"
+        );
+        assert!(<nhs::Number as ValueType>::try_from(Value::from("6541003239")).is_err());
+        assert_eq!(<chi::Number as Nullable>::null(), Value::String(None));
+    }
+}
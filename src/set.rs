@@ -0,0 +1,365 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! A compact set of numbers to answer “have I seen this number before?”.
+//!
+//! Numbers are keyed by their 9 main digits given the check digit is derived
+//! from them. The exact representation is a bitset split in pages of 65,536
+//! bits that are only allocated when a number falls in them. The whole range
+//! of numbers takes at most 125MB.
+//!
+//! When memory is tighter than that, a bloom filter can be used instead at
+//! the cost of false positives.
+
+use crate::number::Digit;
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8; 8] = b"HEIDISET";
+const VERSION: u8 = 1;
+const KEYS: u64 = 1_000_000_000;
+const PAGE_WORDS: usize = 1024;
+const PAGE_BITS: u64 = (PAGE_WORDS * 64) as u64;
+
+#[derive(PartialEq, Clone, Debug)]
+enum Repr {
+    Bits(Vec<Option<Box<[u64]>>>),
+    Bloom { words: Vec<u64>, hashes: u32 },
+}
+
+/// A set of numbers.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::nhs::Number;
+/// use heidi::set::NumberSet;
+/// use std::str::FromStr;
+///
+/// let mut set = NumberSet::new();
+/// let number = Number::from_str("6541003238").unwrap();
+///
+/// assert!(set.insert(number.digits()));
+/// assert!(!set.insert(number.digits()));
+/// assert!(set.contains(number.digits()));
+/// ```
+#[derive(PartialEq, Clone, Debug)]
+pub struct NumberSet {
+    repr: Repr,
+    len: usize,
+}
+
+impl NumberSet {
+    /// Creates an empty exact set.
+    pub fn new() -> Self {
+        let pages = KEYS.div_ceil(PAGE_BITS) as usize;
+
+        NumberSet {
+            repr: Repr::Bits(vec![None; pages]),
+            len: 0,
+        }
+    }
+
+    /// Creates an empty bloom filter sized for `capacity` numbers with the
+    /// given false positive rate (e.g. `0.001`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use heidi::set::NumberSet;
+    ///
+    /// let mut set = NumberSet::bloom(1_000, 0.01);
+    ///
+    /// set.insert(&[6, 5, 4, 1, 0, 0, 3, 2, 3]);
+    ///
+    /// assert!(set.contains(&[6, 5, 4, 1, 0, 0, 3, 2, 3]));
+    /// assert!(!set.is_exact());
+    /// ```
+    pub fn bloom(capacity: usize, fp_rate: f64) -> Self {
+        let capacity = capacity.max(1) as f64;
+        let fp_rate = fp_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-capacity * fp_rate.ln() / (ln2 * ln2)).ceil().max(64.0);
+        let hashes = ((bits / capacity) * ln2).round().max(1.0) as u32;
+
+        NumberSet {
+            repr: Repr::Bloom {
+                words: vec![0; (bits as usize).div_ceil(64)],
+                hashes,
+            },
+            len: 0,
+        }
+    }
+
+    /// Whether the set answers without false positives.
+    pub fn is_exact(&self) -> bool {
+        matches!(self.repr, Repr::Bits(_))
+    }
+
+    /// Number of insertions that were not already in the set.
+    ///
+    /// For a bloom filter this is an approximation.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Adds the number with the given main digits. Returns whether it was
+    /// not present before.
+    pub fn insert(&mut self, digits: &[Digit; 9]) -> bool {
+        let key = key(digits);
+        let fresh = match &mut self.repr {
+            Repr::Bits(pages) => {
+                let page = pages[(key / PAGE_BITS) as usize]
+                    .get_or_insert_with(|| vec![0; PAGE_WORDS].into_boxed_slice());
+                let offset = key % PAGE_BITS;
+                let (word, mask) = ((offset / 64) as usize, 1 << (offset % 64));
+                let fresh = page[word] & mask == 0;
+
+                page[word] |= mask;
+
+                fresh
+            }
+            Repr::Bloom { words, hashes } => {
+                let mut fresh = false;
+
+                for bit in bloom_bits(key, *hashes, words.len()) {
+                    let (word, mask) = (bit / 64, 1 << (bit % 64));
+
+                    fresh |= words[word] & mask == 0;
+                    words[word] |= mask;
+                }
+
+                fresh
+            }
+        };
+
+        if fresh {
+            self.len += 1;
+        }
+
+        fresh
+    }
+
+    /// Whether the number with the given main digits is in the set.
+    pub fn contains(&self, digits: &[Digit; 9]) -> bool {
+        let key = key(digits);
+
+        match &self.repr {
+            Repr::Bits(pages) => match &pages[(key / PAGE_BITS) as usize] {
+                Some(page) => {
+                    let offset = key % PAGE_BITS;
+
+                    page[(offset / 64) as usize] & (1 << (offset % 64)) != 0
+                }
+                None => false,
+            },
+            Repr::Bloom { words, hashes } => bloom_bits(key, *hashes, words.len())
+                .all(|bit| words[bit / 64] & (1 << (bit % 64)) != 0),
+        }
+    }
+
+    /// Serialises the set into the given writer.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [io::Error] when the writer fails.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+
+        match &self.repr {
+            Repr::Bits(pages) => {
+                writer.write_all(&[0])?;
+                writer.write_all(&(self.len as u64).to_le_bytes())?;
+
+                let present = pages.iter().filter(|page| page.is_some()).count();
+                writer.write_all(&(present as u32).to_le_bytes())?;
+
+                for (idx, page) in pages.iter().enumerate() {
+                    if let Some(page) = page {
+                        writer.write_all(&(idx as u32).to_le_bytes())?;
+                        write_words(&mut writer, page)?;
+                    }
+                }
+            }
+            Repr::Bloom { words, hashes } => {
+                writer.write_all(&[1])?;
+                writer.write_all(&(self.len as u64).to_le_bytes())?;
+                writer.write_all(&hashes.to_le_bytes())?;
+                writer.write_all(&(words.len() as u64).to_le_bytes())?;
+                write_words(&mut writer, words)?;
+            }
+        }
+
+        writer.flush()
+    }
+
+    /// Deserialises a set previously written with [`NumberSet::write_to`].
+    ///
+    /// ```
+    /// use heidi::set::NumberSet;
+    ///
+    /// let mut set = NumberSet::new();
+    /// set.insert(&[6, 5, 4, 1, 0, 0, 3, 2, 3]);
+    ///
+    /// let mut buffer = Vec::new();
+    /// set.write_to(&mut buffer).unwrap();
+    ///
+    /// assert_eq!(NumberSet::read_from(&buffer[..]).unwrap(), set);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with [io::Error] when the reader fails or the content is not a
+    /// serialised set.
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("Not a serialised number set"));
+        }
+
+        let mut header = [0; 2];
+        reader.read_exact(&mut header)?;
+        if header[0] != VERSION {
+            return Err(invalid_data("Unsupported number set version"));
+        }
+
+        let len = read_u64(&mut reader)? as usize;
+
+        match header[1] {
+            0 => {
+                let mut set = NumberSet::new();
+                let present = read_u32(&mut reader)?;
+
+                if let Repr::Bits(pages) = &mut set.repr {
+                    for _ in 0..present {
+                        let idx = read_u32(&mut reader)? as usize;
+                        let page = pages
+                            .get_mut(idx)
+                            .ok_or_else(|| invalid_data("Page out of range"))?;
+
+                        *page = Some(read_words(&mut reader, PAGE_WORDS)?.into_boxed_slice());
+                    }
+                }
+                set.len = len;
+
+                Ok(set)
+            }
+            1 => {
+                let hashes = read_u32(&mut reader)?;
+                let count = read_u64(&mut reader)? as usize;
+                if count == 0 || hashes == 0 {
+                    return Err(invalid_data("Empty bloom filter"));
+                }
+                let words = read_words(&mut reader, count)?;
+
+                Ok(NumberSet {
+                    repr: Repr::Bloom { words, hashes },
+                    len,
+                })
+            }
+            _ => Err(invalid_data("Unknown number set kind")),
+        }
+    }
+}
+
+impl Default for NumberSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn key(digits: &[Digit; 9]) -> u64 {
+    digits
+        .iter()
+        .fold(0, |acc, &digit| acc * 10 + u64::from(digit % 10))
+}
+
+fn bloom_bits(key: u64, hashes: u32, words: usize) -> impl Iterator<Item = usize> {
+    let bits = (words * 64) as u64;
+    let h1 = splitmix64(key);
+    let h2 = splitmix64(h1) | 1;
+
+    (0..u64::from(hashes)).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % bits) as usize)
+}
+
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+fn write_words<W: Write>(writer: &mut W, words: &[u64]) -> io::Result<()> {
+    for word in words {
+        writer.write_all(&word.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+fn read_words<R: Read>(reader: &mut R, count: usize) -> io::Result<Vec<u64>> {
+    (0..count).map(|_| read_u64(reader)).collect()
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut buffer = [0; 4];
+    reader.read_exact(&mut buffer)?;
+
+    Ok(u32::from_le_bytes(buffer))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut buffer = [0; 8];
+    reader.read_exact(&mut buffer)?;
+
+    Ok(u64::from_le_bytes(buffer))
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_boundaries() {
+        let mut set = NumberSet::new();
+
+        assert!(set.insert(&[0; 9]));
+        assert!(set.insert(&[9; 9]));
+        assert!(!set.contains(&[0, 0, 0, 0, 0, 0, 0, 0, 1]));
+        assert!(set.contains(&[0; 9]));
+        assert!(set.contains(&[9; 9]));
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn bloom_roundtrip() -> io::Result<()> {
+        let mut set = NumberSet::bloom(100, 0.01);
+        set.insert(&[4, 0, 1, 0, 2, 3, 2, 1, 7]);
+
+        let mut buffer = Vec::new();
+        set.write_to(&mut buffer)?;
+        let restored = NumberSet::read_from(&buffer[..])?;
+
+        assert!(restored.contains(&[4, 0, 1, 0, 2, 3, 2, 1, 7]));
+        assert_eq!(restored, set);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(NumberSet::read_from(&b"NOTASET!"[..]).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Column types for sqlx so queries return numbers already validated.
+//!
+//! NHS and CHI numbers are encoded as `TEXT` of 10 digits and decoded from
+//! either `TEXT` or `BIGINT` columns in Postgres, MySQL and SQLite. Wrap them
+//! in [`BigInt`] to bind them to `BIGINT` columns instead. Decoding fails
+//! when the stored number is not valid.
+//!
+//! ```ignore
+//! let number: heidi::nhs::Number = sqlx::query_scalar("SELECT nhs_number FROM patient")
+//!     .fetch_one(&pool)
+//!     .await?;
+//! ```
+//!
+//! Requires the `sqlx` feature.
+
+use crate::codec::to_packed;
+use crate::error::ValidationError;
+use crate::number::{self, from_bigint, Digit};
+use crate::{chi, nhs};
+use sqlx::database::Database;
+use sqlx::decode::Decode;
+use sqlx::encode::{Encode, IsNull};
+use sqlx::error::BoxDynError;
+use sqlx::types::Type;
+use sqlx::{TypeInfo, ValueRef};
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+/// A number encoded as `BIGINT`, where leading zeroes are lost.
+///
+/// ```ignore
+/// sqlx::query("INSERT INTO patient (nhs_number) VALUES ($1)")
+///     .bind(heidi::sql::BigInt(number))
+///     .execute(&pool)
+///     .await?;
+/// ```
+#[derive(PartialEq, Clone, Debug)]
+pub struct BigInt<N>(pub N);
+
+impl<N, DB: Database> Type<DB> for BigInt<N>
+where
+    i64: Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <i64 as Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <i64 as Type<DB>>::compatible(ty)
+    }
+}
+
+impl<'q, N, DB: Database> Encode<'q, DB> for BigInt<N>
+where
+    N: AsRef<number::Number>,
+    i64: Encode<'q, DB>,
+{
+    fn encode_by_ref(
+        &self,
+        buf: &mut <DB as Database>::ArgumentBuffer<'q>,
+    ) -> Result<IsNull, BoxDynError> {
+        i64::try_from(to_packed(&self.0))?.encode(buf)
+    }
+}
+
+impl<'r, N, DB: Database> Decode<'r, DB> for BigInt<N>
+where
+    N: for<'a> TryFrom<&'a [Digit; 10], Error = ValidationError>,
+    i64: Decode<'r, DB>,
+{
+    fn decode(value: <DB as Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
+        Ok(BigInt(from_bigint(<i64 as Decode<DB>>::decode(value)?)?))
+    }
+}
+
+macro_rules! column {
+    ($number:ty) => {
+        impl<DB: Database> Type<DB> for $number
+        where
+            String: Type<DB>,
+            i64: Type<DB>,
+        {
+            fn type_info() -> DB::TypeInfo {
+                <String as Type<DB>>::type_info()
+            }
+
+            fn compatible(ty: &DB::TypeInfo) -> bool {
+                <String as Type<DB>>::compatible(ty) || <i64 as Type<DB>>::compatible(ty)
+            }
+        }
+
+        impl<'q, DB: Database> Encode<'q, DB> for $number
+        where
+            String: Encode<'q, DB>,
+        {
+            fn encode_by_ref(
+                &self,
+                buf: &mut <DB as Database>::ArgumentBuffer<'q>,
+            ) -> Result<IsNull, BoxDynError> {
+                self.to_string().encode(buf)
+            }
+        }
+
+        impl<'r, DB: Database> Decode<'r, DB> for $number
+        where
+            &'r str: Decode<'r, DB>,
+            i64: Decode<'r, DB> + Type<DB>,
+        {
+            fn decode(value: <DB as Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
+                let ty = value.type_info();
+                let bigint = !ty.is_null() && <i64 as Type<DB>>::compatible(&ty);
+
+                if bigint {
+                    Ok(from_bigint(<i64 as Decode<DB>>::decode(value)?)?)
+                } else {
+                    Ok(<$number>::from_str(<&str as Decode<DB>>::decode(value)?)?)
+                }
+            }
+        }
+    };
+}
+
+column!(nhs::Number);
+column!(chi::Number);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn bigints() {
+        let nhs: nhs::Number = from_bigint(6541003238).unwrap();
+        let chi: chi::Number = from_bigint(101990014).unwrap();
+
+        assert_eq!(nhs.to_string(), "6541003238");
+        assert_eq!(chi.to_string(), "0101990014");
+        assert_eq!(
+            from_bigint::<nhs::Number>(-6541003238).unwrap_err().kind(),
+            ErrorKind::Digit
+        );
+    }
+
+    #[test]
+    fn columns() {
+        fn column<DB: Database>()
+        where
+            nhs::Number: Type<DB> + for<'q> Encode<'q, DB> + for<'r> Decode<'r, DB>,
+            chi::Number: Type<DB> + for<'q> Encode<'q, DB> + for<'r> Decode<'r, DB>,
+        {
+        }
+
+        column::<sqlx::MySql>();
+        column::<sqlx::Postgres>();
+        column::<sqlx::Sqlite>();
+    }
+
+    #[test]
+    fn bigint_columns() {
+        use sqlx::sqlite::SqliteArgumentValue;
+
+        fn column<DB: Database>()
+        where
+            BigInt<nhs::Number>: Type<DB> + for<'q> Encode<'q, DB> + for<'r> Decode<'r, DB>,
+            BigInt<chi::Number>: Type<DB> + for<'q> Encode<'q, DB> + for<'r> Decode<'r, DB>,
+        {
+        }
+
+        column::<sqlx::MySql>();
+        column::<sqlx::Postgres>();
+        column::<sqlx::Sqlite>();
+
+        let chi = BigInt(chi::Number::from_str("0101990014").unwrap());
+        let mut buf = Vec::new();
+
+        let encoded = Encode::<sqlx::Sqlite>::encode_by_ref(&chi, &mut buf).unwrap();
+
+        assert!(matches!(encoded, IsNull::No));
+        assert!(matches!(buf[..], [SqliteArgumentValue::Int64(101990014)]));
+    }
+}
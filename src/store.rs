@@ -0,0 +1,265 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Persistent pseudonym mappings backed by SQLite.
+//!
+//! Sequential pseudonyms cannot be derived again from the original number so
+//! a store keeps every issued pseudonym per study. Separate runs, or separate
+//! machines sharing the database file, get the same pseudonym for the same
+//! number.
+//!
+//! Requires the `store` feature.
+
+use crate::number;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+pub use rusqlite::Error;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS pseudonym (
+    study TEXT NOT NULL,
+    original TEXT NOT NULL,
+    pseudonym TEXT NOT NULL,
+    issued_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (study, original),
+    UNIQUE (study, pseudonym)
+);
+";
+
+/// What a store keeps about the original numbers.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Retention {
+    /// Keeps the original numbers so pseudonyms can be reversed.
+    Originals,
+    /// Keeps only the SHA-256 digest of each original number salted with
+    /// the study. Pseudonyms cannot be reversed from the store alone but, as
+    /// the number space is small, the digests can be brute forced by anyone
+    /// with access to the database.
+    Digests,
+}
+
+/// A SQLite database of issued pseudonyms.
+///
+/// # Examples
+///
+/// ```
+/// use heidi::nhs::Number;
+/// use heidi::store::Store;
+/// use std::str::FromStr;
+///
+/// let store = Store::in_memory().unwrap();
+/// let a = Number::from_str("6541003238").unwrap();
+/// let b = Number::from_str("0101990014").unwrap();
+///
+/// assert_eq!(store.pseudonym("study-1", &a).unwrap(), "ID-000001");
+/// assert_eq!(store.pseudonym("study-1", &b).unwrap(), "ID-000002");
+/// assert_eq!(store.pseudonym("study-1", &a).unwrap(), "ID-000001");
+/// assert_eq!(store.pseudonym("study-2", &b).unwrap(), "ID-000001");
+/// ```
+pub struct Store {
+    connection: Connection,
+    retention: Retention,
+}
+
+impl Store {
+    /// Opens or creates the database at the given path.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [Error] when the database cannot be opened or initialised.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Store::init(Connection::open(path)?)
+    }
+
+    /// Creates a database that lives as long as the store.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [Error] when the database cannot be initialised.
+    pub fn in_memory() -> Result<Self, Error> {
+        Store::init(Connection::open_in_memory()?)
+    }
+
+    fn init(connection: Connection) -> Result<Self, Error> {
+        connection.busy_timeout(std::time::Duration::from_secs(5))?;
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(Store {
+            connection,
+            retention: Retention::Originals,
+        })
+    }
+
+    /// Sets what is kept about the original numbers. Defaults to
+    /// [Retention::Originals].
+    ///
+    /// A database should be used with a single retention throughout.
+    pub fn retention(mut self, retention: Retention) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Returns the pseudonym of the number in the given study, issuing the
+    /// next one if the number has not been seen before.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [Error] when the database cannot be read or written.
+    pub fn pseudonym<N: AsRef<number::Number>>(
+        &self,
+        study: &str,
+        number: &N,
+    ) -> Result<String, Error> {
+        let original = self.original_key(study, number.as_ref());
+        let tx =
+            rusqlite::Transaction::new_unchecked(&self.connection, TransactionBehavior::Immediate)?;
+        let existing: Option<String> = tx
+            .query_row(
+                "SELECT pseudonym FROM pseudonym WHERE study = ?1 AND original = ?2",
+                params![study, original],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        let pseudonym = match existing {
+            Some(pseudonym) => pseudonym,
+            None => {
+                let issued: i64 = tx.query_row(
+                    "SELECT COUNT(*) FROM pseudonym WHERE study = ?1",
+                    params![study],
+                    |row| row.get(0),
+                )?;
+                let pseudonym = format!("ID-{:06}", issued + 1);
+
+                tx.execute(
+                    "INSERT INTO pseudonym (study, original, pseudonym) VALUES (?1, ?2, ?3)",
+                    params![study, original, pseudonym],
+                )?;
+
+                pseudonym
+            }
+        };
+
+        tx.commit()?;
+
+        Ok(pseudonym)
+    }
+
+    /// Returns the number a pseudonym was issued for, if the store keeps
+    /// originals and the pseudonym is known.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [Error] when the database cannot be read.
+    pub fn original(&self, study: &str, pseudonym: &str) -> Result<Option<number::Number>, Error> {
+        if self.retention != Retention::Originals {
+            return Ok(None);
+        }
+
+        let original: Option<String> = self
+            .connection
+            .query_row(
+                "SELECT original FROM pseudonym WHERE study = ?1 AND pseudonym = ?2",
+                params![study, pseudonym],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(original.and_then(|original| number::Number::from_str(&original).ok()))
+    }
+
+    /// Amount of pseudonyms issued in the given study.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [Error] when the database cannot be read.
+    pub fn issued(&self, study: &str) -> Result<usize, Error> {
+        self.connection
+            .query_row(
+                "SELECT COUNT(*) FROM pseudonym WHERE study = ?1",
+                params![study],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|count| count as usize)
+    }
+
+    fn original_key(&self, study: &str, number: &number::Number) -> String {
+        match self.retention {
+            Retention::Originals => number.to_string(),
+            Retention::Digests => {
+                let mut hasher = Sha256::new();
+                hasher.update(study.as_bytes());
+                hasher.update(b":");
+                hasher.update(number.to_string().as_bytes());
+
+                hasher
+                    .finalize()
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect()
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Store {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Store")
+            .field("path", &self.connection.path())
+            .field("retention", &self.retention)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(s: &str) -> number::Number {
+        number::Number::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn persists_across_connections() -> Result<(), Error> {
+        let path = std::env::temp_dir().join(format!("heidi-store-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let first = Store::open(&path)?.pseudonym("s", &number("6541003238"))?;
+        let store = Store::open(&path)?;
+
+        assert_eq!(store.pseudonym("s", &number("0101990014"))?, "ID-000002");
+        assert_eq!(store.pseudonym("s", &number("6541003238"))?, first);
+        assert_eq!(store.original("s", &first)?, Some(number("6541003238")));
+        assert_eq!(store.issued("s")?, 2);
+
+        drop(store);
+        std::fs::remove_file(&path).ok();
+
+        Ok(())
+    }
+
+    #[test]
+    fn digests_only() -> Result<(), Error> {
+        let store = Store::in_memory()?.retention(Retention::Digests);
+        let pseudonym = store.pseudonym("s", &number("6541003238"))?;
+
+        assert_eq!(store.pseudonym("s", &number("6541003238"))?, pseudonym);
+        assert_eq!(store.original("s", &pseudonym)?, None);
+
+        let stored: String =
+            store
+                .connection
+                .query_row("SELECT original FROM pseudonym", [], |row| row.get(0))?;
+
+        assert_eq!(stored.len(), 64);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright 2020 Arnau Siches
+
+// Licensed under the MIT license <LICENCE or http://opensource.org/licenses/MIT>.
+// This file may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Local validation rules that run after the built-in checks, e.g. to reject
+//! numbers outside the range a trust is commissioned for.
+//!
+//! Each scheme has its own alias, [`nhs::Validator`](crate::nhs::Validator)
+//! and [`chi::Validator`](crate::chi::Validator).
+//!
+//! ```
+//! use heidi::error::{ErrorKind, ValidationError};
+//! use heidi::nhs;
+//!
+//! let validator = nhs::Validator::new()
+//!     .with_range(nhs::TEST_RANGE)
+//!     .with_rule(|number| match number.checkdigit() {
+//!         0 => Err(ValidationError::new("Numbers ending in 0 are on hold")),
+//!         _ => Ok(()),
+//!     });
+//!
+//! assert!(validator.validate("999 000 1235").is_ok());
+//! assert_eq!(
+//!     validator.validate("6541003238").unwrap_err().kind(),
+//!     ErrorKind::Other
+//! );
+//! assert_eq!(
+//!     validator.validate("6541003239").unwrap_err().kind(),
+//!     ErrorKind::CheckDigit
+//! );
+//! ```
+
+use crate::codec::to_packed;
+use crate::error::ValidationError;
+use crate::number;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+/// A rule run on numbers that pass the built-in checks.
+pub type Rule<N> = Box<dyn Fn(&N) -> Result<(), ValidationError> + Send + Sync>;
+
+/// Validates numbers with the built-in checks followed by the given rules,
+/// in the order they were added.
+pub struct Validator<N> {
+    rules: Vec<Rule<N>>,
+}
+
+impl<N> Validator<N> {
+    pub fn new() -> Self {
+        Validator { rules: Vec::new() }
+    }
+
+    /// Adds a rule. The first rule to fail gives the error.
+    pub fn with_rule<F>(mut self, rule: F) -> Self
+    where
+        F: Fn(&N) -> Result<(), ValidationError> + Send + Sync + 'static,
+    {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    /// Runs the rules on a number that passed the built-in checks.
+    pub fn check(&self, number: &N) -> Result<(), ValidationError> {
+        self.rules.iter().try_for_each(|rule| rule(number))
+    }
+}
+
+impl<N: AsRef<number::Number>> Validator<N> {
+    /// Adds a rule rejecting numbers outside the given range, check digit
+    /// included, such as [`region::SCOTLAND`](crate::region::SCOTLAND).
+    pub fn with_range(self, range: RangeInclusive<u64>) -> Self {
+        self.with_rule(move |number| {
+            let value = to_packed(number);
+
+            if range.contains(&value) {
+                Ok(())
+            } else {
+                Err(ValidationError::new(&format!(
+                    "{:010} is outside of the range {:010} to {:010}",
+                    value,
+                    range.start(),
+                    range.end()
+                )))
+            }
+        })
+    }
+}
+
+impl<N: FromStr<Err = ValidationError>> Validator<N> {
+    /// Parses a number as `FromStr` does and runs the rules on it.
+    pub fn validate(&self, input: &str) -> Result<N, ValidationError> {
+        let number = N::from_str(input)?;
+
+        self.check(&number)?;
+
+        Ok(number)
+    }
+}
+
+impl<N> Default for Validator<N> {
+    fn default() -> Self {
+        Validator::new()
+    }
+}
+
+impl<N> fmt::Debug for Validator<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Validator")
+            .field("rules", &self.rules.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::{chi, region};
+
+    #[test]
+    fn rules_run_in_order() {
+        let validator = chi::Validator::new()
+            .with_rule(|_| Err(ValidationError::with_kind(ErrorKind::Date, "First")))
+            .with_rule(|_| Err(ValidationError::new("Second")));
+
+        assert_eq!(
+            validator.validate("0101990014").unwrap_err().to_string(),
+            "First"
+        );
+        assert_eq!(
+            validator.validate("0101990015").unwrap_err().kind(),
+            ErrorKind::CheckDigit
+        );
+        assert!(chi::Validator::new()
+            .with_range(region::SCOTLAND)
+            .validate("0101990014")
+            .is_ok());
+    }
+}